@@ -14,6 +14,7 @@ use voxelar::vulkan::descriptors::descriptor_set_logic_builder::DescriptorSetLog
 use voxelar::vulkan::descriptors::descriptor_set_update_builder::DescriptorSetUpdateBuilder;
 use voxelar::vulkan::egui_integration::SetUpEguiIntegration;
 use voxelar::vulkan::graphics_pipeline_builder::GraphicsPipelineBuilder;
+use voxelar::vulkan::ownership::Owned;
 use voxelar::vulkan::per_frame::PerFrame;
 use voxelar::vulkan::pipeline_layout::SetUpPipelineLayout;
 use voxelar::vulkan::pipeline_layout_builder::PipelineLayoutBuilder;
@@ -38,12 +39,12 @@ pub struct DemoBillboardBuffer {
 }
 
 pub struct DemoDescriptorBuffers {
-    camera_buffer: SetUpUniformBuffer<DemoCameraBuffer>,
-    billboard_buffer: SetUpStorageBuffer<DemoBillboardBuffer>,
+    camera_buffer: Owned<SetUpUniformBuffer<DemoCameraBuffer>>,
+    billboard_buffer: Owned<SetUpStorageBuffer<DemoBillboardBuffer>>,
 }
 
 pub struct PerFrameData {
-    descriptor_set_logic: SetUpDescriptorSetLogic,
+    descriptor_set_logic: Owned<SetUpDescriptorSetLogic>,
 }
 
 pub struct Demo {
@@ -51,15 +52,15 @@ pub struct Demo {
     viewport: vk::Viewport,
     scissor: vk::Rect2D,
 
-    descriptor_set_layouts: Vec<SetUpDescriptorSetLayout>,
+    descriptor_set_layouts: Owned<Vec<SetUpDescriptorSetLayout>>,
     per_frame_data: PerFrame<PerFrameData>,
     descriptor_buffers: DemoDescriptorBuffers,
 
-    pipeline_layout: SetUpPipelineLayout,
-    pipelines: Vec<vk::Pipeline>,
+    pipeline_layout: Owned<SetUpPipelineLayout>,
+    pipelines: Owned<Vec<vk::Pipeline>>,
 
-    vertex_shader_module: CompiledShaderModule,
-    fragment_shader_module: CompiledShaderModule,
+    vertex_shader_module: Owned<CompiledShaderModule>,
+    fragment_shader_module: Owned<CompiledShaderModule>,
 
     camera: OrbitalCamera,
     frame_time_manager: FrameTimeManager,
@@ -68,7 +69,7 @@ pub struct Demo {
 }
 
 impl Demo {
-    pub unsafe fn create(
+    pub fn create(
         voxelar_context: &Voxelar,
         vulkan_context: &VulkanContext,
         egui_integration: SetUpEguiIntegration,
@@ -90,7 +91,7 @@ impl Demo {
                 vk::ShaderStageFlags::VERTEX,
             )
             .build(logical_device)?;
-        let descriptor_set_layouts = vec![global_set_layout];
+        let descriptor_set_layouts = vulkan_context.own(vec![global_set_layout])?;
 
         let descriptor_buffers = DemoDescriptorBuffers {
             camera_buffer: vulkan_context
@@ -113,9 +114,8 @@ impl Demo {
             },
         ];
 
-        descriptor_buffers
-            .billboard_buffer
-            .copy_from_slice(&billboard_data)?;
+        vulkan_context
+            .copy_data_to_storage_buffer(&descriptor_buffers.billboard_buffer, &billboard_data)?;
 
         let per_frame_data = PerFrame::try_init(
             |_| {
@@ -124,11 +124,12 @@ impl Demo {
                     .add_pool_size(vk::DescriptorType::STORAGE_BUFFER, 1)
                     .set_layouts(&descriptor_set_layouts)
                     .build(logical_device)?;
+                let descriptor_set_logic = vulkan_context.own(descriptor_set_logic)?;
                 let destination_set = descriptor_set_logic.get_set(0);
 
                 DescriptorSetUpdateBuilder::new()
-                    .add_dynamic_uniform_buffer_descriptor(&descriptor_buffers.camera_buffer, 0)
-                    .add_storage_buffer_descriptor(&descriptor_buffers.billboard_buffer, 1)
+                    .add_dynamic_uniform_buffer_descriptor(&*descriptor_buffers.camera_buffer, 0)
+                    .add_storage_buffer_descriptor(&*descriptor_buffers.billboard_buffer, 1)
                     .update(logical_device, destination_set);
 
                 Ok(PerFrameData {
@@ -138,9 +139,11 @@ impl Demo {
             vulkan_context.frame_overlap(),
         )?;
 
-        let pipeline_layout = PipelineLayoutBuilder::new()
-            .set_layouts(&descriptor_set_layouts)
-            .build(logical_device)?;
+        let pipeline_layout = vulkan_context.own(
+            PipelineLayoutBuilder::new()
+                .set_layouts(&descriptor_set_layouts)
+                .build(logical_device)?,
+        )?;
 
         let surface_resolution = vulkan_context.get_surface_extent()?;
         let surface_width = surface_resolution.width;
@@ -198,7 +201,7 @@ impl Demo {
             descriptor_buffers,
 
             pipeline_layout,
-            pipelines: vec![graphics_pipeline],
+            pipelines: vulkan_context.own(vec![graphics_pipeline])?,
 
             vertex_shader_module,
             fragment_shader_module,
@@ -223,7 +226,7 @@ impl Demo {
         vulkan_context: &VulkanContext,
         egui_integration: SetUpEguiIntegration,
     ) -> crate::Result<Self> {
-        unsafe { Self::create(voxelar_context, vulkan_context, egui_integration) }
+        Self::create(voxelar_context, vulkan_context, egui_integration)
     }
 
     pub fn on_resize(&mut self, vulkan_context: &VulkanContext) -> crate::Result<()> {
@@ -279,7 +282,7 @@ impl Demo {
 
             let total_frames = self.frame_time_manager.total_frames();
             let current_frame_index = total_frames as usize % vulkan_context.frame_overlap();
-            vulkan_context.select_frame(current_frame_index);
+            vulkan_context.select_frame(current_frame_index)?;
             self.per_frame_data.select(current_frame_index);
 
             vulkan_context.wait_for_current_frame_draw_buffer_fences()?;
@@ -397,39 +400,11 @@ impl Demo {
         self.frame_time_manager.update(context);
     }
 
+    /// The GPU resources of the demo are owned and destroyed when it's dropped, only the egui
+    /// integration has to be destroyed manually.
     pub fn destroy(&mut self, vulkan_context: &VulkanContext) -> crate::Result<()> {
-        let logical_device = vulkan_context.logical_device()?;
-
-        logical_device.wait()?;
+        vulkan_context.logical_device()?.wait()?;
         self.egui_integration.destroy();
-
-        unsafe {
-            let mut allocator = vulkan_context.lock_allocator()?;
-
-            self.descriptor_buffers
-                .camera_buffer
-                .destroy(logical_device, &mut allocator)?;
-            self.descriptor_buffers
-                .billboard_buffer
-                .destroy(logical_device, &mut allocator)?;
-
-            for descriptor_data in self.per_frame_data.iter_mut() {
-                descriptor_data.descriptor_set_logic.destroy(logical_device);
-            }
-
-            for descriptor_set_layout in self.descriptor_set_layouts.iter_mut() {
-                descriptor_set_layout.destroy(logical_device);
-            }
-
-            for pipeline in self.pipelines.iter() {
-                logical_device.destroy_pipeline(*pipeline, None);
-            }
-            self.pipeline_layout.destroy(logical_device);
-
-            self.vertex_shader_module.destroy(logical_device);
-            self.fragment_shader_module.destroy(logical_device);
-        }
-
         Ok(())
     }
 }
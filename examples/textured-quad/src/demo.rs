@@ -8,7 +8,6 @@ use voxelar::shaderc::ShaderKind;
 use voxelar::vulkan::buffers::typed_buffer::TypedAllocatedBuffer;
 use voxelar::vulkan::buffers::uniform_buffer::SetUpUniformBuffer;
 use voxelar::vulkan::descriptors::descriptor_set_layout::SetUpDescriptorSetLayout;
-use voxelar::vulkan::descriptors::descriptor_set_layout_builder::DescriptorSetLayoutBuilder;
 use voxelar::vulkan::descriptors::descriptor_set_logic::SetUpDescriptorSetLogic;
 use voxelar::vulkan::descriptors::descriptor_set_logic_builder::DescriptorSetLogicBuilder;
 use voxelar::vulkan::descriptors::descriptor_set_update_builder::DescriptorSetUpdateBuilder;
@@ -16,9 +15,11 @@ use voxelar::vulkan::egui_integration::SetUpEguiIntegration;
 use voxelar::vulkan::graphics_pipeline_builder::GraphicsPipelineBuilder;
 use voxelar::vulkan::image::sampler::SetUpSampler;
 use voxelar::vulkan::image::texture::Texture;
+use voxelar::vulkan::ownership::Owned;
 use voxelar::vulkan::per_frame::PerFrame;
 use voxelar::vulkan::pipeline_layout::SetUpPipelineLayout;
-use voxelar::vulkan::pipeline_layout_builder::PipelineLayoutBuilder;
+use voxelar::vulkan::render_info::RenderInfo;
+use voxelar::vulkan::shader::reflection::PipelineReflection;
 use voxelar::vulkan::shader::CompiledShaderModule;
 use voxelar::vulkan::VulkanContext;
 use voxelar::window::VoxelarWindow;
@@ -40,12 +41,12 @@ pub struct DemoSceneBuffer {
 }
 
 pub struct DemoDescriptorBuffers {
-    camera_buffer: SetUpUniformBuffer<DemoCameraBuffer>,
-    scene_buffer: SetUpUniformBuffer<DemoSceneBuffer>,
+    camera_buffer: Owned<SetUpUniformBuffer<DemoCameraBuffer>>,
+    scene_buffer: Owned<SetUpUniformBuffer<DemoSceneBuffer>>,
 }
 
 pub struct PerFrameData {
-    descriptor_set_logic: SetUpDescriptorSetLogic,
+    descriptor_set_logic: Owned<SetUpDescriptorSetLogic>,
 }
 
 pub struct Demo {
@@ -53,30 +54,31 @@ pub struct Demo {
     viewport: vk::Viewport,
     scissor: vk::Rect2D,
 
-    descriptor_set_layouts: Vec<SetUpDescriptorSetLayout>,
+    descriptor_set_layouts: Owned<Vec<SetUpDescriptorSetLayout>>,
     per_frame_data: PerFrame<PerFrameData>,
     descriptor_buffers: DemoDescriptorBuffers,
 
-    pipeline_layout: SetUpPipelineLayout,
-    pipelines: Vec<vk::Pipeline>,
+    pipeline_layout: Owned<SetUpPipelineLayout>,
+    pipelines: Owned<Vec<vk::Pipeline>>,
 
-    vertex_shader_module: CompiledShaderModule,
-    fragment_shader_module: CompiledShaderModule,
-    vertex_buffer: TypedAllocatedBuffer<VertexData>,
-    index_buffer: TypedAllocatedBuffer<u32>,
+    vertex_shader_module: Owned<CompiledShaderModule>,
+    fragment_shader_module: Owned<CompiledShaderModule>,
+    vertex_buffer: Owned<TypedAllocatedBuffer<VertexData>>,
+    index_buffer: Owned<TypedAllocatedBuffer<u32>>,
     index_count: usize,
 
-    sampler: SetUpSampler,
-    texture: Texture<u8>,
+    sampler: Owned<SetUpSampler>,
+    texture: Owned<Texture<u8>>,
 
     camera: OrbitalCamera,
     frame_time_manager: FrameTimeManager,
 
+    render_info: RenderInfo,
     egui_integration: SetUpEguiIntegration,
 }
 
 impl Demo {
-    pub unsafe fn create(
+    pub fn create(
         voxelar_context: &Voxelar,
         vulkan_context: &VulkanContext,
         egui_integration: SetUpEguiIntegration,
@@ -84,27 +86,24 @@ impl Demo {
         let render_pass = vulkan_context.render_pass()?;
         let logical_device = vulkan_context.logical_device()?;
 
-        let global_set_layout = DescriptorSetLayoutBuilder::new()
-            .add_binding(
-                0, // In the shader, this will specify binding = 0 in the uniform layout
-                1,
-                vk::DescriptorType::UNIFORM_BUFFER_DYNAMIC,
-                vk::ShaderStageFlags::VERTEX,
-            )
-            .add_binding(
-                1, // In the shader, this will specify binding = 1 in the uniform layout
-                1,
-                vk::DescriptorType::UNIFORM_BUFFER_DYNAMIC,
-                vk::ShaderStageFlags::FRAGMENT,
-            )
-            .add_binding(
-                2, // In the shader, this will specifiy binding = 2 in the uniform layout
-                1,
-                vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
-                vk::ShaderStageFlags::FRAGMENT,
-            )
-            .build(logical_device)?;
-        let descriptor_set_layouts = vec![global_set_layout];
+        let compiled_vert =
+            compile_shader_from_included_src!(ShaderKind::Vertex, "../shader/triangle.vert")?;
+        let vertex_shader_module = vulkan_context.create_vertex_shader(compiled_vert)?;
+
+        let compiled_frag =
+            compile_shader_from_included_src!(ShaderKind::Fragment, "../shader/triangle.frag")?;
+        let fragment_shader_module = vulkan_context.create_fragment_shader(compiled_frag)?;
+
+        // The set layout and the pipeline layout are derived from the shaders; only the dynamic
+        // uniform buffers can't be told apart from regular ones
+        let mut reflection = PipelineReflection::from_shader_modules(&[
+            &*vertex_shader_module,
+            &*fragment_shader_module,
+        ])?;
+        reflection.set_descriptor_type(0, 0, vk::DescriptorType::UNIFORM_BUFFER_DYNAMIC)?;
+        reflection.set_descriptor_type(0, 1, vk::DescriptorType::UNIFORM_BUFFER_DYNAMIC)?;
+        let descriptor_set_layouts =
+            vulkan_context.own(reflection.build_descriptor_set_layouts(logical_device, 0)?)?;
 
         let descriptor_buffers = DemoDescriptorBuffers {
             camera_buffer: vulkan_context
@@ -132,14 +131,15 @@ impl Demo {
                     .add_pool_size(vk::DescriptorType::COMBINED_IMAGE_SAMPLER, 1)
                     .set_layouts(&descriptor_set_layouts)
                     .build(logical_device)?;
+                let descriptor_set_logic = vulkan_context.own(descriptor_set_logic)?;
                 let destination_set = descriptor_set_logic.get_set(0);
 
                 DescriptorSetUpdateBuilder::new()
-                    .add_dynamic_uniform_buffer_descriptor(&descriptor_buffers.camera_buffer, 0)
-                    .add_dynamic_uniform_buffer_descriptor(&descriptor_buffers.scene_buffer, 1)
+                    .add_dynamic_uniform_buffer_descriptor(&*descriptor_buffers.camera_buffer, 0)
+                    .add_dynamic_uniform_buffer_descriptor(&*descriptor_buffers.scene_buffer, 1)
                     .add_texture_descriptor(
                         &sampler,
-                        &texture,
+                        &*texture,
                         2,
                         vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
                     )
@@ -152,9 +152,8 @@ impl Demo {
             vulkan_context.frame_overlap(),
         )?;
 
-        let pipeline_layout = PipelineLayoutBuilder::new()
-            .set_layouts(&descriptor_set_layouts)
-            .build(logical_device)?;
+        let pipeline_layout = vulkan_context
+            .own(reflection.build_pipeline_layout(logical_device, &descriptor_set_layouts)?)?;
 
         let surface_resolution = vulkan_context.get_surface_extent()?;
         let surface_width = surface_resolution.width;
@@ -188,14 +187,6 @@ impl Demo {
         let index_buffer_data = vec![0, 1, 2, 0, 2, 3];
         let index_buffer = vulkan_context.create_index_buffer(&index_buffer_data)?;
 
-        let compiled_vert =
-            compile_shader_from_included_src!(ShaderKind::Vertex, "../shader/triangle.vert")?;
-        let vertex_shader_module = vulkan_context.create_vertex_shader(compiled_vert)?;
-
-        let compiled_frag =
-            compile_shader_from_included_src!(ShaderKind::Fragment, "../shader/triangle.frag")?;
-        let fragment_shader_module = vulkan_context.create_fragment_shader(compiled_frag)?;
-
         let viewport = vk::Viewport {
             x: 0.0,
             y: 0.0,
@@ -233,7 +224,7 @@ impl Demo {
             descriptor_buffers,
 
             pipeline_layout,
-            pipelines: vec![graphics_pipeline],
+            pipelines: vulkan_context.own(vec![graphics_pipeline])?,
 
             vertex_shader_module,
             fragment_shader_module,
@@ -255,6 +246,7 @@ impl Demo {
                 100.0,
             ),
 
+            render_info: vulkan_context.render_info()?,
             egui_integration,
         })
     }
@@ -264,7 +256,7 @@ impl Demo {
         vulkan_context: &VulkanContext,
         egui_integration: SetUpEguiIntegration,
     ) -> crate::Result<Self> {
-        unsafe { Self::create(voxelar_context, vulkan_context, egui_integration) }
+        Self::create(voxelar_context, vulkan_context, egui_integration)
     }
 
     pub fn on_resize(&mut self, vulkan_context: &VulkanContext) -> crate::Result<()> {
@@ -320,7 +312,7 @@ impl Demo {
 
             let total_frames = self.frame_time_manager.total_frames();
             let current_frame_index = total_frames as usize % vulkan_context.frame_overlap();
-            vulkan_context.select_frame(current_frame_index);
+            vulkan_context.select_frame(current_frame_index)?;
             self.per_frame_data.select(current_frame_index);
 
             vulkan_context.wait_for_current_frame_draw_buffer_fences()?;
@@ -442,6 +434,7 @@ impl Demo {
                                 "Frame Time: {:.1}ms",
                                 self.frame_time_manager.frame_time() * 1000.0
                             ));
+                            ui.collapsing("Render Info", |ui| self.render_info.show(ui));
                         });
                         Ok(())
                     },
@@ -465,45 +458,11 @@ impl Demo {
         self.frame_time_manager.update(context);
     }
 
+    /// The GPU resources of the demo are owned and destroyed when it's dropped, only the egui
+    /// integration has to be destroyed manually.
     pub fn destroy(&mut self, vulkan_context: &VulkanContext) -> crate::Result<()> {
-        let logical_device = vulkan_context.logical_device()?;
-
-        logical_device.wait()?;
+        vulkan_context.logical_device()?.wait()?;
         self.egui_integration.destroy();
-
-        unsafe {
-            let mut allocator = vulkan_context.lock_allocator()?;
-
-            self.descriptor_buffers
-                .camera_buffer
-                .destroy(logical_device, &mut allocator)?;
-            self.descriptor_buffers
-                .scene_buffer
-                .destroy(logical_device, &mut allocator)?;
-
-            for descriptor_data in self.per_frame_data.iter_mut() {
-                descriptor_data.descriptor_set_logic.destroy(logical_device);
-            }
-
-            for descriptor_set_layout in self.descriptor_set_layouts.iter_mut() {
-                descriptor_set_layout.destroy(logical_device);
-            }
-
-            for pipeline in self.pipelines.iter() {
-                logical_device.destroy_pipeline(*pipeline, None);
-            }
-            self.pipeline_layout.destroy(logical_device);
-
-            self.vertex_shader_module.destroy(logical_device);
-            self.fragment_shader_module.destroy(logical_device);
-
-            self.index_buffer.destroy(logical_device, &mut allocator)?;
-            self.vertex_buffer.destroy(logical_device, &mut allocator)?;
-
-            self.texture.destroy(logical_device, &mut allocator)?;
-            self.sampler.destroy(logical_device);
-        }
-
         Ok(())
     }
 }
@@ -3,7 +3,7 @@ extern crate voxelar;
 mod demo;
 mod vertex;
 
-use voxelar::ash::vk::PresentModeKHR;
+use voxelar::ash::vk::{PresentModeKHR, SampleCountFlags};
 
 use voxelar::vulkan::creation_info::*;
 use voxelar::vulkan::debug::*;
@@ -30,11 +30,14 @@ fn main() -> Result<()> {
         swapchain_present_mode: PresentModeInitMode::Find(PresentModeKHR::FIFO),
         frame_overlap: 2,
         allocator_debug_settings: Default::default(),
+        msaa_samples: SampleCountFlags::TYPE_1,
+        depth_buffer: Default::default(),
+        rendering_path: Default::default(),
     };
     vulkan_context.create_default_data_structures(window.get_size(), creation_info)?;
 
-    let phys_device = vulkan_context.physical_device()?;
-    println!("Found physical device: {:?}", phys_device.name());
+    let render_info = vulkan_context.render_info()?;
+    println!("{render_info}");
 
     let egui_integration = vulkan_context.create_egui_integration(&window, &event_loop)?;
 
@@ -3,12 +3,57 @@ use nalgebra::Point3;
 
 pub mod orbital_camera;
 
+/// This function creates a perspective projection matrix. With `reverse_z`, the near plane is
+/// mapped to a depth of 1.0 and the far plane to 0.0 (in Vulkan's depth range of 0.0 to 1.0),
+/// which has to be used together with a reverse-Z depth buffer.
+pub fn perspective_projection(
+    aspect_ratio: f32,
+    fov_radians: f32,
+    znear: f32,
+    zfar: f32,
+    reverse_z: bool,
+) -> Matrix4<f32> {
+    let mut projection = Matrix4::new_perspective(aspect_ratio, fov_radians, znear, zfar);
+    if reverse_z {
+        projection[(2, 2)] = znear / (zfar - znear);
+        projection[(2, 3)] = znear * zfar / (zfar - znear);
+    }
+    projection
+}
+
 pub trait Camera {
     fn view_rotation_matrix(&self) -> Matrix4<f32>;
     fn view_matrix(&self) -> Matrix4<f32>;
 
     fn projection_matrix(&self) -> Matrix4<f32>;
 
+    /// This function returns the distances of the near and far planes of the projection.
+    ///
+    /// By default, they are derived from the `projection_matrix`, which has to be created by
+    /// `perspective_projection` with the camera's `reverse_z` setting. Cameras with other
+    /// projections have to override this function.
+    fn clip_planes(&self) -> (f32, f32) {
+        let projection = self.projection_matrix();
+        let (depth_scale, depth_offset) = (projection[(2, 2)], projection[(2, 3)]);
+        if self.reverse_z() {
+            (
+                depth_offset / (depth_scale + 1.0),
+                depth_offset / depth_scale,
+            )
+        } else {
+            (
+                depth_offset / (depth_scale - 1.0),
+                depth_offset / (depth_scale + 1.0),
+            )
+        }
+    }
+
+    /// This function returns whether the projection maps the near plane to a depth of 1.0 and
+    /// the far plane to 0.0 (see `perspective_projection`).
+    fn reverse_z(&self) -> bool {
+        false
+    }
+
     fn on_resize(&mut self, size: (u32, u32));
     fn on_single_update(&mut self);
 
@@ -20,3 +65,63 @@ pub trait Camera {
     }
 }
 
+#[cfg(test)]
+mod tests {
+    use nalgebra::Vector4;
+
+    use super::*;
+
+    #[test]
+    fn reverse_z_projection_maps_near_to_one_and_far_to_zero() {
+        let projection = perspective_projection(1.0, 1.0, 0.1, 1000.0, true);
+        let depth = |z: f32| {
+            let clip = projection * Vector4::new(0.0, 0.0, z, 1.0);
+            clip.z / clip.w
+        };
+
+        assert!((depth(-0.1) - 1.0).abs() < 1e-5);
+        assert!(depth(-1000.0).abs() < 1e-5);
+        assert!(depth(-10.0) > depth(-100.0));
+    }
+
+    struct PerspectiveCamera {
+        reverse_z: bool,
+    }
+
+    impl Camera for PerspectiveCamera {
+        fn view_rotation_matrix(&self) -> Matrix4<f32> {
+            Matrix4::identity()
+        }
+
+        fn view_matrix(&self) -> Matrix4<f32> {
+            Matrix4::identity()
+        }
+
+        fn projection_matrix(&self) -> Matrix4<f32> {
+            perspective_projection(1.5, 1.0, 0.1, 1000.0, self.reverse_z)
+        }
+
+        fn reverse_z(&self) -> bool {
+            self.reverse_z
+        }
+
+        fn on_resize(&mut self, _size: (u32, u32)) {}
+
+        fn on_single_update(&mut self) {}
+
+        fn position(&self) -> Point3<f32> {
+            Point3::origin()
+        }
+
+        fn set_position(&mut self, _position: Point3<f32>) {}
+    }
+
+    #[test]
+    fn default_clip_planes_are_derived_from_the_projection() {
+        for reverse_z in [false, true] {
+            let (near, far) = PerspectiveCamera { reverse_z }.clip_planes();
+            assert!((near - 0.1).abs() < 1e-4);
+            assert!((far - 1000.0).abs() < 1.0);
+        }
+    }
+}
@@ -11,6 +11,7 @@ pub struct OrbitalCamera {
     pub fov_radians: f32,
     pub znear: f32,
     pub zfar: f32,
+    pub reverse_z: bool,
     pub current_projection_matrix: Matrix4<f32>,
 }
 
@@ -34,6 +35,7 @@ impl OrbitalCamera {
             fov_radians,
             znear,
             zfar,
+            reverse_z: false,
             current_projection_matrix: Matrix4::new_perspective(
                 aspect_ratio,
                 fov_radians,
@@ -43,6 +45,27 @@ impl OrbitalCamera {
         }
     }
 
+    /// This function switches the camera to a reverse-Z projection, which has to be used when
+    /// the depth buffer was created with `DepthBufferInfo::reverse_z`.
+    pub fn with_reverse_z(mut self) -> Self {
+        // The aspect ratio isn't stored, but can be recovered from the current projection
+        let aspect_ratio =
+            self.current_projection_matrix[(1, 1)] / self.current_projection_matrix[(0, 0)];
+        self.reverse_z = true;
+        self.update_projection_matrix(aspect_ratio);
+        self
+    }
+
+    fn update_projection_matrix(&mut self, aspect_ratio: f32) {
+        self.current_projection_matrix = perspective_projection(
+            aspect_ratio,
+            self.fov_radians,
+            self.znear,
+            self.zfar,
+            self.reverse_z,
+        );
+    }
+
     fn calculate_fixed_distance_vector_to_target(&self) -> Vector3<f32> {
         let normalized_vector = (self.target - self.current_position).normalize();
         normalized_vector * self.distance_from_target
@@ -63,10 +86,17 @@ impl Camera for OrbitalCamera {
         self.current_projection_matrix
     }
 
+    fn clip_planes(&self) -> (f32, f32) {
+        (self.znear, self.zfar)
+    }
+
+    fn reverse_z(&self) -> bool {
+        self.reverse_z
+    }
+
     fn on_resize(&mut self, size: (u32, u32)) {
         let aspect_ratio = size.0 as f32 / size.1 as f32;
-        self.current_projection_matrix =
-            Matrix4::new_perspective(aspect_ratio, self.fov_radians, self.znear, self.zfar);
+        self.update_projection_matrix(aspect_ratio);
     }
 
     fn on_single_update(&mut self) {
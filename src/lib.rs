@@ -41,15 +41,16 @@ pub mod voxel;
 pub mod vulkan;
 pub mod window;
 
+use std::cell::Cell;
 use std::time::Instant;
 
 pub use result::*;
 
-use render_context::RenderContext;
+use render_context::{HeadlessRenderContext, RenderContext};
 use window::*;
 
 use winit::dpi::LogicalSize;
-use winit::event_loop::EventLoop;
+use winit::event_loop::{EventLoop, EventLoopWindowTarget};
 use winit::window::WindowBuilder;
 
 /// The main Voxelar context from which everything is initialized.
@@ -57,6 +58,7 @@ use winit::window::WindowBuilder;
 /// This structure stores an initialized GLFW context.
 pub struct Voxelar {
     start_time: Instant,
+    event_loop_created: Cell<bool>,
 }
 
 impl Voxelar {
@@ -79,13 +81,30 @@ impl Voxelar {
     pub fn new() -> crate::Result<Self> {
         Ok(Self {
             start_time: Instant::now(),
+            event_loop_created: Cell::new(false),
         })
     }
 
+    /// This function creates the `VoxelarEventLoop` that receives the events of all windows.
+    ///
+    /// NOTE: winit only allows one event loop per application, so this function returns an
+    ///       error if it's called a second time. Additional windows are created with
+    ///       `Voxelar::create_window_for_event_loop`.
+    pub fn create_event_loop(&self) -> crate::Result<VoxelarEventLoop> {
+        crate::verify!(
+            !self.event_loop_created.replace(true),
+            "The event loop was already created, only one event loop is allowed per application"
+        );
+        Ok(VoxelarEventLoop::new(EventLoop::new()))
+    }
+
     /// This function is used to create a window with some specific `width` and `height`, a `title` and a
     /// `window_mode` (windowed or maximized), as well as an `EventLoop` receiving from this
     /// window.
     ///
+    /// Because this function creates a new event loop, it can only be used for the first window.
+    /// Use `Voxelar::create_window_for_event_loop` for every other window.
+    ///
     /// # Examples
     ///
     /// ```
@@ -108,14 +127,56 @@ impl Voxelar {
         title: &str,
         window_mode: VoxelarWindowMode,
     ) -> crate::Result<(VoxelarWindow, VoxelarEventLoop)> {
-        let event_loop = EventLoop::new();
+        let event_loop = self.create_event_loop()?;
+        let window = self.create_window_for_event_loop(
+            &event_loop.event_loop,
+            width,
+            height,
+            title,
+            window_mode,
+        )?;
+        Ok((window, event_loop))
+    }
+
+    /// This function is used to create a window that receives its events from an existing event
+    /// loop.
+    ///
+    /// The `event_loop_target` can either be the `EventLoop` of a `VoxelarEventLoop` (before the
+    /// loop is run) or the `EventLoopWindowTarget` that is passed to the event handler.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use voxelar::*;
+    /// use voxelar::window::*;
+    ///
+    /// fn main() -> Result<()> {
+    ///     let mut ctx = Voxelar::new()?;
+    ///
+    ///     let event_loop = ctx.create_event_loop()?;
+    ///     let viewport = ctx.create_window_for_event_loop(
+    ///         &event_loop.event_loop, 800, 600, "Viewport", VoxelarWindowMode::Windowed
+    ///     )?;
+    ///     let palette = ctx.create_window_for_event_loop(
+    ///         &event_loop.event_loop, 300, 600, "Palette", VoxelarWindowMode::Windowed
+    ///     )?;
+    ///     assert_ne!(viewport.id(), palette.id());
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn create_window_for_event_loop(
+        &self,
+        event_loop_target: &EventLoopWindowTarget<()>,
+        width: u32,
+        height: u32,
+        title: &str,
+        window_mode: VoxelarWindowMode,
+    ) -> crate::Result<VoxelarWindow> {
         let builder = WindowBuilder::new()
             .with_title(title)
             .with_maximized(window_mode == VoxelarWindowMode::Maximized)
             .with_inner_size(LogicalSize::new(width, height));
-        let window = VoxelarWindow::from_window_builder(builder, &event_loop)?;
-        let event_loop = VoxelarEventLoop::new(event_loop);
-        Ok((window, event_loop))
+        VoxelarWindow::from_window_builder(builder, event_loop_target)
     }
 
     /// This function loads a specific `RenderContext` using its `load` function.
@@ -150,6 +211,33 @@ impl Voxelar {
         C::load(self, window)
     }
 
+    /// This function loads a specific `HeadlessRenderContext` using its `load_headless` function.
+    ///
+    /// No window is required for this, so the context can be used for offscreen rendering.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use voxelar::*;
+    /// use voxelar::vulkan::*;
+    /// use voxelar::vulkan::debug::*;
+    ///
+    /// fn main() -> Result<()> {
+    ///     let mut ctx = Voxelar::new()?;
+    ///
+    ///     let vulkan_context = ctx
+    ///         .load_headless_render_context::<NoVerification, VulkanContext>();
+    ///     assert!(vulkan_context.is_ok());
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn load_headless_render_context<LoadData, C: HeadlessRenderContext<LoadData>>(
+        &mut self,
+    ) -> crate::Result<C> {
+        C::load_headless(self)
+    }
+
     pub fn current_time(&self) -> f64 {
         let current_time = Instant::now();
         let time_diff = current_time - self.start_time;
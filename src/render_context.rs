@@ -1,8 +1,11 @@
 //! This is a module that contains the `RenderContext` trait, an abstract interface for graphics
 //! APIs to be loaded from a `Voxelar` context and a `VoxelarWindow`.
 //!
-//! If wanted, the API can provide an implementation of `RenderContext::get_info` to provide the
-//! user with some API-related information.
+//! Using `RenderContext::get_info`, the API provides the user with some API-related information
+//! (e.g. the used device) in the form of its `RenderContext::Info` type.
+//!
+//! APIs that are able to render without a window can additionally implement the
+//! `HeadlessRenderContext` trait.
 
 use crate::window::VoxelarWindow;
 use crate::Voxelar;
@@ -14,6 +17,9 @@ use crate::Voxelar;
 ///
 /// The `LoadData` generic is used to provide additional data to the context for loading.
 pub trait RenderContext<LoadData> {
+    /// The type of information that is returned by `RenderContext::get_info`.
+    type Info;
+
     /// This function loads this API using the `Voxelar` context and the `VoxelarWindow`.
     ///
     /// If loading the API failed, the result of this function will be an `Err`.
@@ -21,8 +27,19 @@ pub trait RenderContext<LoadData> {
     where
         Self: Sized;
 
-    /// This function returns information about this `RenderContext` in the form of the type T.
+    /// This function returns information about this `RenderContext` (e.g. the used device).
+    fn get_info(&self) -> crate::Result<Self::Info>;
+}
+
+/// The `HeadlessRenderContext` trait.
+///
+/// This trait should be implemented by APIs that can be loaded without a `VoxelarWindow`, e.g. for
+/// offscreen rendering in tests or on servers.
+pub trait HeadlessRenderContext<LoadData> {
+    /// This function loads this API using only the `Voxelar` context.
     ///
-    /// Previously, I just returned a String here, but this seems much more flexible.
-    fn get_info<T>(&self) -> crate::Result<T>;
+    /// If loading the API failed, the result of this function will be an `Err`.
+    fn load_headless(ctx: &mut Voxelar) -> crate::Result<Self>
+    where
+        Self: Sized;
 }
@@ -65,6 +65,20 @@ impl<T> AlignedBuffer<T> {
         Ok(())
     }
 
+    pub unsafe fn flush_all_memory(
+        &self,
+        logical_device: &SetUpLogicalDevice,
+    ) -> crate::Result<()> {
+        let memory_range = MappedMemoryRange::builder()
+            .memory(self.buffer.allocation()?.memory())
+            .size(self.buffer.allocation()?.size())
+            .offset(self.buffer.allocation()?.offset())
+            .build();
+
+        logical_device.flush_mapped_memory_ranges(&[memory_range])?;
+        Ok(())
+    }
+
     pub unsafe fn mapped_ptr(&self) -> crate::Result<*mut T> {
         Ok(self.buffer.get_mapped_ptr()?.as_ptr() as *mut T)
     }
@@ -17,6 +17,9 @@ use crate::vulkan::logical_device::SetUpLogicalDevice;
 pub struct AllocatedBuffer {
     pub allocation: Option<Allocation>,
     pub buffer: Buffer,
+    /// The sharing mode that the buffer was created with, which is `EXCLUSIVE` if the device
+    /// only has a single queue family
+    pub sharing_mode: SharingMode,
 }
 
 impl AllocatedBuffer {
@@ -25,6 +28,10 @@ impl AllocatedBuffer {
     /// The buffer is created using the `size`, `usage` and `sharing_mode` arguments, and the
     /// buffer data is allocated according to the `MemoryPropertyFlags` and buffer's memory
     /// requirements using the provided `Allocator`.
+    ///
+    /// If `sharing_mode` is `SharingMode::CONCURRENT`, the buffer is shared between all queue
+    /// families of the logical device, so it can be used by the graphics, transfer and compute
+    /// queues without ownership transfers.
     pub unsafe fn allocate(
         logical_device: &SetUpLogicalDevice,
         allocator: &mut MutexGuard<Allocator>,
@@ -33,10 +40,12 @@ impl AllocatedBuffer {
         sharing_mode: SharingMode,
         memory_location: MemoryLocation,
     ) -> crate::Result<Self> {
+        let (sharing_mode, queue_family_indices) = logical_device.resolve_sharing_mode(sharing_mode);
         let buffer_info = BufferCreateInfo::builder()
             .size(size as u64)
             .usage(usage)
-            .sharing_mode(sharing_mode);
+            .sharing_mode(sharing_mode)
+            .queue_family_indices(&queue_family_indices);
 
         let buffer = logical_device.create_buffer(&buffer_info, None)?;
         let memory_requirements = logical_device.get_buffer_memory_requirements(buffer);
@@ -58,6 +67,7 @@ impl AllocatedBuffer {
         Ok(Self {
             allocation: Some(buffer_allocation),
             buffer,
+            sharing_mode,
         })
     }
 
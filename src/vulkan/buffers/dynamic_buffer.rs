@@ -0,0 +1,440 @@
+//! This is a module that contains the `DynamicGpuBuffer` structure, a GPU-only buffer whose
+//! contents can be changed partially and that grows when more elements are pushed than it can
+//! hold.
+//!
+//! The buffer keeps a copy of its elements in CPU memory and tracks which ranges of them were
+//! changed since the last upload. An upload only copies these dirty ranges through a staging
+//! buffer.
+//!
+//! Frames in flight may still read the buffer while the next upload is recorded, so every upload
+//! writes into a new buffer (which is larger if the buffer has to grow): The previous contents
+//! are copied on the GPU, so they don't have to be uploaded again, and the previous buffer is
+//! destroyed once the frames in flight completed.
+
+use std::ops::Range;
+use std::sync::MutexGuard;
+
+use ash::vk::{AccessFlags, BufferCopy, BufferUsageFlags, DependencyFlags, MemoryBarrier};
+use ash::vk::{PipelineStageFlags, SharingMode};
+
+use gpu_allocator::vulkan::Allocator;
+use gpu_allocator::MemoryLocation;
+
+use crate::vulkan::command::command_buffer::SetUpCommandBufferWithFence;
+use crate::vulkan::logical_device::SetUpLogicalDevice;
+
+use super::staging_buffer::SetUpStagingBuffer;
+use super::typed_buffer::TypedAllocatedBuffer;
+
+/// A sorted list of disjoint element ranges
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct DirtyRanges {
+    ranges: Vec<Range<usize>>,
+}
+
+impl DirtyRanges {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// This function marks the `range` as dirty. Overlapping and adjacent ranges are merged.
+    pub fn insert(&mut self, range: Range<usize>) {
+        if range.is_empty() {
+            return;
+        }
+
+        let first = self.ranges.partition_point(|other| other.end < range.start);
+        let last = self
+            .ranges
+            .partition_point(|other| other.start <= range.end);
+        let merged = if first < last {
+            self.ranges[first].start.min(range.start)..self.ranges[last - 1].end.max(range.end)
+        } else {
+            range
+        };
+        self.ranges.splice(first..last, [merged]);
+    }
+
+    /// This function removes all parts of the ranges at or after `length`.
+    pub fn truncate(&mut self, length: usize) {
+        self.ranges.retain(|range| range.start < length);
+        if let Some(last) = self.ranges.last_mut() {
+            last.end = last.end.min(length);
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.ranges.clear();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ranges.is_empty()
+    }
+
+    pub fn ranges(&self) -> &[Range<usize>] {
+        &self.ranges
+    }
+
+    /// This function returns the number of dirty elements.
+    pub fn element_count(&self) -> usize {
+        self.ranges.iter().map(|range| range.len()).sum()
+    }
+}
+
+/// The buffers that have to be destroyed after the commands of an upload completed
+pub struct DynamicBufferUpload<T> {
+    pub staging_buffer: Option<SetUpStagingBuffer<T>>,
+    /// The buffer the contents were copied from. It may still be used by frames in flight.
+    pub previous_buffer: TypedAllocatedBuffer<T>,
+}
+
+pub struct DynamicGpuBuffer<T> {
+    pub buffer: TypedAllocatedBuffer<T>,
+    pub usage: BufferUsageFlags,
+    elements: Vec<T>,
+    dirty_ranges: DirtyRanges,
+    /// The number of elements that were uploaded to `buffer`
+    uploaded_length: usize,
+}
+
+impl<T> DynamicGpuBuffer<T> {
+    /// This function allocates an empty buffer for `capacity` elements. The transfer usages
+    /// that are needed for uploads and growing are added to `usage`.
+    pub unsafe fn allocate(
+        logical_device: &SetUpLogicalDevice,
+        allocator: &mut MutexGuard<Allocator>,
+        capacity: usize,
+        usage: BufferUsageFlags,
+    ) -> crate::Result<Self> {
+        let usage = usage | BufferUsageFlags::TRANSFER_SRC | BufferUsageFlags::TRANSFER_DST;
+        let buffer = Self::allocate_buffer(logical_device, allocator, capacity.max(1), usage)?;
+
+        Ok(Self {
+            buffer,
+            usage,
+            elements: Vec::with_capacity(capacity),
+            dirty_ranges: DirtyRanges::new(),
+            uploaded_length: 0,
+        })
+    }
+
+    unsafe fn allocate_buffer(
+        logical_device: &SetUpLogicalDevice,
+        allocator: &mut MutexGuard<Allocator>,
+        capacity: usize,
+        usage: BufferUsageFlags,
+    ) -> crate::Result<TypedAllocatedBuffer<T>> {
+        TypedAllocatedBuffer::allocate(
+            logical_device,
+            allocator,
+            capacity,
+            usage,
+            SharingMode::EXCLUSIVE,
+            MemoryLocation::GpuOnly,
+        )
+    }
+
+    pub fn len(&self) -> usize {
+        self.elements.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.elements.is_empty()
+    }
+
+    /// This function returns the number of elements the GPU buffer can hold without growing.
+    pub fn capacity(&self) -> usize {
+        self.buffer.element_amount
+    }
+
+    /// This function returns the CPU copy of the elements, including changes that were not
+    /// uploaded yet.
+    pub fn as_slice(&self) -> &[T] {
+        &self.elements
+    }
+
+    pub fn dirty_ranges(&self) -> &DirtyRanges {
+        &self.dirty_ranges
+    }
+
+    /// This function returns whether the buffer has changes that were not uploaded yet.
+    pub fn needs_upload(&self) -> bool {
+        !self.dirty_ranges.is_empty() || self.len() > self.capacity()
+    }
+
+    /// This function appends the `value` and returns its index.
+    pub fn push(&mut self, value: T) -> usize {
+        let index = self.elements.len();
+        self.elements.push(value);
+        self.dirty_ranges.insert(index..index + 1);
+        index
+    }
+
+    /// This function appends the `data` and returns the range of indices it was stored at.
+    pub fn extend_from_slice(&mut self, data: &[T]) -> Range<usize>
+    where
+        T: Copy,
+    {
+        let range = self.elements.len()..self.elements.len() + data.len();
+        self.elements.extend_from_slice(data);
+        self.dirty_ranges.insert(range.clone());
+        range
+    }
+
+    /// This function overwrites the elements starting at the index `start` with the `data`.
+    pub fn update_range(&mut self, start: usize, data: &[T]) -> crate::Result<()>
+    where
+        T: Copy,
+    {
+        let range = start..start + data.len();
+        crate::verify!(
+            range.end <= self.elements.len(),
+            "The range {:?} is out of bounds of the buffer with {} elements",
+            range,
+            (self.elements.len())
+        );
+
+        self.elements[range.clone()].copy_from_slice(data);
+        self.dirty_ranges.insert(range);
+        Ok(())
+    }
+
+    /// This function removes the elements in the `range`. The following elements are moved to
+    /// the front, so their indices change and they have to be uploaded again.
+    pub fn remove_range(&mut self, range: Range<usize>) -> crate::Result<()> {
+        crate::verify!(
+            range.start <= range.end && range.end <= self.elements.len(),
+            "The range {:?} is out of bounds of the buffer with {} elements",
+            range,
+            (self.elements.len())
+        );
+
+        let moved_start = range.start;
+        self.elements.drain(range);
+        self.dirty_ranges.truncate(self.elements.len());
+        self.dirty_ranges.insert(moved_start..self.elements.len());
+        Ok(())
+    }
+
+    pub fn clear(&mut self) {
+        self.elements.clear();
+        self.dirty_ranges.clear();
+    }
+
+    /// This function records the commands that bring the GPU buffer up to date with the CPU
+    /// copy: A new buffer is allocated, which has (at least) twice the capacity if the buffer is
+    /// too small, the uploaded elements are copied into it on the GPU and the dirty ranges are
+    /// copied from a staging buffer. Afterwards, the new buffer replaces the previous one.
+    ///
+    /// The returned buffers must be destroyed after the commands completed. If recording fails,
+    /// the buffer is left unchanged.
+    pub fn record_upload(
+        &mut self,
+        logical_device: &SetUpLogicalDevice,
+        allocator: &mut MutexGuard<Allocator>,
+        command_buffer: &SetUpCommandBufferWithFence,
+    ) -> crate::Result<DynamicBufferUpload<T>>
+    where
+        T: Copy,
+    {
+        let capacity = if self.len() > self.capacity() {
+            self.len().max(self.capacity() * 2)
+        } else {
+            self.capacity()
+        };
+        let mut buffer =
+            unsafe { Self::allocate_buffer(logical_device, allocator, capacity, self.usage)? };
+
+        let staging_buffer =
+            match self.record_copies_into(logical_device, allocator, command_buffer, &buffer) {
+                Ok(staging_buffer) => staging_buffer,
+                Err(err) => {
+                    // The commands are never submitted, so the new buffer isn't used yet
+                    let _ = buffer.destroy(logical_device, allocator);
+                    return Err(err);
+                }
+            };
+
+        let previous_buffer = std::mem::replace(&mut self.buffer, buffer);
+        self.dirty_ranges.clear();
+        self.uploaded_length = self.len();
+
+        Ok(DynamicBufferUpload {
+            staging_buffer,
+            previous_buffer,
+        })
+    }
+
+    fn record_copies_into(
+        &self,
+        logical_device: &SetUpLogicalDevice,
+        allocator: &mut MutexGuard<Allocator>,
+        command_buffer: &SetUpCommandBufferWithFence,
+        buffer: &TypedAllocatedBuffer<T>,
+    ) -> crate::Result<Option<SetUpStagingBuffer<T>>>
+    where
+        T: Copy,
+    {
+        let copied_length = self.uploaded_length.min(self.len());
+        if copied_length > 0 {
+            self.record_copy_from_previous(logical_device, command_buffer, buffer, copied_length);
+        }
+
+        let staging_buffer = if self.dirty_ranges.is_empty() {
+            None
+        } else {
+            Some(self.record_copy_of_dirty_ranges(
+                logical_device,
+                allocator,
+                command_buffer,
+                buffer,
+            )?)
+        };
+
+        Self::record_read_barrier(logical_device, command_buffer);
+        Ok(staging_buffer)
+    }
+
+    fn record_copy_from_previous(
+        &self,
+        logical_device: &SetUpLogicalDevice,
+        command_buffer: &SetUpCommandBufferWithFence,
+        buffer: &TypedAllocatedBuffer<T>,
+        element_amount: usize,
+    ) {
+        let buffer_copy = BufferCopy::builder()
+            .src_offset(0)
+            .dst_offset(0)
+            .size((element_amount * std::mem::size_of::<T>()) as u64);
+
+        // The dirty ranges are copied to the same buffer afterwards, which may overwrite the
+        // copied elements
+        let memory_barrier = MemoryBarrier::builder()
+            .src_access_mask(AccessFlags::TRANSFER_WRITE)
+            .dst_access_mask(AccessFlags::TRANSFER_WRITE);
+
+        unsafe {
+            logical_device.cmd_copy_buffer(
+                command_buffer.command_buffer,
+                self.buffer.raw_buffer(),
+                buffer.raw_buffer(),
+                &[*buffer_copy],
+            );
+            logical_device.cmd_pipeline_barrier(
+                command_buffer.command_buffer,
+                PipelineStageFlags::TRANSFER,
+                PipelineStageFlags::TRANSFER,
+                DependencyFlags::empty(),
+                &[*memory_barrier],
+                &[],
+                &[],
+            );
+        }
+    }
+
+    fn record_copy_of_dirty_ranges(
+        &self,
+        logical_device: &SetUpLogicalDevice,
+        allocator: &mut MutexGuard<Allocator>,
+        command_buffer: &SetUpCommandBufferWithFence,
+        buffer: &TypedAllocatedBuffer<T>,
+    ) -> crate::Result<SetUpStagingBuffer<T>>
+    where
+        T: Copy,
+    {
+        let size_of_type = std::mem::size_of::<T>() as u64;
+        let mut staging_data = Vec::with_capacity(self.dirty_ranges.element_count());
+        let mut buffer_copies = Vec::with_capacity(self.dirty_ranges.ranges().len());
+        for range in self.dirty_ranges.ranges() {
+            let buffer_copy = BufferCopy::builder()
+                .src_offset(staging_data.len() as u64 * size_of_type)
+                .dst_offset(range.start as u64 * size_of_type)
+                .size(range.len() as u64 * size_of_type);
+            buffer_copies.push(*buffer_copy);
+            staging_data.extend_from_slice(&self.elements[range.clone()]);
+        }
+
+        unsafe {
+            let mut staging_buffer =
+                SetUpStagingBuffer::allocate(logical_device, allocator, staging_data.len())?;
+            if let Err(err) = staging_buffer.copy_from_slice(logical_device, &staging_data) {
+                let _ = staging_buffer.destroy(logical_device, allocator);
+                return Err(err);
+            }
+
+            logical_device.cmd_copy_buffer(
+                command_buffer.command_buffer,
+                staging_buffer.raw_buffer(),
+                buffer.raw_buffer(),
+                &buffer_copies,
+            );
+            Ok(staging_buffer)
+        }
+    }
+
+    /// This function records the barrier that makes the copied elements visible to later
+    /// submissions, which read them as vertex, index, uniform or storage data.
+    fn record_read_barrier(
+        logical_device: &SetUpLogicalDevice,
+        command_buffer: &SetUpCommandBufferWithFence,
+    ) {
+        let memory_barrier = MemoryBarrier::builder()
+            .src_access_mask(AccessFlags::TRANSFER_WRITE)
+            .dst_access_mask(
+                AccessFlags::VERTEX_ATTRIBUTE_READ
+                    | AccessFlags::INDEX_READ
+                    | AccessFlags::UNIFORM_READ
+                    | AccessFlags::SHADER_READ,
+            );
+
+        unsafe {
+            logical_device.cmd_pipeline_barrier(
+                command_buffer.command_buffer,
+                PipelineStageFlags::TRANSFER,
+                PipelineStageFlags::VERTEX_INPUT
+                    | PipelineStageFlags::VERTEX_SHADER
+                    | PipelineStageFlags::FRAGMENT_SHADER
+                    | PipelineStageFlags::COMPUTE_SHADER,
+                DependencyFlags::empty(),
+                &[*memory_barrier],
+                &[],
+                &[],
+            );
+        }
+    }
+
+    pub fn raw_buffer(&self) -> ash::vk::Buffer {
+        self.buffer.raw_buffer()
+    }
+
+    pub fn destroy(
+        &mut self,
+        logical_device: &SetUpLogicalDevice,
+        allocator: &mut MutexGuard<Allocator>,
+    ) -> crate::Result<()> {
+        self.buffer.destroy(logical_device, allocator)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dirty_ranges_are_merged() {
+        let mut dirty_ranges = DirtyRanges::new();
+        dirty_ranges.insert(10..20);
+        dirty_ranges.insert(30..40);
+        dirty_ranges.insert(0..5);
+        assert_eq!(&[0..5, 10..20, 30..40], dirty_ranges.ranges());
+
+        // Adjacent and overlapping ranges become one
+        dirty_ranges.insert(20..25);
+        dirty_ranges.insert(15..32);
+        assert_eq!(&[0..5, 10..40], dirty_ranges.ranges());
+        assert_eq!(35, dirty_ranges.element_count());
+
+        dirty_ranges.truncate(12);
+        assert_eq!(&[0..5, 10..12], dirty_ranges.ranges());
+    }
+}
@@ -3,6 +3,9 @@
 //! Module overview:
 //! - aligned\_buffer: Provides an abstraction for buffers with custom alignments
 //! - buffer: Provides an abstraction for GPU memory-allocated buffers
+//! - dynamic\_buffer: Provides an abstraction for GPU buffers that grow and are updated partially by uploading only the changed ranges
+//! - readback\_buffer: Provides an abstraction for readback buffers (used when transferring data from GPU- to CPU-visible memory)
+//! - ring\_buffer: Provides a host-visible ring buffer with a linear allocator for each frame in flight (used for streaming per-frame data)
 //! - staging\_buffer: Provides an abstraction for staging buffers (used when transferring data from CPU- to GPU-only memory)
 //! - storage\_buffer: Provides an abstraction for shader storage buffers
 //! - typed\_buffer: Provides an abstraction for buffers that hold data of a specific type
@@ -10,6 +13,9 @@
 
 pub mod aligned_buffer;
 pub mod buffer;
+pub mod dynamic_buffer;
+pub mod readback_buffer;
+pub mod ring_buffer;
 pub mod staging_buffer;
 pub mod storage_buffer;
 pub mod typed_buffer;
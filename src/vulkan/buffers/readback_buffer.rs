@@ -0,0 +1,58 @@
+use std::sync::MutexGuard;
+
+use ash::vk::SharingMode;
+use ash::vk::{Buffer, BufferUsageFlags};
+use gpu_allocator::vulkan::Allocator;
+use gpu_allocator::MemoryLocation;
+
+use crate::vulkan::logical_device::SetUpLogicalDevice;
+
+use super::typed_buffer::TypedAllocatedBuffer;
+
+pub struct SetUpReadbackBuffer<T> {
+    pub buffer: TypedAllocatedBuffer<T>,
+}
+
+impl<T> SetUpReadbackBuffer<T> {
+    pub unsafe fn allocate(
+        logical_device: &SetUpLogicalDevice,
+        allocator: &mut MutexGuard<Allocator>,
+        element_amount: usize,
+    ) -> crate::Result<Self> {
+        let buffer = TypedAllocatedBuffer::allocate(
+            logical_device,
+            allocator,
+            element_amount,
+            BufferUsageFlags::TRANSFER_DST,
+            SharingMode::EXCLUSIVE,
+            MemoryLocation::GpuToCpu,
+        )?;
+
+        Ok(Self { buffer })
+    }
+
+    /// This function copies the contents of this buffer into a `Vec`.
+    ///
+    /// NOTE: The caller has to make sure that all commands writing to this buffer have finished
+    ///       executing before calling this function.
+    pub unsafe fn copy_to_vec(&self) -> crate::Result<Vec<T>>
+    where
+        T: Copy,
+    {
+        let buffer_ptr = self.buffer.mapped_ptr()? as *const T;
+        let data = std::slice::from_raw_parts(buffer_ptr, self.buffer.element_amount);
+        Ok(data.to_vec())
+    }
+
+    pub fn raw_buffer(&self) -> Buffer {
+        self.buffer.raw_buffer()
+    }
+
+    pub fn destroy(
+        &mut self,
+        logical_device: &SetUpLogicalDevice,
+        allocator: &mut MutexGuard<Allocator>,
+    ) -> crate::Result<()> {
+        self.buffer.destroy(logical_device, allocator)
+    }
+}
@@ -0,0 +1,317 @@
+//! This is a module that contains the `SetUpRingBuffer` structure, a large host-visible buffer
+//! for streaming per-frame data.
+//!
+//! The buffer is split into one region per frame in flight. Each region is a linear allocator:
+//! Data is pushed to the end of the region, and the whole region is reset at the start of the
+//! frame, after the fence of the frame was waited for. This replaces separate allocations (or
+//! one slot per frame in an `AlignedBuffer`) for many small uploads, e.g. the uniform data of
+//! each chunk.
+//!
+//! The ring buffer of a window is created with `VulkanContext::create_ring_buffer` and owned by
+//! its `WindowTarget`, so that the region of a frame is reset by
+//! `VulkanContext::wait_for_current_frame_draw_buffer_fences`, together with the deletion queue
+//! and the descriptor allocator of the frame.
+//!
+//! The offsets of the pushed data can be used as dynamic offsets of `UNIFORM_BUFFER_DYNAMIC`
+//! and `STORAGE_BUFFER_DYNAMIC` descriptors (see
+//! `DescriptorSetUpdateBuilder::add_ring_buffer_descriptor`) or to bind vertex and index data.
+
+use std::sync::MutexGuard;
+
+use ash::vk::{Buffer, BufferUsageFlags, MappedMemoryRange, SharingMode};
+use gpu_allocator::vulkan::Allocator;
+use gpu_allocator::MemoryLocation;
+
+use crate::result::Context;
+use crate::vulkan::logical_device::SetUpLogicalDevice;
+use crate::vulkan::per_frame::PerFrame;
+use crate::vulkan::physical_device::SetUpPhysicalDevice;
+use crate::vulkan::util;
+
+use super::buffer::AllocatedBuffer;
+
+/// A bump allocator over the byte range `base_offset..base_offset + capacity` of a buffer
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LinearAllocator {
+    pub base_offset: u64,
+    pub capacity: u64,
+    cursor: u64,
+}
+
+impl LinearAllocator {
+    pub fn new(base_offset: u64, capacity: u64) -> Self {
+        Self {
+            base_offset,
+            capacity,
+            cursor: 0,
+        }
+    }
+
+    /// This function returns the offset of a new sub-range of `size` bytes, which is aligned
+    /// to the power-of-two `alignment` relative to the start of the buffer, or `None` if the
+    /// range doesn't fit anymore.
+    pub fn allocate(&mut self, size: u64, alignment: u64) -> Option<u64> {
+        let offset = util::pad_buffer_size_for_alignment(
+            (self.base_offset + self.cursor) as usize,
+            alignment as usize,
+        ) as u64;
+        let end = offset.checked_add(size)?;
+        if end > self.base_offset + self.capacity {
+            return None;
+        }
+
+        self.cursor = end - self.base_offset;
+        Some(offset)
+    }
+
+    pub fn reset(&mut self) {
+        self.cursor = 0;
+    }
+
+    /// This function returns the number of bytes that were allocated since the last reset,
+    /// including the alignment padding.
+    pub fn used(&self) -> u64 {
+        self.cursor
+    }
+
+    pub fn remaining(&self) -> u64 {
+        self.capacity - self.cursor
+    }
+}
+
+/// A sub-range of a `SetUpRingBuffer`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RingAllocation {
+    pub buffer: Buffer,
+    pub offset: u64,
+    pub size: u64,
+}
+
+impl RingAllocation {
+    /// This function returns the offset of the allocation as a dynamic offset for
+    /// `cmd_bind_descriptor_sets`, which is limited to 32 bits.
+    pub fn dynamic_offset(&self) -> crate::Result<u32> {
+        u32::try_from(self.offset).ok().context(format!(
+            "The offset {} of the ring buffer allocation doesn't fit into a dynamic offset",
+            self.offset
+        ))
+    }
+}
+
+/// This function expands the memory range of `size` bytes at `offset` to multiples of the
+/// power-of-two `non_coherent_atom_size`, as required for flushing non-coherent memory. The
+/// memory blocks of the allocator are multiples of the atom size, so the range stays inside
+/// the memory object.
+pub fn atom_aligned_range(offset: u64, size: u64, non_coherent_atom_size: u64) -> (u64, u64) {
+    let aligned_offset = offset & !(non_coherent_atom_size - 1);
+    let aligned_size = util::pad_buffer_size_for_alignment(
+        (offset - aligned_offset + size) as usize,
+        non_coherent_atom_size as usize,
+    ) as u64;
+    (aligned_offset, aligned_size)
+}
+
+pub struct SetUpRingBuffer {
+    pub buffer: AllocatedBuffer,
+    /// The number of bytes each frame can allocate
+    pub frame_capacity: u64,
+    /// The alignment of all allocations, which satisfies the offset alignments of uniform and
+    /// storage buffer descriptors
+    pub min_alignment: u64,
+    non_coherent_atom_size: u64,
+    frames: PerFrame<LinearAllocator>,
+}
+
+impl SetUpRingBuffer {
+    /// This function allocates a host-visible buffer with `frame_count` regions of (at least)
+    /// `frame_capacity` bytes.
+    pub unsafe fn allocate(
+        logical_device: &SetUpLogicalDevice,
+        physical_device: &SetUpPhysicalDevice,
+        allocator: &mut MutexGuard<Allocator>,
+        frame_capacity: usize,
+        frame_count: usize,
+        usage: BufferUsageFlags,
+    ) -> crate::Result<Self> {
+        crate::verify!(
+            frame_capacity > 0 && frame_count > 0,
+            "A ring buffer needs at least one frame with a capacity of at least one byte"
+        );
+
+        let limits = &physical_device.device_properties.limits;
+        let min_alignment = limits
+            .min_uniform_buffer_offset_alignment
+            .max(limits.min_storage_buffer_offset_alignment)
+            .max(1);
+        let non_coherent_atom_size = limits.non_coherent_atom_size.max(1);
+
+        // The regions start at offsets that can be flushed and bound
+        let region_alignment = min_alignment.max(non_coherent_atom_size) as usize;
+        let frame_capacity = util::pad_buffer_size_for_alignment(frame_capacity, region_alignment);
+
+        let buffer = AllocatedBuffer::allocate(
+            logical_device,
+            allocator,
+            frame_capacity * frame_count,
+            usage,
+            SharingMode::EXCLUSIVE,
+            MemoryLocation::CpuToGpu,
+        )?;
+        let frame_capacity = frame_capacity as u64;
+        let frames = PerFrame::try_init(
+            |index| {
+                Ok(LinearAllocator::new(
+                    index as u64 * frame_capacity,
+                    frame_capacity,
+                ))
+            },
+            frame_count,
+        )?;
+
+        Ok(Self {
+            buffer,
+            frame_capacity,
+            min_alignment,
+            non_coherent_atom_size,
+            frames,
+        })
+    }
+
+    /// This function selects the region of the frame with the given index and resets it. The
+    /// fence of the frame must have been waited for, so that the GPU doesn't read the data of
+    /// the previous use of the region anymore.
+    pub(crate) fn begin_frame(&mut self, frame_index: usize) {
+        self.frames.get_select_mut(frame_index).reset();
+    }
+
+    /// This function allocates `size` bytes in the region of the current frame. The
+    /// `alignment` must be a power of two; the allocation is at least aligned to
+    /// `min_alignment`.
+    pub fn allocate_range(&mut self, size: u64, alignment: u64) -> crate::Result<RingAllocation> {
+        crate::verify!(
+            alignment.is_power_of_two(),
+            "The alignment of a ring buffer allocation must be a power of two, got {}",
+            alignment
+        );
+
+        let frame = self.frames.current_mut();
+        let offset = frame.allocate(size, alignment.max(self.min_alignment));
+        let offset = match offset {
+            Some(offset) => offset,
+            None => crate::bail!(
+                "The ring buffer is full: {} of {} bytes of the frame are used, {} more were requested",
+                (frame.used()),
+                (frame.capacity),
+                size
+            ),
+        };
+
+        Ok(RingAllocation {
+            buffer: self.buffer.buffer,
+            offset,
+            size,
+        })
+    }
+
+    /// This function copies the `value` into the region of the current frame.
+    pub fn push<T: Copy>(&mut self, value: &T) -> crate::Result<RingAllocation> {
+        self.push_slice(std::slice::from_ref(value))
+    }
+
+    /// This function copies the `data` into the region of the current frame.
+    pub fn push_slice<T: Copy>(&mut self, data: &[T]) -> crate::Result<RingAllocation> {
+        let size = std::mem::size_of_val(data) as u64;
+        let allocation = self.allocate_range(size, std::mem::align_of::<T>() as u64)?;
+
+        unsafe {
+            let ptr = self.buffer.get_mapped_ptr()?.as_ptr().cast::<u8>();
+            std::ptr::copy_nonoverlapping(
+                data.as_ptr().cast::<u8>(),
+                ptr.add(allocation.offset as usize),
+                size as usize,
+            );
+        }
+        Ok(allocation)
+    }
+
+    /// This function flushes the data that was pushed in the current frame, so that it's
+    /// visible to the GPU even if the memory isn't host-coherent. This has to be called before
+    /// the commands of the frame are submitted.
+    pub fn flush(&self, logical_device: &SetUpLogicalDevice) -> crate::Result<()> {
+        let frame = self.frames.current();
+        if frame.used() == 0 {
+            return Ok(());
+        }
+
+        let allocation = self.buffer.allocation()?;
+        let (offset, size) = atom_aligned_range(
+            allocation.offset() + frame.base_offset,
+            frame.used(),
+            self.non_coherent_atom_size,
+        );
+        let memory_range = MappedMemoryRange::builder()
+            .memory(unsafe { allocation.memory() })
+            .offset(offset)
+            .size(size)
+            .build();
+
+        unsafe {
+            logical_device.flush_mapped_memory_ranges(&[memory_range])?;
+        }
+        Ok(())
+    }
+
+    pub fn current_frame_index(&self) -> usize {
+        self.frames.current_frame_index()
+    }
+
+    /// This function returns the number of bytes that were allocated in the current frame.
+    pub fn used(&self) -> u64 {
+        self.frames.current().used()
+    }
+
+    pub fn destroy(
+        &mut self,
+        logical_device: &SetUpLogicalDevice,
+        allocator: &mut MutexGuard<Allocator>,
+    ) -> crate::Result<()> {
+        self.buffer.destroy(logical_device, allocator)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn linear_allocator_aligns_and_fills_its_region() {
+        let mut allocator = LinearAllocator::new(256, 256);
+        assert_eq!(Some(256), allocator.allocate(12, 64));
+        assert_eq!(Some(320), allocator.allocate(100, 64));
+        assert_eq!(Some(420), allocator.allocate(4, 4));
+        assert_eq!(None, allocator.allocate(128, 64));
+        assert_eq!(168, allocator.used());
+
+        allocator.reset();
+        assert_eq!(Some(256), allocator.allocate(256, 256));
+        assert_eq!(0, allocator.remaining());
+    }
+
+    #[test]
+    fn flushed_ranges_are_expanded_to_whole_atoms() {
+        assert_eq!((0, 64), atom_aligned_range(0, 10, 64));
+        assert_eq!((64, 128), atom_aligned_range(96, 64, 64));
+        assert_eq!((256, 256), atom_aligned_range(256, 256, 64));
+    }
+
+    #[test]
+    fn dynamic_offsets_are_limited_to_32_bits() {
+        let allocation = |offset| RingAllocation {
+            buffer: Buffer::null(),
+            offset,
+            size: 4,
+        };
+        assert_eq!(256, allocation(256).dynamic_offset().unwrap());
+        assert!(allocation(u32::MAX as u64 + 1).dynamic_offset().is_err());
+    }
+}
@@ -2,7 +2,10 @@
 //! command buffers with access synchronization using a `Fence`.
 
 use ash::vk::PipelineStageFlags;
+use ash::vk::{PipelineLayout, ShaderStageFlags};
 use ash::vk::Queue;
+use ash::vk::{Buffer, DeviceSize, DispatchIndirectCommand};
+use ash::vk::{Rect2D, RenderingAttachmentInfo, RenderingInfo};
 use ash::vk::SubmitInfo;
 use ash::vk::{
     CommandBuffer, CommandBufferBeginInfo, CommandBufferResetFlags, CommandBufferUsageFlags,
@@ -144,6 +147,126 @@ impl SetUpCommandBufferWithFence {
         }
     }
 
+    /// This function records an update of the push constants at `offset` with the contents of
+    /// `push_constants`, which should be a `#[repr(C)]` type that matches the shader's layout.
+    pub fn push_constants<PushConstants>(
+        &self,
+        logical_device: &SetUpLogicalDevice,
+        pipeline_layout: PipelineLayout,
+        stage_flags: ShaderStageFlags,
+        offset: u32,
+        push_constants: &PushConstants,
+    ) {
+        unsafe {
+            let bytes = std::slice::from_raw_parts(
+                push_constants as *const PushConstants as *const u8,
+                std::mem::size_of::<PushConstants>(),
+            );
+            logical_device.cmd_push_constants(
+                self.command_buffer,
+                pipeline_layout,
+                stage_flags,
+                offset,
+                bytes,
+            );
+        }
+    }
+
+    /// This function records a compute dispatch with the given number of work groups.
+    ///
+    /// A compute pipeline has to be bound before calling this function.
+    pub fn dispatch(
+        &self,
+        logical_device: &SetUpLogicalDevice,
+        group_count_x: u32,
+        group_count_y: u32,
+        group_count_z: u32,
+    ) {
+        unsafe {
+            logical_device.cmd_dispatch(
+                self.command_buffer,
+                group_count_x,
+                group_count_y,
+                group_count_z,
+            );
+        }
+    }
+
+    /// This function records a compute dispatch with enough work groups of size `local_size`
+    /// to cover all `invocations`.
+    ///
+    /// The shader has to discard invocations that are outside of the requested range, because
+    /// the work group counts are rounded up.
+    pub fn dispatch_invocations(
+        &self,
+        logical_device: &SetUpLogicalDevice,
+        invocations: (u32, u32, u32),
+        local_size: (u32, u32, u32),
+    ) {
+        let group_count = work_group_count(invocations, local_size);
+        self.dispatch(logical_device, group_count.0, group_count.1, group_count.2);
+    }
+
+    /// This function records an indirect compute dispatch. The work group counts are read from a
+    /// `DispatchIndirectCommand` at the given `offset` in the `buffer`, which must have been
+    /// created with `BufferUsageFlags::INDIRECT_BUFFER`.
+    pub fn dispatch_indirect(
+        &self,
+        logical_device: &SetUpLogicalDevice,
+        buffer: Buffer,
+        offset: DeviceSize,
+    ) {
+        unsafe {
+            logical_device.cmd_dispatch_indirect(self.command_buffer, buffer, offset);
+        }
+    }
+
+    /// This function records an indirect compute dispatch that reads the `index`-th
+    /// `DispatchIndirectCommand` of the `buffer`.
+    pub fn dispatch_indirect_at_index(
+        &self,
+        logical_device: &SetUpLogicalDevice,
+        buffer: Buffer,
+        index: usize,
+    ) {
+        let offset = (index * std::mem::size_of::<DispatchIndirectCommand>()) as DeviceSize;
+        self.dispatch_indirect(logical_device, buffer, offset);
+    }
+
+    /// This function begins dynamic rendering into the given attachments, which have to be in
+    /// the layouts specified by their `RenderingAttachmentInfo`.
+    ///
+    /// This requires a Vulkan 1.3 device with the `dynamic_rendering` feature enabled.
+    pub fn begin_rendering(
+        &self,
+        logical_device: &SetUpLogicalDevice,
+        render_area: Rect2D,
+        color_attachments: &[RenderingAttachmentInfo],
+        depth_attachment: Option<&RenderingAttachmentInfo>,
+        stencil_attachment: Option<&RenderingAttachmentInfo>,
+    ) {
+        let mut rendering_info = RenderingInfo::builder()
+            .render_area(render_area)
+            .layer_count(1)
+            .color_attachments(color_attachments);
+        if let Some(depth_attachment) = depth_attachment {
+            rendering_info = rendering_info.depth_attachment(depth_attachment);
+        }
+        if let Some(stencil_attachment) = stencil_attachment {
+            rendering_info = rendering_info.stencil_attachment(stencil_attachment);
+        }
+
+        unsafe {
+            logical_device.cmd_begin_rendering(self.command_buffer, &rendering_info);
+        }
+    }
+
+    pub fn end_rendering(&self, logical_device: &SetUpLogicalDevice) {
+        unsafe {
+            logical_device.cmd_end_rendering(self.command_buffer);
+        }
+    }
+
     /// This function destroys this buffer's reuse fence.
     pub fn destroy(&mut self, logical_device: &SetUpLogicalDevice) {
         unsafe {
@@ -151,3 +274,25 @@ impl SetUpCommandBufferWithFence {
         }
     }
 }
+
+/// This function calculates the number of work groups with the size `local_size` that are
+/// required to cover all `invocations` in each dimension.
+pub fn work_group_count(invocations: (u32, u32, u32), local_size: (u32, u32, u32)) -> (u32, u32, u32) {
+    (
+        invocations.0.div_ceil(local_size.0.max(1)),
+        invocations.1.div_ceil(local_size.1.max(1)),
+        invocations.2.div_ceil(local_size.2.max(1)),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn work_group_count_is_rounded_up() {
+        assert_eq!((4, 1, 1), work_group_count((64, 1, 1), (16, 1, 1)));
+        assert_eq!((5, 3, 1), work_group_count((65, 17, 1), (16, 8, 1)));
+        assert_eq!((0, 1, 1), work_group_count((0, 1, 1), (8, 8, 8)));
+    }
+}
@@ -12,15 +12,34 @@ pub struct SetUpCommandPool {
 }
 
 impl SetUpCommandPool {
+    /// This function creates a command pool for the graphics queue family.
     pub unsafe fn create(
         logical_device: &SetUpLogicalDevice,
         command_buffer_count: usize,
         level: CommandBufferLevel,
         command_buffer_fence_create_flags: FenceCreateFlags,
+    ) -> crate::Result<Self> {
+        Self::create_for_queue_family(
+            logical_device,
+            logical_device.queue_family_index,
+            command_buffer_count,
+            level,
+            command_buffer_fence_create_flags,
+        )
+    }
+
+    /// This function creates a command pool for the given queue family. Command buffers from
+    /// this pool may only be submitted to queues of that family.
+    pub unsafe fn create_for_queue_family(
+        logical_device: &SetUpLogicalDevice,
+        queue_family_index: u32,
+        command_buffer_count: usize,
+        level: CommandBufferLevel,
+        command_buffer_fence_create_flags: FenceCreateFlags,
     ) -> crate::Result<Self> {
         let pool_create_info = CommandPoolCreateInfo::builder()
             .flags(CommandPoolCreateFlags::RESET_COMMAND_BUFFER)
-            .queue_family_index(logical_device.queue_family_index);
+            .queue_family_index(queue_family_index);
 
         let pool = logical_device.create_command_pool(&pool_create_info, None)?;
 
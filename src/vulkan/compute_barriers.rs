@@ -0,0 +1,143 @@
+//! This is a module that contains barrier utilities for making the results of compute shader
+//! writes visible to later commands, e.g. vertex buffers generated by GPU meshing, indirect draw
+//! arguments written by GPU culling or storage images that are sampled during rendering.
+
+use ash::vk::WHOLE_SIZE;
+use ash::vk::{AccessFlags, Buffer, BufferMemoryBarrier, DependencyFlags, MemoryBarrier};
+use ash::vk::{Image, ImageLayout, ImageMemoryBarrier, ImageSubresourceRange, PipelineStageFlags};
+
+use super::command::command_buffer::SetUpCommandBufferWithFence;
+use super::logical_device::SetUpLogicalDevice;
+
+/// The way the results of a compute shader are consumed after the barrier
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ComputeResultUsage {
+    /// The results are read as vertex attributes
+    VertexInput,
+    /// The results are read as indices
+    IndexInput,
+    /// The results are read as indirect draw or dispatch arguments
+    IndirectArguments,
+    /// The results are read in vertex shaders, e.g. as storage buffers
+    VertexShaderRead,
+    /// The results are read in fragment shaders, e.g. as sampled images
+    FragmentShaderRead,
+    /// The results are read or written by another compute dispatch
+    ComputeShaderAccess,
+    /// The results are copied by a transfer command
+    TransferRead,
+}
+
+impl ComputeResultUsage {
+    /// This function returns the destination stage and access mask for this usage.
+    pub fn destination(&self) -> (PipelineStageFlags, AccessFlags) {
+        match self {
+            Self::VertexInput => (
+                PipelineStageFlags::VERTEX_INPUT,
+                AccessFlags::VERTEX_ATTRIBUTE_READ,
+            ),
+            Self::IndexInput => (PipelineStageFlags::VERTEX_INPUT, AccessFlags::INDEX_READ),
+            Self::IndirectArguments => (
+                PipelineStageFlags::DRAW_INDIRECT,
+                AccessFlags::INDIRECT_COMMAND_READ,
+            ),
+            Self::VertexShaderRead => (PipelineStageFlags::VERTEX_SHADER, AccessFlags::SHADER_READ),
+            Self::FragmentShaderRead => (
+                PipelineStageFlags::FRAGMENT_SHADER,
+                AccessFlags::SHADER_READ,
+            ),
+            Self::ComputeShaderAccess => (
+                PipelineStageFlags::COMPUTE_SHADER,
+                AccessFlags::SHADER_READ | AccessFlags::SHADER_WRITE,
+            ),
+            Self::TransferRead => (PipelineStageFlags::TRANSFER, AccessFlags::TRANSFER_READ),
+        }
+    }
+}
+
+/// This function records a global memory barrier that makes all compute shader writes available
+/// for the given usage.
+pub fn record_compute_memory_barrier(
+    logical_device: &SetUpLogicalDevice,
+    command_buffer: &SetUpCommandBufferWithFence,
+    usage: ComputeResultUsage,
+) {
+    let (dst_stage, dst_access_mask) = usage.destination();
+    let memory_barrier = MemoryBarrier::builder()
+        .src_access_mask(AccessFlags::SHADER_WRITE)
+        .dst_access_mask(dst_access_mask);
+
+    unsafe {
+        logical_device.cmd_pipeline_barrier(
+            command_buffer.command_buffer,
+            PipelineStageFlags::COMPUTE_SHADER,
+            dst_stage,
+            DependencyFlags::empty(),
+            &[*memory_barrier],
+            &[],
+            &[],
+        );
+    }
+}
+
+/// This function records a barrier that makes compute shader writes to the whole `buffer`
+/// available for the given usage.
+pub fn record_compute_buffer_barrier(
+    logical_device: &SetUpLogicalDevice,
+    command_buffer: &SetUpCommandBufferWithFence,
+    buffer: Buffer,
+    usage: ComputeResultUsage,
+) {
+    let (dst_stage, dst_access_mask) = usage.destination();
+    let buffer_barrier = BufferMemoryBarrier::builder()
+        .buffer(buffer)
+        .offset(0)
+        .size(WHOLE_SIZE)
+        .src_access_mask(AccessFlags::SHADER_WRITE)
+        .dst_access_mask(dst_access_mask);
+
+    unsafe {
+        logical_device.cmd_pipeline_barrier(
+            command_buffer.command_buffer,
+            PipelineStageFlags::COMPUTE_SHADER,
+            dst_stage,
+            DependencyFlags::empty(),
+            &[],
+            &[*buffer_barrier],
+            &[],
+        );
+    }
+}
+
+/// This function records a barrier that makes compute shader writes to the `image` available for
+/// the given usage and transitions it from `old_layout` (usually `GENERAL`) to `new_layout`.
+pub fn record_compute_image_barrier(
+    logical_device: &SetUpLogicalDevice,
+    command_buffer: &SetUpCommandBufferWithFence,
+    image: Image,
+    subresource_range: ImageSubresourceRange,
+    old_layout: ImageLayout,
+    new_layout: ImageLayout,
+    usage: ComputeResultUsage,
+) {
+    let (dst_stage, dst_access_mask) = usage.destination();
+    let image_barrier = ImageMemoryBarrier::builder()
+        .image(image)
+        .subresource_range(subresource_range)
+        .old_layout(old_layout)
+        .new_layout(new_layout)
+        .src_access_mask(AccessFlags::SHADER_WRITE)
+        .dst_access_mask(dst_access_mask);
+
+    unsafe {
+        logical_device.cmd_pipeline_barrier(
+            command_buffer.command_buffer,
+            PipelineStageFlags::COMPUTE_SHADER,
+            dst_stage,
+            DependencyFlags::empty(),
+            &[],
+            &[],
+            &[*image_barrier],
+        );
+    }
+}
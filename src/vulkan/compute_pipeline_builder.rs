@@ -0,0 +1,152 @@
+use ash::vk::ComputePipelineCreateInfo;
+use ash::vk::Pipeline;
+use ash::vk::PipelineCache;
+use ash::vk::PipelineShaderStageCreateInfo;
+use ash::vk::ShaderStageFlags;
+use ash::vk::{SpecializationInfo, SpecializationMapEntry};
+
+use crate::result::Context;
+
+use super::logical_device::SetUpLogicalDevice;
+use super::pipeline_layout::SetUpPipelineLayout;
+use super::shader::CompiledShaderModule;
+
+/// A collection of specialization constants and their tightly packed data
+#[derive(Clone, Default, Debug)]
+pub struct SpecializationConstants {
+    map_entries: Vec<SpecializationMapEntry>,
+    data: Vec<u8>,
+}
+
+impl SpecializationConstants {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// This function adds the `value` as the specialization constant with the given
+    /// `constant_id`. The value is copied byte-wise, so it should be a plain type like `u32`,
+    /// `i32`, `f32` or `ash::vk::Bool32`.
+    pub fn add<T: Copy>(mut self, constant_id: u32, value: T) -> Self {
+        let size = std::mem::size_of::<T>();
+        self.map_entries.push(SpecializationMapEntry {
+            constant_id,
+            offset: self.data.len() as u32,
+            size,
+        });
+
+        let bytes = unsafe { std::slice::from_raw_parts(&value as *const T as *const u8, size) };
+        self.data.extend_from_slice(bytes);
+        self
+    }
+
+    pub fn map_entries(&self) -> &[SpecializationMapEntry] {
+        &self.map_entries
+    }
+
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.map_entries.is_empty()
+    }
+
+    /// This function returns the `SpecializationInfo` for these constants. The returned info
+    /// borrows from `self`, so it must not outlive it.
+    pub fn specialization_info(&self) -> SpecializationInfo {
+        *SpecializationInfo::builder()
+            .map_entries(&self.map_entries)
+            .data(&self.data)
+    }
+}
+
+#[derive(Default)]
+pub struct ComputePipelineBuilder {
+    shader_stage: Option<PipelineShaderStageCreateInfo>,
+    specialization_constants: SpecializationConstants,
+}
+
+impl ComputePipelineBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn shader_stage(mut self, shader_stage: PipelineShaderStageCreateInfo) -> Self {
+        self.shader_stage = Some(shader_stage);
+        self
+    }
+
+    /// This function sets the shader stage from a `CompiledShaderModule`, which must have been
+    /// created with `ShaderStageFlags::COMPUTE`.
+    pub fn shader_stage_from_module(self, shader_module: &CompiledShaderModule) -> Self {
+        self.shader_stage(shader_module.get_stage_create_info())
+    }
+
+    pub fn specialization_constants(
+        mut self,
+        specialization_constants: SpecializationConstants,
+    ) -> Self {
+        self.specialization_constants = specialization_constants;
+        self
+    }
+
+    pub fn add_specialization_constant<T: Copy>(mut self, constant_id: u32, value: T) -> Self {
+        self.specialization_constants = self.specialization_constants.add(constant_id, value);
+        self
+    }
+
+    pub fn build(
+        &self,
+        logical_device: &SetUpLogicalDevice,
+        pipeline_layout: &SetUpPipelineLayout,
+    ) -> crate::Result<Pipeline> {
+        let mut shader_stage = self
+            .shader_stage
+            .context("The compute shader stage was not set yet".to_string())?;
+        crate::verify!(
+            shader_stage.stage == ShaderStageFlags::COMPUTE,
+            "Compute pipelines require a shader module with the COMPUTE stage, got {:?}",
+            (shader_stage.stage)
+        );
+
+        let specialization_info = self.specialization_constants.specialization_info();
+        if !self.specialization_constants.is_empty() {
+            shader_stage.p_specialization_info = &specialization_info;
+        }
+
+        let pipeline_create_info = ComputePipelineCreateInfo::builder()
+            .stage(shader_stage)
+            .layout(pipeline_layout.pipeline_layout)
+            .build();
+
+        unsafe {
+            let compute_pipeline = logical_device
+                .create_compute_pipelines(PipelineCache::null(), &[pipeline_create_info], None)
+                .map_err(|(_, err)| err)?;
+            Ok(compute_pipeline[0])
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn specialization_constants_are_tightly_packed() {
+        let constants = SpecializationConstants::new()
+            .add(0, 8u32)
+            .add(3, 0.5f32)
+            .add(1, 1u8);
+
+        let offsets = constants
+            .map_entries()
+            .iter()
+            .map(|entry| (entry.constant_id, entry.offset, entry.size))
+            .collect::<Vec<_>>();
+        assert_eq!(vec![(0, 0, 4), (3, 4, 4), (1, 8, 1)], offsets);
+        assert_eq!(9, constants.data().len());
+        assert_eq!(&8u32.to_ne_bytes(), &constants.data()[0..4]);
+        assert_eq!(&0.5f32.to_ne_bytes(), &constants.data()[4..8]);
+    }
+}
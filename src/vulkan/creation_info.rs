@@ -1,4 +1,6 @@
+use ash::vk::Format;
 use ash::vk::PresentModeKHR;
+use ash::vk::SampleCountFlags;
 use gpu_allocator::AllocatorDebugSettings;
 
 use crate::result::Context;
@@ -33,9 +35,60 @@ impl PresentModeInitMode {
     }
 }
 
+/// The requirements for the depth buffer of each window
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct DepthBufferInfo {
+    /// Only depth formats with a stencil component are considered
+    pub require_stencil: bool,
+    /// The depth buffer is cleared to 0.0 and closer fragments have greater depth values. This
+    /// distributes the floating point precision much more evenly over large view distances.
+    pub reverse_z: bool,
+}
+
+impl DepthBufferInfo {
+    /// This function returns the depth formats to choose from, ordered from most to least
+    /// preferred.
+    pub fn candidate_formats(&self) -> &'static [Format] {
+        if self.require_stencil {
+            &[
+                Format::D32_SFLOAT_S8_UINT,
+                Format::D24_UNORM_S8_UINT,
+                Format::D16_UNORM_S8_UINT,
+            ]
+        } else {
+            &[
+                Format::D32_SFLOAT,
+                Format::D32_SFLOAT_S8_UINT,
+                Format::D24_UNORM_S8_UINT,
+                Format::X8_D24_UNORM_PACK32,
+                Format::D16_UNORM,
+            ]
+        }
+    }
+}
+
+/// The way the windows are rendered to
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum RenderingPath {
+    /// A render pass and one framebuffer per present image are created for each window (see
+    /// `VulkanContext::render_pass` and `VulkanContext::framebuffers`)
+    #[default]
+    RenderPass,
+    /// The present images are rendered to with `VulkanContext::record_dynamic_rendering`, so no
+    /// render pass and framebuffers are created. This requires
+    /// `DeviceRequirements::dynamic_rendering`.
+    DynamicRendering,
+}
+
 #[derive(Clone, Copy)]
 pub struct DataStructureCreationInfo {
     pub swapchain_present_mode: PresentModeInitMode,
     pub frame_overlap: u32,
     pub allocator_debug_settings: AllocatorDebugSettings,
+    /// The number of samples of the color and depth attachments of each window. This is
+    /// clamped to the sample counts that are supported by the device; `TYPE_1` disables MSAA.
+    /// The clamped count is returned by `VulkanContext::msaa_samples`.
+    pub msaa_samples: SampleCountFlags,
+    pub depth_buffer: DepthBufferInfo,
+    pub rendering_path: RenderingPath,
 }
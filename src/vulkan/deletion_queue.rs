@@ -0,0 +1,87 @@
+//! This is a module that contains the `DeletionQueue` structure, which defers the destruction of
+//! resources until the GPU is done with them.
+//!
+//! Every `FrameData` has its own deletion queue. Resources that are pushed while a frame is
+//! recorded are destroyed the next time the fence of that frame was waited for (see
+//! `VulkanContext::wait_for_current_frame_draw_buffer_fences`). At that point, all frames that
+//! were submitted before it are complete, so resources can be replaced every frame without
+//! waiting for the whole device to become idle.
+
+use std::cell::RefCell;
+use std::sync::MutexGuard;
+
+use gpu_allocator::vulkan::Allocator;
+
+use super::logical_device::SetUpLogicalDevice;
+use super::ownership::{DeviceResource, Owned};
+
+type Deletion =
+    Box<dyn FnOnce(&SetUpLogicalDevice, &mut MutexGuard<Allocator>) -> crate::Result<()>>;
+
+/// A queue of pending resource destructions
+///
+/// Resources can be pushed through a shared reference, so that they can be deferred while
+/// commands are recorded.
+#[derive(Default)]
+pub struct DeletionQueue {
+    deletions: RefCell<Vec<Deletion>>,
+}
+
+impl DeletionQueue {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// This function defers the destruction of the `resource`.
+    pub fn push<R: DeviceResource + 'static>(&self, resource: R) {
+        let mut resource = resource;
+        self.push_fn(move |logical_device, allocator| {
+            resource.destroy_resource(logical_device, allocator)
+        });
+    }
+
+    /// This function defers the destruction of an owned resource, which is not destroyed when
+    /// the `Owned` is dropped anymore.
+    pub fn push_owned<R: DeviceResource + 'static>(&self, resource: Owned<R>) {
+        self.push(resource.into_inner());
+    }
+
+    /// This function defers the `deletion_op`, e.g. for resources that are not a
+    /// `DeviceResource`.
+    pub fn push_fn<F>(&self, deletion_op: F)
+    where
+        F: FnOnce(&SetUpLogicalDevice, &mut MutexGuard<Allocator>) -> crate::Result<()> + 'static,
+    {
+        self.deletions.borrow_mut().push(Box::new(deletion_op));
+    }
+
+    pub fn len(&self) -> usize {
+        self.deletions.borrow().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.deletions.borrow().is_empty()
+    }
+
+    /// This function destroys all pending resources in the order they were pushed.
+    ///
+    /// The GPU must not use any of the resources anymore. If a destruction fails, the remaining
+    /// ones are still executed and the first error is returned.
+    pub fn flush(
+        &self,
+        logical_device: &SetUpLogicalDevice,
+        allocator: &mut MutexGuard<Allocator>,
+    ) -> crate::Result<()> {
+        let deletions = std::mem::take(&mut *self.deletions.borrow_mut());
+
+        let mut result = Ok(());
+        for deletion in deletions {
+            if let Err(err) = deletion(logical_device, allocator) {
+                if result.is_ok() {
+                    result = Err(err);
+                }
+            }
+        }
+        result
+    }
+}
@@ -16,9 +16,61 @@ use super::image::image_view::SetUpImageView;
 use super::logical_device::SetUpLogicalDevice;
 use super::surface::SetUpSurfaceInfo;
 
+/// This function returns the aspects of images with the given format.
+pub fn format_aspect_mask(format: Format) -> ImageAspectFlags {
+    match format {
+        Format::D16_UNORM | Format::X8_D24_UNORM_PACK32 | Format::D32_SFLOAT => {
+            ImageAspectFlags::DEPTH
+        }
+        Format::S8_UINT => ImageAspectFlags::STENCIL,
+        Format::D16_UNORM_S8_UINT | Format::D24_UNORM_S8_UINT | Format::D32_SFLOAT_S8_UINT => {
+            ImageAspectFlags::DEPTH | ImageAspectFlags::STENCIL
+        }
+        _ => ImageAspectFlags::COLOR,
+    }
+}
+
+/// The extent, layers, usage and view type of a depth image
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DepthImageDescription {
+    pub extent: Extent2D,
+    pub array_layers: u32,
+    pub usage: ImageUsageFlags,
+    pub view_type: ImageViewType,
+}
+
+impl DepthImageDescription {
+    /// This function creates the description of a 2D depth attachment with a single layer.
+    pub fn new(extent: Extent2D) -> Self {
+        Self {
+            extent,
+            array_layers: 1,
+            usage: ImageUsageFlags::DEPTH_STENCIL_ATTACHMENT,
+            view_type: ImageViewType::TYPE_2D,
+        }
+    }
+
+    pub fn array_layers(mut self, array_layers: u32) -> Self {
+        self.array_layers = array_layers;
+        self
+    }
+
+    pub fn usage(mut self, usage: ImageUsageFlags) -> Self {
+        self.usage = usage;
+        self
+    }
+
+    pub fn view_type(mut self, view_type: ImageViewType) -> Self {
+        self.view_type = view_type;
+        self
+    }
+}
+
 pub struct SetUpDepthImage {
     pub depth_image: AllocatedImage,
     pub depth_image_view: SetUpImageView,
+    pub format: Format,
+    pub array_layers: u32,
 }
 
 impl SetUpDepthImage {
@@ -29,10 +81,30 @@ impl SetUpDepthImage {
         surface_extent: Extent2D,
         samples: SampleCountFlags,
         subresource_range: ImageSubresourceRange,
+    ) -> crate::Result<Self> {
+        Self::create_with_layers(
+            logical_device,
+            allocator,
+            format,
+            samples,
+            DepthImageDescription::new(surface_extent),
+            subresource_range,
+        )
+    }
+
+    /// This function creates a depth image with the layers of the `description`, e.g. one for
+    /// each cascade of a shadow map. The image view covers the given `subresource_range`.
+    pub unsafe fn create_with_layers(
+        logical_device: &SetUpLogicalDevice,
+        allocator: &mut MutexGuard<Allocator>,
+        format: Format,
+        samples: SampleCountFlags,
+        description: DepthImageDescription,
+        subresource_range: ImageSubresourceRange,
     ) -> crate::Result<Self> {
         let image_extent = Extent3D {
-            width: surface_extent.width,
-            height: surface_extent.height,
+            width: description.extent.width,
+            height: description.extent.height,
             depth: 1,
         };
         let depth_image = AllocatedImage::allocate(
@@ -42,16 +114,16 @@ impl SetUpDepthImage {
             format,
             image_extent,
             1,
-            1,
+            description.array_layers,
             samples,
             ImageTiling::OPTIMAL,
-            ImageUsageFlags::DEPTH_STENCIL_ATTACHMENT,
+            description.usage,
             SharingMode::EXCLUSIVE,
         )?;
 
         let depth_image_view = SetUpImageView::create(
             logical_device,
-            ImageViewType::TYPE_2D,
+            description.view_type,
             format,
             subresource_range,
             depth_image.image,
@@ -60,12 +132,20 @@ impl SetUpDepthImage {
         Ok(Self {
             depth_image,
             depth_image_view,
+            format,
+            array_layers: description.array_layers,
         })
     }
 
     pub fn create_default_subresource_range() -> ImageSubresourceRange {
+        Self::create_subresource_range_for_format(Format::D16_UNORM)
+    }
+
+    /// This function creates a subresource range that includes the stencil aspect if the
+    /// `format` has a stencil component.
+    pub fn create_subresource_range_for_format(format: Format) -> ImageSubresourceRange {
         ImageSubresourceRange::builder()
-            .aspect_mask(ImageAspectFlags::DEPTH)
+            .aspect_mask(format_aspect_mask(format))
             .base_mip_level(0)
             .level_count(1)
             .base_array_layer(0)
@@ -77,19 +157,41 @@ impl SetUpDepthImage {
         logical_device: &SetUpLogicalDevice,
         allocator: &mut MutexGuard<Allocator>,
         surface_info: &SetUpSurfaceInfo,
+        format: Format,
+        samples: SampleCountFlags,
     ) -> crate::Result<Self> {
         let surface_extent = surface_info.surface_extent()?;
 
         Self::create(
             logical_device,
             allocator,
-            Format::D16_UNORM,
+            format,
             surface_extent,
-            SampleCountFlags::TYPE_1,
-            Self::create_default_subresource_range(),
+            samples,
+            Self::create_subresource_range_for_format(format),
         )
     }
 
+    pub fn has_stencil(&self) -> bool {
+        format_aspect_mask(self.format).contains(ImageAspectFlags::STENCIL)
+    }
+
+    /// This function returns the subresource range that covers all layers of the image.
+    pub fn subresource_range(&self) -> ImageSubresourceRange {
+        ImageSubresourceRange {
+            layer_count: self.array_layers,
+            ..Self::create_subresource_range_for_format(self.format)
+        }
+    }
+
+    /// This function returns the subresource range of a single layer of the image.
+    pub fn layer_subresource_range(&self, layer: u32) -> ImageSubresourceRange {
+        ImageSubresourceRange {
+            base_array_layer: layer,
+            ..Self::create_subresource_range_for_format(self.format)
+        }
+    }
+
     pub fn destroy(
         &mut self,
         logical_device: &SetUpLogicalDevice,
@@ -100,3 +202,24 @@ impl SetUpDepthImage {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn depth_stencil_formats_have_both_aspects() {
+        assert_eq!(
+            ImageAspectFlags::DEPTH,
+            format_aspect_mask(Format::D32_SFLOAT)
+        );
+        assert_eq!(
+            ImageAspectFlags::DEPTH | ImageAspectFlags::STENCIL,
+            format_aspect_mask(Format::D24_UNORM_S8_UINT)
+        );
+        assert_eq!(
+            ImageAspectFlags::COLOR,
+            format_aspect_mask(Format::R8G8B8A8_UNORM)
+        );
+    }
+}
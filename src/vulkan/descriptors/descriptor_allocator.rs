@@ -0,0 +1,257 @@
+//! This is a module that contains the `DescriptorAllocator` structure, which allocates descriptor
+//! sets from a growing list of descriptor pools.
+//!
+//! Instead of predicting the exact pool sizes of every set (like with the
+//! `DescriptorSetLogicBuilder`), the pools are sized with ratios of descriptors per set. When a
+//! pool runs out of memory, it's marked as full and a new, larger pool is created. Resetting the
+//! allocator resets all pools at once, which frees all sets that were allocated from it. Every
+//! `FrameData` has its own allocator that is reset once the fence of the frame was waited for,
+//! so sets that are only used in one frame can be allocated every frame.
+
+use ash::vk::DescriptorSetVariableDescriptorCountAllocateInfo;
+use ash::vk::{DescriptorPool, DescriptorPoolCreateFlags, DescriptorPoolCreateInfo};
+use ash::vk::{DescriptorPoolResetFlags, DescriptorPoolSize, DescriptorType};
+use ash::vk::{DescriptorSet, DescriptorSetAllocateInfo, DescriptorSetLayout};
+
+use crate::vulkan::logical_device::SetUpLogicalDevice;
+
+/// The upper bound of the number of sets of a single pool
+pub const MAX_SETS_PER_POOL: u32 = 4096;
+
+/// The number of descriptors of a type that a pool contains for each set
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PoolSizeRatio {
+    pub descriptor_type: DescriptorType,
+    pub ratio: f32,
+}
+
+impl PoolSizeRatio {
+    pub fn new(descriptor_type: DescriptorType, ratio: f32) -> Self {
+        Self {
+            descriptor_type,
+            ratio,
+        }
+    }
+}
+
+/// The ratios that are used by `DescriptorAllocator::default`
+pub const DEFAULT_POOL_SIZE_RATIOS: &[PoolSizeRatio] = &[
+    PoolSizeRatio {
+        descriptor_type: DescriptorType::UNIFORM_BUFFER,
+        ratio: 2.0,
+    },
+    PoolSizeRatio {
+        descriptor_type: DescriptorType::UNIFORM_BUFFER_DYNAMIC,
+        ratio: 2.0,
+    },
+    PoolSizeRatio {
+        descriptor_type: DescriptorType::STORAGE_BUFFER,
+        ratio: 2.0,
+    },
+    PoolSizeRatio {
+        descriptor_type: DescriptorType::STORAGE_BUFFER_DYNAMIC,
+        ratio: 1.0,
+    },
+    PoolSizeRatio {
+        descriptor_type: DescriptorType::COMBINED_IMAGE_SAMPLER,
+        ratio: 4.0,
+    },
+    PoolSizeRatio {
+        descriptor_type: DescriptorType::STORAGE_IMAGE,
+        ratio: 1.0,
+    },
+];
+
+/// This function returns the pool sizes of a pool with `set_count` sets. Every type gets at
+/// least one descriptor.
+pub fn pool_sizes_for_ratios(ratios: &[PoolSizeRatio], set_count: u32) -> Vec<DescriptorPoolSize> {
+    ratios
+        .iter()
+        .map(|ratio| {
+            let descriptor_count = (ratio.ratio * set_count as f32).ceil().max(1.0) as u32;
+            DescriptorPoolSize::builder()
+                .ty(ratio.descriptor_type)
+                .descriptor_count(descriptor_count)
+                .build()
+        })
+        .collect()
+}
+
+/// This function returns the number of sets of the pool that is created after a pool with
+/// `sets_per_pool` sets ran out of memory.
+pub fn grow_sets_per_pool(sets_per_pool: u32) -> u32 {
+    sets_per_pool
+        .saturating_add(sets_per_pool / 2)
+        .clamp(1, MAX_SETS_PER_POOL)
+}
+
+pub struct DescriptorAllocator {
+    ratios: Vec<PoolSizeRatio>,
+    pool_flags: DescriptorPoolCreateFlags,
+    sets_per_pool: u32,
+    ready_pools: Vec<DescriptorPool>,
+    full_pools: Vec<DescriptorPool>,
+}
+
+impl Default for DescriptorAllocator {
+    fn default() -> Self {
+        Self::new(64, DEFAULT_POOL_SIZE_RATIOS)
+    }
+}
+
+impl DescriptorAllocator {
+    /// This function creates an allocator whose first pool has `initial_sets_per_pool` sets.
+    /// No pool is created before the first allocation.
+    pub fn new(initial_sets_per_pool: u32, ratios: &[PoolSizeRatio]) -> Self {
+        Self {
+            ratios: ratios.to_vec(),
+            pool_flags: DescriptorPoolCreateFlags::empty(),
+            sets_per_pool: initial_sets_per_pool.clamp(1, MAX_SETS_PER_POOL),
+            ready_pools: vec![],
+            full_pools: vec![],
+        }
+    }
+
+    /// This function sets the creation flags of the pools, e.g. `UPDATE_AFTER_BIND` for sets
+    /// with bindless bindings. It only affects pools that are created afterwards.
+    pub fn pool_flags(mut self, pool_flags: DescriptorPoolCreateFlags) -> Self {
+        self.pool_flags = pool_flags;
+        self
+    }
+
+    unsafe fn create_pool(
+        &self,
+        logical_device: &SetUpLogicalDevice,
+        set_count: u32,
+    ) -> crate::Result<DescriptorPool> {
+        let pool_sizes = pool_sizes_for_ratios(&self.ratios, set_count);
+        let descriptor_pool_create_info = DescriptorPoolCreateInfo::builder()
+            .flags(self.pool_flags)
+            .max_sets(set_count)
+            .pool_sizes(&pool_sizes);
+        Ok(logical_device.create_descriptor_pool(&descriptor_pool_create_info, None)?)
+    }
+
+    unsafe fn get_pool(
+        &mut self,
+        logical_device: &SetUpLogicalDevice,
+    ) -> crate::Result<DescriptorPool> {
+        if let Some(pool) = self.ready_pools.pop() {
+            return Ok(pool);
+        }
+
+        let pool = self.create_pool(logical_device, self.sets_per_pool)?;
+        self.sets_per_pool = grow_sets_per_pool(self.sets_per_pool);
+        Ok(pool)
+    }
+
+    unsafe fn try_allocate(
+        logical_device: &SetUpLogicalDevice,
+        pool: DescriptorPool,
+        layout: DescriptorSetLayout,
+        variable_descriptor_count: Option<u32>,
+    ) -> ash::prelude::VkResult<DescriptorSet> {
+        let descriptor_counts = variable_descriptor_count.as_slice();
+        let mut variable_descriptor_count_allocate_info =
+            DescriptorSetVariableDescriptorCountAllocateInfo::builder()
+                .descriptor_counts(descriptor_counts);
+        let mut descriptor_set_allocate_info = DescriptorSetAllocateInfo::builder()
+            .descriptor_pool(pool)
+            .set_layouts(std::slice::from_ref(&layout));
+        if variable_descriptor_count.is_some() {
+            descriptor_set_allocate_info = descriptor_set_allocate_info
+                .push_next(&mut variable_descriptor_count_allocate_info);
+        }
+
+        let descriptor_sets =
+            logical_device.allocate_descriptor_sets(&descriptor_set_allocate_info)?;
+        Ok(descriptor_sets[0])
+    }
+
+    /// This function allocates a set with the `layout`. If the current pool is full, the set is
+    /// allocated from a new pool.
+    pub fn allocate(
+        &mut self,
+        logical_device: &SetUpLogicalDevice,
+        layout: DescriptorSetLayout,
+    ) -> crate::Result<DescriptorSet> {
+        self.allocate_with_variable_count(logical_device, layout, None)
+    }
+
+    /// This function allocates a set with the `layout` like `allocate`. The
+    /// `variable_descriptor_count` is the descriptor count of the variable-sized binding of the
+    /// layout (if it has one).
+    pub fn allocate_with_variable_count(
+        &mut self,
+        logical_device: &SetUpLogicalDevice,
+        layout: DescriptorSetLayout,
+        variable_descriptor_count: Option<u32>,
+    ) -> crate::Result<DescriptorSet> {
+        unsafe {
+            let pool = self.get_pool(logical_device)?;
+            let result =
+                Self::try_allocate(logical_device, pool, layout, variable_descriptor_count);
+
+            let (pool, result) = match result {
+                Err(ash::vk::Result::ERROR_OUT_OF_POOL_MEMORY)
+                | Err(ash::vk::Result::ERROR_FRAGMENTED_POOL) => {
+                    self.full_pools.push(pool);
+
+                    let pool = self.get_pool(logical_device)?;
+                    let result =
+                        Self::try_allocate(logical_device, pool, layout, variable_descriptor_count);
+                    (pool, result)
+                }
+                result => (pool, result),
+            };
+
+            self.ready_pools.push(pool);
+            Ok(result?)
+        }
+    }
+
+    /// This function resets all pools, which frees all sets that were allocated. The sets must
+    /// not be used by pending commands anymore.
+    pub fn reset_pools(&mut self, logical_device: &SetUpLogicalDevice) -> crate::Result<()> {
+        self.ready_pools.append(&mut self.full_pools);
+        for pool in self.ready_pools.iter() {
+            unsafe {
+                logical_device.reset_descriptor_pool(*pool, DescriptorPoolResetFlags::empty())?;
+            }
+        }
+        Ok(())
+    }
+
+    /// This function returns the number of pools that were created.
+    pub fn pool_count(&self) -> usize {
+        self.ready_pools.len() + self.full_pools.len()
+    }
+
+    pub fn destroy(&mut self, logical_device: &SetUpLogicalDevice) {
+        for pool in self.ready_pools.drain(..).chain(self.full_pools.drain(..)) {
+            unsafe {
+                logical_device.destroy_descriptor_pool(pool, None);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pools_are_sized_by_ratio_and_grow() {
+        let ratios = [
+            PoolSizeRatio::new(DescriptorType::UNIFORM_BUFFER, 1.5),
+            PoolSizeRatio::new(DescriptorType::STORAGE_IMAGE, 0.0),
+        ];
+        let pool_sizes = pool_sizes_for_ratios(&ratios, 10);
+        assert_eq!(15, pool_sizes[0].descriptor_count);
+        assert_eq!(1, pool_sizes[1].descriptor_count);
+
+        assert_eq!(96, grow_sets_per_pool(64));
+        assert_eq!(MAX_SETS_PER_POOL, grow_sets_per_pool(4000));
+        assert_eq!(1, grow_sets_per_pool(0));
+    }
+}
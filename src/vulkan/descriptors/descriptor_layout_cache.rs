@@ -0,0 +1,139 @@
+//! This is a module that contains the `DescriptorLayoutCache` structure, which deduplicates
+//! descriptor set layouts.
+//!
+//! Layouts are keyed by the description of their bindings, so building the same layout twice
+//! (e.g. once per pass or per material) returns the same `DescriptorSetLayout`. The cache owns
+//! the layouts and destroys them all at once.
+
+use std::collections::HashMap;
+
+use ash::vk::{DescriptorBindingFlags, DescriptorSetLayout, DescriptorSetLayoutBinding};
+use ash::vk::{DescriptorType, ShaderStageFlags};
+
+use crate::vulkan::logical_device::SetUpLogicalDevice;
+
+use super::descriptor_set_layout::SetUpDescriptorSetLayout;
+use super::descriptor_set_layout_builder::DescriptorSetLayoutBuilder;
+
+/// The description of a single binding of a layout
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct DescriptorBindingKey {
+    pub binding: u32,
+    pub descriptor_type: DescriptorType,
+    pub descriptor_count: u32,
+    pub stage_flags: ShaderStageFlags,
+    pub binding_flags: DescriptorBindingFlags,
+}
+
+/// The description of all bindings of a layout, sorted by binding number
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct DescriptorLayoutKey {
+    bindings: Vec<DescriptorBindingKey>,
+}
+
+impl DescriptorLayoutKey {
+    /// This function creates the key of a layout with the `bindings`, whose flags are the
+    /// `binding_flags` at the same index.
+    pub fn new(
+        bindings: &[DescriptorSetLayoutBinding],
+        binding_flags: &[DescriptorBindingFlags],
+    ) -> Self {
+        let mut bindings = bindings
+            .iter()
+            .enumerate()
+            .map(|(index, binding)| DescriptorBindingKey {
+                binding: binding.binding,
+                descriptor_type: binding.descriptor_type,
+                descriptor_count: binding.descriptor_count,
+                stage_flags: binding.stage_flags,
+                binding_flags: binding_flags.get(index).copied().unwrap_or_default(),
+            })
+            .collect::<Vec<_>>();
+        bindings.sort_by_key(|binding| binding.binding);
+        Self { bindings }
+    }
+
+    pub fn bindings(&self) -> &[DescriptorBindingKey] {
+        &self.bindings
+    }
+}
+
+#[derive(Default)]
+pub struct DescriptorLayoutCache {
+    layouts: HashMap<DescriptorLayoutKey, SetUpDescriptorSetLayout>,
+}
+
+impl DescriptorLayoutCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// This function returns the cached layout with the bindings of the `builder`, or builds
+    /// and caches it if there is none yet. The layout is owned by the cache and must not be
+    /// destroyed manually.
+    pub fn get_or_create(
+        &mut self,
+        logical_device: &SetUpLogicalDevice,
+        builder: DescriptorSetLayoutBuilder,
+    ) -> crate::Result<DescriptorSetLayout> {
+        let key = builder.layout_key();
+        if let Some(layout) = self.layouts.get(&key) {
+            return Ok(layout.descriptor_set_layout);
+        }
+
+        let layout = builder.build(logical_device)?;
+        let descriptor_set_layout = layout.descriptor_set_layout;
+        self.layouts.insert(key, layout);
+        Ok(descriptor_set_layout)
+    }
+
+    pub fn len(&self) -> usize {
+        self.layouts.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.layouts.is_empty()
+    }
+
+    pub fn destroy(&mut self, logical_device: &SetUpLogicalDevice) {
+        for (_, mut layout) in self.layouts.drain() {
+            layout.destroy(logical_device);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn layout_keys_ignore_the_binding_order() {
+        let uniform = DescriptorSetLayoutBinding::builder()
+            .binding(0)
+            .descriptor_count(1)
+            .descriptor_type(DescriptorType::UNIFORM_BUFFER)
+            .stage_flags(ShaderStageFlags::VERTEX)
+            .build();
+        let texture = DescriptorSetLayoutBinding::builder()
+            .binding(1)
+            .descriptor_count(1)
+            .descriptor_type(DescriptorType::COMBINED_IMAGE_SAMPLER)
+            .stage_flags(ShaderStageFlags::FRAGMENT)
+            .build();
+
+        assert_eq!(
+            DescriptorLayoutKey::new(&[uniform, texture], &[]),
+            DescriptorLayoutKey::new(&[texture, uniform], &[])
+        );
+        assert_ne!(
+            DescriptorLayoutKey::new(&[uniform, texture], &[]),
+            DescriptorLayoutKey::new(
+                &[uniform, texture],
+                &[
+                    DescriptorBindingFlags::empty(),
+                    DescriptorBindingFlags::PARTIALLY_BOUND
+                ]
+            )
+        );
+    }
+}
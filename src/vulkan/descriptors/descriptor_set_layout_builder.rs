@@ -1,16 +1,55 @@
-use ash::vk::DescriptorType;
 use ash::vk::ShaderStageFlags;
-use ash::vk::{
-    DescriptorSetLayoutBinding, DescriptorSetLayoutCreateFlags, DescriptorSetLayoutCreateInfo,
-};
+use ash::vk::{DescriptorBindingFlags, DescriptorSetLayoutBindingFlagsCreateInfo};
+use ash::vk::{DescriptorSetLayout, DescriptorSetLayoutBinding, DescriptorType};
+use ash::vk::{DescriptorSetLayoutCreateFlags, DescriptorSetLayoutCreateInfo};
 
 use crate::vulkan::logical_device::SetUpLogicalDevice;
 
+use super::descriptor_layout_cache::{DescriptorLayoutCache, DescriptorLayoutKey};
 use super::descriptor_set_layout::SetUpDescriptorSetLayout;
 
+/// The binding flags of a bindless descriptor array: The array may contain invalid descriptors
+/// that are not accessed, its size is chosen when the descriptor set is allocated, and unused
+/// descriptors can be written after the set was bound.
+pub const BINDLESS_BINDING_FLAGS: DescriptorBindingFlags = DescriptorBindingFlags::from_raw(
+    DescriptorBindingFlags::PARTIALLY_BOUND.as_raw()
+        | DescriptorBindingFlags::VARIABLE_DESCRIPTOR_COUNT.as_raw()
+        | DescriptorBindingFlags::UPDATE_AFTER_BIND.as_raw()
+        | DescriptorBindingFlags::UPDATE_UNUSED_WHILE_PENDING.as_raw(),
+);
+
+/// This function checks that only the binding with the highest binding number has the
+/// `VARIABLE_DESCRIPTOR_COUNT` flag and returns the layout creation flags that are required by
+/// the `binding_flags`.
+pub fn layout_flags_for_binding_flags(
+    bindings: &[DescriptorSetLayoutBinding],
+    binding_flags: &[DescriptorBindingFlags],
+) -> crate::Result<DescriptorSetLayoutCreateFlags> {
+    let highest_binding = bindings.iter().map(|binding| binding.binding).max();
+    for (binding, flags) in bindings.iter().zip(binding_flags.iter()) {
+        if flags.contains(DescriptorBindingFlags::VARIABLE_DESCRIPTOR_COUNT) {
+            crate::verify!(
+                Some(binding.binding) == highest_binding,
+                "Only the binding with the highest binding number can have a variable descriptor count, got binding {}",
+                (binding.binding)
+            );
+        }
+    }
+
+    let update_after_bind = binding_flags
+        .iter()
+        .any(|flags| flags.contains(DescriptorBindingFlags::UPDATE_AFTER_BIND));
+    if update_after_bind {
+        Ok(DescriptorSetLayoutCreateFlags::UPDATE_AFTER_BIND_POOL)
+    } else {
+        Ok(DescriptorSetLayoutCreateFlags::empty())
+    }
+}
+
 #[derive(Default)]
 pub struct DescriptorSetLayoutBuilder {
     bindings: Vec<DescriptorSetLayoutBinding>,
+    binding_flags: Vec<DescriptorBindingFlags>,
 }
 
 impl DescriptorSetLayoutBuilder {
@@ -19,11 +58,36 @@ impl DescriptorSetLayoutBuilder {
     }
 
     pub fn add_binding(
+        self,
+        binding: u32,
+        descriptor_count: u32,
+        descriptor_type: DescriptorType,
+        stage_flags: ShaderStageFlags,
+    ) -> Self {
+        self.add_binding_with_flags(
+            binding,
+            descriptor_count,
+            descriptor_type,
+            stage_flags,
+            DescriptorBindingFlags::empty(),
+        )
+    }
+
+    /// This function adds a binding with the given `binding_flags`, which require the
+    /// `descriptor_indexing` features (see `DeviceRequirements::descriptor_indexing`).
+    ///
+    /// If the flags contain `VARIABLE_DESCRIPTOR_COUNT`, the `descriptor_count` is the upper
+    /// bound of the count that is chosen when the set is allocated (see
+    /// `DescriptorSetLogicBuilder::variable_descriptor_counts`). If they contain
+    /// `UPDATE_AFTER_BIND`, the set has to be allocated from an update-after-bind pool (see
+    /// `DescriptorSetLogicBuilder::update_after_bind`).
+    pub fn add_binding_with_flags(
         mut self,
         binding: u32,
         descriptor_count: u32,
         descriptor_type: DescriptorType,
         stage_flags: ShaderStageFlags,
+        binding_flags: DescriptorBindingFlags,
     ) -> Self {
         let descriptor_set_layout_binding = DescriptorSetLayoutBinding::builder()
             .binding(binding)
@@ -32,22 +96,100 @@ impl DescriptorSetLayoutBuilder {
             .stage_flags(stage_flags)
             .build();
         self.bindings.push(descriptor_set_layout_binding);
+        self.binding_flags.push(binding_flags);
         self
     }
 
+    /// This function adds a bindless array of up to `max_descriptor_count` descriptors, e.g.
+    /// of textures or storage buffers that are indexed by an ID in the shader. The binding
+    /// must have the highest binding number of the layout.
+    pub fn add_bindless_binding(
+        self,
+        binding: u32,
+        max_descriptor_count: u32,
+        descriptor_type: DescriptorType,
+        stage_flags: ShaderStageFlags,
+    ) -> Self {
+        self.add_binding_with_flags(
+            binding,
+            max_descriptor_count,
+            descriptor_type,
+            stage_flags,
+            BINDLESS_BINDING_FLAGS,
+        )
+    }
+
+    /// This function returns the key of the layout in a `DescriptorLayoutCache`.
+    pub fn layout_key(&self) -> DescriptorLayoutKey {
+        DescriptorLayoutKey::new(&self.bindings, &self.binding_flags)
+    }
+
+    /// This function returns the layout from the `cache`, which only builds it if no layout
+    /// with the same bindings was built before.
+    pub fn build_cached(
+        self,
+        logical_device: &SetUpLogicalDevice,
+        cache: &mut DescriptorLayoutCache,
+    ) -> crate::Result<DescriptorSetLayout> {
+        cache.get_or_create(logical_device, self)
+    }
+
     pub fn build(
         self,
         logical_device: &SetUpLogicalDevice,
     ) -> crate::Result<SetUpDescriptorSetLayout> {
+        let flags = layout_flags_for_binding_flags(&self.bindings, &self.binding_flags)?;
+        let has_binding_flags = self.binding_flags.iter().any(|flags| !flags.is_empty());
+
         unsafe {
-            let descriptor_set_layout_create_info = DescriptorSetLayoutCreateInfo::builder()
-                .flags(DescriptorSetLayoutCreateFlags::empty())
-                .bindings(&self.bindings)
-                .build();
+            let mut binding_flags_create_info =
+                DescriptorSetLayoutBindingFlagsCreateInfo::builder()
+                    .binding_flags(&self.binding_flags);
+            let mut descriptor_set_layout_create_info = DescriptorSetLayoutCreateInfo::builder()
+                .flags(flags)
+                .bindings(&self.bindings);
+            if has_binding_flags {
+                descriptor_set_layout_create_info =
+                    descriptor_set_layout_create_info.push_next(&mut binding_flags_create_info);
+            }
             SetUpDescriptorSetLayout::create_from_build_info(
                 logical_device,
-                descriptor_set_layout_create_info,
+                *descriptor_set_layout_create_info,
             )
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn binding_flags_determine_layout_flags() {
+        let builder = DescriptorSetLayoutBuilder::new()
+            .add_binding(
+                0,
+                1,
+                DescriptorType::UNIFORM_BUFFER,
+                ShaderStageFlags::VERTEX,
+            )
+            .add_bindless_binding(
+                1,
+                1024,
+                DescriptorType::COMBINED_IMAGE_SAMPLER,
+                ShaderStageFlags::FRAGMENT,
+            );
+        assert_eq!(
+            DescriptorSetLayoutCreateFlags::UPDATE_AFTER_BIND_POOL,
+            layout_flags_for_binding_flags(&builder.bindings, &builder.binding_flags).unwrap()
+        );
+
+        let builder = builder.add_binding(
+            2,
+            1,
+            DescriptorType::STORAGE_BUFFER,
+            ShaderStageFlags::FRAGMENT,
+        );
+        assert!(layout_flags_for_binding_flags(&builder.bindings, &builder.binding_flags).is_err());
+    }
+}
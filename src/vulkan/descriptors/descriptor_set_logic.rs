@@ -1,5 +1,6 @@
 use ash::vk::{DescriptorPool, DescriptorPoolCreateInfo, DescriptorSetLayout};
 use ash::vk::{DescriptorSet, DescriptorSetAllocateInfo};
+use ash::vk::DescriptorSetVariableDescriptorCountAllocateInfo;
 
 use crate::vulkan::logical_device::SetUpLogicalDevice;
 
@@ -9,17 +10,28 @@ pub struct SetUpDescriptorSetLogic {
 }
 
 impl SetUpDescriptorSetLogic {
+    /// This function creates the descriptor pool and allocates one set for each of the
+    /// `set_layouts`. The `variable_descriptor_counts` contain the descriptor counts of the
+    /// variable-sized bindings of the layouts (if any).
     pub unsafe fn create(
         logical_device: &SetUpLogicalDevice,
         descriptor_pool_create_info: DescriptorPoolCreateInfo,
         set_layouts: &[DescriptorSetLayout],
+        variable_descriptor_counts: Option<&[u32]>,
     ) -> crate::Result<Self> {
         let descriptor_pool =
             logical_device.create_descriptor_pool(&descriptor_pool_create_info, None)?;
 
-        let descriptor_set_allocate_info = DescriptorSetAllocateInfo::builder()
+        let mut variable_descriptor_count_allocate_info =
+            DescriptorSetVariableDescriptorCountAllocateInfo::builder()
+                .descriptor_counts(variable_descriptor_counts.unwrap_or(&[]));
+        let mut descriptor_set_allocate_info = DescriptorSetAllocateInfo::builder()
             .descriptor_pool(descriptor_pool)
             .set_layouts(&set_layouts);
+        if variable_descriptor_counts.is_some() {
+            descriptor_set_allocate_info =
+                descriptor_set_allocate_info.push_next(&mut variable_descriptor_count_allocate_info);
+        }
 
         let descriptor_sets =
             logical_device.allocate_descriptor_sets(&descriptor_set_allocate_info)?;
@@ -11,6 +11,8 @@ use super::descriptor_set_logic::SetUpDescriptorSetLogic;
 pub struct DescriptorSetLogicBuilder<'builder> {
     pool_sizes: Vec<DescriptorPoolSize>,
     set_layouts: Option<&'builder [SetUpDescriptorSetLayout]>,
+    pool_flags: DescriptorPoolCreateFlags,
+    variable_descriptor_counts: Option<&'builder [u32]>,
 }
 
 impl<'builder> DescriptorSetLogicBuilder<'builder> {
@@ -32,6 +34,20 @@ impl<'builder> DescriptorSetLogicBuilder<'builder> {
         self
     }
 
+    /// This function allows the allocation of sets with layouts that contain
+    /// `UPDATE_AFTER_BIND` bindings (see `DescriptorSetLayoutBuilder::add_bindless_binding`).
+    pub fn update_after_bind(mut self) -> Self {
+        self.pool_flags |= DescriptorPoolCreateFlags::UPDATE_AFTER_BIND;
+        self
+    }
+
+    /// This function sets the descriptor counts of the variable-sized bindings, one count for
+    /// each set layout. Counts of layouts without such a binding are ignored.
+    pub fn variable_descriptor_counts(mut self, counts: &'builder [u32]) -> Self {
+        self.variable_descriptor_counts = Some(counts);
+        self
+    }
+
     pub fn build(
         self,
         logical_device: &SetUpLogicalDevice,
@@ -41,8 +57,17 @@ impl<'builder> DescriptorSetLogicBuilder<'builder> {
                 .set_layouts
                 .context("Descriptor set layouts must be set".to_string())?;
 
+            if let Some(counts) = self.variable_descriptor_counts {
+                crate::verify!(
+                    counts.len() == set_layouts.len(),
+                    "Expected one variable descriptor count for each of the {} set layouts, got {}",
+                    (set_layouts.len()),
+                    (counts.len())
+                );
+            }
+
             let descriptor_pool_create_info = DescriptorPoolCreateInfo::builder()
-                .flags(DescriptorPoolCreateFlags::empty())
+                .flags(self.pool_flags)
                 .max_sets(set_layouts.len() as u32)
                 .pool_sizes(&self.pool_sizes);
 
@@ -57,6 +82,7 @@ impl<'builder> DescriptorSetLogicBuilder<'builder> {
                 std::mem::transmute::<&[SetUpDescriptorSetLayout], &[DescriptorSetLayout]>(
                     set_layouts,
                 ),
+                self.variable_descriptor_counts,
             )
         }
     }
@@ -5,6 +5,7 @@ use ash::vk::WriteDescriptorSet;
 use ash::vk::{DescriptorImageInfo, ImageLayout};
 
 use crate::vulkan::buffers::buffer::AllocatedBuffer;
+use crate::vulkan::buffers::ring_buffer::SetUpRingBuffer;
 use crate::vulkan::buffers::storage_buffer::SetUpStorageBuffer;
 use crate::vulkan::buffers::typed_buffer::TypedAllocatedBuffer;
 use crate::vulkan::buffers::uniform_buffer::SetUpUniformBuffer;
@@ -17,6 +18,7 @@ use crate::vulkan::logical_device::SetUpLogicalDevice;
 pub struct WriteBufferDescriptorSetParams {
     buffer_info: DescriptorBufferInfo,
     destination_binding: u32,
+    destination_array_element: u32,
     descriptor_type: DescriptorType,
 }
 
@@ -24,6 +26,7 @@ pub struct WriteBufferDescriptorSetParams {
 pub struct WriteImageDescriptorSetParams {
     image_info: DescriptorImageInfo,
     destination_binding: u32,
+    destination_array_element: u32,
     descriptor_type: DescriptorType,
 }
 
@@ -39,9 +42,30 @@ impl DescriptorSetUpdateBuilder {
     }
 
     pub fn add_buffer_descriptor(
+        self,
+        buffer: &AllocatedBuffer,
+        destination_binding: u32,
+        descriptor_type: DescriptorType,
+        offset: usize,
+        range: usize,
+    ) -> Self {
+        self.add_buffer_descriptor_at(
+            buffer,
+            destination_binding,
+            0,
+            descriptor_type,
+            offset,
+            range,
+        )
+    }
+
+    /// This function writes the buffer descriptor to the element `destination_array_element`
+    /// of an array binding, e.g. of a bindless storage buffer array.
+    pub fn add_buffer_descriptor_at(
         mut self,
         buffer: &AllocatedBuffer,
         destination_binding: u32,
+        destination_array_element: u32,
         descriptor_type: DescriptorType,
         offset: usize,
         range: usize,
@@ -55,6 +79,7 @@ impl DescriptorSetUpdateBuilder {
             .push(WriteBufferDescriptorSetParams {
                 buffer_info: *descriptor_buffer_info,
                 destination_binding,
+                destination_array_element,
                 descriptor_type,
             });
 
@@ -111,11 +136,49 @@ impl DescriptorSetUpdateBuilder {
         self.add_buffer_descriptor(&buffer, destination_binding, descriptor_type, 0, range)
     }
 
+    /// This function adds a dynamic descriptor for a ring buffer. The descriptor covers `range`
+    /// bytes, which is the size of the data that is bound through it, and the offset of the
+    /// data is passed as a dynamic offset (see `RingAllocation::dynamic_offset`).
+    pub fn add_ring_buffer_descriptor(
+        self,
+        ring_buffer: &SetUpRingBuffer,
+        destination_binding: u32,
+        descriptor_type: DescriptorType,
+        range: usize,
+    ) -> Self {
+        self.add_buffer_descriptor(
+            &ring_buffer.buffer,
+            destination_binding,
+            descriptor_type,
+            0,
+            range,
+        )
+    }
+
     pub fn add_image_descriptor(
+        self,
+        sampler: &SetUpSampler,
+        image_view: &SetUpImageView,
+        destination_binding: u32,
+        descriptor_type: DescriptorType,
+    ) -> Self {
+        self.add_image_descriptor_at(
+            sampler,
+            image_view,
+            destination_binding,
+            0,
+            descriptor_type,
+        )
+    }
+
+    /// This function writes the image descriptor to the element `destination_array_element`
+    /// of an array binding, e.g. of a bindless texture array.
+    pub fn add_image_descriptor_at(
         mut self,
         sampler: &SetUpSampler,
         image_view: &SetUpImageView,
         destination_binding: u32,
+        destination_array_element: u32,
         descriptor_type: DescriptorType,
     ) -> Self {
         let descriptor_image_info = DescriptorImageInfo::builder()
@@ -126,6 +189,7 @@ impl DescriptorSetUpdateBuilder {
         self.image_write_params.push(WriteImageDescriptorSetParams {
             image_info: *descriptor_image_info,
             destination_binding,
+            destination_array_element,
             descriptor_type,
         });
 
@@ -147,12 +211,30 @@ impl DescriptorSetUpdateBuilder {
         )
     }
 
+    pub fn add_texture_descriptor_at<T>(
+        self,
+        sampler: &SetUpSampler,
+        texture: &Texture<T>,
+        destination_binding: u32,
+        destination_array_element: u32,
+        descriptor_type: DescriptorType,
+    ) -> Self {
+        self.add_image_descriptor_at(
+            sampler,
+            &texture.image_view,
+            destination_binding,
+            destination_array_element,
+            descriptor_type,
+        )
+    }
+
     pub fn update(self, logical_device: &SetUpLogicalDevice, destination_set: &DescriptorSet) {
         unsafe {
             let mut writes = Vec::with_capacity(self.buffer_write_params.len());
             for buf_params in self.buffer_write_params.iter() {
                 let write = WriteDescriptorSet::builder()
                     .dst_binding(buf_params.destination_binding)
+                    .dst_array_element(buf_params.destination_array_element)
                     .dst_set(*destination_set)
                     .descriptor_type(buf_params.descriptor_type)
                     .buffer_info(std::slice::from_ref(&buf_params.buffer_info))
@@ -163,6 +245,7 @@ impl DescriptorSetUpdateBuilder {
             for img_params in self.image_write_params.iter() {
                 let write = WriteDescriptorSet::builder()
                     .dst_binding(img_params.destination_binding)
+                    .dst_array_element(img_params.destination_array_element)
                     .dst_set(*destination_set)
                     .descriptor_type(img_params.descriptor_type)
                     .image_info(std::slice::from_ref(&img_params.image_info))
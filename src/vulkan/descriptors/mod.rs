@@ -1,14 +1,20 @@
 //! This is a module that provides all descriptor-related functionality.
 //! There are abstractions for building descriptor sets, attaching descriptors to them etc.
 //!
+//! - descriptor\_allocator: Provides a `DescriptorAllocator` that allocates descriptor sets from growing pools which are reset per frame
+//! - descriptor\_layout\_cache: Provides a `DescriptorLayoutCache` that deduplicates descriptor set layouts with identical bindings
 //! - descriptor\_set\_layout: Provides a wrapper around `DescriptorSetLayout`s
 //! - descriptor\_set\_layout\_builder: Provides an abstraction for building `(SetUp)DescriptorSetLayout`s
 //! - descriptor\_set\_logic: Provides an abstraction for `DescriptorSet` allocation
 //! - descriptor\_set\_logic\_builder: Provides an abstraction for building `SetUpDescriptorSetLogic`s
 //! - descriptor\_set\_update\_builder: Provides an abstraction for updating descriptor sets and specifying attached descriptors
+//! - texture\_registry: Provides a bindless texture array whose elements are referenced by handles
 
+pub mod descriptor_allocator;
+pub mod descriptor_layout_cache;
 pub mod descriptor_set_layout;
 pub mod descriptor_set_layout_builder;
 pub mod descriptor_set_logic;
 pub mod descriptor_set_logic_builder;
 pub mod descriptor_set_update_builder;
+pub mod texture_registry;
@@ -0,0 +1,273 @@
+//! This is a module that contains the `SetUpTextureRegistry` structure, a bindless texture array
+//! whose elements are referenced by handles.
+//!
+//! The registry owns one descriptor set with a single variable-sized, partially bound array of
+//! combined image samplers. Registering a texture writes it to a free element of the array and
+//! returns a `TextureHandle`, whose index can be passed to shaders (e.g. as the ID of a block
+//! texture in vertex data or push constants) and used to index the array:
+//!
+//! ```glsl
+//! #extension GL_EXT_nonuniform_qualifier : require
+//! layout(set = 1, binding = 0) uniform sampler2D textures[];
+//! // ...
+//! vec4 color = texture(textures[nonuniformEXT(texture_id)], uv);
+//! ```
+//!
+//! The registry requires the descriptor indexing features (see
+//! `DeviceRequirements::descriptor_indexing`). Because the array is update-after-bind, textures
+//! can be registered while the set is bound by pending command buffers.
+
+use ash::vk::{DescriptorSet, DescriptorType, ShaderStageFlags};
+
+use crate::vulkan::image::image_view::SetUpImageView;
+use crate::vulkan::image::sampler::SetUpSampler;
+use crate::vulkan::image::texture::Texture;
+use crate::vulkan::logical_device::SetUpLogicalDevice;
+
+use super::descriptor_set_layout::SetUpDescriptorSetLayout;
+use super::descriptor_set_layout_builder::DescriptorSetLayoutBuilder;
+use super::descriptor_set_logic::SetUpDescriptorSetLogic;
+use super::descriptor_set_logic_builder::DescriptorSetLogicBuilder;
+use super::descriptor_set_update_builder::DescriptorSetUpdateBuilder;
+
+/// The binding of the texture array in the descriptor set of a `SetUpTextureRegistry`
+pub const TEXTURE_REGISTRY_BINDING: u32 = 0;
+
+/// A handle to a texture in a `SetUpTextureRegistry`
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct TextureHandle(u32);
+
+impl TextureHandle {
+    /// This function returns the index of the texture in the texture array of the registry.
+    pub fn index(&self) -> u32 {
+        self.0
+    }
+}
+
+/// An allocator for the indices of a fixed number of slots; freed indices are reused first
+#[derive(Clone, Debug, Default)]
+pub struct SlotAllocator {
+    capacity: u32,
+    next_unused: u32,
+    free: Vec<u32>,
+}
+
+impl SlotAllocator {
+    pub fn new(capacity: u32) -> Self {
+        Self {
+            capacity,
+            next_unused: 0,
+            free: vec![],
+        }
+    }
+
+    pub fn allocate(&mut self) -> Option<u32> {
+        if let Some(index) = self.free.pop() {
+            return Some(index);
+        }
+
+        if self.next_unused < self.capacity {
+            self.next_unused += 1;
+            Some(self.next_unused - 1)
+        } else {
+            None
+        }
+    }
+
+    pub fn is_allocated(&self, index: u32) -> bool {
+        index < self.next_unused && !self.free.contains(&index)
+    }
+
+    /// This function frees the `index`, returning false if it isn't allocated.
+    pub fn free(&mut self, index: u32) -> bool {
+        if !self.is_allocated(index) {
+            return false;
+        }
+        self.free.push(index);
+        true
+    }
+
+    pub fn len(&self) -> u32 {
+        self.next_unused - self.free.len() as u32
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn capacity(&self) -> u32 {
+        self.capacity
+    }
+}
+
+pub struct SetUpTextureRegistry {
+    pub descriptor_set_layout: SetUpDescriptorSetLayout,
+    pub descriptor_set_logic: SetUpDescriptorSetLogic,
+    slots: SlotAllocator,
+}
+
+impl SetUpTextureRegistry {
+    /// This function creates a registry for up to `capacity` textures that can be sampled in
+    /// the shader stages `stage_flags`.
+    pub unsafe fn create(
+        logical_device: &SetUpLogicalDevice,
+        capacity: u32,
+        stage_flags: ShaderStageFlags,
+    ) -> crate::Result<Self> {
+        crate::verify!(
+            capacity > 0,
+            "A texture registry needs a capacity of at least 1"
+        );
+
+        let descriptor_set_layout = DescriptorSetLayoutBuilder::new()
+            .add_bindless_binding(
+                TEXTURE_REGISTRY_BINDING,
+                capacity,
+                DescriptorType::COMBINED_IMAGE_SAMPLER,
+                stage_flags,
+            )
+            .build(logical_device)?;
+
+        let descriptor_set_logic = DescriptorSetLogicBuilder::new()
+            .add_pool_size(DescriptorType::COMBINED_IMAGE_SAMPLER, capacity)
+            .set_layouts(std::slice::from_ref(&descriptor_set_layout))
+            .variable_descriptor_counts(&[capacity])
+            .update_after_bind()
+            .build(logical_device);
+        let descriptor_set_logic = match descriptor_set_logic {
+            Ok(descriptor_set_logic) => descriptor_set_logic,
+            Err(err) => {
+                let mut descriptor_set_layout = descriptor_set_layout;
+                descriptor_set_layout.destroy(logical_device);
+                return Err(err);
+            }
+        };
+
+        Ok(Self {
+            descriptor_set_layout,
+            descriptor_set_logic,
+            slots: SlotAllocator::new(capacity),
+        })
+    }
+
+    /// This function writes the texture to a free element of the array and returns its handle.
+    /// The `sampler` and the `image_view` must not be destroyed while they are registered.
+    pub fn register(
+        &mut self,
+        logical_device: &SetUpLogicalDevice,
+        sampler: &SetUpSampler,
+        image_view: &SetUpImageView,
+    ) -> crate::Result<TextureHandle> {
+        let index = self.slots.allocate();
+        let index = match index {
+            Some(index) => index,
+            None => crate::bail!(
+                "The texture registry is full, all {} textures are used",
+                (self.slots.capacity())
+            ),
+        };
+
+        let handle = TextureHandle(index);
+        self.write(logical_device, handle, sampler, image_view);
+        Ok(handle)
+    }
+
+    pub fn register_texture<T>(
+        &mut self,
+        logical_device: &SetUpLogicalDevice,
+        sampler: &SetUpSampler,
+        texture: &Texture<T>,
+    ) -> crate::Result<TextureHandle> {
+        self.register(logical_device, sampler, &texture.image_view)
+    }
+
+    /// This function replaces the texture of the `handle`, e.g. after the texture was reloaded.
+    /// Commands that are still pending must not access the element anymore.
+    pub fn replace(
+        &mut self,
+        logical_device: &SetUpLogicalDevice,
+        handle: TextureHandle,
+        sampler: &SetUpSampler,
+        image_view: &SetUpImageView,
+    ) -> crate::Result<()> {
+        crate::verify!(
+            self.slots.is_allocated(handle.index()),
+            "The texture handle {} is not registered",
+            (handle.index())
+        );
+        self.write(logical_device, handle, sampler, image_view);
+        Ok(())
+    }
+
+    /// This function frees the `handle`, whose index is reused by the next `register` call.
+    /// Commands that are still pending must not access the element anymore.
+    pub fn unregister(&mut self, handle: TextureHandle) -> crate::Result<()> {
+        crate::verify!(
+            self.slots.free(handle.index()),
+            "The texture handle {} is not registered",
+            (handle.index())
+        );
+        Ok(())
+    }
+
+    fn write(
+        &self,
+        logical_device: &SetUpLogicalDevice,
+        handle: TextureHandle,
+        sampler: &SetUpSampler,
+        image_view: &SetUpImageView,
+    ) {
+        DescriptorSetUpdateBuilder::new()
+            .add_image_descriptor_at(
+                sampler,
+                image_view,
+                TEXTURE_REGISTRY_BINDING,
+                handle.index(),
+                DescriptorType::COMBINED_IMAGE_SAMPLER,
+            )
+            .update(logical_device, self.descriptor_set());
+    }
+
+    pub fn descriptor_set(&self) -> &DescriptorSet {
+        self.descriptor_set_logic.get_set(0)
+    }
+
+    /// This function returns the number of registered textures.
+    pub fn len(&self) -> u32 {
+        self.slots.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.slots.is_empty()
+    }
+
+    pub fn capacity(&self) -> u32 {
+        self.slots.capacity()
+    }
+
+    pub fn destroy(&mut self, logical_device: &SetUpLogicalDevice) {
+        self.descriptor_set_logic.destroy(logical_device);
+        self.descriptor_set_layout.destroy(logical_device);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn slot_allocator_reuses_freed_slots() {
+        let mut slots = SlotAllocator::new(3);
+        assert_eq!(Some(0), slots.allocate());
+        assert_eq!(Some(1), slots.allocate());
+        assert_eq!(Some(2), slots.allocate());
+        assert_eq!(None, slots.allocate());
+
+        assert!(slots.free(1));
+        assert!(!slots.free(1));
+        assert!(!slots.free(5));
+        assert_eq!(2, slots.len());
+        assert_eq!(Some(1), slots.allocate());
+        assert_eq!(3, slots.len());
+    }
+}
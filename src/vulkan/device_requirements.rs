@@ -0,0 +1,670 @@
+//! This is a module that contains the `DeviceRequirements` builder, which describes what a
+//! physical device must (and should) support in order to be selected.
+//!
+//! Devices that satisfy all requirements are ranked using a scoring callback. By default,
+//! discrete GPUs are preferred over integrated GPUs, and devices with more memory are preferred
+//! over devices with less memory.
+//!
+//! The selection can be overridden with the `VOXELAR_DEVICE` environment variable, which either
+//! contains the index of a device (as returned by `vkEnumeratePhysicalDevices`) or a part of
+//! its name (case-insensitive).
+
+use std::ffi::{CStr, CString};
+use std::sync::Arc;
+
+use ash::vk::{FALSE, TRUE};
+use ash::vk::{PhysicalDevice, PhysicalDeviceFeatures, PhysicalDeviceFeatures2};
+use ash::vk::{PhysicalDeviceLimits, PhysicalDeviceMemoryProperties, PhysicalDeviceProperties};
+use ash::vk::{PhysicalDeviceType, PhysicalDeviceVulkan12Features, PhysicalDeviceVulkan13Features};
+use ash::Instance;
+
+use super::render_info::format_api_version;
+
+/// The environment variable that is used to override the device selection.
+pub const DEVICE_OVERRIDE_ENV_VAR: &str = "VOXELAR_DEVICE";
+
+macro_rules! missing_features {
+    ($requested:expr, $supported:expr, $prefix:literal, [$($feature:ident),* $(,)?]) => {{
+        let mut missing = vec![];
+        $(
+            if $requested.$feature != FALSE && $supported.$feature == FALSE {
+                missing.push(concat!($prefix, stringify!($feature)));
+            }
+        )*
+        missing
+    }};
+}
+
+/// This function returns the names of all features that are requested, but not supported.
+pub fn missing_features(
+    requested: &PhysicalDeviceFeatures,
+    supported: &PhysicalDeviceFeatures,
+) -> Vec<&'static str> {
+    missing_features!(
+        requested,
+        supported,
+        "",
+        [
+            robust_buffer_access, full_draw_index_uint32, image_cube_array, independent_blend,
+            geometry_shader, tessellation_shader, sample_rate_shading, dual_src_blend, logic_op,
+            multi_draw_indirect, draw_indirect_first_instance, depth_clamp, depth_bias_clamp,
+            fill_mode_non_solid, depth_bounds, wide_lines, large_points, alpha_to_one,
+            multi_viewport, sampler_anisotropy, texture_compression_etc2,
+            texture_compression_astc_ldr, texture_compression_bc, occlusion_query_precise,
+            pipeline_statistics_query, vertex_pipeline_stores_and_atomics,
+            fragment_stores_and_atomics, shader_tessellation_and_geometry_point_size,
+            shader_image_gather_extended, shader_storage_image_extended_formats,
+            shader_storage_image_multisample, shader_storage_image_read_without_format,
+            shader_storage_image_write_without_format,
+            shader_uniform_buffer_array_dynamic_indexing,
+            shader_sampled_image_array_dynamic_indexing,
+            shader_storage_buffer_array_dynamic_indexing,
+            shader_storage_image_array_dynamic_indexing, shader_clip_distance,
+            shader_cull_distance, shader_float64, shader_int64, shader_int16,
+            shader_resource_residency, shader_resource_min_lod, sparse_binding,
+            sparse_residency_buffer, sparse_residency_image2_d, sparse_residency_image3_d,
+            sparse_residency2_samples, sparse_residency4_samples, sparse_residency8_samples,
+            sparse_residency16_samples, sparse_residency_aliased, variable_multisample_rate,
+            inherited_queries,
+        ]
+    )
+}
+
+/// This function returns the names of all Vulkan 1.2 features that are requested, but not
+/// supported.
+pub fn missing_features_12(
+    requested: &PhysicalDeviceVulkan12Features,
+    supported: &PhysicalDeviceVulkan12Features,
+) -> Vec<&'static str> {
+    missing_features!(
+        requested,
+        supported,
+        "vulkan12.",
+        [
+            sampler_mirror_clamp_to_edge, draw_indirect_count, storage_buffer8_bit_access,
+            uniform_and_storage_buffer8_bit_access, storage_push_constant8,
+            shader_buffer_int64_atomics, shader_shared_int64_atomics, shader_float16,
+            shader_int8, descriptor_indexing, shader_input_attachment_array_dynamic_indexing,
+            shader_uniform_texel_buffer_array_dynamic_indexing,
+            shader_storage_texel_buffer_array_dynamic_indexing,
+            shader_uniform_buffer_array_non_uniform_indexing,
+            shader_sampled_image_array_non_uniform_indexing,
+            shader_storage_buffer_array_non_uniform_indexing,
+            shader_storage_image_array_non_uniform_indexing,
+            shader_input_attachment_array_non_uniform_indexing,
+            shader_uniform_texel_buffer_array_non_uniform_indexing,
+            shader_storage_texel_buffer_array_non_uniform_indexing,
+            descriptor_binding_uniform_buffer_update_after_bind,
+            descriptor_binding_sampled_image_update_after_bind,
+            descriptor_binding_storage_image_update_after_bind,
+            descriptor_binding_storage_buffer_update_after_bind,
+            descriptor_binding_uniform_texel_buffer_update_after_bind,
+            descriptor_binding_storage_texel_buffer_update_after_bind,
+            descriptor_binding_update_unused_while_pending, descriptor_binding_partially_bound,
+            descriptor_binding_variable_descriptor_count, runtime_descriptor_array,
+            sampler_filter_minmax, scalar_block_layout, imageless_framebuffer,
+            uniform_buffer_standard_layout, shader_subgroup_extended_types,
+            separate_depth_stencil_layouts, host_query_reset, timeline_semaphore,
+            buffer_device_address, buffer_device_address_capture_replay,
+            buffer_device_address_multi_device, vulkan_memory_model,
+            vulkan_memory_model_device_scope, vulkan_memory_model_availability_visibility_chains,
+            shader_output_viewport_index, shader_output_layer, subgroup_broadcast_dynamic_id,
+        ]
+    )
+}
+
+/// This function returns the names of all Vulkan 1.3 features that are requested, but not
+/// supported.
+pub fn missing_features_13(
+    requested: &PhysicalDeviceVulkan13Features,
+    supported: &PhysicalDeviceVulkan13Features,
+) -> Vec<&'static str> {
+    missing_features!(
+        requested,
+        supported,
+        "vulkan13.",
+        [
+            robust_image_access, inline_uniform_block,
+            descriptor_binding_inline_uniform_block_update_after_bind,
+            pipeline_creation_cache_control, private_data, shader_demote_to_helper_invocation,
+            shader_terminate_invocation, subgroup_size_control, compute_full_subgroups,
+            synchronization2, texture_compression_astc_hdr,
+            shader_zero_initialize_workgroup_memory, dynamic_rendering,
+            shader_integer_dot_product, maintenance4,
+        ]
+    )
+}
+
+/// Minimum values for a subset of the `PhysicalDeviceLimits`. Limits that are `None` are not
+/// checked.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct DeviceLimitRequirements {
+    pub max_image_dimension_2d: Option<u32>,
+    pub max_image_array_layers: Option<u32>,
+    pub max_push_constants_size: Option<u32>,
+    pub max_bound_descriptor_sets: Option<u32>,
+    pub max_uniform_buffer_range: Option<u32>,
+    pub max_storage_buffer_range: Option<u32>,
+    pub max_per_stage_descriptor_samplers: Option<u32>,
+    pub max_per_stage_descriptor_sampled_images: Option<u32>,
+    pub max_descriptor_set_sampled_images: Option<u32>,
+    pub max_color_attachments: Option<u32>,
+    pub max_compute_work_group_invocations: Option<u32>,
+    pub max_sampler_anisotropy: Option<f32>,
+}
+
+macro_rules! unsatisfied_limits {
+    ($requirements:expr, $limits:expr, [$($name:ident => $limit:ident),* $(,)?]) => {{
+        let mut unsatisfied = vec![];
+        $(
+            if let Some(minimum) = $requirements.$name {
+                if $limits.$limit < minimum {
+                    unsatisfied.push(format!(
+                        "limit {} is {}, but at least {} is required",
+                        stringify!($name),
+                        $limits.$limit,
+                        minimum
+                    ));
+                }
+            }
+        )*
+        unsatisfied
+    }};
+}
+
+impl DeviceLimitRequirements {
+    /// This function returns a description of every limit that doesn't satisfy its minimum.
+    pub fn unsatisfied_limits(&self, limits: &PhysicalDeviceLimits) -> Vec<String> {
+        unsatisfied_limits!(
+            self,
+            limits,
+            [
+                max_image_dimension_2d => max_image_dimension2_d,
+                max_image_array_layers => max_image_array_layers,
+                max_push_constants_size => max_push_constants_size,
+                max_bound_descriptor_sets => max_bound_descriptor_sets,
+                max_uniform_buffer_range => max_uniform_buffer_range,
+                max_storage_buffer_range => max_storage_buffer_range,
+                max_per_stage_descriptor_samplers => max_per_stage_descriptor_samplers,
+                max_per_stage_descriptor_sampled_images => max_per_stage_descriptor_sampled_images,
+                max_descriptor_set_sampled_images => max_descriptor_set_sampled_images,
+                max_color_attachments => max_color_attachments,
+                max_compute_work_group_invocations => max_compute_work_group_invocations,
+                max_sampler_anisotropy => max_sampler_anisotropy,
+            ]
+        )
+    }
+}
+
+/// An explicit choice of a physical device, either by its index or by a part of its name.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DeviceOverride {
+    Index(usize),
+    Name(String),
+}
+
+impl DeviceOverride {
+    pub fn parse(value: &str) -> Option<Self> {
+        let value = value.trim();
+        if value.is_empty() {
+            return None;
+        }
+
+        match value.parse::<usize>() {
+            Ok(index) => Some(Self::Index(index)),
+            Err(_) => Some(Self::Name(value.to_lowercase())),
+        }
+    }
+
+    /// This function reads the override from the `VOXELAR_DEVICE` environment variable.
+    pub fn from_env() -> Option<Self> {
+        std::env::var(DEVICE_OVERRIDE_ENV_VAR)
+            .ok()
+            .and_then(|value| Self::parse(&value))
+    }
+
+    pub fn matches(&self, index: usize, device_name: &str) -> bool {
+        match self {
+            Self::Index(override_index) => *override_index == index,
+            Self::Name(name) => device_name.to_lowercase().contains(name.as_str()),
+        }
+    }
+}
+
+/// A device that satisfies all requirements, passed to the scoring callback.
+#[derive(Clone, Copy)]
+pub struct DeviceCandidate {
+    pub index: usize,
+    pub physical_device: PhysicalDevice,
+    pub properties: PhysicalDeviceProperties,
+    pub memory_properties: PhysicalDeviceMemoryProperties,
+}
+
+impl DeviceCandidate {
+    pub fn name(&self) -> String {
+        unsafe {
+            CStr::from_ptr(self.properties.device_name.as_ptr())
+                .to_string_lossy()
+                .into_owned()
+        }
+    }
+
+    pub fn heap_size_sum(&self) -> u64 {
+        self.memory_properties.memory_heaps[..self.memory_properties.memory_heap_count as usize]
+            .iter()
+            .map(|heap| heap.size)
+            .sum()
+    }
+}
+
+/// The default scoring function: Discrete GPUs are preferred over integrated GPUs, which are
+/// preferred over all other device types. Devices of the same type are ranked by the sum of
+/// their memory heap sizes.
+pub fn default_device_score(candidate: &DeviceCandidate) -> u64 {
+    let device_type_score = match candidate.properties.device_type {
+        PhysicalDeviceType::DISCRETE_GPU => 2,
+        PhysicalDeviceType::INTEGRATED_GPU => 1,
+        _ => 0,
+    };
+
+    // The heap size is measured in MiB, so that it can't overflow into the device type score
+    let heap_size_score = (candidate.heap_size_sum() >> 20).min((1 << 56) - 1);
+    (device_type_score << 56) | heap_size_score
+}
+
+/// The reasons why a device was rejected during device selection.
+#[derive(Clone, Debug)]
+pub struct DeviceRejection {
+    pub index: usize,
+    pub device_name: String,
+    pub reasons: Vec<String>,
+}
+
+impl std::fmt::Display for DeviceRejection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Device {} ({}) was rejected: {}",
+            self.index,
+            self.device_name,
+            self.reasons.join("; ")
+        )
+    }
+}
+
+/// The result of checking a device against the `DeviceRequirements`.
+#[derive(Clone, Debug)]
+pub enum DeviceCheck {
+    /// The device satisfies all requirements; contains the names of the extensions to enable
+    Suitable(Vec<CString>),
+    /// The device doesn't satisfy the requirements; contains the reasons for the rejection
+    Rejected(Vec<String>),
+}
+
+pub type DeviceScoringCallback = Arc<dyn Fn(&DeviceCandidate) -> u64>;
+
+#[derive(Clone)]
+pub struct DeviceRequirements {
+    min_api_version: u32,
+    required_extensions: Vec<CString>,
+    optional_extensions: Vec<CString>,
+    features: PhysicalDeviceFeatures,
+    features_12: PhysicalDeviceVulkan12Features,
+    features_13: PhysicalDeviceVulkan13Features,
+    min_limits: DeviceLimitRequirements,
+    scoring: Option<DeviceScoringCallback>,
+    device_override: Option<DeviceOverride>,
+}
+
+impl Default for DeviceRequirements {
+    fn default() -> Self {
+        Self {
+            min_api_version: ash::vk::make_api_version(0, 1, 0, 0),
+            required_extensions: vec![],
+            optional_extensions: vec![],
+            features: PhysicalDeviceFeatures {
+                shader_clip_distance: TRUE,
+                ..Default::default()
+            },
+            features_12: PhysicalDeviceVulkan12Features::default(),
+            features_13: PhysicalDeviceVulkan13Features::default(),
+            min_limits: DeviceLimitRequirements::default(),
+            scoring: None,
+            device_override: None,
+        }
+    }
+}
+
+impl DeviceRequirements {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn min_api_version(mut self, api_version: u32) -> Self {
+        self.min_api_version = api_version;
+        self
+    }
+
+    pub fn require_extension(mut self, extension_name: &CStr) -> Self {
+        if !self.required_extensions.iter().any(|name| name.as_c_str() == extension_name) {
+            self.required_extensions.push(extension_name.to_owned());
+        }
+        self
+    }
+
+    /// Optional extensions are enabled if the selected device supports them, but devices without
+    /// them aren't rejected. Use `SetUpLogicalDevice::is_extension_enabled` to check whether an
+    /// optional extension was enabled.
+    pub fn optional_extension(mut self, extension_name: &CStr) -> Self {
+        if !self.optional_extensions.iter().any(|name| name.as_c_str() == extension_name) {
+            self.optional_extensions.push(extension_name.to_owned());
+        }
+        self
+    }
+
+    /// This function sets the required Vulkan 1.0 features. Note that this replaces the default
+    /// features (`shader_clip_distance`).
+    pub fn features(mut self, features: PhysicalDeviceFeatures) -> Self {
+        self.features = features;
+        self
+    }
+
+    /// This function sets the required Vulkan 1.2 features. Requesting any of them requires a
+    /// device that supports Vulkan 1.2.
+    pub fn features_12(mut self, features: PhysicalDeviceVulkan12Features) -> Self {
+        self.features_12 = PhysicalDeviceVulkan12Features {
+            p_next: std::ptr::null_mut(),
+            ..features
+        };
+        self
+    }
+
+    /// This function sets the required Vulkan 1.3 features. Requesting any of them requires a
+    /// device that supports Vulkan 1.3.
+    pub fn features_13(mut self, features: PhysicalDeviceVulkan13Features) -> Self {
+        self.features_13 = PhysicalDeviceVulkan13Features {
+            p_next: std::ptr::null_mut(),
+            ..features
+        };
+        self
+    }
+
+    /// This function requires the `dynamic_rendering` feature, which is needed for rendering
+    /// without render passes and framebuffers.
+    pub fn dynamic_rendering(mut self) -> Self {
+        self.features_13.dynamic_rendering = ash::vk::TRUE;
+        self
+    }
+
+    /// This function returns whether the `dynamic_rendering` feature was requested.
+    pub fn requests_dynamic_rendering(&self) -> bool {
+        self.features_13.dynamic_rendering == ash::vk::TRUE
+    }
+
+    /// This function requires the `sampler_anisotropy` feature, which is needed for samplers
+    /// with anisotropic filtering.
+    pub fn sampler_anisotropy(mut self) -> Self {
+        self.features.sampler_anisotropy = ash::vk::TRUE;
+        self
+    }
+
+    /// This function returns whether the `sampler_anisotropy` feature was requested.
+    pub fn requests_sampler_anisotropy(&self) -> bool {
+        self.features.sampler_anisotropy == ash::vk::TRUE
+    }
+
+    /// This function requires the Vulkan 1.2 descriptor indexing features that are needed for
+    /// bindless descriptor arrays: Partially bound arrays with a variable descriptor count that
+    /// are indexed non-uniformly and can be updated after they were bound.
+    pub fn descriptor_indexing(mut self) -> Self {
+        self.features_12.descriptor_indexing = TRUE;
+        self.features_12.runtime_descriptor_array = TRUE;
+        self.features_12.descriptor_binding_partially_bound = TRUE;
+        self.features_12.descriptor_binding_variable_descriptor_count = TRUE;
+        self.features_12.descriptor_binding_update_unused_while_pending = TRUE;
+        self.features_12.descriptor_binding_sampled_image_update_after_bind = TRUE;
+        self.features_12.descriptor_binding_storage_buffer_update_after_bind = TRUE;
+        self.features_12.shader_sampled_image_array_non_uniform_indexing = TRUE;
+        self.features_12.shader_storage_buffer_array_non_uniform_indexing = TRUE;
+        self
+    }
+
+    /// This function returns whether the features of `descriptor_indexing` were requested.
+    pub fn requests_descriptor_indexing(&self) -> bool {
+        let requested = Self::new().descriptor_indexing().features_12;
+        missing_features_12(&requested, &self.features_12).is_empty()
+    }
+
+    pub fn min_limits(mut self, min_limits: DeviceLimitRequirements) -> Self {
+        self.min_limits = min_limits;
+        self
+    }
+
+    /// This function sets the callback that ranks all devices satisfying the requirements. The
+    /// device with the highest score is selected.
+    pub fn scoring<F>(mut self, scoring: F) -> Self
+    where
+        F: Fn(&DeviceCandidate) -> u64 + 'static,
+    {
+        self.scoring = Some(Arc::new(scoring));
+        self
+    }
+
+    /// This function selects a specific device. The `VOXELAR_DEVICE` environment variable takes
+    /// precedence over this override.
+    pub fn device_override(mut self, device_override: DeviceOverride) -> Self {
+        self.device_override = Some(device_override);
+        self
+    }
+
+    pub fn requested_features(&self) -> &PhysicalDeviceFeatures {
+        &self.features
+    }
+
+    /// This function returns the requested Vulkan 1.2 features if any of them were requested.
+    pub fn requested_features_12(&self) -> Option<&PhysicalDeviceVulkan12Features> {
+        let any_requested =
+            !missing_features_12(&self.features_12, &Default::default()).is_empty();
+        any_requested.then_some(&self.features_12)
+    }
+
+    /// This function returns the requested Vulkan 1.3 features if any of them were requested.
+    pub fn requested_features_13(&self) -> Option<&PhysicalDeviceVulkan13Features> {
+        let any_requested =
+            !missing_features_13(&self.features_13, &Default::default()).is_empty();
+        any_requested.then_some(&self.features_13)
+    }
+
+    fn required_api_version(&self) -> u32 {
+        let mut required_api_version = self.min_api_version;
+        if self.requested_features_12().is_some() {
+            required_api_version = required_api_version.max(ash::vk::make_api_version(0, 1, 2, 0));
+        }
+        if self.requested_features_13().is_some() {
+            required_api_version = required_api_version.max(ash::vk::make_api_version(0, 1, 3, 0));
+        }
+        required_api_version
+    }
+
+    pub fn effective_device_override(&self) -> Option<DeviceOverride> {
+        DeviceOverride::from_env().or_else(|| self.device_override.clone())
+    }
+
+    pub fn score(&self, candidate: &DeviceCandidate) -> u64 {
+        match &self.scoring {
+            Some(scoring) => scoring(candidate),
+            None => default_device_score(candidate),
+        }
+    }
+
+    /// This function checks whether the `physical_device` satisfies these requirements.
+    ///
+    /// If it does, the names of all extensions that should be enabled (required extensions and
+    /// supported optional extensions) are returned in `DeviceCheck::Suitable`. Otherwise, the
+    /// reasons for the rejection are returned in `DeviceCheck::Rejected`.
+    ///
+    /// The `instance_api_version` is the API version the instance was created with.
+    pub unsafe fn check_device(
+        &self,
+        instance: &Instance,
+        instance_api_version: u32,
+        physical_device: PhysicalDevice,
+    ) -> crate::Result<DeviceCheck> {
+        let mut reasons = vec![];
+
+        let properties = instance.get_physical_device_properties(physical_device);
+        let api_version = properties.api_version.min(instance_api_version);
+        let required_api_version = self.required_api_version();
+        if api_version < required_api_version {
+            reasons.push(format!(
+                "API version {} is lower than the required version {}",
+                format_api_version(api_version),
+                format_api_version(required_api_version)
+            ));
+        }
+
+        let available_extensions = instance.enumerate_device_extension_properties(physical_device)?;
+        let is_extension_available = |extension_name: &CStr| {
+            available_extensions.iter().any(|properties| {
+                CStr::from_ptr(properties.extension_name.as_ptr()) == extension_name
+            })
+        };
+
+        for extension_name in self.required_extensions.iter() {
+            if !is_extension_available(extension_name) {
+                reasons.push(format!(
+                    "required extension {} is not supported",
+                    extension_name.to_string_lossy()
+                ));
+            }
+        }
+
+        let supported_features = instance.get_physical_device_features(physical_device);
+        let mut missing = missing_features(&self.features, &supported_features);
+
+        // The feature chain can only be queried if the API version is high enough
+        if api_version >= required_api_version {
+            let mut supported_features_12 = PhysicalDeviceVulkan12Features::default();
+            let mut supported_features_13 = PhysicalDeviceVulkan13Features::default();
+            let mut supported_features2 = PhysicalDeviceFeatures2::builder();
+            if self.requested_features_12().is_some() {
+                supported_features2 = supported_features2.push_next(&mut supported_features_12);
+            }
+            if self.requested_features_13().is_some() {
+                supported_features2 = supported_features2.push_next(&mut supported_features_13);
+            }
+
+            if self.requested_features_12().is_some() || self.requested_features_13().is_some() {
+                instance.get_physical_device_features2(physical_device, &mut supported_features2);
+                missing.extend(missing_features_12(&self.features_12, &supported_features_12));
+                missing.extend(missing_features_13(&self.features_13, &supported_features_13));
+            }
+        }
+
+        for feature in missing {
+            reasons.push(format!("feature {} is not supported", feature));
+        }
+
+        reasons.extend(self.min_limits.unsatisfied_limits(&properties.limits));
+
+        if !reasons.is_empty() {
+            return Ok(DeviceCheck::Rejected(reasons));
+        }
+
+        let mut enabled_extensions = self.required_extensions.clone();
+        enabled_extensions.extend(
+            self.optional_extensions
+                .iter()
+                .filter(|extension_name| is_extension_available(extension_name))
+                .filter(|extension_name| !enabled_extensions.contains(extension_name))
+                .cloned()
+                .collect::<Vec<_>>(),
+        );
+        Ok(DeviceCheck::Suitable(enabled_extensions))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unsupported_requested_features_are_missing() {
+        let requested = PhysicalDeviceFeatures {
+            sampler_anisotropy: TRUE,
+            shader_float64: TRUE,
+            ..Default::default()
+        };
+        let supported = PhysicalDeviceFeatures {
+            sampler_anisotropy: TRUE,
+            ..Default::default()
+        };
+
+        assert_eq!(vec!["shader_float64"], missing_features(&requested, &supported));
+    }
+
+    #[test]
+    fn missing_vulkan_12_features_are_prefixed() {
+        let requested = PhysicalDeviceVulkan12Features {
+            descriptor_indexing: TRUE,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            vec!["vulkan12.descriptor_indexing"],
+            missing_features_12(&requested, &Default::default())
+        );
+    }
+
+    #[test]
+    fn requested_feature_chains_are_only_reported_if_used() {
+        let requirements = DeviceRequirements::new();
+        assert!(requirements.requested_features_12().is_none());
+        assert!(requirements.requested_features_13().is_none());
+
+        let requirements = requirements.features_13(PhysicalDeviceVulkan13Features {
+            dynamic_rendering: TRUE,
+            ..Default::default()
+        });
+        assert!(requirements.requested_features_13().is_some());
+        assert_eq!(
+            ash::vk::make_api_version(0, 1, 3, 0),
+            requirements.required_api_version()
+        );
+    }
+
+    #[test]
+    fn unsatisfied_limits_are_reported() {
+        let requirements = DeviceLimitRequirements {
+            max_push_constants_size: Some(256),
+            max_bound_descriptor_sets: Some(4),
+            ..Default::default()
+        };
+        let limits = PhysicalDeviceLimits {
+            max_push_constants_size: 128,
+            max_bound_descriptor_sets: 8,
+            ..Default::default()
+        };
+
+        let unsatisfied = requirements.unsatisfied_limits(&limits);
+        assert_eq!(1, unsatisfied.len());
+        assert!(unsatisfied[0].contains("max_push_constants_size"));
+    }
+
+    #[test]
+    fn device_override_is_parsed_as_index_or_name() {
+        assert_eq!(Some(DeviceOverride::Index(1)), DeviceOverride::parse("1"));
+        assert_eq!(
+            Some(DeviceOverride::Name("geforce".to_string())),
+            DeviceOverride::parse(" GeForce ")
+        );
+        assert_eq!(None, DeviceOverride::parse(""));
+    }
+
+    #[test]
+    fn device_override_by_name_matches_case_insensitively() {
+        let device_override = DeviceOverride::parse("llvmpipe").unwrap();
+        assert!(device_override.matches(0, "llvmpipe (LLVM 15.0.7, 256 bits)"));
+        assert!(!device_override.matches(0, "AMD Radeon RX 6800"));
+    }
+}
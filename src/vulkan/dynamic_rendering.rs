@@ -0,0 +1,129 @@
+//! This is a module that contains helpers for rendering without render passes and framebuffers
+//! (`VK_KHR_dynamic_rendering`, core in Vulkan 1.3).
+
+use ash::vk::{AttachmentLoadOp, AttachmentStoreOp, ClearValue, Format, ImageLayout, ImageView};
+use ash::vk::{PipelineRenderingCreateInfo, RenderingAttachmentInfo, ResolveModeFlags};
+
+/// The attachment formats a pipeline for dynamic rendering is created with
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct RenderingFormats {
+    pub color_formats: Vec<Format>,
+    pub depth_format: Option<Format>,
+    pub stencil_format: Option<Format>,
+}
+
+impl RenderingFormats {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn color_format(mut self, format: Format) -> Self {
+        self.color_formats.push(format);
+        self
+    }
+
+    pub fn depth_format(mut self, format: Format) -> Self {
+        self.depth_format = Some(format);
+        self
+    }
+
+    pub fn stencil_format(mut self, format: Format) -> Self {
+        self.stencil_format = Some(format);
+        self
+    }
+
+    /// This function returns the `PipelineRenderingCreateInfo` for these formats. The returned
+    /// info borrows from `self`, so it must not outlive it.
+    pub fn pipeline_rendering_create_info(&self) -> PipelineRenderingCreateInfo {
+        *PipelineRenderingCreateInfo::builder()
+            .color_attachment_formats(&self.color_formats)
+            .depth_attachment_format(self.depth_format.unwrap_or(Format::UNDEFINED))
+            .stencil_attachment_format(self.stencil_format.unwrap_or(Format::UNDEFINED))
+    }
+}
+
+/// This function creates the info for a color attachment in `COLOR_ATTACHMENT_OPTIMAL` layout.
+/// The attachment is cleared with the `clear_value` if one is given, otherwise its previous
+/// contents are loaded.
+pub fn color_attachment_info(
+    image_view: ImageView,
+    clear_value: Option<ClearValue>,
+) -> RenderingAttachmentInfo {
+    attachment_info(
+        image_view,
+        ImageLayout::COLOR_ATTACHMENT_OPTIMAL,
+        clear_value,
+        AttachmentStoreOp::STORE,
+    )
+}
+
+/// This function creates the info for a multisampled color attachment that is averaged into the
+/// `resolve_image_view` at the end of rendering. Both images have to be in
+/// `COLOR_ATTACHMENT_OPTIMAL` layout, and the multisampled contents aren't stored.
+pub fn resolved_color_attachment_info(
+    image_view: ImageView,
+    resolve_image_view: ImageView,
+    clear_value: Option<ClearValue>,
+) -> RenderingAttachmentInfo {
+    RenderingAttachmentInfo {
+        resolve_mode: ResolveModeFlags::AVERAGE,
+        resolve_image_view,
+        resolve_image_layout: ImageLayout::COLOR_ATTACHMENT_OPTIMAL,
+        ..attachment_info(
+            image_view,
+            ImageLayout::COLOR_ATTACHMENT_OPTIMAL,
+            clear_value,
+            AttachmentStoreOp::DONT_CARE,
+        )
+    }
+}
+
+/// This function creates the info for a depth (or stencil) attachment in
+/// `DEPTH_STENCIL_ATTACHMENT_OPTIMAL` layout. The contents of the attachment aren't stored after
+/// rendering.
+pub fn depth_attachment_info(
+    image_view: ImageView,
+    clear_value: Option<ClearValue>,
+) -> RenderingAttachmentInfo {
+    attachment_info(
+        image_view,
+        ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL,
+        clear_value,
+        AttachmentStoreOp::DONT_CARE,
+    )
+}
+
+fn attachment_info(
+    image_view: ImageView,
+    image_layout: ImageLayout,
+    clear_value: Option<ClearValue>,
+    store_op: AttachmentStoreOp,
+) -> RenderingAttachmentInfo {
+    let load_op = match clear_value {
+        Some(_) => AttachmentLoadOp::CLEAR,
+        None => AttachmentLoadOp::LOAD,
+    };
+    *RenderingAttachmentInfo::builder()
+        .image_view(image_view)
+        .image_layout(image_layout)
+        .load_op(load_op)
+        .store_op(store_op)
+        .clear_value(clear_value.unwrap_or_default())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn missing_depth_and_stencil_formats_are_undefined() {
+        let formats = RenderingFormats::new()
+            .color_format(Format::B8G8R8A8_SRGB)
+            .depth_format(Format::D32_SFLOAT);
+        let create_info = formats.pipeline_rendering_create_info();
+
+        assert_eq!(1, create_info.color_attachment_count);
+        assert_eq!(Format::D32_SFLOAT, create_info.depth_attachment_format);
+        assert_eq!(Format::UNDEFINED, create_info.stencil_attachment_format);
+    }
+}
@@ -1,3 +1,5 @@
+use std::cell::RefCell;
+
 use ash::vk::CommandBufferLevel;
 use ash::vk::CommandBufferResetFlags;
 use ash::vk::FenceCreateFlags;
@@ -6,12 +8,19 @@ use ash::vk::Queue;
 
 use super::command::command_buffer::SetUpCommandBufferWithFence;
 use super::command::command_pool::SetUpCommandPool;
+use super::deletion_queue::DeletionQueue;
+use super::descriptors::descriptor_allocator::DescriptorAllocator;
 use super::logical_device::SetUpLogicalDevice;
 use super::sync::RenderingSyncPrimitives;
 
 pub struct FrameData {
     pub sync_primitives: RenderingSyncPrimitives,
     pub command_pool: SetUpCommandPool,
+    /// The resources that are destroyed once the draw buffers of this frame completed
+    pub deletion_queue: DeletionQueue,
+    /// The allocator for descriptor sets that are only used in this frame; it's reset once the
+    /// draw buffers of this frame completed
+    pub descriptor_allocator: RefCell<DescriptorAllocator>,
 }
 
 impl FrameData {
@@ -27,6 +36,8 @@ impl FrameData {
         Ok(Self {
             sync_primitives,
             command_pool,
+            deletion_queue: DeletionQueue::new(),
+            descriptor_allocator: RefCell::new(DescriptorAllocator::default()),
         })
     }
 
@@ -93,5 +104,6 @@ impl FrameData {
     pub fn destroy(&mut self, logical_device: &SetUpLogicalDevice) {
         self.sync_primitives.destroy(logical_device);
         self.command_pool.destroy(logical_device);
+        self.descriptor_allocator.get_mut().destroy(logical_device);
     }
 }
@@ -2,6 +2,7 @@ use ash::vk::{Framebuffer, FramebufferCreateInfo};
 
 use super::depth_image::SetUpDepthImage;
 use super::logical_device::SetUpLogicalDevice;
+use super::msaa_color_image::SetUpMsaaColorImage;
 use super::present_images::SetUpPresentImages;
 use super::render_pass::SetUpRenderPass;
 use super::surface::SetUpSurfaceInfo;
@@ -11,9 +12,13 @@ pub struct SetUpFramebuffers {
 }
 
 impl SetUpFramebuffers {
+    /// This function creates a framebuffer for each present image. If a multisampled color
+    /// image is given, it is used as the color attachment and the present image is used as the
+    /// resolve attachment (see `SetUpRenderPass::create_with_multisampled_attachments`).
     pub unsafe fn create(
         logical_device: &SetUpLogicalDevice,
         depth_image: &SetUpDepthImage,
+        msaa_color_image: Option<&SetUpMsaaColorImage>,
         surface_info: &SetUpSurfaceInfo,
         present_images: &SetUpPresentImages,
         render_pass: &SetUpRenderPass,
@@ -24,7 +29,14 @@ impl SetUpFramebuffers {
 
         let mut framebuffers: Vec<Framebuffer> = Vec::with_capacity(present_image_views.len());
         for present_image_view in present_image_views.iter() {
-            let framebuffer_attachments = [*present_image_view, depth_image_view.image_view];
+            let framebuffer_attachments = match msaa_color_image {
+                Some(msaa_color_image) => vec![
+                    msaa_color_image.color_image_view.image_view,
+                    depth_image_view.image_view,
+                    *present_image_view,
+                ],
+                None => vec![*present_image_view, depth_image_view.image_view],
+            };
             let frame_buffer_create_info = FramebufferCreateInfo::builder()
                 .render_pass(render_pass.render_pass)
                 .attachments(&framebuffer_attachments)
@@ -1,9 +1,11 @@
 use ash::vk::GraphicsPipelineCreateInfo;
 use ash::vk::Pipeline;
 use ash::vk::PipelineCache;
+use ash::vk::PipelineRenderingCreateInfo;
 use ash::vk::PipelineShaderStageCreateInfo;
 use ash::vk::PipelineVertexInputStateCreateInfo;
 use ash::vk::PipelineViewportStateCreateInfo;
+use ash::vk::RenderPass;
 use ash::vk::{
     BlendFactor, BlendOp, ColorComponentFlags, LogicOp, PipelineColorBlendAttachmentState,
     PipelineColorBlendStateCreateInfo,
@@ -17,6 +19,7 @@ use ash::vk::{Rect2D, Viewport};
 
 use crate::result::Context;
 
+use super::dynamic_rendering::RenderingFormats;
 use super::logical_device::SetUpLogicalDevice;
 use super::pipeline_layout::SetUpPipelineLayout;
 use super::render_pass::SetUpRenderPass;
@@ -30,7 +33,9 @@ pub struct GraphicsPipelineBuilder {
     rasterization: Option<PipelineRasterizationStateCreateInfo>,
     multisample: Option<PipelineMultisampleStateCreateInfo>,
     color_blend_attachment: Option<PipelineColorBlendAttachmentState>,
+    without_color_attachments: bool,
     depth_stencil: Option<PipelineDepthStencilStateCreateInfo>,
+    reverse_z: bool,
     dynamic_states: Vec<DynamicState>,
 
     viewport: Option<Viewport>,
@@ -99,6 +104,26 @@ impl GraphicsPipelineBuilder {
         self.rasterization(rasterization)
     }
 
+    /// This function enables a depth bias, which offsets the depth values of the rasterized
+    /// fragments. This is used to avoid self-shadowing artifacts ("shadow acne") when rendering
+    /// into shadow maps.
+    pub fn rasterization_with_depth_bias(
+        self,
+        polygon_mode: PolygonMode,
+        constant_factor: f32,
+        slope_factor: f32,
+    ) -> Self {
+        let rasterization = PipelineRasterizationStateCreateInfo::builder()
+            .polygon_mode(polygon_mode)
+            .front_face(FrontFace::COUNTER_CLOCKWISE)
+            .line_width(1.0)
+            .depth_bias_enable(true)
+            .depth_bias_constant_factor(constant_factor)
+            .depth_bias_slope_factor(slope_factor)
+            .build();
+        self.rasterization(rasterization)
+    }
+
     builder_setter!(multisample, PipelineMultisampleStateCreateInfo);
     pub fn multisample_with_samples(self, rasterization_samples: SampleCountFlags) -> Self {
         let multisample = PipelineMultisampleStateCreateInfo::builder()
@@ -122,6 +147,13 @@ impl GraphicsPipelineBuilder {
         self.color_blend_attachment(color_blend_attachment)
     }
 
+    /// This function builds the pipeline without color attachments, e.g. for depth-only render
+    /// passes. The color blend attachment doesn't need to be set in this case.
+    pub fn without_color_attachments(mut self) -> Self {
+        self.without_color_attachments = true;
+        self
+    }
+
     builder_setter!(depth_stencil, PipelineDepthStencilStateCreateInfo);
     pub fn depth_stencil_with_stencil_ops(
         self,
@@ -148,6 +180,14 @@ impl GraphicsPipelineBuilder {
         self.depth_stencil_with_stencil_ops(noop_stencil_state, noop_stencil_state)
     }
 
+    /// This function enables reverse-Z depth testing: The depth compare op of the depth stencil
+    /// state is reversed when the pipeline is built (e.g. `LESS_OR_EQUAL` becomes
+    /// `GREATER_OR_EQUAL`), so the same depth stencil state can be used in both cases.
+    pub fn reverse_z(mut self, reverse_z: bool) -> Self {
+        self.reverse_z = reverse_z;
+        self
+    }
+
     pub fn add_dynamic_state(mut self, dynamic_state: DynamicState) -> Self {
         self.dynamic_states.push(dynamic_state);
         self
@@ -162,6 +202,38 @@ impl GraphicsPipelineBuilder {
         logical_device: &SetUpLogicalDevice,
         render_pass: &SetUpRenderPass,
         pipeline_layout: &SetUpPipelineLayout,
+    ) -> crate::Result<Pipeline> {
+        self.build_with(
+            logical_device,
+            render_pass.render_pass,
+            None,
+            pipeline_layout,
+        )
+    }
+
+    /// This function builds a pipeline for dynamic rendering, which doesn't need a render pass.
+    /// The attachment formats have to match the attachments that are used when rendering.
+    pub fn build_for_dynamic_rendering(
+        &self,
+        logical_device: &SetUpLogicalDevice,
+        rendering_formats: &RenderingFormats,
+        pipeline_layout: &SetUpPipelineLayout,
+    ) -> crate::Result<Pipeline> {
+        let mut pipeline_rendering_create_info = rendering_formats.pipeline_rendering_create_info();
+        self.build_with(
+            logical_device,
+            RenderPass::null(),
+            Some(&mut pipeline_rendering_create_info),
+            pipeline_layout,
+        )
+    }
+
+    fn build_with(
+        &self,
+        logical_device: &SetUpLogicalDevice,
+        render_pass: RenderPass,
+        pipeline_rendering_create_info: Option<&mut PipelineRenderingCreateInfo>,
+        pipeline_layout: &SetUpPipelineLayout,
     ) -> crate::Result<Pipeline> {
         let viewports = [get_safe_copy(self.viewport, "viewport")?];
         let scissor = [get_safe_copy(self.scissor, "scissors")?];
@@ -169,10 +241,14 @@ impl GraphicsPipelineBuilder {
             .viewports(&viewports)
             .scissors(&scissor);
 
-        let color_blend_attachments = [get_safe_copy(
-            self.color_blend_attachment,
-            "color blend attachment",
-        )?];
+        let color_blend_attachments = if self.without_color_attachments {
+            vec![]
+        } else {
+            vec![get_safe_copy(
+                self.color_blend_attachment,
+                "color blend attachment",
+            )?]
+        };
         let color_blend_state = PipelineColorBlendStateCreateInfo::builder()
             .logic_op(LogicOp::CLEAR)
             .attachments(&color_blend_attachments);
@@ -180,7 +256,12 @@ impl GraphicsPipelineBuilder {
         let dynamic_state =
             PipelineDynamicStateCreateInfo::builder().dynamic_states(&self.dynamic_states);
 
-        let pipeline_create_info = GraphicsPipelineCreateInfo::builder()
+        let mut depth_stencil = *get_safe_ref(&self.depth_stencil, "depth stencil state")?;
+        if self.reverse_z {
+            depth_stencil.depth_compare_op = reversed_compare_op(depth_stencil.depth_compare_op);
+        }
+
+        let mut pipeline_create_info = GraphicsPipelineCreateInfo::builder()
             .stages(&self.shader_stages)
             .vertex_input_state(get_safe_ref(&self.vertex_input, "vertex input state")?)
             .input_assembly_state(get_safe_ref(&self.input_assembly, "input assembly state")?)
@@ -188,11 +269,14 @@ impl GraphicsPipelineBuilder {
             .rasterization_state(get_safe_ref(&self.rasterization, "rasterization state")?)
             .multisample_state(get_safe_ref(&self.multisample, "multisample state")?)
             .color_blend_state(&color_blend_state)
-            .depth_stencil_state(get_safe_ref(&self.depth_stencil, "depth stencil state")?)
+            .depth_stencil_state(&depth_stencil)
             .dynamic_state(&dynamic_state)
             .layout(pipeline_layout.pipeline_layout)
-            .render_pass(render_pass.render_pass)
-            .build();
+            .render_pass(render_pass);
+        if let Some(pipeline_rendering_create_info) = pipeline_rendering_create_info {
+            pipeline_create_info = pipeline_create_info.push_next(pipeline_rendering_create_info);
+        }
+        let pipeline_create_info = pipeline_create_info.build();
 
         unsafe {
             let graphics_pipeline = logical_device
@@ -202,3 +286,35 @@ impl GraphicsPipelineBuilder {
         }
     }
 }
+
+/// This function returns the compare op that gives the same result when both depth values are
+/// reversed (`1.0 - depth`).
+pub fn reversed_compare_op(compare_op: CompareOp) -> CompareOp {
+    match compare_op {
+        CompareOp::LESS => CompareOp::GREATER,
+        CompareOp::LESS_OR_EQUAL => CompareOp::GREATER_OR_EQUAL,
+        CompareOp::GREATER => CompareOp::LESS,
+        CompareOp::GREATER_OR_EQUAL => CompareOp::LESS_OR_EQUAL,
+        other => other,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reversing_compare_ops_is_symmetric() {
+        assert_eq!(
+            CompareOp::GREATER_OR_EQUAL,
+            reversed_compare_op(CompareOp::LESS_OR_EQUAL)
+        );
+        assert_eq!(CompareOp::EQUAL, reversed_compare_op(CompareOp::EQUAL));
+        for compare_op in [CompareOp::LESS, CompareOp::GREATER_OR_EQUAL] {
+            assert_eq!(
+                compare_op,
+                reversed_compare_op(reversed_compare_op(compare_op))
+            );
+        }
+    }
+}
@@ -45,6 +45,8 @@ impl AllocatedImage {
             depth: 1,
         };
 
+        // Concurrent images are shared between all queue families of the logical device
+        let (sharing_mode, queue_family_indices) = logical_device.resolve_sharing_mode(sharing_mode);
         let image_create_info = ImageCreateInfo::builder()
             .image_type(image_type)
             .format(format)
@@ -54,7 +56,8 @@ impl AllocatedImage {
             .samples(samples)
             .tiling(tiling)
             .usage(image_usage)
-            .sharing_mode(sharing_mode);
+            .sharing_mode(sharing_mode)
+            .queue_family_indices(&queue_family_indices);
 
         let image = logical_device.create_image(&image_create_info, None)?;
         let image_memory_req = logical_device.get_image_memory_requirements(image);
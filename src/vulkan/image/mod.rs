@@ -3,14 +3,18 @@
 //!
 //! - image: Provides an abstraction for GPU memory-allocated images
 //! - image\_view: Provides an abstraction for Vulkan image views
+//! - readback: Provides functionality for converting image data read back from the GPU into `RgbaImage`s
 //! - sampler: Provides a wrapper around image samplers
-//! - texture: Provides an abstraction for GPU-allocated textures
+//! - texture: Provides an abstraction for GPU-allocated textures and texture arrays
+//! - texture\_atlas: Provides a packer that combines a directory of block textures into a texture array or atlas
 //! - typed\_image: Provides an abstraction for images that hold data of a specific type
 
 pub mod image;
 pub mod image_view;
+pub mod readback;
 pub mod sampler;
 pub mod texture;
+pub mod texture_atlas;
 pub mod typed_image;
 
 pub use image_crate::*;
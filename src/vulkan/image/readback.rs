@@ -0,0 +1,88 @@
+//! This is a module that provides functionality for converting image data that was read back from
+//! the GPU into images of the `image` crate.
+
+use ash::vk::Format;
+use image_crate::RgbaImage;
+
+use crate::result::Context;
+
+/// This function returns whether image data of the given `format` can be converted into an
+/// `RgbaImage` using `rgba_image_from_raw`.
+pub fn is_format_convertible_to_rgba(format: Format) -> bool {
+    matches!(
+        format,
+        Format::R8G8B8A8_UNORM
+            | Format::R8G8B8A8_SRGB
+            | Format::B8G8R8A8_UNORM
+            | Format::B8G8R8A8_SRGB
+    )
+}
+
+/// This function converts tightly packed image data of the given `format` into an `RgbaImage`.
+///
+/// Image data in a BGRA format (which is the common format of swapchain images) is swizzled to
+/// RGBA. The color values are not converted between color spaces.
+pub fn rgba_image_from_raw(
+    width: u32,
+    height: u32,
+    format: Format,
+    mut data: Vec<u8>,
+) -> crate::Result<RgbaImage> {
+    crate::verify!(
+        is_format_convertible_to_rgba(format),
+        "Image data of format {:?} can't be converted to RGBA",
+        format
+    );
+
+    let expected_size = width as usize * height as usize * 4;
+    crate::verify!(
+        data.len() == expected_size,
+        "The image data must have a size of {} bytes, got {}",
+        expected_size,
+        (data.len())
+    );
+
+    if format == Format::B8G8R8A8_UNORM || format == Format::B8G8R8A8_SRGB {
+        for pixel in data.chunks_exact_mut(4) {
+            pixel.swap(0, 2);
+        }
+    }
+
+    RgbaImage::from_raw(width, height, data)
+        .context("Unable to create RGBA image from raw data".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rgba_data_is_kept_as_is() {
+        let data = vec![1, 2, 3, 4, 5, 6, 7, 8];
+        let image = rgba_image_from_raw(2, 1, Format::R8G8B8A8_UNORM, data.clone())
+            .expect("Converting RGBA data failed");
+
+        assert_eq!(data, image.into_raw());
+    }
+
+    #[test]
+    fn bgra_data_is_swizzled() {
+        let data = vec![1, 2, 3, 4, 5, 6, 7, 8];
+        let image = rgba_image_from_raw(1, 2, Format::B8G8R8A8_SRGB, data)
+            .expect("Converting BGRA data failed");
+
+        assert_eq!(vec![3, 2, 1, 4, 7, 6, 5, 8], image.into_raw());
+    }
+
+    #[test]
+    fn data_of_wrong_size_is_rejected() {
+        let data = vec![0; 12];
+        assert!(rgba_image_from_raw(2, 2, Format::R8G8B8A8_UNORM, data).is_err());
+    }
+
+    #[test]
+    fn unsupported_format_is_rejected() {
+        let data = vec![0; 8];
+        assert!(rgba_image_from_raw(1, 1, Format::R16G16B16A16_SFLOAT, data).is_err());
+    }
+}
@@ -1,24 +1,113 @@
 use ash::vk::Filter;
-use ash::vk::{Sampler, SamplerAddressMode, SamplerCreateInfo};
+use ash::vk::{BorderColor, CompareOp};
+use ash::vk::{Sampler, SamplerAddressMode, SamplerCreateInfo, SamplerMipmapMode};
 
 use crate::vulkan::logical_device::SetUpLogicalDevice;
 
+/// The range of mip levels a sampler selects from and the bias that is added to the computed
+/// level of detail
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SamplerLod {
+    pub min_lod: f32,
+    pub max_lod: f32,
+    pub mip_lod_bias: f32,
+}
+
+impl Default for SamplerLod {
+    /// Only the first mip level is sampled.
+    fn default() -> Self {
+        Self {
+            min_lod: 0.0,
+            max_lod: 0.0,
+            mip_lod_bias: 0.0,
+        }
+    }
+}
+
+impl SamplerLod {
+    /// This function creates a LOD range that covers all `mip_levels` of a texture.
+    pub fn for_mip_levels(mip_levels: u32) -> Self {
+        Self {
+            max_lod: mip_levels.saturating_sub(1) as f32,
+            ..Default::default()
+        }
+    }
+}
+
 pub struct SetUpSampler {
     pub sampler: Sampler,
 }
 
 impl SetUpSampler {
+    /// This function creates a sampler with the given filter for magnification, minification
+    /// and (with `SamplerMipmapMode::LINEAR`) between mip levels.
+    ///
+    /// Anisotropic filtering is enabled if a `max_anisotropy` is given, which requires the
+    /// `sampler_anisotropy` device feature. The value must not exceed the
+    /// `max_sampler_anisotropy` limit of the device.
     pub unsafe fn create(
         logical_device: &SetUpLogicalDevice,
         filter: Filter,
         sampler_address_mode: SamplerAddressMode,
+        mipmap_mode: SamplerMipmapMode,
+        lod: SamplerLod,
+        max_anisotropy: Option<f32>,
     ) -> crate::Result<Self> {
         let sampler_create_info = SamplerCreateInfo::builder()
             .mag_filter(filter)
             .min_filter(filter)
+            .mipmap_mode(mipmap_mode)
             .address_mode_u(sampler_address_mode)
             .address_mode_v(sampler_address_mode)
-            .address_mode_w(sampler_address_mode);
+            .address_mode_w(sampler_address_mode)
+            .min_lod(lod.min_lod)
+            .max_lod(lod.max_lod)
+            .mip_lod_bias(lod.mip_lod_bias)
+            .anisotropy_enable(max_anisotropy.is_some())
+            .max_anisotropy(max_anisotropy.unwrap_or(1.0));
+
+        let sampler = logical_device.create_sampler(&sampler_create_info, None)?;
+
+        Ok(Self { sampler })
+    }
+
+    /// This function creates a sampler that only samples the first mip level, without
+    /// anisotropic filtering.
+    pub unsafe fn create_with_defaults(
+        logical_device: &SetUpLogicalDevice,
+        filter: Filter,
+        sampler_address_mode: SamplerAddressMode,
+    ) -> crate::Result<Self> {
+        Self::create(
+            logical_device,
+            filter,
+            sampler_address_mode,
+            SamplerMipmapMode::NEAREST,
+            SamplerLod::default(),
+            None,
+        )
+    }
+
+    /// This function creates a sampler that compares the sampled depth values with a reference
+    /// value (`sampler2DShadow`/`sampler2DArrayShadow` in GLSL), e.g. for shadow maps. With a
+    /// linear `filter`, the results of the comparisons are filtered (hardware PCF).
+    ///
+    /// Coordinates outside of the image are clamped to an opaque white border, so that they
+    /// pass a `LESS_OR_EQUAL` comparison.
+    pub unsafe fn create_with_compare_op(
+        logical_device: &SetUpLogicalDevice,
+        filter: Filter,
+        compare_op: CompareOp,
+    ) -> crate::Result<Self> {
+        let sampler_create_info = SamplerCreateInfo::builder()
+            .mag_filter(filter)
+            .min_filter(filter)
+            .address_mode_u(SamplerAddressMode::CLAMP_TO_BORDER)
+            .address_mode_v(SamplerAddressMode::CLAMP_TO_BORDER)
+            .address_mode_w(SamplerAddressMode::CLAMP_TO_BORDER)
+            .border_color(BorderColor::FLOAT_OPAQUE_WHITE)
+            .compare_enable(true)
+            .compare_op(compare_op);
 
         let sampler = logical_device.create_sampler(&sampler_create_info, None)?;
 
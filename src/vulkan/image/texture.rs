@@ -6,6 +6,8 @@ use ash::vk::ImageSubresourceLayers;
 use ash::vk::ImageType;
 use ash::vk::SampleCountFlags;
 use ash::vk::SharingMode;
+use ash::vk::{AccessFlags, ImageLayout, PipelineStageFlags};
+use ash::vk::{Filter, ImageBlit, Offset3D};
 use ash::vk::{
     ImageAspectFlags, ImageSubresourceRange, ImageTiling, ImageUsageFlags, ImageViewType,
 };
@@ -18,9 +20,22 @@ use crate::vulkan::logical_device::SetUpLogicalDevice;
 use super::image_view::SetUpImageView;
 use super::typed_image::TypedAllocatedImage;
 
+/// This function returns the number of mip levels of a full mip chain for an image with the
+/// given width and height, down to a 1x1 level.
+pub fn full_mip_level_count(width: u32, height: u32) -> u32 {
+    u32::BITS - width.max(height).max(1).leading_zeros()
+}
+
+/// This function returns the width and height of the mip level with the given index.
+pub fn mip_level_size(width: u32, height: u32, mip_level: u32) -> (u32, u32) {
+    ((width >> mip_level).max(1), (height >> mip_level).max(1))
+}
+
 pub struct Texture<T> {
     pub image: TypedAllocatedImage<T>,
     pub image_view: SetUpImageView,
+    pub mip_levels: u32,
+    pub array_layers: u32,
 }
 
 impl<T> Texture<T> {
@@ -30,46 +45,129 @@ impl<T> Texture<T> {
         format: Format,
         texture_extent: Extent3D,
     ) -> crate::Result<Self> {
+        Self::create_with_mip_levels(logical_device, allocator, format, texture_extent, 1)
+    }
+
+    /// This function creates a texture with `array_layers` layers of the same size and a
+    /// `TYPE_2D_ARRAY` view. The layers are uploaded together by `copy_from_staging_buffer`,
+    /// so the staging buffer has to contain the data of all layers one after another.
+    pub unsafe fn create_array(
+        logical_device: &SetUpLogicalDevice,
+        allocator: &mut MutexGuard<Allocator>,
+        format: Format,
+        layer_extent: Extent3D,
+        array_layers: u32,
+        mip_levels: u32,
+    ) -> crate::Result<Self> {
+        crate::verify!(
+            array_layers >= 1,
+            "A texture array needs at least one layer"
+        );
+        Self::create_with_layers(
+            logical_device,
+            allocator,
+            format,
+            layer_extent,
+            mip_levels,
+            array_layers,
+            ImageViewType::TYPE_2D_ARRAY,
+        )
+    }
+
+    /// This function creates a texture with `mip_levels` mip levels. Only the first level is
+    /// filled by `copy_from_staging_buffer`, the others have to be generated with
+    /// `generate_mipmaps`.
+    pub unsafe fn create_with_mip_levels(
+        logical_device: &SetUpLogicalDevice,
+        allocator: &mut MutexGuard<Allocator>,
+        format: Format,
+        texture_extent: Extent3D,
+        mip_levels: u32,
+    ) -> crate::Result<Self> {
+        Self::create_with_layers(
+            logical_device,
+            allocator,
+            format,
+            texture_extent,
+            mip_levels,
+            1,
+            ImageViewType::TYPE_2D,
+        )
+    }
+
+    unsafe fn create_with_layers(
+        logical_device: &SetUpLogicalDevice,
+        allocator: &mut MutexGuard<Allocator>,
+        format: Format,
+        texture_extent: Extent3D,
+        mip_levels: u32,
+        array_layers: u32,
+        image_view_type: ImageViewType,
+    ) -> crate::Result<Self> {
+        crate::verify!(
+            mip_levels >= 1
+                && mip_levels <= full_mip_level_count(texture_extent.width, texture_extent.height),
+            "A {}x{} texture can't have {} mip levels",
+            (texture_extent.width),
+            (texture_extent.height),
+            mip_levels
+        );
+
+        // The mip levels are generated by blitting from the previous level
+        let mut image_usage = ImageUsageFlags::SAMPLED | ImageUsageFlags::TRANSFER_DST;
+        if mip_levels > 1 {
+            image_usage |= ImageUsageFlags::TRANSFER_SRC;
+        }
+
         let image = TypedAllocatedImage::<T>::allocate(
             logical_device,
             allocator,
             ImageType::TYPE_2D,
             format,
             texture_extent,
-            1,
-            1,
+            mip_levels,
+            array_layers,
             SampleCountFlags::TYPE_1,
             ImageTiling::OPTIMAL,
-            ImageUsageFlags::SAMPLED | ImageUsageFlags::TRANSFER_DST,
+            image_usage,
             SharingMode::EXCLUSIVE,
         )?;
         let image_view = SetUpImageView::create(
             logical_device,
-            ImageViewType::TYPE_2D,
+            image_view_type,
             format,
-            Self::create_default_subresource_range(),
+            Self::create_subresource_range(0, mip_levels, array_layers),
             image.image.image,
         )?;
 
-        Ok(Self { image, image_view })
+        Ok(Self {
+            image,
+            image_view,
+            mip_levels,
+            array_layers,
+        })
     }
 
-    fn create_default_subresource_range() -> ImageSubresourceRange {
+    fn create_subresource_range(
+        base_mip_level: u32,
+        level_count: u32,
+        layer_count: u32,
+    ) -> ImageSubresourceRange {
         ImageSubresourceRange::builder()
             .aspect_mask(ImageAspectFlags::COLOR)
-            .base_mip_level(0)
-            .level_count(1)
+            .base_mip_level(base_mip_level)
+            .level_count(level_count)
             .base_array_layer(0)
-            .layer_count(1)
+            .layer_count(layer_count)
             .build()
     }
 
-    fn create_default_subresource_layers() -> ImageSubresourceLayers {
+    fn create_subresource_layers(mip_level: u32, layer_count: u32) -> ImageSubresourceLayers {
         ImageSubresourceLayers::builder()
             .aspect_mask(ImageAspectFlags::COLOR)
-            .mip_level(0)
+            .mip_level(mip_level)
             .base_array_layer(0)
-            .layer_count(1)
+            .layer_count(layer_count)
             .build()
     }
 
@@ -81,7 +179,7 @@ impl<T> Texture<T> {
         self.image.layout_transition_to_copy_target(
             logical_device,
             setup_command_buffer,
-            Self::create_default_subresource_range(),
+            Self::create_subresource_range(0, self.mip_levels, self.array_layers),
         )
     }
 
@@ -95,7 +193,7 @@ impl<T> Texture<T> {
             logical_device,
             staging_buffer,
             setup_command_buffer,
-            Self::create_default_subresource_layers(),
+            Self::create_subresource_layers(0, self.array_layers),
         )
     }
 
@@ -107,10 +205,97 @@ impl<T> Texture<T> {
         self.image.layout_transition_to_shader_readable(
             logical_device,
             setup_command_buffer,
-            Self::create_default_subresource_range(),
+            Self::create_subresource_range(0, self.mip_levels, self.array_layers),
         )
     }
 
+    /// This function fills all mip levels after the first one by blitting each level into the
+    /// next one with linear filtering, and transitions all levels to `SHADER_READ_ONLY_OPTIMAL`.
+    /// This replaces `layout_transition_to_shader_readable`.
+    ///
+    /// All levels have to be in `TRANSFER_DST_OPTIMAL` layout (see
+    /// `layout_transition_to_copy_target`) and the format has to support linear blits (see
+    /// `SetUpPhysicalDevice::supports_linear_blit`).
+    pub fn generate_mipmaps(
+        &mut self,
+        logical_device: &SetUpLogicalDevice,
+        setup_command_buffer: &SetUpCommandBufferWithFence,
+    ) {
+        let extent = self.image.image.image_extent;
+        let array_layers = self.array_layers;
+        let allocated_image = &mut self.image.image;
+
+        for mip_level in 1..self.mip_levels {
+            let source_level = mip_level - 1;
+
+            // Wait until the source level was written (copied or blitted to)
+            allocated_image.add_layout_transition_pipeline_barrier(
+                logical_device,
+                setup_command_buffer,
+                Self::create_subresource_range(source_level, 1, array_layers),
+                AccessFlags::TRANSFER_WRITE,
+                AccessFlags::TRANSFER_READ,
+                ImageLayout::TRANSFER_DST_OPTIMAL,
+                ImageLayout::TRANSFER_SRC_OPTIMAL,
+                PipelineStageFlags::TRANSFER,
+                PipelineStageFlags::TRANSFER,
+            );
+
+            let (source_width, source_height) =
+                mip_level_size(extent.width, extent.height, source_level);
+            let (width, height) = mip_level_size(extent.width, extent.height, mip_level);
+            let image_blit = ImageBlit::builder()
+                .src_subresource(Self::create_subresource_layers(source_level, array_layers))
+                .src_offsets([
+                    Offset3D::default(),
+                    Offset3D {
+                        x: source_width as i32,
+                        y: source_height as i32,
+                        z: 1,
+                    },
+                ])
+                .dst_subresource(Self::create_subresource_layers(mip_level, array_layers))
+                .dst_offsets([
+                    Offset3D::default(),
+                    Offset3D {
+                        x: width as i32,
+                        y: height as i32,
+                        z: 1,
+                    },
+                ]);
+            unsafe {
+                logical_device.cmd_blit_image(
+                    setup_command_buffer.command_buffer,
+                    allocated_image.image,
+                    ImageLayout::TRANSFER_SRC_OPTIMAL,
+                    allocated_image.image,
+                    ImageLayout::TRANSFER_DST_OPTIMAL,
+                    &[*image_blit],
+                    Filter::LINEAR,
+                );
+            }
+
+            allocated_image.add_layout_transition_pipeline_barrier(
+                logical_device,
+                setup_command_buffer,
+                Self::create_subresource_range(source_level, 1, array_layers),
+                AccessFlags::TRANSFER_READ,
+                AccessFlags::SHADER_READ,
+                ImageLayout::TRANSFER_SRC_OPTIMAL,
+                ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+                PipelineStageFlags::TRANSFER,
+                PipelineStageFlags::FRAGMENT_SHADER,
+            );
+        }
+
+        // The last level was only written to
+        self.image.layout_transition_to_shader_readable(
+            logical_device,
+            setup_command_buffer,
+            Self::create_subresource_range(self.mip_levels - 1, 1, array_layers),
+        );
+    }
+
     pub fn destroy(
         &mut self,
         logical_device: &SetUpLogicalDevice,
@@ -121,3 +306,19 @@ impl<T> Texture<T> {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn full_mip_chain_ends_at_one_texel() {
+        assert_eq!(1, full_mip_level_count(1, 1));
+        assert_eq!(9, full_mip_level_count(256, 256));
+        assert_eq!(9, full_mip_level_count(300, 17));
+
+        let last_level = full_mip_level_count(300, 17) - 1;
+        assert_eq!((1, 1), mip_level_size(300, 17, last_level));
+        assert_eq!((150, 8), mip_level_size(300, 17, 1));
+    }
+}
@@ -0,0 +1,410 @@
+//! This is a module that contains a packer for block textures: The images of a directory are
+//! either stacked into the layers of a texture array or packed into a single atlas image, and
+//! the location of each image is stored under its file name, so that meshers can look up the
+//! texture coordinates of a block face.
+
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+use ash::vk::{Extent3D, Format};
+use image_crate::{GenericImage, RgbaImage};
+use nalgebra::Vector2;
+
+use crate::result::Context;
+use crate::vulkan::ownership::Owned;
+use crate::vulkan::VulkanContext;
+
+use super::texture::{full_mip_level_count, Texture};
+
+/// The location of a packed image
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AtlasEntry {
+    /// The array layer the image is in (always 0 for atlases)
+    pub layer: u32,
+    /// The texture coordinates of the top left corner of the image
+    pub uv_min: Vector2<f32>,
+    /// The texture coordinates of the bottom right corner of the image
+    pub uv_max: Vector2<f32>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PackingMode {
+    /// Every image becomes a layer of a texture array; all images must have the same size
+    Array,
+    /// All images are packed into a single image. The images are surrounded by `padding` texels
+    /// that repeat their edges, so that filtering doesn't bleed between them. Every mip level
+    /// halves the padding, so mipmapped atlases only get `log2(padding) + 1` mip levels (see
+    /// `atlas_mip_level_count`).
+    Atlas { padding: u32 },
+}
+
+pub struct PackedTextures {
+    pub mode: PackingMode,
+    /// The layers of the texture array, or the atlas image
+    pub images: Vec<RgbaImage>,
+    pub entries: HashMap<String, AtlasEntry>,
+}
+
+impl PackedTextures {
+    /// This function loads all PNG images of the directory at `path` (see
+    /// `load_images_from_directory`) and packs them.
+    pub fn load_directory<P: AsRef<Path>>(path: P, mode: PackingMode) -> crate::Result<Self> {
+        let images = load_images_from_directory(path)?;
+        Self::pack(images, mode)
+    }
+
+    pub fn pack(images: Vec<(String, RgbaImage)>, mode: PackingMode) -> crate::Result<Self> {
+        verify_unique_names(&images)?;
+        match mode {
+            PackingMode::Array => pack_array(images),
+            PackingMode::Atlas { padding } => pack_atlas(images, padding),
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<&AtlasEntry> {
+        self.entries.get(name)
+    }
+
+    /// This function returns the width and height of the layers or the atlas.
+    pub fn size(&self) -> (u32, u32) {
+        self.images
+            .first()
+            .map_or((0, 0), |image| image.dimensions())
+    }
+
+    /// This function uploads the packed images into a texture (array) with 4 channels, e.g.
+    /// with the format `R8G8B8A8_SRGB`. With `mipmapped`, the full mip chain is generated for
+    /// texture arrays, and as many mip levels as the padding allows for atlases.
+    pub fn create_texture(
+        &self,
+        vulkan_context: &VulkanContext,
+        format: Format,
+        mipmapped: bool,
+    ) -> crate::Result<Owned<Texture<u8>>> {
+        let (width, height) = self.size();
+        let extent = Extent3D {
+            width,
+            height,
+            depth: 4, // 4 channels
+        };
+        let mip_levels = match (mipmapped, self.mode) {
+            (false, _) => 1,
+            (true, PackingMode::Array) => full_mip_level_count(width, height),
+            (true, PackingMode::Atlas { padding }) => {
+                full_mip_level_count(width, height).min(atlas_mip_level_count(padding))
+            }
+        };
+
+        match self.mode {
+            PackingMode::Array => {
+                let layers = self
+                    .images
+                    .iter()
+                    .map(|image| image.as_raw().as_slice())
+                    .collect::<Vec<_>>();
+                vulkan_context.create_texture_array(format, extent, &layers, mip_levels)
+            }
+            PackingMode::Atlas { .. } => vulkan_context.create_texture_with_mip_levels(
+                format,
+                extent,
+                &self.images[0],
+                mip_levels,
+            ),
+        }
+    }
+}
+
+/// This function loads all PNG images of the directory at `path` with `vulkan::image::open`.
+/// The images are named after their file names without the extension and sorted by name, so
+/// the packing is deterministic.
+pub fn load_images_from_directory<P: AsRef<Path>>(
+    path: P,
+) -> crate::Result<Vec<(String, RgbaImage)>> {
+    let mut images = vec![];
+    for entry in std::fs::read_dir(path.as_ref())? {
+        let path = entry?.path();
+        let is_png = path
+            .extension()
+            .is_some_and(|extension| extension.eq_ignore_ascii_case("png"));
+        if !path.is_file() || !is_png {
+            continue;
+        }
+
+        let name = path
+            .file_stem()
+            .and_then(|name| name.to_str())
+            .context(format!("The file name of {:?} is not valid UTF-8", path))?
+            .to_string();
+        let image = super::open(&path)?.into_rgba8();
+        images.push((name, image));
+    }
+    images.sort_by(|(a, _), (b, _)| a.cmp(b));
+    verify_unique_names(&images)?;
+
+    crate::verify!(
+        !images.is_empty(),
+        "The directory {:?} doesn't contain any PNG images",
+        (path.as_ref())
+    );
+    Ok(images)
+}
+
+/// This function returns the number of mip levels of an atlas with the given `padding`, in
+/// which no level mixes texels of neighbouring images.
+pub fn atlas_mip_level_count(padding: u32) -> u32 {
+    match padding {
+        0 => 1,
+        padding => padding.ilog2() + 1,
+    }
+}
+
+/// This function checks that no two images have the same name, e.g. `stone.png` and
+/// `stone.PNG`, since only one of them could be looked up.
+fn verify_unique_names(images: &[(String, RgbaImage)]) -> crate::Result<()> {
+    let mut names = HashSet::new();
+    for (name, _) in images {
+        crate::verify!(
+            names.insert(name.as_str()),
+            "There are multiple images named {}",
+            name
+        );
+    }
+    Ok(())
+}
+
+fn pack_array(images: Vec<(String, RgbaImage)>) -> crate::Result<PackedTextures> {
+    let size = images
+        .first()
+        .map(|(_, image)| image.dimensions())
+        .context("There are no images to pack".to_string())?;
+
+    let mut entries = HashMap::new();
+    let mut layers = Vec::with_capacity(images.len());
+    for (layer, (name, image)) in images.into_iter().enumerate() {
+        crate::verify!(
+            image.dimensions() == size,
+            "All layers of a texture array must have the same size: {} is {:?}, expected {:?}",
+            name,
+            (image.dimensions()),
+            size
+        );
+
+        entries.insert(
+            name,
+            AtlasEntry {
+                layer: layer as u32,
+                uv_min: Vector2::new(0.0, 0.0),
+                uv_max: Vector2::new(1.0, 1.0),
+            },
+        );
+        layers.push(image);
+    }
+
+    Ok(PackedTextures {
+        mode: PackingMode::Array,
+        images: layers,
+        entries,
+    })
+}
+
+fn pack_atlas(images: Vec<(String, RgbaImage)>, padding: u32) -> crate::Result<PackedTextures> {
+    crate::verify!(!images.is_empty(), "There are no images to pack");
+
+    let sizes = images
+        .iter()
+        .map(|(_, image)| image.dimensions())
+        .collect::<Vec<_>>();
+    let (atlas_width, atlas_height, positions) = pack_rectangles(&sizes, padding);
+
+    let mut atlas = RgbaImage::new(atlas_width, atlas_height);
+    let mut entries = HashMap::new();
+    for ((name, image), (x, y)) in images.into_iter().zip(positions) {
+        copy_with_padding(&mut atlas, &image, x, y, padding)?;
+
+        let (width, height) = image.dimensions();
+        entries.insert(
+            name,
+            AtlasEntry {
+                layer: 0,
+                uv_min: Vector2::new(
+                    x as f32 / atlas_width as f32,
+                    y as f32 / atlas_height as f32,
+                ),
+                uv_max: Vector2::new(
+                    (x + width) as f32 / atlas_width as f32,
+                    (y + height) as f32 / atlas_height as f32,
+                ),
+            },
+        );
+    }
+
+    Ok(PackedTextures {
+        mode: PackingMode::Atlas { padding },
+        images: vec![atlas],
+        entries,
+    })
+}
+
+/// This function packs rectangles of the given `sizes` into rows ("shelves"), with `padding`
+/// texels around each of them. It returns the power-of-two size of the atlas and the position
+/// of each rectangle (without the padding), in the order of `sizes`.
+pub fn pack_rectangles(sizes: &[(u32, u32)], padding: u32) -> (u32, u32, Vec<(u32, u32)>) {
+    let padded_sizes = sizes
+        .iter()
+        .map(|(width, height)| (width + 2 * padding, height + 2 * padding))
+        .collect::<Vec<_>>();
+
+    // Start with a square that could fit all rectangles and grow the height as needed
+    let area = padded_sizes
+        .iter()
+        .map(|(width, height)| *width as u64 * *height as u64)
+        .sum::<u64>();
+    let widest = padded_sizes
+        .iter()
+        .map(|(width, _)| *width)
+        .max()
+        .unwrap_or(1);
+    let atlas_width = ((area as f64).sqrt().ceil() as u32)
+        .max(widest)
+        .next_power_of_two();
+
+    // Tall rectangles first, so that the shelves waste less space
+    let mut order = (0..sizes.len()).collect::<Vec<_>>();
+    order.sort_by_key(|index| std::cmp::Reverse(padded_sizes[*index].1));
+
+    let mut positions = vec![(0, 0); sizes.len()];
+    let (mut shelf_x, mut shelf_y, mut shelf_height) = (0, 0, 0);
+    for index in order {
+        let (width, height) = padded_sizes[index];
+        if shelf_x + width > atlas_width {
+            shelf_x = 0;
+            shelf_y += shelf_height;
+            shelf_height = 0;
+        }
+
+        positions[index] = (shelf_x + padding, shelf_y + padding);
+        shelf_x += width;
+        shelf_height = shelf_height.max(height);
+    }
+    let atlas_height = (shelf_y + shelf_height).max(1).next_power_of_two();
+
+    (atlas_width, atlas_height, positions)
+}
+
+/// This function copies the `image` to (`x`, `y`) in the `atlas` and fills the `padding`
+/// texels around it with the nearest edge texels of the image.
+fn copy_with_padding(
+    atlas: &mut RgbaImage,
+    image: &RgbaImage,
+    x: u32,
+    y: u32,
+    padding: u32,
+) -> crate::Result<()> {
+    atlas.copy_from(image, x, y)?;
+
+    let (width, height) = image.dimensions();
+    let padding = padding as i64;
+    for offset_y in -padding..height as i64 + padding {
+        for offset_x in -padding..width as i64 + padding {
+            let inside_x = (0..width as i64).contains(&offset_x);
+            let inside_y = (0..height as i64).contains(&offset_y);
+            if inside_x && inside_y {
+                continue;
+            }
+
+            let source_x = offset_x.clamp(0, width as i64 - 1) as u32;
+            let source_y = offset_y.clamp(0, height as i64 - 1) as u32;
+            atlas.put_pixel(
+                (x as i64 + offset_x) as u32,
+                (y as i64 + offset_y) as u32,
+                *image.get_pixel(source_x, source_y),
+            );
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use image_crate::Rgba;
+
+    use super::*;
+
+    #[test]
+    fn packed_rectangles_do_not_overlap() {
+        let sizes = [(16, 16), (32, 16), (16, 16), (8, 32), (16, 16)];
+        let padding = 2;
+        let (atlas_width, atlas_height, positions) = pack_rectangles(&sizes, padding);
+        assert!(atlas_width.is_power_of_two() && atlas_height.is_power_of_two());
+
+        let padded_bounds = sizes
+            .iter()
+            .zip(&positions)
+            .map(|((width, height), (x, y))| {
+                (
+                    x - padding,
+                    y - padding,
+                    x + width + padding,
+                    y + height + padding,
+                )
+            })
+            .collect::<Vec<_>>();
+        for (index, a) in padded_bounds.iter().enumerate() {
+            assert!(a.2 <= atlas_width && a.3 <= atlas_height);
+            for b in &padded_bounds[index + 1..] {
+                let overlaps = a.0 < b.2 && b.0 < a.2 && a.1 < b.3 && b.1 < a.3;
+                assert!(!overlaps, "{:?} overlaps {:?}", a, b);
+            }
+        }
+    }
+
+    #[test]
+    fn atlas_entries_point_at_their_images() {
+        let red = RgbaImage::from_pixel(4, 4, Rgba([255, 0, 0, 255]));
+        let blue = RgbaImage::from_pixel(4, 4, Rgba([0, 0, 255, 255]));
+        let packed = PackedTextures::pack(
+            vec![("red".to_string(), red), ("blue".to_string(), blue)],
+            PackingMode::Atlas { padding: 1 },
+        )
+        .unwrap();
+
+        let (width, height) = packed.size();
+        let blue_entry = packed.get("blue").unwrap();
+        let texel = |uv: Vector2<f32>| {
+            packed.images[0].get_pixel((uv.x * width as f32) as u32, (uv.y * height as f32) as u32)
+        };
+        assert_eq!(Rgba([0, 0, 255, 255]), *texel(blue_entry.uv_min));
+        // The padding repeats the edge of the image
+        let before_min = blue_entry.uv_min - Vector2::new(1.0 / width as f32, 0.0);
+        assert_eq!(Rgba([0, 0, 255, 255]), *texel(before_min));
+
+        let array = PackedTextures::pack(
+            vec![
+                ("a".to_string(), RgbaImage::new(4, 4)),
+                ("b".to_string(), RgbaImage::new(8, 8)),
+            ],
+            PackingMode::Array,
+        );
+        assert!(array.is_err());
+    }
+
+    #[test]
+    fn atlas_mip_levels_are_limited_by_the_padding() {
+        assert_eq!(1, atlas_mip_level_count(0));
+        assert_eq!(1, atlas_mip_level_count(1));
+        assert_eq!(2, atlas_mip_level_count(3));
+        assert_eq!(3, atlas_mip_level_count(4));
+    }
+
+    #[test]
+    fn duplicate_names_are_rejected() {
+        let packed = PackedTextures::pack(
+            vec![
+                ("stone".to_string(), RgbaImage::new(4, 4)),
+                ("stone".to_string(), RgbaImage::new(4, 4)),
+            ],
+            PackingMode::Atlas { padding: 1 },
+        );
+        assert!(packed.is_err());
+    }
+}
@@ -82,7 +82,8 @@ impl<T> TypedAllocatedImage<T> {
         setup_command_buffer: &SetUpCommandBufferWithFence,
         image_subresource: ImageSubresourceLayers,
     ) -> crate::Result<()> {
-        let image_size = self.image.full_image_size();
+        // The layers are expected to be tightly packed one after another in the buffer
+        let image_size = self.image.full_image_size() * image_subresource.layer_count;
         let staging_buffer_size = staging_buffer.buffer.element_amount;
         crate::verify!(
             image_size as usize == staging_buffer_size,
@@ -1,18 +1,33 @@
-use ash::extensions::khr::Swapchain;
+use std::ffi::{c_void, CStr};
+
 #[cfg(any(target_os = "macos", target_os = "ios"))]
 use ash::vk::KhrPortabilitySubsetFn;
-use ash::vk::PhysicalDeviceFeatures;
-use ash::vk::Queue;
+use ash::vk::{Queue, SharingMode};
 use ash::vk::{DeviceCreateInfo, DeviceQueueCreateInfo};
+use ash::vk::{
+    PhysicalDeviceFeatures, PhysicalDeviceVulkan12Features, PhysicalDeviceVulkan13Features,
+};
 use ash::{Device, Instance};
 
+use super::device_requirements::DeviceRequirements;
 use super::physical_device::SetUpPhysicalDevice;
+use super::queue_families::QueueFamilyIndices;
 
+#[derive(Clone)]
 pub struct SetUpLogicalDevice {
     pub device: Device,
+    pub enabled_extensions: Vec<String>,
 
+    pub graphics_queue: Queue,
     pub present_queue: Queue,
+    /// The dedicated transfer queue, or the graphics queue if the device has none
+    pub transfer_queue: Queue,
+    /// The async compute queue, or the graphics queue if the device has none
+    pub compute_queue: Queue,
+
+    /// The index of the graphics queue family
     pub queue_family_index: u32,
+    pub queue_family_indices: QueueFamilyIndices,
 }
 
 impl SetUpLogicalDevice {
@@ -23,50 +38,140 @@ impl SetUpLogicalDevice {
         features: PhysicalDeviceFeatures,
         priorities: &[f32],
     ) -> crate::Result<Self> {
-        let queue_info = DeviceQueueCreateInfo::builder()
-            .queue_family_index(physical_device.queue_family_index)
-            .queue_priorities(&priorities);
+        Self::create_with_p_next(
+            instance,
+            physical_device,
+            device_extension_names_raw,
+            features,
+            priorities,
+            std::ptr::null(),
+        )
+    }
 
-        let device_create_info = DeviceCreateInfo::builder()
-            .queue_create_infos(std::slice::from_ref(&queue_info))
+    unsafe fn create_with_p_next(
+        instance: &Instance,
+        physical_device: &SetUpPhysicalDevice,
+        device_extension_names_raw: &[*const i8],
+        features: PhysicalDeviceFeatures,
+        priorities: &[f32],
+        p_next: *const c_void,
+    ) -> crate::Result<Self> {
+        // One queue is created for every distinct queue family
+        let queue_family_indices = physical_device.queue_family_indices;
+        let queue_infos = queue_family_indices
+            .unique_families()
+            .into_iter()
+            .map(|queue_family_index| {
+                *DeviceQueueCreateInfo::builder()
+                    .queue_family_index(queue_family_index)
+                    .queue_priorities(&priorities)
+            })
+            .collect::<Vec<_>>();
+
+        let mut device_create_info = DeviceCreateInfo::builder()
+            .queue_create_infos(&queue_infos)
             .enabled_extension_names(&device_extension_names_raw)
             .enabled_features(&features);
+        device_create_info.p_next = p_next;
 
         let device = instance.create_device(**physical_device, &device_create_info, None)?;
 
-        let present_queue = device.get_device_queue(physical_device.queue_family_index, 0);
+        let graphics_queue = device.get_device_queue(queue_family_indices.graphics, 0);
+        let present_queue = device.get_device_queue(queue_family_indices.present_or_graphics(), 0);
+        let transfer_queue = device.get_device_queue(queue_family_indices.transfer_or_graphics(), 0);
+        let compute_queue = device.get_device_queue(queue_family_indices.compute_or_graphics(), 0);
+
+        let enabled_extensions = device_extension_names_raw
+            .iter()
+            .map(|name| CStr::from_ptr(*name).to_string_lossy().into_owned())
+            .collect();
 
         Ok(Self {
             device,
+            enabled_extensions,
+            graphics_queue,
             present_queue,
-            queue_family_index: physical_device.queue_family_index,
+            transfer_queue,
+            compute_queue,
+            queue_family_index: queue_family_indices.graphics,
+            queue_family_indices,
         })
     }
 
-    pub unsafe fn create_with_defaults(
+    /// This function creates a logical device with the extensions that were chosen during the
+    /// selection of the `physical_device` and the features of the `requirements`.
+    pub unsafe fn create_with_requirements(
         instance: &Instance,
-        physical_device_info: &SetUpPhysicalDevice,
+        physical_device: &SetUpPhysicalDevice,
+        requirements: &DeviceRequirements,
     ) -> crate::Result<Self> {
-        let device_extension_names_raw = [
-            Swapchain::name().as_ptr(),
-            #[cfg(any(target_os = "macos", target_os = "ios"))]
-            KhrPortabilitySubsetFn::name().as_ptr(),
-        ];
-        let features = PhysicalDeviceFeatures {
-            shader_clip_distance: 1,
-            ..Default::default()
-        };
+        #[allow(unused_mut)]
+        let mut device_extension_names_raw = physical_device
+            .enabled_extensions
+            .iter()
+            .map(|name| name.as_ptr())
+            .collect::<Vec<_>>();
+        #[cfg(any(target_os = "macos", target_os = "ios"))]
+        device_extension_names_raw.push(KhrPortabilitySubsetFn::name().as_ptr());
+
+        // The Vulkan 1.2 and 1.3 feature structures are chained if any of their features were
+        // requested
+        let mut features_12 = requirements.requested_features_12().copied();
+        let mut features_13 = requirements.requested_features_13().copied();
+        let mut p_next: *mut c_void = std::ptr::null_mut();
+        if let Some(features_13) = features_13.as_mut() {
+            features_13.p_next = p_next;
+            p_next = features_13 as *mut PhysicalDeviceVulkan13Features as *mut c_void;
+        }
+        if let Some(features_12) = features_12.as_mut() {
+            features_12.p_next = p_next;
+            p_next = features_12 as *mut PhysicalDeviceVulkan12Features as *mut c_void;
+        }
+
         let priorities = [1.0];
 
-        Self::create(
+        Self::create_with_p_next(
             instance,
-            physical_device_info,
+            physical_device,
             &device_extension_names_raw,
-            features,
+            *requirements.requested_features(),
             &priorities,
+            p_next,
+        )
+    }
+
+    pub unsafe fn create_with_defaults(
+        instance: &Instance,
+        physical_device_info: &SetUpPhysicalDevice,
+    ) -> crate::Result<Self> {
+        Self::create_with_requirements(
+            instance,
+            physical_device_info,
+            &DeviceRequirements::default(),
         )
     }
 
+    /// This function returns the sharing mode and queue family indices that should be used for
+    /// resources with the requested `sharing_mode`.
+    ///
+    /// Concurrent sharing requires at least two distinct queue families, so `EXCLUSIVE` is used
+    /// instead if the device only uses a single family.
+    pub fn resolve_sharing_mode(&self, sharing_mode: SharingMode) -> (SharingMode, Vec<u32>) {
+        let unique_families = self.queue_family_indices.unique_families();
+        if sharing_mode == SharingMode::CONCURRENT && unique_families.len() > 1 {
+            (SharingMode::CONCURRENT, unique_families)
+        } else {
+            (SharingMode::EXCLUSIVE, vec![])
+        }
+    }
+
+    pub fn is_extension_enabled(&self, extension_name: &CStr) -> bool {
+        let extension_name = extension_name.to_string_lossy();
+        self.enabled_extensions
+            .iter()
+            .any(|enabled_extension| *enabled_extension == extension_name)
+    }
+
     pub fn wait(&self) -> crate::Result<()> {
         unsafe {
             self.device_wait_idle()?;
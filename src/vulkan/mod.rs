@@ -5,40 +5,66 @@
 //! Module overview:
 //! - buffers: Provides all abstractions for Vulkan buffers
 //! - command: Provides all abstractions and functionality for Vulkan commands
+//! - compute\_barriers: Provides barrier utilities for making the results of compute shaders visible to graphics, transfer and other compute commands
+//! - compute\_pipeline\_builder: Provides an abstraction for building compute `Pipeline`s with specialization constants
 //! - creation\_info: Provides a `DataStructureCreationInfo` struct for high-level information related to the `VulkanContext` data structure initialization
 //! - debug: Provides an abstraction for the verification layer setup (if requested)
+//! - deletion\_queue: Provides a per-frame queue that defers the destruction of resources until the frame's draw buffers completed
 //! - depth\_image: Provides an abstraction for depth image creation
+//! - device\_requirements: Provides a `DeviceRequirements` builder for configuring the physical device selection and the enabled extensions and features
 //! - descriptors: Provides all descriptor logic and abstractions
 //! - dynamic\_uniform\_buffer: Provides an abstraction for uniform buffers that can be used with dynamic descriptor sets
+//! - dynamic\_rendering: Provides helpers for rendering without render passes and framebuffers (`VK_KHR_dynamic_rendering`)
 //! - egui\_integration: A wrapper for the egui integration provided by the `egui-winit-ash-integration` crate
 //! - frame\_data: Provides an abstraction for per-frame synchronization and command logic in double/triple/...-buffering scenarios
 //! - framebuffers: Provides an abstraction for framebuffer creation for each present image of a swapchain
 //! - graphics\_pipeline\_builder: Provides an abstraction for building Vulkan `Pipeline`s
 //! - image: Provides abstractions for all image-related functionality
 //! - logical\_device: Provides a wrapper around logical Vulkan devices
+//! - msaa\_color\_image: Provides an abstraction for multisampled color images that are resolved into the present images
+//! - offscreen\_target: Provides an abstraction for rendering into offscreen color and depth images that can be read back to CPU memory
+//! - ownership: Provides shared device handles and an `Owned` wrapper that destroys resources when they are dropped
 //! - per\_frame: Provides an abstraction for tracking data of each frame in double/triple/...-buffering scenarios; used with `FrameData` in this module
 //! - physical\_device: Provides an abstraction for finding a suitable `PhysicalDevice` for rendering, also queries important device information
 //! - pipeline\_layout: Provide a wrapper around `PipelineLayout`s
 //! - pipeline\_layout\_builder: Provides an abstraction for building `(SetUp)PipelineLayout`s
+//! - post\_process: Provides an HDR target with bloom and tonemapping passes that write into the present images
 //! - present\_images: Provides an abstraction for getting the images of a swapchain
+//! - queue\_families: Provides a `QueueFamilyIndices` struct for discovering the graphics, present, transfer and compute queue families of a device
+//! - queue\_ownership: Provides an abstraction for the barriers that transfer the ownership of buffers and images between queue families
+//! - render\_graph: Provides a render graph that schedules passes and automatically inserts barriers, layout transitions and transient attachments
+//! - render\_info: Provides a `RenderInfo` struct with information about the instance, device and swapchain of a `VulkanContext`
 //! - render\_pass: Provides an abstraction for the creation of a default render pass
-//! - shader: Provides an abstraction for shader compilation and shader module creation
+//! - shader: Provides an abstraction for shader compilation, shader module creation, SPIR-V reflection and shader hot-reloading
+//! - shadow\_map: Provides cascaded shadow maps for directional lights
 //! - surface: Provides an abstraction for the window surface and all related information
 //! - swapchain: Provides an abstraction for the creation of a default swapchain
 //! - sync: Provides a wrapper around synchronization structures (related to rendering)
 //! - util: Provides random utility functions used by the vulkan module
+//! - validation: Provides the parameter validation of the safe resource creation functions of the `VulkanContext`
+//! - window\_target: Provides an abstraction for all data structures that belong to a single window (surface, swapchain, framebuffers etc.)
 
+use std::cell::{RefCell, RefMut};
+use std::collections::HashMap;
 use std::ffi::{c_char, CStr, CString};
 use std::mem::ManuallyDrop;
+use std::path::Path;
 use std::sync::{Arc, Mutex, MutexGuard};
 
 use ash::vk::ApplicationInfo;
+use ash::vk::{BufferUsageFlags, SharingMode};
 use ash::vk::ClearValue;
+use ash::vk::{DescriptorSet, DescriptorSetLayout};
+use ash::vk::{AccessFlags, DependencyFlags, ImageMemoryBarrier};
+use ash::vk::{ImageAspectFlags, ImageSubresourceRange};
 use ash::vk::Filter;
 use ash::vk::Format;
+use ash::vk::{FormatFeatureFlags, ImageTiling};
+use ash::vk::ImageLayout;
 use ash::vk::PipelineStageFlags;
 use ash::vk::PresentInfoKHR;
-use ash::vk::SamplerAddressMode;
+use ash::vk::SampleCountFlags;
+use ash::vk::{SamplerAddressMode, SamplerMipmapMode};
 use ash::vk::ShaderStageFlags;
 use ash::vk::{CommandBufferLevel, CommandPoolResetFlags};
 use ash::vk::{Extent2D, Extent3D};
@@ -48,18 +74,39 @@ use ash::vk::{RenderPassBeginInfo, SubpassContents};
 use ash::{Entry, Instance};
 use gpu_allocator::vulkan::*;
 use gpu_allocator::*;
+use winit::window::WindowId;
 
+use image_crate::RgbaImage;
 use paste::paste;
 
 #[cfg(any(target_os = "macos", target_os = "ios"))]
 use ash::vk::{KhrGetPhysicalDeviceProperties2Fn, KhrPortabilityEnumerationFn};
 
+macro_rules! generate_safe_getter {
+    ($name:ident, $type:ty, $err_message:tt) => {
+        pub fn $name(&self) -> crate::Result<&$type> {
+            self.$name.as_ref().context($err_message.to_string())
+        }
+
+        paste! {
+            pub fn [<$name _mut>](&mut self) -> crate::Result<&mut $type> {
+                self.$name.as_mut().context($err_message.to_string())
+            }
+        }
+    };
+}
+
 pub mod buffers;
 pub mod command;
+pub mod compute_barriers;
+pub mod compute_pipeline_builder;
 pub mod creation_info;
 pub mod debug;
+pub mod deletion_queue;
 pub mod depth_image;
+pub mod device_requirements;
 pub mod descriptors;
+pub mod dynamic_rendering;
 
 #[cfg(feature = "egui-integration")]
 pub mod egui_integration;
@@ -68,98 +115,199 @@ pub mod framebuffers;
 pub mod graphics_pipeline_builder;
 pub mod image;
 pub mod logical_device;
+pub mod msaa_color_image;
+pub mod offscreen_target;
+pub mod ownership;
 pub mod per_frame;
 pub mod physical_device;
 pub mod pipeline_layout;
 pub mod pipeline_layout_builder;
+pub mod post_process;
 pub mod present_images;
+pub mod queue_families;
+pub mod queue_ownership;
+pub mod render_graph;
+pub mod render_info;
 pub mod render_pass;
 pub mod shader;
+pub mod shadow_map;
 pub mod surface;
 pub mod swapchain;
 pub mod sync;
 pub mod util;
+pub mod validation;
+pub mod window_target;
 
-use crate::render_context::RenderContext;
+use crate::render_context::{HeadlessRenderContext, RenderContext};
 use crate::result::Context;
 use crate::window::{VoxelarEventLoop, VoxelarWindow};
 use crate::Voxelar;
 
-use crate::vulkan::per_frame::PerFrame;
-
+use self::buffers::dynamic_buffer::DynamicGpuBuffer;
+use self::buffers::readback_buffer::SetUpReadbackBuffer;
+use self::buffers::ring_buffer::SetUpRingBuffer;
 use self::buffers::staging_buffer::SetUpStagingBuffer;
 use self::buffers::storage_buffer::SetUpStorageBuffer;
 use self::buffers::typed_buffer::TypedAllocatedBuffer;
 use self::buffers::uniform_buffer::SetUpUniformBuffer;
 use self::command::command_buffer::SetUpCommandBufferWithFence;
 use self::command::command_pool::SetUpCommandPool;
-use self::creation_info::DataStructureCreationInfo;
-use self::creation_info::PresentModeInitMode;
+use self::creation_info::{DataStructureCreationInfo, DepthBufferInfo, RenderingPath};
 use self::debug::VerificationProvider;
+use self::deletion_queue::DeletionQueue;
+use self::descriptors::descriptor_layout_cache::DescriptorLayoutCache;
+use self::descriptors::descriptor_set_layout_builder::DescriptorSetLayoutBuilder;
+use self::descriptors::texture_registry::SetUpTextureRegistry;
 use self::depth_image::SetUpDepthImage;
+use self::dynamic_rendering::RenderingFormats;
+use self::device_requirements::DeviceRequirements;
 #[cfg(feature = "egui-integration")]
 use self::egui_integration::SetUpEguiIntegration;
-use self::frame_data::FrameData;
 use self::framebuffers::SetUpFramebuffers;
-use self::image::sampler::SetUpSampler;
-use self::image::texture::Texture;
+use self::image::readback;
+use self::image::sampler::{SamplerLod, SetUpSampler};
+use self::image::texture::{full_mip_level_count, Texture};
 use self::logical_device::SetUpLogicalDevice;
+use self::offscreen_target::SetUpOffscreenTarget;
+use self::ownership::{DeviceHandle, DeviceResource, Owned};
 use self::physical_device::SetUpPhysicalDevice;
+use self::post_process::bloom::BloomSettings;
+use self::post_process::chain::PostProcessChain;
+use self::post_process::shaders::PostProcessShaders;
 use self::present_images::SetUpPresentImages;
+use self::queue_ownership::{BufferOwnershipRange, QueueAccess, QueueOwnershipTransfer};
+use self::render_graph::graph::RenderGraph;
+use self::render_graph::resource::{ImportedImageDescription, ResourceId};
+use self::render_info::RenderInfo;
 use self::render_pass::SetUpRenderPass;
 use self::shader::CompiledShaderModule;
+#[cfg(feature = "shaderc-crate")]
+use self::shader::hot_reload::{HotReloadPipelineId, ShaderHotReloader};
+use self::shadow_map::cascaded_shadow_map::SetUpCascadedShadowMap;
 use self::surface::SetUpSurfaceInfo;
 use self::swapchain::SetUpSwapchain;
+use self::window_target::WindowTarget;
 
 pub struct VulkanContext {
     pub entry: Entry,
     pub instance: Instance,
-    pub surface_info: SetUpSurfaceInfo,
+    pub api_version: u32,
+    pub enabled_extensions: Vec<String>,
+    pub enabled_layers: Vec<String>,
 
     pub verification: Box<dyn VerificationProvider>,
 
     pub last_creation_info: Option<DataStructureCreationInfo>,
+    pub device_requirements: DeviceRequirements,
     pub physical_device: Option<SetUpPhysicalDevice>,
     pub logical_device: Option<SetUpLogicalDevice>,
     pub allocator: Option<ManuallyDrop<Arc<Mutex<Allocator>>>>, // This type is interesting
-    pub swapchain: Option<SetUpSwapchain>,
-    pub present_images: Option<SetUpPresentImages>,
+    /// The shared handle that destroys the device, the allocator and the instance once the
+    /// context and all `Owned` resources were dropped
+    pub device_handle: Option<DeviceHandle>,
+    /// The cache of the layouts that are created with `create_cached_descriptor_set_layout`
+    pub descriptor_layout_cache: RefCell<DescriptorLayoutCache>,
     pub command_pool_for_setup: Option<SetUpCommandPool>,
-    pub depth_image: Option<SetUpDepthImage>,
-    pub render_pass: Option<SetUpRenderPass>,
-    pub framebuffers: Option<SetUpFramebuffers>,
-
-    pub frames: PerFrame<FrameData>,
-}
-
-macro_rules! generate_safe_getter {
-    ($name:ident, $type:ty, $err_message:tt) => {
-        pub fn $name(&self) -> crate::Result<&$type> {
-            self.$name.as_ref().context($err_message.to_string())
-        }
+    pub command_pool_for_transfer: Option<SetUpCommandPool>,
 
-        paste! {
-            pub fn [<$name _mut>](&mut self) -> crate::Result<&mut $type> {
-                self.$name.as_mut().context($err_message.to_string())
-            }
-        }
-    };
+    pub window_targets: HashMap<WindowId, WindowTarget>,
+    pub selected_window_id: Option<WindowId>,
 }
 
 impl VulkanContext {
-    fn create_app_info(window: &VoxelarWindow) -> ApplicationInfo {
-        let app_name = CString::new(window.get_title()).unwrap();
-
+    fn create_app_info(app_name: &CStr, api_version: u32) -> ApplicationInfo {
         let app_info = ApplicationInfo::builder()
-            .application_name(&app_name)
+            .application_name(app_name)
             .application_version(0)
-            .engine_name(&app_name)
+            .engine_name(app_name)
             .engine_version(0)
-            .api_version(ash::vk::make_api_version(0, 1, 0, 0));
+            .api_version(api_version);
 
         *app_info
     }
 
+    unsafe fn create_instance<Verification: VerificationProvider + 'static>(
+        app_name: &str,
+        mut extension_names_raw: Vec<*const c_char>,
+    ) -> crate::Result<(Entry, Instance, u32, Box<dyn VerificationProvider>, Vec<String>, Vec<String>)>
+    {
+        let entry = Entry::load()?;
+
+        // API version: The newest version supported by both the loader and voxelar is used.
+        // Vulkan 1.0 loaders don't support querying the instance version.
+        let api_version = entry
+            .try_enumerate_instance_version()?
+            .unwrap_or(ash::vk::make_api_version(0, 1, 0, 0))
+            .min(ash::vk::make_api_version(0, 1, 3, 0));
+
+        // App info
+        let app_name = CString::new(app_name)?;
+        let app_info = Self::create_app_info(&app_name, api_version);
+
+        // Extension names
+        let verification_required_extensions = Verification::get_extensions();
+        let verification_names_raw: Vec<*const c_char> =
+            util::map_vec_ref(&verification_required_extensions, |name| name.as_ptr());
+        extension_names_raw.extend(verification_names_raw);
+
+        #[cfg(any(target_os = "macos", target_os = "ios"))]
+        {
+            extension_names_raw.push(KhrPortabilityEnumerationFn::name().as_ptr());
+            // Enabling this extension is a requirement when using `VK_KHR_portability_subset`
+            extension_names_raw.push(KhrGetPhysicalDeviceProperties2Fn::name().as_ptr());
+        }
+
+        println!(
+            "Extensions: {:?}",
+            extension_names_raw
+                .iter()
+                .map(|ptr| CStr::from_ptr(*ptr).to_str().ok())
+                .collect::<Vec<Option<&str>>>()
+        );
+
+        // Layer names
+        let verification_required_layers = Verification::get_layers();
+        println!("Layers: {:?}", verification_required_layers);
+
+        let layers_names_raw: Vec<*const c_char> =
+            util::map_vec_ref(&verification_required_layers, |name| name.as_ptr());
+
+        // Create flags
+        let create_flags = if cfg!(any(target_os = "macos", target_os = "ios")) {
+            InstanceCreateFlags::ENUMERATE_PORTABILITY_KHR
+        } else {
+            InstanceCreateFlags::default()
+        };
+
+        let create_info = InstanceCreateInfo::builder()
+            .application_info(&app_info)
+            .enabled_extension_names(&extension_names_raw)
+            .enabled_layer_names(&layers_names_raw)
+            .flags(create_flags);
+
+        let instance: Instance = entry.create_instance(&create_info, None)?;
+
+        let verification = Box::new(Verification::load(&entry, &instance)?);
+
+        let enabled_extensions = extension_names_raw
+            .iter()
+            .map(|ptr| CStr::from_ptr(*ptr).to_string_lossy().into_owned())
+            .collect();
+        let enabled_layers = verification_required_layers
+            .iter()
+            .map(|name| name.to_string_lossy().into_owned())
+            .collect();
+
+        Ok((
+            entry,
+            instance,
+            api_version,
+            verification,
+            enabled_extensions,
+            enabled_layers,
+        ))
+    }
+
     generate_safe_getter!(
         physical_device,
         SetUpPhysicalDevice,
@@ -189,16 +337,18 @@ impl VulkanContext {
     }
 
     generate_safe_getter!(
-        swapchain,
-        SetUpSwapchain,
-        "No swapchain was set up yet! Use VulkanContext::create_swapchain to do so"
+        device_handle,
+        DeviceHandle,
+        "No device handle was set up yet! Use VulkanContext::create_allocator to do so"
     );
 
-    generate_safe_getter!(
-        present_images,
-        SetUpPresentImages,
-        "No present image were set up yet! Use VulkanContext::create_present_images to do so"
-    );
+    /// This function wraps the `resource` into an `Owned`, which destroys it when it's dropped.
+    /// The resources that are created by the context are already owned; this is only required
+    /// for resources that are created with their own constructors. Resources can still be
+    /// destroyed manually, see `Owned::into_inner`.
+    pub fn own<R: DeviceResource + 'static>(&self, resource: R) -> crate::Result<Owned<R>> {
+        Ok(Owned::new(resource, self.device_handle()?.clone()))
+    }
 
     generate_safe_getter!(
         command_pool_for_setup,
@@ -207,28 +357,118 @@ impl VulkanContext {
     );
 
     generate_safe_getter!(
-        depth_image,
-        SetUpDepthImage,
-        "No depth image was set up yet! Use VulkanContext::create_depth_image to do so"
+        command_pool_for_transfer,
+        SetUpCommandPool,
+        "No command logic for transfer commands was set up yet! Use VulkanContext::create_command_pool_for_transfer to do so"
     );
 
-    generate_safe_getter!(
-        render_pass,
-        SetUpRenderPass,
-        "No render pass was set up yet! Use VulkanContext::create_render_pass to do so"
-    );
+    pub fn selected_window_id(&self) -> crate::Result<WindowId> {
+        self.selected_window_id.context(
+            "No window was selected yet! Use VulkanContext::select_window to do so".to_string(),
+        )
+    }
 
-    generate_safe_getter!(
-        framebuffers,
-        SetUpFramebuffers,
-        "No framebuffers were set up yet! Use VulkanContext::create_framebuffers to do so"
-    );
+    /// This function selects the window that all window-specific functions (e.g.
+    /// `VulkanContext::acquire_next_image` or `VulkanContext::record_render_pass`) operate on.
+    pub fn select_window(&mut self, window_id: WindowId) -> crate::Result<()> {
+        crate::verify!(
+            self.window_targets.contains_key(&window_id),
+            "Window {:?} was not added to the Vulkan context",
+            window_id
+        );
+        self.selected_window_id = Some(window_id);
+        Ok(())
+    }
+
+    pub fn window_ids(&self) -> impl Iterator<Item = &WindowId> {
+        self.window_targets.keys()
+    }
+
+    pub fn window_target_for(&self, window_id: WindowId) -> crate::Result<&WindowTarget> {
+        self.window_targets
+            .get(&window_id)
+            .context(format!("Window {:?} was not added to the Vulkan context", window_id))
+    }
+
+    pub fn window_target_for_mut(&mut self, window_id: WindowId) -> crate::Result<&mut WindowTarget> {
+        self.window_targets
+            .get_mut(&window_id)
+            .context(format!("Window {:?} was not added to the Vulkan context", window_id))
+    }
+
+    pub fn window_target(&self) -> crate::Result<&WindowTarget> {
+        self.window_target_for(self.selected_window_id()?)
+    }
+
+    pub fn window_target_mut(&mut self) -> crate::Result<&mut WindowTarget> {
+        self.window_target_for_mut(self.selected_window_id()?)
+    }
+
+    pub fn surface_info(&self) -> crate::Result<&SetUpSurfaceInfo> {
+        Ok(&self.window_target()?.surface_info)
+    }
+
+    pub fn swapchain(&self) -> crate::Result<&SetUpSwapchain> {
+        self.window_target()?.swapchain()
+    }
+
+    pub fn present_images(&self) -> crate::Result<&SetUpPresentImages> {
+        self.window_target()?.present_images()
+    }
+
+    pub fn depth_image(&self) -> crate::Result<&SetUpDepthImage> {
+        self.window_target()?.depth_image()
+    }
+
+    /// This function returns the number of samples of the attachments of the selected window,
+    /// which has to be used for the multisample state of graphics pipelines.
+    pub fn msaa_samples(&self) -> crate::Result<SampleCountFlags> {
+        Ok(self.window_target()?.msaa_samples())
+    }
+
+    /// This function returns whether the depth buffer of the selected window uses reverse-Z,
+    /// which has to be passed to `GraphicsPipelineBuilder::reverse_z` and the camera.
+    pub fn reverse_z(&self) -> crate::Result<bool> {
+        Ok(self.window_target()?.reverse_z())
+    }
+
+    /// This function returns the depth clear value of the selected window's depth buffer.
+    pub fn depth_clear_value(&self) -> crate::Result<ClearValue> {
+        Ok(render_pass::depth_clear_value(self.reverse_z()?))
+    }
+
+    pub fn render_pass(&self) -> crate::Result<&SetUpRenderPass> {
+        self.window_target()?.render_pass()
+    }
+
+    pub fn framebuffers(&self) -> crate::Result<&SetUpFramebuffers> {
+        self.window_target()?.framebuffers()
+    }
+
+    /// This function sets the requirements that are used for selecting the physical device and
+    /// creating the logical device. It must be called before the data structures are created.
+    pub fn set_device_requirements(&mut self, device_requirements: DeviceRequirements) {
+        self.device_requirements = device_requirements;
+    }
 
     pub fn find_usable_physical_device(&mut self) -> crate::Result<()> {
         unsafe {
-            self.physical_device = Some(SetUpPhysicalDevice::find_usable_device(
+            self.physical_device = Some(SetUpPhysicalDevice::find_device_for_surface(
+                &self.instance,
+                self.api_version,
+                self.surface_info()?,
+                &self.device_requirements,
+            )?);
+        }
+        Ok(())
+    }
+
+    pub fn find_usable_headless_physical_device(&mut self) -> crate::Result<()> {
+        unsafe {
+            self.physical_device = Some(SetUpPhysicalDevice::find_headless_device(
                 &self.instance,
-                &self.surface_info,
+                self.api_version,
+                &self.device_requirements,
             )?);
         }
         Ok(())
@@ -236,9 +476,10 @@ impl VulkanContext {
 
     pub fn create_logical_device(&mut self) -> crate::Result<()> {
         unsafe {
-            self.logical_device = Some(SetUpLogicalDevice::create_with_defaults(
+            self.logical_device = Some(SetUpLogicalDevice::create_with_requirements(
                 &self.instance,
                 self.physical_device()?,
+                &self.device_requirements,
             )?);
         }
 
@@ -257,47 +498,17 @@ impl VulkanContext {
             buffer_device_address: false,
         })?;
         let allocator = Arc::new(Mutex::new(allocator));
+        self.device_handle = Some(unsafe {
+            DeviceHandle::new(
+                self.instance.clone(),
+                self.logical_device()?.clone(),
+                Arc::clone(&allocator),
+            )
+        });
         self.allocator = Some(ManuallyDrop::new(allocator));
         Ok(())
     }
 
-    pub fn create_new_swapchain(
-        &mut self,
-        present_mode_init_mode: PresentModeInitMode,
-    ) -> crate::Result<SetUpSwapchain> {
-        unsafe {
-            let new_swapchain = SetUpSwapchain::create_with_defaults(
-                &self.instance,
-                &self.surface_info,
-                self.logical_device()?,
-                present_mode_init_mode,
-                self.swapchain.as_ref(),
-            )?;
-
-            Ok(new_swapchain)
-        }
-    }
-
-    pub fn create_swapchain(
-        &mut self,
-        present_mode_init_mode: PresentModeInitMode,
-    ) -> crate::Result<()> {
-        self.swapchain = Some(self.create_new_swapchain(present_mode_init_mode)?);
-        Ok(())
-    }
-
-    pub fn create_present_images(&mut self) -> crate::Result<()> {
-        unsafe {
-            self.present_images = Some(SetUpPresentImages::create_with_defaults(
-                self.logical_device()?,
-                self.swapchain()?,
-                &self.surface_info,
-            )?);
-        }
-
-        Ok(())
-    }
-
     pub fn create_command_pool_for_setup(&mut self) -> crate::Result<()> {
         unsafe {
             self.command_pool_for_setup = Some(SetUpCommandPool::create(
@@ -310,56 +521,89 @@ impl VulkanContext {
         }
     }
 
-    pub fn create_depth_image(&mut self) -> crate::Result<()> {
-        unsafe {
-            let depth_image = {
-                let allocator = &mut self.lock_allocator()?;
-                SetUpDepthImage::create_with_defaults(
-                    self.logical_device()?,
-                    allocator,
-                    &self.surface_info,
-                )?
-            };
-
-            self.depth_image = Some(depth_image);
-        }
-        Ok(())
-    }
-
-    pub fn create_framebuffers(&mut self) -> crate::Result<()> {
+    /// This function creates the command pool for immediate transfer commands on the transfer
+    /// queue family (or the graphics queue family if the device has no dedicated transfer family).
+    pub fn create_command_pool_for_transfer(&mut self) -> crate::Result<()> {
         unsafe {
-            self.framebuffers = Some(SetUpFramebuffers::create(
-                self.logical_device()?,
-                self.depth_image()?,
-                &self.surface_info,
-                self.present_images()?,
-                self.render_pass()?,
+            let logical_device = self.logical_device()?;
+            self.command_pool_for_transfer = Some(SetUpCommandPool::create_for_queue_family(
+                logical_device,
+                logical_device.queue_family_indices.transfer_or_graphics(),
+                1,
+                CommandBufferLevel::PRIMARY,
+                FenceCreateFlags::empty(),
             )?);
+            Ok(())
         }
-
-        Ok(())
     }
 
-    pub fn create_render_pass(&mut self) -> crate::Result<()> {
-        unsafe {
-            self.render_pass = Some(SetUpRenderPass::create_with_defaults(
-                self.logical_device()?,
-                &self.surface_info,
-            )?);
-        }
-
-        Ok(())
+    fn with_window_target<F>(&mut self, window_id: WindowId, target_op: F) -> crate::Result<()>
+    where
+        F: FnOnce(
+            &Instance,
+            &SetUpPhysicalDevice,
+            &SetUpLogicalDevice,
+            &mut MutexGuard<Allocator>,
+            &mut WindowTarget,
+        ) -> crate::Result<()>,
+    {
+        // The fields are borrowed separately, so that the window target can be borrowed mutably
+        // while the device and allocator are borrowed immutably
+        let physical_device = self.physical_device.as_ref().context(
+            "No physical device was set up yet! Use VulkanContext::find_usable_physical_device to do so".to_string(),
+        )?;
+        let logical_device = self.logical_device.as_ref().context(
+            "No logical device was set up yet! Use VulkanContext::create_logical_device to do so".to_string(),
+        )?;
+        let allocator = self.allocator.as_ref().context(
+            "No allocator was set up yet! Use VulkanContext::create_allocator to do so".to_string(),
+        )?;
+        let mut allocator = allocator
+            .lock()
+            .context("Unable to acquire allocator mutex lock".to_string())?;
+
+        let window_target = self
+            .window_targets
+            .get_mut(&window_id)
+            .context(format!("Window {:?} was not added to the Vulkan context", window_id))?;
+        target_op(
+            &self.instance,
+            physical_device,
+            logical_device,
+            &mut allocator,
+            window_target,
+        )
     }
 
-    pub fn create_per_frame_data(&mut self, frame_overlap: u32) -> crate::Result<()> {
-        unsafe {
-            let logical_device = self.logical_device()?;
-            self.frames = PerFrame::try_init(
-                |_| FrameData::create_with_defaults(logical_device),
-                frame_overlap as usize,
-            )?;
-            Ok(())
-        }
+    /// This function creates the window-specific data structures (swapchain, present images,
+    /// depth image, render pass, framebuffers and per-frame data) for the window with the given
+    /// id, using the device that was set up by `VulkanContext::create_default_data_structures`.
+    /// The render pass and framebuffers are only created for `RenderingPath::RenderPass`.
+    pub fn create_window_data_structures(
+        &mut self,
+        window_id: WindowId,
+        window_size: (u32, u32),
+        creation_info: &DataStructureCreationInfo,
+    ) -> crate::Result<()> {
+        crate::verify!(
+            creation_info.rendering_path != RenderingPath::DynamicRendering
+                || self.device_requirements.requests_dynamic_rendering(),
+            "The dynamic rendering path was chosen, but dynamic rendering was not requested in the device requirements"
+        );
+
+        self.with_window_target(
+            window_id,
+            |instance, physical_device, logical_device, allocator, window_target| {
+                window_target.create_data_structures(
+                    instance,
+                    physical_device,
+                    logical_device,
+                    allocator,
+                    window_size,
+                    creation_info,
+                )
+            },
+        )
     }
 
     pub fn create_default_data_structures(
@@ -368,76 +612,118 @@ impl VulkanContext {
         creation_info: DataStructureCreationInfo,
     ) -> crate::Result<()> {
         self.find_usable_physical_device()?;
-        if let Some(physical_device) = &self.physical_device {
-            self.surface_info.update(physical_device, window_size)?;
-        }
         self.create_logical_device()?;
         self.create_allocator(creation_info.allocator_debug_settings)?;
-        self.create_swapchain(creation_info.swapchain_present_mode)?;
-        self.create_present_images()?;
         self.create_command_pool_for_setup()?;
-        self.create_depth_image()?;
-        self.create_render_pass()?;
-        self.create_framebuffers()?;
-        self.create_per_frame_data(creation_info.frame_overlap)?;
+        self.create_command_pool_for_transfer()?;
+        self.create_window_data_structures(self.selected_window_id()?, window_size, &creation_info)?;
         self.last_creation_info = Some(creation_info);
 
         Ok(())
     }
 
-    pub fn update_swapchain(&mut self, window_size: (u32, u32)) -> crate::Result<()> {
+    /// This function creates all data structures required for offscreen rendering with a context
+    /// that was loaded using `HeadlessRenderContext::load_headless`.
+    ///
+    /// Only the `allocator_debug_settings` of the `creation_info` are used, since there is no
+    /// swapchain. Use `VulkanContext::create_offscreen_target` to create a render target.
+    pub fn create_headless_data_structures(
+        &mut self,
+        creation_info: DataStructureCreationInfo,
+    ) -> crate::Result<()> {
+        self.find_usable_headless_physical_device()?;
+        self.create_logical_device()?;
+        self.create_allocator(creation_info.allocator_debug_settings)?;
+        self.create_command_pool_for_setup()?;
+        self.create_command_pool_for_transfer()?;
+        self.last_creation_info = Some(creation_info);
+
+        Ok(())
+    }
+
+    /// This function adds another window to this context.
+    ///
+    /// The new window shares the instance, devices and allocator with all other windows, but gets
+    /// its own surface, swapchain, depth image, render pass, framebuffers and per-frame data.
+    /// `VulkanContext::create_default_data_structures` must have been called before, since the
+    /// window's data structures are created with the same `DataStructureCreationInfo`.
+    ///
+    /// Use `VulkanContext::select_window` to render into the new window.
+    pub fn add_window(&mut self, window: &VoxelarWindow) -> crate::Result<()> {
         let creation_info = self.last_creation_info.context(
-            "No last creation info was set, so data structures can't be recreated".to_string(),
+            "No last creation info was set, so the data structures of the new window can't be created. Use VulkanContext::create_default_data_structures first".to_string(),
         )?;
 
-        if let Some(physical_device) = &self.physical_device {
-            self.surface_info.update(physical_device, window_size)?;
+        let window_id = window.id();
+        crate::verify!(
+            !self.window_targets.contains_key(&window_id),
+            "Window {:?} was already added to the Vulkan context",
+            window_id
+        );
+
+        let window_target = unsafe { WindowTarget::create(window, &self.entry, &self.instance)? };
+        self.window_targets.insert(window_id, window_target);
+
+        let result = self.create_window_data_structures(window_id, window.get_size(), &creation_info);
+        if result.is_err() {
+            // The cleanup is best-effort, the creation error is more useful to the caller
+            let _ = self.remove_window(window_id);
         }
+        result
+    }
 
-        let new_swapchain = self.create_new_swapchain(creation_info.swapchain_present_mode)?;
+    /// This function removes the window with the given id from this context, destroying all of
+    /// its data structures and its surface.
+    ///
+    /// If the removed window was selected, any remaining window will be selected instead.
+    pub fn remove_window(&mut self, window_id: WindowId) -> crate::Result<()> {
+        let mut window_target = self
+            .window_targets
+            .remove(&window_id)
+            .context(format!("Window {:?} was not added to the Vulkan context", window_id))?;
 
         if let Some(logical_device) = self.logical_device.as_ref() {
             logical_device.wait()?;
+            let mut allocator = self.lock_allocator()?;
+            window_target.destroy_data_structures(logical_device, &mut allocator)?;
+        }
+        window_target.destroy_surface();
 
-            if let Some(render_pass) = self.render_pass.as_mut() {
-                render_pass.destroy(&logical_device);
-            }
-
-            if let Some(framebuffers) = self.framebuffers.as_mut() {
-                framebuffers.destroy(&logical_device);
-            }
-
-            if let Some(mut depth_image) = self.depth_image.take() {
-                let mut allocator = self.lock_allocator()?;
-                depth_image.destroy(&logical_device, &mut allocator)?;
-            }
-
-            if let Some(command_pool_for_setup) = self.command_pool_for_setup.as_mut() {
-                command_pool_for_setup.destroy(&logical_device);
-            }
-
-            if let Some(present_images) = self.present_images.as_mut() {
-                present_images.destroy(&logical_device);
-            }
+        if self.selected_window_id == Some(window_id) {
+            self.selected_window_id = self.window_targets.keys().next().copied();
+        }
 
-            for frame in self.frames.iter_mut() {
-                frame.destroy(&logical_device);
-            }
+        Ok(())
+    }
 
-            if let Some(swapchain) = self.swapchain.as_mut() {
-                swapchain.destroy();
-            }
-        }
+    /// This function recreates the swapchain of the window with the given id and all data
+    /// structures that depend on it.
+    pub fn update_window_swapchain(
+        &mut self,
+        window_id: WindowId,
+        window_size: (u32, u32),
+    ) -> crate::Result<()> {
+        let creation_info = self.last_creation_info.context(
+            "No last creation info was set, so data structures can't be recreated".to_string(),
+        )?;
 
-        self.swapchain = Some(new_swapchain);
-        self.create_present_images()?;
-        self.create_command_pool_for_setup()?;
-        self.create_depth_image()?;
-        self.create_render_pass()?;
-        self.create_framebuffers()?;
-        self.create_per_frame_data(creation_info.frame_overlap)?;
+        self.with_window_target(
+            window_id,
+            |instance, physical_device, logical_device, allocator, window_target| {
+                window_target.update_swapchain(
+                    instance,
+                    physical_device,
+                    logical_device,
+                    allocator,
+                    window_size,
+                    &creation_info,
+                )
+            },
+        )
+    }
 
-        Ok(())
+    pub fn update_swapchain(&mut self, window_size: (u32, u32)) -> crate::Result<()> {
+        self.update_window_swapchain(self.selected_window_id()?, window_size)
     }
 
     #[cfg(feature = "egui-integration")]
@@ -449,7 +735,8 @@ impl VulkanContext {
         let size = window.get_size();
         let logical_device = self.logical_device()?;
         let allocator = self.create_allocator_ref()?;
-        let surface_format = self.surface_info.surface_format(0)?;
+        let window_target = self.window_target_for(window.id())?;
+        let surface_format = window_target.surface_info.surface_format(0)?;
         Ok(SetUpEguiIntegration::new(
             &event_loop.event_loop,
             size.0 as u32,
@@ -458,8 +745,8 @@ impl VulkanContext {
             logical_device,
             allocator,
             self.physical_device()?.queue_family_index,
-            logical_device.present_queue,
-            self.swapchain()?,
+            logical_device.graphics_queue,
+            window_target.swapchain()?,
             surface_format,
         ))
     }
@@ -474,7 +761,7 @@ impl VulkanContext {
             window_size.0,
             window_size.1,
             self.swapchain()?,
-            &self.surface_info,
+            self.surface_info()?,
         )
     }
 
@@ -488,11 +775,13 @@ impl VulkanContext {
 
 impl VulkanContext {
     pub fn get_surface_extent(&self) -> crate::Result<Extent2D> {
-        self.surface_info.surface_extent()
+        self.surface_info()?.surface_extent()
     }
 
     pub fn frame_overlap(&self) -> usize {
-        self.frames.len()
+        // All windows are created using the last creation info, so they share the same frame overlap
+        self.last_creation_info
+            .map_or(0, |creation_info| creation_info.frame_overlap as usize)
     }
 
     pub fn submit_immediate_setup_commands<F>(&self, command_buffer_op: F) -> crate::Result<()>
@@ -503,33 +792,123 @@ impl VulkanContext {
 
         let setup_command_buffer = command_pool.get_command_buffer(0);
         let logical_device = self.logical_device()?;
-        let present_queue = logical_device.present_queue;
+        let graphics_queue = logical_device.graphics_queue;
         setup_command_buffer
             .record_commands_for_one_time_submit(logical_device, command_buffer_op)?;
-        setup_command_buffer.submit(logical_device, present_queue, &[], &[], &[])?;
+        setup_command_buffer.submit(logical_device, graphics_queue, &[], &[], &[])?;
         setup_command_buffer.wait_for_fence(logical_device)?;
         setup_command_buffer.reset_fence(logical_device)?;
 
         command_pool.reset(logical_device, CommandPoolResetFlags::empty())
     }
 
-    pub fn select_frame(&mut self, current_frame_index: usize) {
-        self.frames.select(current_frame_index);
+    /// This function records and submits commands to the transfer queue and waits for them to
+    /// complete.
+    ///
+    /// Resources that are used by the commands either have to be created with
+    /// `SharingMode::CONCURRENT`, or their ownership has to be transferred using a
+    /// `QueueOwnershipTransfer`.
+    pub fn submit_immediate_transfer_commands<F>(&self, command_buffer_op: F) -> crate::Result<()>
+    where
+        F: FnOnce(&SetUpLogicalDevice, &SetUpCommandBufferWithFence) -> crate::Result<()>,
+    {
+        let command_pool = self.command_pool_for_transfer()?;
+
+        let transfer_command_buffer = command_pool.get_command_buffer(0);
+        let logical_device = self.logical_device()?;
+        let transfer_queue = logical_device.transfer_queue;
+        transfer_command_buffer
+            .record_commands_for_one_time_submit(logical_device, command_buffer_op)?;
+        transfer_command_buffer.submit(logical_device, transfer_queue, &[], &[], &[])?;
+        transfer_command_buffer.wait_for_fence(logical_device)?;
+        transfer_command_buffer.reset_fence(logical_device)?;
+
+        command_pool.reset(logical_device, CommandPoolResetFlags::empty())
+    }
+
+    pub fn select_frame(&mut self, current_frame_index: usize) -> crate::Result<()> {
+        self.window_target_mut()?.frames.select(current_frame_index);
+        Ok(())
     }
 
+    /// This function waits until the draw buffers of the current frame completed and destroys
+    /// the resources in the deletion queue of the frame afterwards, as well as the `Owned`
+    /// resources that were dropped while the allocator was locked. The descriptor sets that
+    /// were allocated for the frame are freed as well.
     pub fn wait_for_current_frame_draw_buffer_fences(&self) -> crate::Result<()> {
-        let current_frame = self.frames.current();
+        let window_target = self.window_target()?;
+        let current_frame = window_target.frames.current();
+        let logical_device = self.logical_device()?;
 
         for draw_buffer_index in 0..current_frame.draw_buffers_count() {
-            current_frame.wait_for_draw_buffer_fence(self.logical_device()?, draw_buffer_index)?;
+            current_frame.wait_for_draw_buffer_fence(logical_device, draw_buffer_index)?;
+        }
+
+        {
+            let allocator = &mut self.lock_allocator()?;
+            if !current_frame.deletion_queue.is_empty() {
+                current_frame
+                    .deletion_queue
+                    .flush(logical_device, allocator)?;
+            }
+            self.device_handle()?.flush_deletion_queue(allocator)?;
+        }
+        current_frame
+            .descriptor_allocator
+            .borrow_mut()
+            .reset_pools(logical_device)?;
+        let mut ring_buffer = window_target.ring_buffer.try_borrow_mut().ok().context(
+            "The ring buffer is still borrowed while the frame is waited for".to_string(),
+        )?;
+        if let Some(ring_buffer) = ring_buffer.as_mut() {
+            ring_buffer.begin_frame(window_target.frames.current_frame_index());
         }
 
         Ok(())
     }
 
+    /// This function returns the deletion queue of the current frame of the selected window.
+    pub fn deletion_queue(&self) -> crate::Result<&DeletionQueue> {
+        Ok(&self.window_target()?.frames.current().deletion_queue)
+    }
+
+    /// This function allocates a descriptor set with the `layout` that is valid until the next
+    /// time the current frame is waited for (see `wait_for_current_frame_draw_buffer_fences`).
+    pub fn allocate_frame_descriptor_set(
+        &self,
+        layout: DescriptorSetLayout,
+    ) -> crate::Result<DescriptorSet> {
+        self.window_target()?
+            .frames
+            .current()
+            .descriptor_allocator
+            .borrow_mut()
+            .allocate(self.logical_device()?, layout)
+    }
+
+    /// This function returns the layout with the bindings of the `builder` from the layout cache
+    /// of the context, which only builds it once. The layout is destroyed with the context.
+    pub fn create_cached_descriptor_set_layout(
+        &self,
+        builder: DescriptorSetLayoutBuilder,
+    ) -> crate::Result<DescriptorSetLayout> {
+        builder.build_cached(
+            self.logical_device()?,
+            &mut self.descriptor_layout_cache.borrow_mut(),
+        )
+    }
+
+    /// This function destroys the `resource` once the current frame completed, instead of
+    /// waiting for the device to become idle. The `resource` may still be used by the commands
+    /// of the current frame.
+    pub fn defer_destruction<R: DeviceResource + 'static>(&self, resource: R) -> crate::Result<()> {
+        self.deletion_queue()?.push(resource);
+        Ok(())
+    }
+
     pub fn acquire_next_image(&self) -> crate::Result<(u32, bool)> {
         unsafe {
-            let frame = self.frames.current();
+            let frame = self.window_target()?.frames.current();
             let result = self.swapchain()?.swapchain_loader.acquire_next_image(
                 self.swapchain()?.swapchain,
                 std::u64::MAX,
@@ -541,7 +920,13 @@ impl VulkanContext {
                 Err(ash::vk::Result::ERROR_OUT_OF_DATE_KHR | ash::vk::Result::SUBOPTIMAL_KHR) => {
                     Ok((0, true))
                 }
-                other => Ok(other?),
+                other => {
+                    let (present_index, suboptimal) = other?;
+                    self.window_target()?
+                        .current_present_index
+                        .set(Some(present_index));
+                    Ok((present_index, suboptimal))
+                }
             }
         }
     }
@@ -555,7 +940,7 @@ impl VulkanContext {
     {
         let logical_device = self.logical_device()?;
 
-        let current_frame = self.frames.current();
+        let current_frame = self.window_target()?.frames.current();
 
         current_frame.reset_draw_buffer_fence(logical_device, 0)?;
         current_frame.reset_draw_buffer(logical_device, 0)?;
@@ -580,7 +965,7 @@ impl VulkanContext {
     {
         let logical_device = self.logical_device()?;
 
-        let surface_resolution = self.surface_info.surface_extent()?;
+        let surface_resolution = self.get_surface_extent()?;
         let render_pass_begin_info = RenderPassBeginInfo::builder()
             .render_pass(self.render_pass()?.render_pass)
             .framebuffer(self.framebuffers()?.framebuffers[present_index as usize])
@@ -600,33 +985,178 @@ impl VulkanContext {
         Ok(())
     }
 
-    pub fn submit_draw_buffers(&self) -> crate::Result<()> {
-        let current_frame = self.frames.current();
-        let logical_device = self.logical_device()?;
-
-        for draw_buffer_index in 0..current_frame.draw_buffers_count() {
-            current_frame.submit_draw_buffer_to_queue(
-                logical_device,
-                draw_buffer_index,
-                logical_device.present_queue,
-                &[PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT],
-            )?;
+    /// This function returns the attachment formats of the selected window, which are used to
+    /// create pipelines for `VulkanContext::record_dynamic_rendering`.
+    pub fn rendering_formats(&self) -> crate::Result<RenderingFormats> {
+        let surface_format = self.surface_info()?.surface_format(0)?;
+        let depth_image = self.depth_image()?;
+        let rendering_formats = RenderingFormats::new()
+            .color_format(surface_format.format)
+            .depth_format(depth_image.format);
+        if depth_image.has_stencil() {
+            Ok(rendering_formats.stencil_format(depth_image.format))
+        } else {
+            Ok(rendering_formats)
         }
-        Ok(())
     }
 
-    pub fn present_image(&self, present_index: u32) -> crate::Result<bool> {
-        let frame = self.frames.current();
-        let wait_semaphores = [frame.sync_primitives.rendering_complete_semaphore];
-        let swapchains = [self.swapchain()?.swapchain];
-        let image_indices = [present_index];
-        let present_info = PresentInfoKHR::builder()
-            .wait_semaphores(&wait_semaphores) // &base.rendering_complete_semaphore)
-            .swapchains(&swapchains)
-            .image_indices(&image_indices);
+    /// This function is the equivalent of `VulkanContext::record_render_pass` for dynamic
+    /// rendering: The present image and the depth image of the selected window are rendered
+    /// into directly, so no render pass or framebuffers are needed.
+    ///
+    /// The `clear_values` contain the color clear value followed by the depth clear value.
+    /// Dynamic rendering has to be requested with `DeviceRequirements::dynamic_rendering`.
+    pub fn record_dynamic_rendering<RenderingOp>(
+        &self,
+        present_index: u32,
+        draw_command_buffer: &SetUpCommandBufferWithFence,
+        clear_values: &[ClearValue],
+        mut rendering_op: RenderingOp,
+    ) -> crate::Result<()>
+    where
+        RenderingOp: FnMut() -> crate::Result<()>,
+    {
+        crate::verify!(
+            self.device_requirements.requests_dynamic_rendering(),
+            "Dynamic rendering was not requested in the device requirements"
+        );
+        crate::verify!(
+            clear_values.len() == 2,
+            "Expected a color and a depth clear value, got {} clear values",
+            (clear_values.len())
+        );
 
-        unsafe {
-            let result = self
+        let logical_device = self.logical_device()?;
+        let present_images = self.present_images()?;
+        let present_image = present_images.present_images[present_index as usize];
+        let present_image_view = present_images.present_image_views[present_index as usize];
+        let depth_image = self.depth_image()?;
+        let msaa_color_image = self.window_target()?.msaa_color_image.as_ref();
+
+        let color_subresource_range = ImageSubresourceRange::builder()
+            .aspect_mask(ImageAspectFlags::COLOR)
+            .level_count(1)
+            .layer_count(1)
+            .build();
+        let color_attachment_barrier = |image| {
+            *ImageMemoryBarrier::builder()
+                .image(image)
+                .src_access_mask(AccessFlags::empty())
+                .dst_access_mask(AccessFlags::COLOR_ATTACHMENT_WRITE)
+                .old_layout(ImageLayout::UNDEFINED)
+                .new_layout(ImageLayout::COLOR_ATTACHMENT_OPTIMAL)
+                .subresource_range(color_subresource_range)
+        };
+        let to_depth_attachment_barrier = ImageMemoryBarrier::builder()
+            .image(depth_image.depth_image.image)
+            .src_access_mask(AccessFlags::DEPTH_STENCIL_ATTACHMENT_WRITE)
+            .dst_access_mask(
+                AccessFlags::DEPTH_STENCIL_ATTACHMENT_READ
+                    | AccessFlags::DEPTH_STENCIL_ATTACHMENT_WRITE,
+            )
+            .old_layout(ImageLayout::UNDEFINED)
+            .new_layout(ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL)
+            .subresource_range(depth_image.subresource_range());
+        let to_present_src_barrier = ImageMemoryBarrier::builder()
+            .image(present_image)
+            .src_access_mask(AccessFlags::COLOR_ATTACHMENT_WRITE)
+            .dst_access_mask(AccessFlags::empty())
+            .old_layout(ImageLayout::COLOR_ATTACHMENT_OPTIMAL)
+            .new_layout(ImageLayout::PRESENT_SRC_KHR)
+            .subresource_range(color_subresource_range);
+
+        let mut attachment_barriers = vec![
+            color_attachment_barrier(present_image),
+            *to_depth_attachment_barrier,
+        ];
+        // With MSAA, the multisampled image is rendered into and resolved into the present image
+        let color_attachments = match msaa_color_image {
+            Some(msaa_color_image) => {
+                attachment_barriers.push(color_attachment_barrier(
+                    msaa_color_image.color_image.image,
+                ));
+                [dynamic_rendering::resolved_color_attachment_info(
+                    msaa_color_image.color_image_view.image_view,
+                    present_image_view,
+                    Some(clear_values[0]),
+                )]
+            }
+            None => [dynamic_rendering::color_attachment_info(
+                present_image_view,
+                Some(clear_values[0]),
+            )],
+        };
+        let depth_attachment = dynamic_rendering::depth_attachment_info(
+            depth_image.depth_image_view.image_view,
+            Some(clear_values[1]),
+        );
+
+        unsafe {
+            logical_device.cmd_pipeline_barrier(
+                draw_command_buffer.command_buffer,
+                PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT
+                    | PipelineStageFlags::LATE_FRAGMENT_TESTS,
+                PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT
+                    | PipelineStageFlags::EARLY_FRAGMENT_TESTS,
+                DependencyFlags::empty(),
+                &[],
+                &[],
+                &attachment_barriers,
+            );
+        }
+
+        draw_command_buffer.begin_rendering(
+            logical_device,
+            self.get_surface_extent()?.into(),
+            &color_attachments,
+            Some(&depth_attachment),
+            depth_image.has_stencil().then_some(&depth_attachment),
+        );
+        rendering_op()?;
+        draw_command_buffer.end_rendering(logical_device);
+
+        unsafe {
+            logical_device.cmd_pipeline_barrier(
+                draw_command_buffer.command_buffer,
+                PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
+                PipelineStageFlags::BOTTOM_OF_PIPE,
+                DependencyFlags::empty(),
+                &[],
+                &[],
+                &[*to_present_src_barrier],
+            );
+        }
+
+        Ok(())
+    }
+
+    pub fn submit_draw_buffers(&self) -> crate::Result<()> {
+        let current_frame = self.window_target()?.frames.current();
+        let logical_device = self.logical_device()?;
+
+        for draw_buffer_index in 0..current_frame.draw_buffers_count() {
+            current_frame.submit_draw_buffer_to_queue(
+                logical_device,
+                draw_buffer_index,
+                logical_device.graphics_queue,
+                &[PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT],
+            )?;
+        }
+        Ok(())
+    }
+
+    pub fn present_image(&self, present_index: u32) -> crate::Result<bool> {
+        let frame = self.window_target()?.frames.current();
+        let wait_semaphores = [frame.sync_primitives.rendering_complete_semaphore];
+        let swapchains = [self.swapchain()?.swapchain];
+        let image_indices = [present_index];
+        let present_info = PresentInfoKHR::builder()
+            .wait_semaphores(&wait_semaphores) // &base.rendering_complete_semaphore)
+            .swapchains(&swapchains)
+            .image_indices(&image_indices);
+
+        unsafe {
+            let result = self
                 .swapchain()?
                 .swapchain_loader
                 .queue_present(self.logical_device()?.present_queue, &present_info);
@@ -639,11 +1169,79 @@ impl VulkanContext {
         }
     }
 
+    /// This function copies the most recently acquired present image of the selected window into
+    /// CPU memory.
+    ///
+    /// This function must be called after `VulkanContext::submit_draw_buffers` and before
+    /// `VulkanContext::present_image`, because the image is owned by the presentation engine
+    /// afterwards. Colors are returned as stored in the surface format, i.e. values of sRGB
+    /// surfaces are already gamma-encoded.
+    pub fn capture_screenshot(&self) -> crate::Result<RgbaImage> {
+        let window_target = self.window_target()?;
+        let present_index = window_target.current_present_index.get().context(
+            "No present image was acquired yet! Use VulkanContext::acquire_next_image to do so"
+                .to_string(),
+        )?;
+
+        let swapchain = window_target.swapchain()?;
+        crate::verify!(
+            swapchain.supports_transfer_src(),
+            "The swapchain images don't support TRANSFER_SRC usage, so screenshots can't be captured"
+        );
+
+        let surface_format = window_target.surface_info.surface_format(0)?;
+        crate::verify!(
+            readback::is_format_convertible_to_rgba(surface_format.format),
+            "Screenshots of surfaces with format {:?} are not supported",
+            (surface_format.format)
+        );
+
+        let surface_extent = window_target.surface_info.surface_extent()?;
+        let present_images = window_target.present_images()?;
+        let logical_device = self.logical_device()?;
+        unsafe {
+            let mut readback_buffer = SetUpReadbackBuffer::<u8>::allocate(
+                logical_device,
+                &mut self.lock_allocator()?,
+                surface_extent.width as usize * surface_extent.height as usize * 4,
+            )?;
+
+            // Commands on the same queue are executed after the submitted draw buffers
+            self.submit_immediate_setup_commands(|device, command_buffer| {
+                present_images.record_copy_to_readback_buffer(
+                    device,
+                    command_buffer,
+                    present_index,
+                    surface_extent,
+                    &readback_buffer,
+                )
+            })?;
+            let data = readback_buffer.copy_to_vec()?;
+            readback_buffer.destroy(logical_device, &mut self.lock_allocator()?)?;
+
+            readback::rgba_image_from_raw(
+                surface_extent.width,
+                surface_extent.height,
+                surface_format.format,
+                data,
+            )
+        }
+    }
+
+    /// This function captures a screenshot using `VulkanContext::capture_screenshot` and saves it
+    /// to the given path. The image format is derived from the file extension (e.g. `.png`).
+    pub fn save_screenshot<P: AsRef<Path>>(&self, path: P) -> crate::Result<()> {
+        let screenshot = self.capture_screenshot()?;
+        screenshot.save(path)?;
+        Ok(())
+    }
+
     pub fn copy_data_to_buffer<T: Copy>(
         &self,
         buffer: &TypedAllocatedBuffer<T>,
         data: &[T],
     ) -> crate::Result<()> {
+        validation::verify_data_length("buffer", data.len(), buffer.element_amount)?;
         let allocator = &mut self.lock_allocator()?;
         let logical_device = self.logical_device()?;
         let element_amount = buffer.element_amount;
@@ -659,102 +1257,327 @@ impl VulkanContext {
         Ok(())
     }
 
-    pub fn create_vertex_buffer<T: Copy>(
+    /// This function writes the data into the mapped memory of the storage `buffer` and flushes
+    /// it, so that it's visible to the device.
+    ///
+    /// The data has to contain exactly one element for each element of the buffer. The function
+    /// waits until the device is idle before writing, since the buffer may still be read by
+    /// frames in flight.
+    pub fn copy_data_to_storage_buffer<T: Copy>(
         &self,
+        buffer: &SetUpStorageBuffer<T>,
         data: &[T],
-    ) -> crate::Result<TypedAllocatedBuffer<T>> {
+    ) -> crate::Result<()> {
+        validation::verify_data_length("storage buffer", data.len(), buffer.element_amount)?;
+        let logical_device = self.logical_device()?;
         unsafe {
-            let data_amount = data.len();
-            let buffer = TypedAllocatedBuffer::<T>::allocate_vertex_buffer(
-                self.logical_device()?,
-                &mut self.lock_allocator()?,
-                data_amount,
-            )?;
-            self.copy_data_to_buffer(&buffer, data)?;
-            Ok(buffer)
+            logical_device.device_wait_idle()?;
+            buffer.copy_from_slice(data)?;
+            buffer.flush_all_memory(logical_device)?;
         }
+        Ok(())
     }
 
-    pub fn create_index_buffer<T: Copy>(
+    /// This function copies the data to the buffer using the transfer queue, so that the upload
+    /// doesn't occupy the graphics queue.
+    ///
+    /// If the buffer is `EXCLUSIVE`, its ownership is released by the transfer queue and acquired
+    /// by the graphics queue afterwards (see `QueueOwnershipTransfer`). `CONCURRENT` buffers can
+    /// be used on the graphics queue without an ownership transfer.
+    pub fn copy_data_to_buffer_on_transfer_queue<T: Copy>(
         &self,
+        buffer: &TypedAllocatedBuffer<T>,
         data: &[T],
-    ) -> crate::Result<TypedAllocatedBuffer<T>> {
+    ) -> crate::Result<()> {
+        validation::verify_data_length("buffer", data.len(), buffer.element_amount)?;
+        let allocator = &mut self.lock_allocator()?;
+        let logical_device = self.logical_device()?;
+        let element_amount = buffer.element_amount;
+        let queue_family_indices = logical_device.queue_family_indices;
+        let ownership_transfer = QueueOwnershipTransfer {
+            src_queue_family_index: queue_family_indices.transfer_or_graphics(),
+            dst_queue_family_index: queue_family_indices.graphics,
+        };
+        let transfers_ownership = buffer.buffer.sharing_mode == SharingMode::EXCLUSIVE;
         unsafe {
-            let data_amount = data.len();
-            let buffer = TypedAllocatedBuffer::<T>::allocate_index_buffer(
+            let mut staging_buffer =
+                SetUpStagingBuffer::allocate(logical_device, allocator, element_amount)?;
+            staging_buffer.copy_from_slice(logical_device, data)?;
+            let result = self.submit_immediate_transfer_commands(|device, transfer_command_buffer| {
+                buffer.copy_from_staging_buffer(device, &staging_buffer, transfer_command_buffer)?;
+                if transfers_ownership {
+                    ownership_transfer.record_buffer_release(
+                        device,
+                        transfer_command_buffer,
+                        BufferOwnershipRange::whole(buffer.buffer.buffer),
+                        QueueAccess::new(
+                            AccessFlags::TRANSFER_WRITE,
+                            PipelineStageFlags::TRANSFER,
+                        ),
+                    );
+                }
+                Ok(())
+            });
+            staging_buffer.destroy(logical_device, allocator)?;
+            result?;
+
+            if transfers_ownership {
+                self.submit_immediate_setup_commands(|device, setup_command_buffer| {
+                    ownership_transfer.record_buffer_acquire(
+                        device,
+                        setup_command_buffer,
+                        BufferOwnershipRange::whole(buffer.buffer.buffer),
+                        QueueAccess::new(
+                            AccessFlags::MEMORY_READ,
+                            PipelineStageFlags::ALL_COMMANDS,
+                        ),
+                    );
+                    Ok(())
+                })?;
+            }
+        }
+        Ok(())
+    }
+
+    /// This function allocates a buffer with `element_amount` elements of type `T`. The element
+    /// amount and the usage flags are validated (see `validation`), so that no invalid buffer is
+    /// passed to Vulkan.
+    pub fn allocate_buffer<T: 'static>(
+        &self,
+        element_amount: usize,
+        usage: BufferUsageFlags,
+        sharing_mode: SharingMode,
+        memory_location: MemoryLocation,
+    ) -> crate::Result<Owned<TypedAllocatedBuffer<T>>> {
+        validation::buffer_size_in_bytes::<T>(element_amount)?;
+        validation::verify_buffer_usage(usage)?;
+
+        let buffer = unsafe {
+            TypedAllocatedBuffer::<T>::allocate(
                 self.logical_device()?,
                 &mut self.lock_allocator()?,
-                data_amount,
-            )?;
-            self.copy_data_to_buffer(&buffer, data)?;
-            Ok(buffer)
+                element_amount,
+                usage,
+                sharing_mode,
+                memory_location,
+            )?
+        };
+        self.own(buffer)
+    }
+
+    pub fn create_vertex_buffer<T: Copy + 'static>(
+        &self,
+        data: &[T],
+    ) -> crate::Result<Owned<TypedAllocatedBuffer<T>>> {
+        let buffer = self.allocate_buffer(
+            data.len(),
+            BufferUsageFlags::VERTEX_BUFFER | BufferUsageFlags::TRANSFER_DST,
+            SharingMode::EXCLUSIVE,
+            MemoryLocation::GpuOnly,
+        )?;
+        self.copy_data_to_buffer(&buffer, data)?;
+        Ok(buffer)
+    }
+
+    pub fn create_index_buffer<T: Copy + 'static>(
+        &self,
+        data: &[T],
+    ) -> crate::Result<Owned<TypedAllocatedBuffer<T>>> {
+        let buffer = self.allocate_buffer(
+            data.len(),
+            BufferUsageFlags::INDEX_BUFFER | BufferUsageFlags::TRANSFER_DST,
+            SharingMode::EXCLUSIVE,
+            MemoryLocation::GpuOnly,
+        )?;
+        self.copy_data_to_buffer(&buffer, data)?;
+        Ok(buffer)
+    }
+
+    /// This function allocates an empty dynamic buffer with space for `capacity` elements, e.g.
+    /// with `BufferUsageFlags::VERTEX_BUFFER` for the vertices of a chunk mesh.
+    pub fn create_dynamic_buffer<T: 'static>(
+        &self,
+        capacity: usize,
+        usage: BufferUsageFlags,
+    ) -> crate::Result<Owned<DynamicGpuBuffer<T>>> {
+        validation::buffer_size_in_bytes::<T>(capacity.max(1))?;
+        validation::verify_buffer_usage(usage)?;
+        let buffer = unsafe {
+            DynamicGpuBuffer::allocate(
+                self.logical_device()?,
+                &mut self.lock_allocator()?,
+                capacity,
+                usage,
+            )?
+        };
+        self.own(buffer)
+    }
+
+    /// This function uploads the changes of the dynamic `buffer` with the setup command buffer
+    /// and waits for the upload to complete.
+    ///
+    /// The changes are written into a new buffer, since the previous buffer may still be used by
+    /// frames in flight. It's destroyed through the deletion queue of the current frame (or
+    /// right away if there is no window to render to).
+    pub fn upload_dynamic_buffer<T: Copy + 'static>(
+        &self,
+        buffer: &mut DynamicGpuBuffer<T>,
+    ) -> crate::Result<()> {
+        if !buffer.needs_upload() {
+            return Ok(());
+        }
+
+        let logical_device = self.logical_device()?;
+        let allocator = &mut self.lock_allocator()?;
+        let mut upload = None;
+        let result = self.submit_immediate_setup_commands(|device, setup_command_buffer| {
+            upload = Some(buffer.record_upload(device, allocator, setup_command_buffer)?);
+            Ok(())
+        });
+
+        // The buffers of a recorded upload are released even if the submission failed
+        let upload = match upload {
+            Some(upload) => upload,
+            None => {
+                result?;
+                crate::bail!("The upload of the dynamic buffer wasn't recorded")
+            }
+        };
+        if let Some(mut staging_buffer) = upload.staging_buffer {
+            staging_buffer.destroy(logical_device, allocator)?;
         }
+        let mut previous_buffer = upload.previous_buffer;
+        match self.deletion_queue() {
+            Ok(deletion_queue) => deletion_queue.push(previous_buffer),
+            Err(_) => previous_buffer.destroy(logical_device, allocator)?,
+        }
+        result
     }
 
-    pub fn allocate_static_uniform_buffer<T>(&self) -> crate::Result<SetUpUniformBuffer<T>> {
-        unsafe {
+    pub fn allocate_static_uniform_buffer<T: 'static>(
+        &self,
+    ) -> crate::Result<Owned<SetUpUniformBuffer<T>>> {
+        let buffer = unsafe {
             SetUpUniformBuffer::<T>::allocate_static_uniform_buffer(
                 self.logical_device()?,
                 self.physical_device()?,
                 &mut self.lock_allocator()?,
-            )
-        }
+            )?
+        };
+        self.own(buffer)
     }
 
-    pub fn allocate_dynamic_uniform_buffer<T>(
+    pub fn allocate_dynamic_uniform_buffer<T: 'static>(
         &self,
         count: usize,
-    ) -> crate::Result<SetUpUniformBuffer<T>> {
-        unsafe {
+    ) -> crate::Result<Owned<SetUpUniformBuffer<T>>> {
+        validation::buffer_size_in_bytes::<T>(count)?;
+        let buffer = unsafe {
             SetUpUniformBuffer::<T>::allocate_dynamic_uniform_buffer(
                 self.logical_device()?,
                 self.physical_device()?,
                 &mut self.lock_allocator()?,
                 count,
-            )
-        }
+            )?
+        };
+        self.own(buffer)
     }
 
-    pub fn allocate_storage_buffer<T>(&self, count: usize) -> crate::Result<SetUpStorageBuffer<T>> {
-        unsafe {
+    pub fn allocate_storage_buffer<T: 'static>(
+        &self,
+        count: usize,
+    ) -> crate::Result<Owned<SetUpStorageBuffer<T>>> {
+        validation::buffer_size_in_bytes::<T>(count)?;
+        let buffer = unsafe {
             SetUpStorageBuffer::<T>::allocate(
                 self.logical_device()?,
                 &mut self.lock_allocator()?,
                 count,
-            )
+            )?
+        };
+        self.own(buffer)
+    }
+
+    /// This function allocates the ring buffer of the selected window with one region of
+    /// `frame_capacity` bytes for each frame in flight. The data pushed to it can be used as
+    /// uniform, storage, vertex and index data; see `SetUpRingBuffer` for how the regions are
+    /// reused. A previous ring buffer of the window is destroyed once the current frame completed.
+    pub fn create_ring_buffer(&self, frame_capacity: usize) -> crate::Result<()> {
+        crate::verify!(
+            self.frame_overlap() > 0,
+            "The frame overlap is unknown, create the default data structures first"
+        );
+        let mut ring_buffer = unsafe {
+            SetUpRingBuffer::allocate(
+                self.logical_device()?,
+                self.physical_device()?,
+                &mut self.lock_allocator()?,
+                frame_capacity,
+                self.frame_overlap(),
+                BufferUsageFlags::UNIFORM_BUFFER
+                    | BufferUsageFlags::STORAGE_BUFFER
+                    | BufferUsageFlags::VERTEX_BUFFER
+                    | BufferUsageFlags::INDEX_BUFFER,
+            )?
+        };
+        let window_target = self.window_target()?;
+        ring_buffer.begin_frame(window_target.frames.current_frame_index());
+
+        let previous_ring_buffer = window_target.ring_buffer.replace(Some(ring_buffer));
+        if let Some(previous_ring_buffer) = previous_ring_buffer {
+            self.defer_destruction(previous_ring_buffer)?;
         }
+        Ok(())
+    }
+
+    /// This function returns the ring buffer of the selected window (see
+    /// `VulkanContext::create_ring_buffer`). The region of the current frame is reset by
+    /// `VulkanContext::wait_for_current_frame_draw_buffer_fences`, so the returned borrow must
+    /// not be held across that call.
+    pub fn ring_buffer(&self) -> crate::Result<RefMut<'_, SetUpRingBuffer>> {
+        let ring_buffer = self
+            .window_target()?
+            .ring_buffer
+            .try_borrow_mut()
+            .ok()
+            .context("The ring buffer is already borrowed".to_string())?;
+        RefMut::filter_map(ring_buffer, Option::as_mut).ok().context(
+            "No ring buffer was set up yet! Use VulkanContext::create_ring_buffer to do so"
+                .to_string(),
+        )
     }
 
     pub fn create_shader_of_stage(
         &self,
         compiled_bytes: Vec<u8>,
         stage: ShaderStageFlags,
-    ) -> crate::Result<CompiledShaderModule> {
-        unsafe {
+    ) -> crate::Result<Owned<CompiledShaderModule>> {
+        let shader_module = unsafe {
             CompiledShaderModule::create_shader_of_stage(
                 compiled_bytes,
                 self.logical_device()?,
                 stage,
-            )
-        }
+            )?
+        };
+        self.own(shader_module)
     }
 
     pub fn create_vertex_shader(
         &self,
         compiled_bytes: Vec<u8>,
-    ) -> crate::Result<CompiledShaderModule> {
+    ) -> crate::Result<Owned<CompiledShaderModule>> {
         self.create_shader_of_stage(compiled_bytes, ShaderStageFlags::VERTEX)
     }
 
     pub fn create_fragment_shader(
         &self,
         compiled_bytes: Vec<u8>,
-    ) -> crate::Result<CompiledShaderModule> {
+    ) -> crate::Result<Owned<CompiledShaderModule>> {
         self.create_shader_of_stage(compiled_bytes, ShaderStageFlags::FRAGMENT)
     }
 
     #[cfg(feature = "shaderc-crate")]
-    pub fn load_vertex_shader(&self, path: &str) -> crate::Result<CompiledShaderModule> {
+    pub fn load_vertex_shader(&self, path: &str) -> crate::Result<Owned<CompiledShaderModule>> {
         use shaderc::ShaderKind;
         let src = std::fs::read_to_string(path)?;
         let compiled_vert = shader::compile_bytes(ShaderKind::Vertex, src.as_str(), path)?;
@@ -762,59 +1585,517 @@ impl VulkanContext {
     }
 
     #[cfg(feature = "shaderc-crate")]
-    pub fn load_fragment_shader(&self, path: &str) -> crate::Result<CompiledShaderModule> {
+    pub fn load_fragment_shader(&self, path: &str) -> crate::Result<Owned<CompiledShaderModule>> {
         use shaderc::ShaderKind;
         let src = std::fs::read_to_string(path)?;
         let compiled_vert = shader::compile_bytes(ShaderKind::Fragment, src.as_str(), path)?;
         self.create_fragment_shader(compiled_vert)
     }
 
+    /// This function recompiles the shaders of the `hot_reloader` that changed and rebuilds
+    /// their pipelines. It should be called after `wait_for_current_frame_draw_buffer_fences`,
+    /// because the replaced pipelines are destroyed with the deletion queue of the frame.
+    ///
+    /// The result of each reloaded pipeline is returned, see `ShaderHotReloader::reload_changed`.
+    #[cfg(feature = "shaderc-crate")]
+    pub fn reload_changed_shaders(
+        &self,
+        hot_reloader: &mut ShaderHotReloader,
+    ) -> crate::Result<Vec<(HotReloadPipelineId, crate::Result<()>)>> {
+        Ok(hot_reloader.reload_changed(self.logical_device()?, self.deletion_queue()?))
+    }
+
     pub fn create_texture<T>(
         &self,
         format: Format,
         texture_dimensions: Extent3D,
         data: &[T],
-    ) -> crate::Result<Texture<T>>
+    ) -> crate::Result<Owned<Texture<T>>>
     where
-        T: Copy,
+        T: Copy + 'static,
     {
-        unsafe {
-            let logical_device = self.logical_device()?;
-            let mut texture = Texture::<T>::create(
+        self.create_texture_with_mip_levels(format, texture_dimensions, data, 1)
+    }
+
+    /// This function creates a texture with a full mip chain, which is generated on the GPU.
+    pub fn create_mipmapped_texture<T>(
+        &self,
+        format: Format,
+        texture_dimensions: Extent3D,
+        data: &[T],
+    ) -> crate::Result<Owned<Texture<T>>>
+    where
+        T: Copy + 'static,
+    {
+        let mip_levels =
+            full_mip_level_count(texture_dimensions.width, texture_dimensions.height);
+        self.create_texture_with_mip_levels(format, texture_dimensions, data, mip_levels)
+    }
+
+    /// This function creates a texture with `mip_levels` mip levels. The `data` is copied into
+    /// the first level, and the other levels are generated from it by blitting. This requires
+    /// a format that supports linear blits.
+    pub fn create_texture_with_mip_levels<T>(
+        &self,
+        format: Format,
+        texture_dimensions: Extent3D,
+        data: &[T],
+        mip_levels: u32,
+    ) -> crate::Result<Owned<Texture<T>>>
+    where
+        T: Copy + 'static,
+    {
+        self.verify_texture_parameters(format, texture_dimensions, 1, mip_levels)?;
+        let texel_count = texture_dimensions.width as usize * texture_dimensions.height as usize;
+        validation::verify_data_length(
+            "texture",
+            data.len(),
+            texel_count * texture_dimensions.depth as usize,
+        )?;
+
+        let mut texture = self.own(unsafe {
+            Texture::<T>::create_with_mip_levels(
                 self.logical_device()?,
                 &mut self.lock_allocator()?,
                 format,
                 texture_dimensions,
-            )?;
+                mip_levels,
+            )?
+        })?;
+        self.upload_texture_data(&mut texture, data)?;
+        Ok(texture)
+    }
+
+    /// This function creates a texture array from `layers` of the same size, e.g. the textures
+    /// of all block types. The `layer_dimensions` use the same convention as in
+    /// `create_texture` (the depth is the number of channels). If `mip_levels` is greater than
+    /// 1, the mip levels of all layers are generated on the GPU.
+    pub fn create_texture_array<T>(
+        &self,
+        format: Format,
+        layer_dimensions: Extent3D,
+        layers: &[&[T]],
+        mip_levels: u32,
+    ) -> crate::Result<Owned<Texture<T>>>
+    where
+        T: Copy + 'static,
+    {
+        self.verify_texture_parameters(format, layer_dimensions, layers.len() as u32, mip_levels)?;
+        let layer_size = layer_dimensions.width as usize
+            * layer_dimensions.height as usize
+            * layer_dimensions.depth as usize;
+        for (index, layer) in layers.iter().enumerate() {
+            crate::verify!(
+                layer.len() == layer_size,
+                "Layer {} of the texture array has {} elements, expected {}",
+                index,
+                (layer.len()),
+                layer_size
+            );
+        }
+
+        let mut texture = self.own(unsafe {
+            Texture::<T>::create_array(
+                self.logical_device()?,
+                &mut self.lock_allocator()?,
+                format,
+                layer_dimensions,
+                layers.len() as u32,
+                mip_levels,
+            )?
+        })?;
+        self.upload_texture_data(&mut texture, &layers.concat())?;
+        Ok(texture)
+    }
+
+    /// This function checks the parameters of a texture against the limits of the device and
+    /// checks that the `format` can be sampled and uploaded to (and blitted with linear
+    /// filtering, if mipmaps are generated).
+    fn verify_texture_parameters(
+        &self,
+        format: Format,
+        texture_dimensions: Extent3D,
+        array_layers: u32,
+        mip_levels: u32,
+    ) -> crate::Result<()> {
+        let physical_device = self.physical_device()?;
+        let limits = &physical_device.device_properties.limits;
+        validation::verify_texture_extent(texture_dimensions, limits)?;
+        validation::verify_array_layers(array_layers, limits)?;
+        crate::verify!(
+            mip_levels >= 1
+                && mip_levels
+                    <= full_mip_level_count(texture_dimensions.width, texture_dimensions.height),
+            "A {}x{} texture can't have {} mip levels",
+            (texture_dimensions.width),
+            (texture_dimensions.height),
+            mip_levels
+        );
+
+        validation::verify_format_features(
+            &self.instance,
+            physical_device,
+            format,
+            ImageTiling::OPTIMAL,
+            FormatFeatureFlags::SAMPLED_IMAGE | FormatFeatureFlags::TRANSFER_DST,
+        )?;
+        crate::verify!(
+            mip_levels == 1 || physical_device.supports_linear_blit(&self.instance, format),
+            "The format {:?} doesn't support linear blits, so mipmaps can't be generated",
+            format
+        );
+        Ok(())
+    }
+
+    /// This function copies the `data` into the first mip level of the `texture`, generates the
+    /// other mip levels and leaves the texture in `SHADER_READ_ONLY_OPTIMAL` layout.
+    fn upload_texture_data<T>(&self, texture: &mut Texture<T>, data: &[T]) -> crate::Result<()>
+    where
+        T: Copy,
+    {
+        let layer_size = texture.image.image.full_image_size() as usize;
+        validation::verify_data_length(
+            "texture",
+            data.len(),
+            layer_size * texture.array_layers as usize,
+        )?;
 
-            let element_amount =
-                texture_dimensions.width * texture_dimensions.height * texture_dimensions.depth;
+        unsafe {
+            let logical_device = self.logical_device()?;
             let mut staging_buffer = SetUpStagingBuffer::allocate(
                 logical_device,
                 &mut self.lock_allocator()?,
-                element_amount as usize,
+                data.len(),
             )?;
             staging_buffer.copy_from_slice(logical_device, data)?;
 
             self.submit_immediate_setup_commands(|device, setup_command_buffer| {
                 texture.layout_transition_to_copy_target(device, setup_command_buffer);
                 texture.copy_from_staging_buffer(device, &staging_buffer, setup_command_buffer)?;
-                texture.layout_transition_to_shader_readable(device, setup_command_buffer);
+                texture.generate_mipmaps(device, setup_command_buffer);
                 Ok(())
             })?;
 
             staging_buffer.destroy(logical_device, &mut self.lock_allocator()?)?;
-
-            Ok(texture)
+            Ok(())
         }
     }
 
-    pub unsafe fn create_sampler(
+    pub fn create_sampler(
+        &self,
+        filter: Filter,
+        sampler_address_mode: SamplerAddressMode,
+    ) -> crate::Result<Owned<SetUpSampler>> {
+        let sampler = unsafe {
+            SetUpSampler::create_with_defaults(
+                self.logical_device()?,
+                filter,
+                sampler_address_mode,
+            )?
+        };
+        self.own(sampler)
+    }
+
+    /// This function creates a sampler for textures with `mip_levels` mip levels, which blends
+    /// linearly between the levels.
+    ///
+    /// Anisotropic filtering requires the `sampler_anisotropy` feature (see
+    /// `DeviceRequirements::sampler_anisotropy`), and the `max_anisotropy` is clamped to the
+    /// limit of the device.
+    pub fn create_mipmapped_sampler(
         &self,
         filter: Filter,
         sampler_address_mode: SamplerAddressMode,
-    ) -> crate::Result<SetUpSampler> {
-        unsafe { SetUpSampler::create(self.logical_device()?, filter, sampler_address_mode) }
+        mip_levels: u32,
+        max_anisotropy: Option<f32>,
+    ) -> crate::Result<Owned<SetUpSampler>> {
+        crate::verify!(
+            mip_levels >= 1,
+            "A mipmapped sampler needs at least one mip level"
+        );
+        self.create_sampler_with_lod(
+            filter,
+            sampler_address_mode,
+            SamplerMipmapMode::LINEAR,
+            SamplerLod::for_mip_levels(mip_levels),
+            max_anisotropy,
+        )
+    }
+
+    /// This function creates a sampler with the given `lod` parameters, which are validated
+    /// against the limits of the device. Anisotropic filtering is handled like in
+    /// `create_mipmapped_sampler`.
+    pub fn create_sampler_with_lod(
+        &self,
+        filter: Filter,
+        sampler_address_mode: SamplerAddressMode,
+        mipmap_mode: SamplerMipmapMode,
+        lod: SamplerLod,
+        max_anisotropy: Option<f32>,
+    ) -> crate::Result<Owned<SetUpSampler>> {
+        let physical_device = self.physical_device()?;
+        validation::verify_sampler_lod(&lod, &physical_device.device_properties.limits)?;
+        validation::verify_max_anisotropy(max_anisotropy)?;
+        if max_anisotropy.is_some() {
+            crate::verify!(
+                self.device_requirements.requests_sampler_anisotropy(),
+                "Anisotropic filtering requires the sampler_anisotropy feature, use DeviceRequirements::sampler_anisotropy to request it"
+            );
+        }
+        let device_max_anisotropy = physical_device.max_sampler_anisotropy();
+        let max_anisotropy =
+            max_anisotropy.map(|max_anisotropy| max_anisotropy.min(device_max_anisotropy));
+
+        let sampler = unsafe {
+            SetUpSampler::create(
+                self.logical_device()?,
+                filter,
+                sampler_address_mode,
+                mipmap_mode,
+                lod,
+                max_anisotropy,
+            )?
+        };
+        self.own(sampler)
+    }
+
+    /// This function creates a bindless registry for up to `capacity` textures that can be
+    /// sampled in the shader stages `stage_flags`. It requires the descriptor indexing features
+    /// (see `DeviceRequirements::descriptor_indexing`).
+    pub fn create_texture_registry(
+        &self,
+        capacity: u32,
+        stage_flags: ShaderStageFlags,
+    ) -> crate::Result<Owned<SetUpTextureRegistry>> {
+        crate::verify!(
+            self.device_requirements.requests_descriptor_indexing(),
+            "A texture registry requires the descriptor indexing features, use DeviceRequirements::descriptor_indexing to request them"
+        );
+        let texture_registry =
+            unsafe { SetUpTextureRegistry::create(self.logical_device()?, capacity, stage_flags)? };
+        self.own(texture_registry)
+    }
+
+    /// This function creates an offscreen target with the most preferred depth format that is
+    /// supported by the physical device.
+    pub fn create_offscreen_target(
+        &self,
+        extent: Extent2D,
+    ) -> crate::Result<Owned<SetUpOffscreenTarget>> {
+        let depth_format = self
+            .physical_device()?
+            .find_depth_format(&self.instance, &DepthBufferInfo::default())?;
+        let offscreen_target = unsafe {
+            SetUpOffscreenTarget::create_with_defaults(
+                self.logical_device()?,
+                &mut self.lock_allocator()?,
+                extent,
+                depth_format,
+            )?
+        };
+        self.own(offscreen_target)
+    }
+
+    /// This function records a render pass into the offscreen target, submits it and waits until
+    /// rendering has finished.
+    pub fn render_offscreen<RenderPassOp>(
+        &self,
+        offscreen_target: &SetUpOffscreenTarget,
+        clear_values: &[ClearValue],
+        render_pass_op: RenderPassOp,
+    ) -> crate::Result<()>
+    where
+        RenderPassOp: FnOnce(&SetUpLogicalDevice, &SetUpCommandBufferWithFence) -> crate::Result<()>,
+    {
+        self.submit_immediate_setup_commands(|device, command_buffer| {
+            offscreen_target.record_render_pass(device, command_buffer, clear_values, || {
+                render_pass_op(device, command_buffer)
+            })
+        })
+    }
+
+    /// This function copies the color image of the offscreen target into CPU memory.
+    ///
+    /// The offscreen target must have been rendered to using `VulkanContext::render_offscreen`
+    /// before.
+    pub fn read_back_offscreen_target(
+        &self,
+        offscreen_target: &SetUpOffscreenTarget,
+    ) -> crate::Result<RgbaImage> {
+        let logical_device = self.logical_device()?;
+        unsafe {
+            let mut readback_buffer = SetUpReadbackBuffer::<u8>::allocate(
+                logical_device,
+                &mut self.lock_allocator()?,
+                offscreen_target.color_image_size(),
+            )?;
+
+            self.submit_immediate_setup_commands(|device, command_buffer| {
+                offscreen_target.record_copy_to_readback_buffer(
+                    device,
+                    command_buffer,
+                    &readback_buffer,
+                )
+            })?;
+            let data = readback_buffer.copy_to_vec()?;
+            readback_buffer.destroy(logical_device, &mut self.lock_allocator()?)?;
+
+            readback::rgba_image_from_raw(
+                offscreen_target.extent.width,
+                offscreen_target.extent.height,
+                offscreen_target.color_format,
+                data,
+            )
+        }
+    }
+
+    /// This function returns the description for importing the present images of the selected
+    /// window into a render graph. The images are transitioned to `PRESENT_SRC_KHR` at the end of
+    /// the graph.
+    pub fn present_image_description(&self) -> crate::Result<ImportedImageDescription> {
+        Ok(ImportedImageDescription {
+            format: self.surface_info()?.surface_format(0)?.format,
+            initial_layout: ImageLayout::UNDEFINED,
+            final_layout: ImageLayout::PRESENT_SRC_KHR,
+        })
+    }
+
+    /// This function compiles the render graph for the surface extent of the selected window.
+    pub fn compile_render_graph(&self, render_graph: &mut RenderGraph) -> crate::Result<()> {
+        render_graph.compile(
+            self.logical_device()?,
+            &mut self.lock_allocator()?,
+            self.get_surface_extent()?,
+        )
+    }
+
+    /// This function recreates the size-dependent resources of the render graph after the
+    /// swapchain of the selected window was updated.
+    pub fn resize_render_graph(&self, render_graph: &mut RenderGraph) -> crate::Result<()> {
+        render_graph.resize(
+            self.logical_device()?,
+            &mut self.lock_allocator()?,
+            self.get_surface_extent()?,
+        )
+    }
+
+    /// This function binds the present image with the given index to the imported `resource` of
+    /// the render graph.
+    pub fn bind_present_image_to_render_graph(
+        &self,
+        render_graph: &mut RenderGraph,
+        resource: ResourceId,
+        present_index: u32,
+    ) -> crate::Result<()> {
+        let present_images = self.present_images()?;
+        let index = present_index as usize;
+        let image = *present_images
+            .present_images
+            .get(index)
+            .context(format!("No present image with index {} exists", present_index))?;
+        render_graph.bind_imported_image(
+            resource,
+            image,
+            present_images.present_image_views[index],
+            self.get_surface_extent()?,
+        )
+    }
+
+    pub fn destroy_render_graph(&self, render_graph: &mut RenderGraph) -> crate::Result<()> {
+        render_graph.destroy(self.logical_device()?, &mut self.lock_allocator()?)
+    }
+
+    /// This function creates a post-process chain for the present images of the selected
+    /// window. Bloom is only enabled if `bloom_settings` are given.
+    pub fn create_post_process_chain(
+        &self,
+        shaders: &PostProcessShaders,
+        bloom_settings: Option<BloomSettings>,
+    ) -> crate::Result<Owned<PostProcessChain>> {
+        let window_target = self.window_target()?;
+        let chain = unsafe {
+            PostProcessChain::create(
+                self.logical_device()?,
+                &mut self.lock_allocator()?,
+                shaders,
+                window_target.surface_info(),
+                window_target.present_images()?,
+                window_target.depth_format,
+                bloom_settings,
+            )?
+        };
+        self.own(chain)
+    }
+
+    /// This function recreates the size-dependent resources of the post-process chain after the
+    /// swapchain of the selected window was updated.
+    pub fn resize_post_process_chain(&self, chain: &mut PostProcessChain) -> crate::Result<()> {
+        let window_target = self.window_target()?;
+        let logical_device = self.logical_device()?;
+        logical_device.wait()?;
+        unsafe {
+            chain.resize(
+                logical_device,
+                &mut self.lock_allocator()?,
+                window_target.surface_info(),
+                window_target.present_images()?,
+            )
+        }
+    }
+
+    /// This function destroys the post-process chain right away and returns the errors that
+    /// dropping it would ignore.
+    pub fn destroy_post_process_chain(&self, chain: Owned<PostProcessChain>) -> crate::Result<()> {
+        chain.destroy_with_allocator(&mut self.lock_allocator()?)
+    }
+
+    /// This function creates a cascaded shadow map with the best supported shadow map format.
+    pub fn create_cascaded_shadow_map(
+        &self,
+        resolution: u32,
+        cascade_count: u32,
+    ) -> crate::Result<Owned<SetUpCascadedShadowMap>> {
+        let format = SetUpCascadedShadowMap::find_format(&self.instance, self.physical_device()?)?;
+        let shadow_map = unsafe {
+            SetUpCascadedShadowMap::create(
+                self.logical_device()?,
+                &mut self.lock_allocator()?,
+                format,
+                resolution,
+                cascade_count,
+            )?
+        };
+        self.own(shadow_map)
+    }
+
+    /// This function destroys the shadow map right away and returns the errors that dropping it
+    /// would ignore.
+    pub fn destroy_cascaded_shadow_map(
+        &self,
+        shadow_map: Owned<SetUpCascadedShadowMap>,
+    ) -> crate::Result<()> {
+        shadow_map.destroy_with_allocator(&mut self.lock_allocator()?)
+    }
+
+    /// This function collects information about the instance, the physical device and the
+    /// swapchain of the selected window (if there is one).
+    pub fn render_info(&self) -> crate::Result<RenderInfo> {
+        let (present_mode, swapchain_format) = match self.window_target() {
+            Ok(window_target) => (
+                Some(window_target.swapchain()?.present_mode),
+                Some(window_target.surface_info.surface_format(0)?),
+            ),
+            Err(_) => (None, None),
+        };
+
+        Ok(RenderInfo::from_physical_device(
+            self.physical_device()?,
+            self.enabled_extensions.clone(),
+            self.logical_device()?.enabled_extensions.clone(),
+            self.enabled_layers.clone(),
+            present_mode,
+            swapchain_format,
+        ))
     }
 
     pub fn wait_for_present_queue(&self) -> crate::Result<()> {
@@ -832,88 +2113,86 @@ impl<Verification: VerificationProvider + 'static> RenderContext<Verification> f
         Self: Sized,
     {
         unsafe {
-            // App info
-            let app_info = Self::create_app_info(&window);
-
-            // Extension names
-            let mut extension_names_raw =
+            let extension_names_raw =
                 ash_window::enumerate_required_extensions(window.raw_display_handle())
                     .unwrap()
                     .to_vec();
+            let (entry, instance, api_version, verification, enabled_extensions, enabled_layers) =
+                Self::create_instance::<Verification>(&window.get_title(), extension_names_raw)?;
 
-            let verification_required_extensions = Verification::get_extensions();
-            let verification_names_raw: Vec<*const c_char> =
-                util::map_vec_ref(&verification_required_extensions, |name| name.as_ptr());
-            extension_names_raw.extend(verification_names_raw);
-
-            #[cfg(any(target_os = "macos", target_os = "ios"))]
-            {
-                extension_names_raw.push(KhrPortabilityEnumerationFn::name().as_ptr());
-                // Enabling this extension is a requirement when using `VK_KHR_portability_subset`
-                extension_names_raw.push(KhrGetPhysicalDeviceProperties2Fn::name().as_ptr());
-            }
+            let window_target = WindowTarget::create(&window, &entry, &instance)?;
+            let window_id = window_target.window_id();
 
-            println!(
-                "Extensions: {:?}",
-                extension_names_raw
-                    .iter()
-                    .map(|ptr| CStr::from_ptr(*ptr).to_str().ok())
-                    .collect::<Vec<Option<&str>>>()
-            );
+            let mut window_targets = HashMap::new();
+            window_targets.insert(window_id, window_target);
 
-            // Layer names
-            let verification_required_layers = Verification::get_layers();
-            println!("Layers: {:?}", verification_required_layers);
+            Ok(Self {
+                entry,
+                instance,
+                api_version,
+                enabled_extensions,
+                enabled_layers,
 
-            let layers_names_raw: Vec<*const c_char> =
-                util::map_vec_ref(&verification_required_layers, |name| name.as_ptr());
+                verification,
 
-            // Create flags
-            let create_flags = if cfg!(any(target_os = "macos", target_os = "ios")) {
-                InstanceCreateFlags::ENUMERATE_PORTABILITY_KHR
-            } else {
-                InstanceCreateFlags::default()
-            };
+                last_creation_info: None,
+                device_requirements: DeviceRequirements::default(),
+                physical_device: None,
+                logical_device: None,
+                allocator: None,
+                device_handle: None,
+                descriptor_layout_cache: RefCell::new(DescriptorLayoutCache::new()),
+                command_pool_for_setup: None,
+                command_pool_for_transfer: None,
 
-            let create_info = InstanceCreateInfo::builder()
-                .application_info(&app_info)
-                .enabled_extension_names(&extension_names_raw)
-                .enabled_layer_names(&layers_names_raw)
-                .flags(create_flags);
+                window_targets,
+                selected_window_id: Some(window_id),
+            })
+        }
+    }
 
-            let entry = Entry::load()?;
-            let instance: Instance = entry.create_instance(&create_info, None)?;
+    type Info = RenderInfo;
 
-            let verification = Box::new(Verification::load(&entry, &instance)?);
+    fn get_info(&self) -> crate::Result<RenderInfo> {
+        self.render_info()
+    }
+}
 
-            let surface_info = SetUpSurfaceInfo::create(&window, &entry, &instance)?;
+impl<Verification: VerificationProvider + 'static> HeadlessRenderContext<Verification>
+    for VulkanContext
+{
+    fn load_headless(_: &mut Voxelar) -> crate::Result<Self>
+    where
+        Self: Sized,
+    {
+        unsafe {
+            let (entry, instance, api_version, verification, enabled_extensions, enabled_layers) =
+                Self::create_instance::<Verification>("voxelar (headless)", vec![])?;
 
             Ok(Self {
                 entry,
                 instance,
-                surface_info,
+                api_version,
+                enabled_extensions,
+                enabled_layers,
 
                 verification,
 
                 last_creation_info: None,
+                device_requirements: DeviceRequirements::default(),
                 physical_device: None,
                 logical_device: None,
                 allocator: None,
-                swapchain: None,
-                present_images: None,
+                device_handle: None,
+                descriptor_layout_cache: RefCell::new(DescriptorLayoutCache::new()),
                 command_pool_for_setup: None,
-                depth_image: None,
-                render_pass: None,
-                framebuffers: None,
+                command_pool_for_transfer: None,
 
-                frames: PerFrame::empty(),
+                window_targets: HashMap::new(),
+                selected_window_id: None,
             })
         }
     }
-
-    fn get_info<T>(&self) -> crate::Result<T> {
-        unimplemented!()
-    }
 }
 
 impl Drop for VulkanContext {
@@ -921,35 +2200,25 @@ impl Drop for VulkanContext {
         if let Some(logical_device) = self.logical_device.as_ref() {
             logical_device.wait().unwrap();
 
-            if let Some(render_pass) = self.render_pass.as_mut() {
-                render_pass.destroy(&logical_device);
+            if let Some(allocator) = self.allocator.as_ref() {
+                let mut allocator = allocator.lock().expect("Unable to acquire allocator mutex lock");
+                for window_target in self.window_targets.values_mut() {
+                    window_target
+                        .destroy_data_structures(&logical_device, &mut allocator)
+                        .expect("Failed to destroy window data structures");
+                }
             }
 
-            if let Some(framebuffers) = self.framebuffers.as_mut() {
-                framebuffers.destroy(&logical_device);
-            }
-
-            if let Some(mut depth_image) = self.depth_image.take() {
-                let mut allocator = self.lock_allocator().expect("No allocator defined");
-                depth_image
-                    .destroy(&logical_device, &mut allocator)
-                    .expect("Failed to destroy depth image");
-            }
+            self.descriptor_layout_cache
+                .get_mut()
+                .destroy(&logical_device);
 
             if let Some(command_pool_for_setup) = self.command_pool_for_setup.as_mut() {
                 command_pool_for_setup.destroy(&logical_device);
             }
 
-            if let Some(present_images) = self.present_images.as_mut() {
-                present_images.destroy(&logical_device);
-            }
-
-            for frame in self.frames.iter_mut() {
-                frame.destroy(&logical_device);
-            }
-
-            if let Some(swapchain) = self.swapchain.as_mut() {
-                swapchain.destroy();
+            if let Some(command_pool_for_transfer) = self.command_pool_for_transfer.as_mut() {
+                command_pool_for_transfer.destroy(&logical_device);
             }
 
             if let Some(allocator) = &mut self.allocator {
@@ -959,13 +2228,22 @@ impl Drop for VulkanContext {
             }
         }
 
+        for window_target in self.window_targets.values_mut() {
+            window_target.destroy_surface();
+        }
+        self.verification.destroy();
+
+        // The device handle destroys the device and the instance once the last `Owned` resource
+        // was dropped
+        if self.device_handle.take().is_some() {
+            return;
+        }
+
         if let Some(logical_device) = self.logical_device.as_mut() {
             logical_device.destroy();
         }
 
         unsafe {
-            self.surface_info.destroy();
-            self.verification.destroy();
             self.instance.destroy_instance(None);
         }
     }
@@ -0,0 +1,105 @@
+use std::sync::MutexGuard;
+
+use ash::vk::Extent3D;
+use ash::vk::Format;
+use ash::vk::SampleCountFlags;
+use ash::vk::SharingMode;
+use ash::vk::{
+    ImageAspectFlags, ImageSubresourceRange, ImageTiling, ImageType, ImageUsageFlags, ImageViewType,
+};
+use gpu_allocator::vulkan::*;
+
+use super::image::image::AllocatedImage;
+use super::image::image_view::SetUpImageView;
+use super::logical_device::SetUpLogicalDevice;
+use super::surface::SetUpSurfaceInfo;
+
+/// A multisampled color image that is rendered into and resolved into a present image at the
+/// end of the render pass
+pub struct SetUpMsaaColorImage {
+    pub color_image: AllocatedImage,
+    pub color_image_view: SetUpImageView,
+    pub samples: SampleCountFlags,
+}
+
+impl SetUpMsaaColorImage {
+    pub unsafe fn create(
+        logical_device: &SetUpLogicalDevice,
+        allocator: &mut MutexGuard<Allocator>,
+        format: Format,
+        image_extent: Extent3D,
+        samples: SampleCountFlags,
+    ) -> crate::Result<Self> {
+        // The image is only used within a render pass, so tile-based GPUs can keep its contents
+        // in tile memory. It's still backed by regular device-local memory, because the
+        // allocator doesn't request lazily allocated memory.
+        let color_image = AllocatedImage::allocate(
+            logical_device,
+            allocator,
+            ImageType::TYPE_2D,
+            format,
+            image_extent,
+            1,
+            1,
+            samples,
+            ImageTiling::OPTIMAL,
+            ImageUsageFlags::COLOR_ATTACHMENT | ImageUsageFlags::TRANSIENT_ATTACHMENT,
+            SharingMode::EXCLUSIVE,
+        )?;
+
+        let subresource_range = ImageSubresourceRange::builder()
+            .aspect_mask(ImageAspectFlags::COLOR)
+            .base_mip_level(0)
+            .level_count(1)
+            .base_array_layer(0)
+            .layer_count(1)
+            .build();
+        let color_image_view = SetUpImageView::create(
+            logical_device,
+            ImageViewType::TYPE_2D,
+            format,
+            subresource_range,
+            color_image.image,
+        )?;
+
+        Ok(Self {
+            color_image,
+            color_image_view,
+            samples,
+        })
+    }
+
+    /// This function creates a multisampled color image with the format and extent of the
+    /// surface.
+    pub unsafe fn create_with_defaults(
+        logical_device: &SetUpLogicalDevice,
+        allocator: &mut MutexGuard<Allocator>,
+        surface_info: &SetUpSurfaceInfo,
+        samples: SampleCountFlags,
+    ) -> crate::Result<Self> {
+        let surface_format = surface_info.surface_format(0)?;
+        let surface_extent = surface_info.surface_extent()?;
+
+        Self::create(
+            logical_device,
+            allocator,
+            surface_format.format,
+            Extent3D {
+                width: surface_extent.width,
+                height: surface_extent.height,
+                depth: 1,
+            },
+            samples,
+        )
+    }
+
+    pub fn destroy(
+        &mut self,
+        logical_device: &SetUpLogicalDevice,
+        allocator: &mut MutexGuard<Allocator>,
+    ) -> crate::Result<()> {
+        self.color_image_view.destroy(logical_device);
+        self.color_image.destroy(logical_device, allocator)?;
+        Ok(())
+    }
+}
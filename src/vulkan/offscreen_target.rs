@@ -0,0 +1,232 @@
+//! This is a module that contains the `SetUpOffscreenTarget` structure, which bundles a color
+//! image, a depth image, a render pass and a framebuffer for rendering without a window.
+//!
+//! The color image can be read back into CPU memory after rendering, e.g. for golden-image tests
+//! or thumbnail generation.
+
+use std::sync::MutexGuard;
+
+use ash::vk::{BufferImageCopy, ClearValue, Extent2D, Extent3D, Format, ImageLayout};
+use ash::vk::{Framebuffer, FramebufferCreateInfo, RenderPassBeginInfo, SubpassContents};
+use ash::vk::{ImageAspectFlags, ImageSubresourceLayers, ImageSubresourceRange};
+use ash::vk::{ImageTiling, ImageType, ImageUsageFlags, ImageViewType};
+use ash::vk::{SampleCountFlags, SharingMode};
+use gpu_allocator::vulkan::Allocator;
+
+use super::buffers::readback_buffer::SetUpReadbackBuffer;
+use super::command::command_buffer::SetUpCommandBufferWithFence;
+use super::depth_image::SetUpDepthImage;
+use super::image::image::AllocatedImage;
+use super::image::image_view::SetUpImageView;
+use super::image::readback;
+use super::logical_device::SetUpLogicalDevice;
+use super::render_pass::SetUpRenderPass;
+
+pub struct SetUpOffscreenTarget {
+    pub extent: Extent2D,
+    pub color_format: Format,
+
+    pub color_image: AllocatedImage,
+    pub color_image_view: SetUpImageView,
+    pub depth_image: SetUpDepthImage,
+    pub render_pass: SetUpRenderPass,
+    pub framebuffer: Framebuffer,
+}
+
+impl SetUpOffscreenTarget {
+    pub unsafe fn create(
+        logical_device: &SetUpLogicalDevice,
+        allocator: &mut MutexGuard<Allocator>,
+        extent: Extent2D,
+        color_format: Format,
+        depth_format: Format,
+    ) -> crate::Result<Self> {
+        crate::verify!(
+            readback::is_format_convertible_to_rgba(color_format),
+            "Offscreen targets only support 8-bit RGBA or BGRA color formats, got {:?}",
+            color_format
+        );
+
+        // The depth of the extent is interpreted as the channel count by `AllocatedImage`
+        let color_image_extent = Extent3D {
+            width: extent.width,
+            height: extent.height,
+            depth: 4,
+        };
+        let color_image = AllocatedImage::allocate(
+            logical_device,
+            allocator,
+            ImageType::TYPE_2D,
+            color_format,
+            color_image_extent,
+            1,
+            1,
+            SampleCountFlags::TYPE_1,
+            ImageTiling::OPTIMAL,
+            ImageUsageFlags::COLOR_ATTACHMENT | ImageUsageFlags::TRANSFER_SRC,
+            SharingMode::EXCLUSIVE,
+        )?;
+        let color_image_view = SetUpImageView::create(
+            logical_device,
+            ImageViewType::TYPE_2D,
+            color_format,
+            Self::create_color_subresource_range(),
+            color_image.image,
+        )?;
+
+        let depth_image = SetUpDepthImage::create(
+            logical_device,
+            allocator,
+            depth_format,
+            extent,
+            SampleCountFlags::TYPE_1,
+            SetUpDepthImage::create_default_subresource_range(),
+        )?;
+
+        let render_pass = SetUpRenderPass::create_for_offscreen_target(
+            logical_device,
+            color_format,
+            depth_format,
+        )?;
+
+        let framebuffer_attachments = [
+            color_image_view.image_view,
+            depth_image.depth_image_view.image_view,
+        ];
+        let framebuffer_create_info = FramebufferCreateInfo::builder()
+            .render_pass(render_pass.render_pass)
+            .attachments(&framebuffer_attachments)
+            .width(extent.width)
+            .height(extent.height)
+            .layers(1);
+        let framebuffer = logical_device.create_framebuffer(&framebuffer_create_info, None)?;
+
+        Ok(Self {
+            extent,
+            color_format,
+            color_image,
+            color_image_view,
+            depth_image,
+            render_pass,
+            framebuffer,
+        })
+    }
+
+    pub unsafe fn create_with_defaults(
+        logical_device: &SetUpLogicalDevice,
+        allocator: &mut MutexGuard<Allocator>,
+        extent: Extent2D,
+        depth_format: Format,
+    ) -> crate::Result<Self> {
+        Self::create(
+            logical_device,
+            allocator,
+            extent,
+            Format::R8G8B8A8_UNORM,
+            depth_format,
+        )
+    }
+
+    fn create_color_subresource_range() -> ImageSubresourceRange {
+        ImageSubresourceRange::builder()
+            .aspect_mask(ImageAspectFlags::COLOR)
+            .base_mip_level(0)
+            .level_count(1)
+            .base_array_layer(0)
+            .layer_count(1)
+            .build()
+    }
+
+    fn create_color_subresource_layers() -> ImageSubresourceLayers {
+        ImageSubresourceLayers::builder()
+            .aspect_mask(ImageAspectFlags::COLOR)
+            .mip_level(0)
+            .base_array_layer(0)
+            .layer_count(1)
+            .build()
+    }
+
+    /// This function returns the size of the color image in bytes.
+    pub fn color_image_size(&self) -> usize {
+        self.color_image.full_image_size() as usize
+    }
+
+    pub fn record_render_pass<RenderPassOp>(
+        &self,
+        logical_device: &SetUpLogicalDevice,
+        command_buffer: &SetUpCommandBufferWithFence,
+        clear_values: &[ClearValue],
+        render_pass_op: RenderPassOp,
+    ) -> crate::Result<()>
+    where
+        RenderPassOp: FnOnce() -> crate::Result<()>,
+    {
+        let render_pass_begin_info = RenderPassBeginInfo::builder()
+            .render_pass(self.render_pass.render_pass)
+            .framebuffer(self.framebuffer)
+            .render_area(self.extent.into())
+            .clear_values(clear_values);
+
+        unsafe {
+            logical_device.cmd_begin_render_pass(
+                command_buffer.command_buffer,
+                &render_pass_begin_info,
+                SubpassContents::INLINE,
+            );
+            render_pass_op()?;
+            logical_device.cmd_end_render_pass(command_buffer.command_buffer);
+        }
+
+        Ok(())
+    }
+
+    /// This function records a copy of the color image into the `readback_buffer`.
+    ///
+    /// The color image must be in the `TRANSFER_SRC_OPTIMAL` layout, which is the case after
+    /// the render pass of this target has ended.
+    pub fn record_copy_to_readback_buffer(
+        &self,
+        logical_device: &SetUpLogicalDevice,
+        command_buffer: &SetUpCommandBufferWithFence,
+        readback_buffer: &SetUpReadbackBuffer<u8>,
+    ) -> crate::Result<()> {
+        let image_size = self.color_image_size();
+        let readback_buffer_size = readback_buffer.buffer.element_amount;
+        crate::verify!(
+            image_size == readback_buffer_size,
+            "The readback buffer must have the same size as the color image! Expected: {image_size}, got: {readback_buffer_size}"
+        );
+
+        let buffer_image_copy = BufferImageCopy::builder()
+            .buffer_offset(0)
+            .image_subresource(Self::create_color_subresource_layers())
+            .image_extent(self.color_image.image_extent);
+
+        unsafe {
+            logical_device.cmd_copy_image_to_buffer(
+                command_buffer.command_buffer,
+                self.color_image.image,
+                ImageLayout::TRANSFER_SRC_OPTIMAL,
+                readback_buffer.raw_buffer(),
+                &[*buffer_image_copy],
+            );
+        }
+
+        Ok(())
+    }
+
+    pub fn destroy(
+        &mut self,
+        logical_device: &SetUpLogicalDevice,
+        allocator: &mut MutexGuard<Allocator>,
+    ) -> crate::Result<()> {
+        unsafe {
+            logical_device.destroy_framebuffer(self.framebuffer, None);
+        }
+        self.render_pass.destroy(logical_device);
+        self.depth_image.destroy(logical_device, allocator)?;
+        self.color_image_view.destroy(logical_device);
+        self.color_image.destroy(logical_device, allocator)?;
+        Ok(())
+    }
+}
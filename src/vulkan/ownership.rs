@@ -0,0 +1,356 @@
+//! This is a module that provides the RAII ownership model for Vulkan resources.
+//!
+//! A `DeviceHandle` is a shared handle to the logical device and the allocator. The device, the
+//! allocator and the instance are destroyed when the last handle is dropped, so resources that
+//! hold a handle may outlive the `VulkanContext`.
+//!
+//! The resources that are created by the `VulkanContext` are wrapped in an `Owned`, which holds
+//! such a handle and destroys the resource when it's dropped, using the same `destroy` functions
+//! as the manual API. Resources that are created with their own constructors (e.g.
+//! `SetUpSampler::create`) can be wrapped with `VulkanContext::own`. `Owned::into_inner` opts out
+//! and gives the resource back for manual destruction; an owned resource must never be destroyed
+//! through its `Deref` implementation.
+//!
+//! Resources never lock the allocator themselves: The locked allocator is passed into
+//! `DeviceResource::destroy_resource`. Dropping an `Owned` only tries to lock it. If the allocator
+//! is already locked (e.g. by a `VulkanContext::lock_allocator` guard of the same thread), the
+//! resource is pushed to the deletion queue of the device handle instead of blocking. That queue
+//! is flushed by the next `Owned` that is dropped while the allocator is unlocked, when the fences
+//! of a frame are waited for (see `VulkanContext::wait_for_current_frame_draw_buffer_fences`) and
+//! when the device is destroyed.
+//!
+//! # Examples
+//!
+//! ```ignore
+//! let sampler = vulkan_context.create_sampler(Filter::LINEAR, SamplerAddressMode::REPEAT)?;
+//! // `sampler` derefs to `SetUpSampler` and is destroyed when it goes out of scope
+//! ```
+
+use std::mem::ManuallyDrop;
+use std::ops::{Deref, DerefMut};
+use std::rc::Rc;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use ash::vk::Pipeline;
+use ash::Instance;
+use gpu_allocator::vulkan::Allocator;
+
+use crate::result::Context;
+
+use super::buffers::aligned_buffer::AlignedBuffer;
+use super::buffers::buffer::AllocatedBuffer;
+use super::buffers::dynamic_buffer::DynamicGpuBuffer;
+use super::buffers::readback_buffer::SetUpReadbackBuffer;
+use super::buffers::ring_buffer::SetUpRingBuffer;
+use super::buffers::staging_buffer::SetUpStagingBuffer;
+use super::buffers::storage_buffer::SetUpStorageBuffer;
+use super::buffers::typed_buffer::TypedAllocatedBuffer;
+use super::buffers::uniform_buffer::SetUpUniformBuffer;
+use super::deletion_queue::DeletionQueue;
+use super::depth_image::SetUpDepthImage;
+use super::descriptors::descriptor_set_layout::SetUpDescriptorSetLayout;
+use super::descriptors::descriptor_set_logic::SetUpDescriptorSetLogic;
+use super::descriptors::texture_registry::SetUpTextureRegistry;
+use super::image::image::AllocatedImage;
+use super::image::image_view::SetUpImageView;
+use super::image::sampler::SetUpSampler;
+use super::image::texture::Texture;
+use super::image::typed_image::TypedAllocatedImage;
+use super::logical_device::SetUpLogicalDevice;
+use super::offscreen_target::SetUpOffscreenTarget;
+use super::pipeline_layout::SetUpPipelineLayout;
+use super::post_process::chain::PostProcessChain;
+use super::render_graph::graph::RenderGraph;
+use super::render_pass::SetUpRenderPass;
+use super::shader::CompiledShaderModule;
+use super::shadow_map::cascaded_shadow_map::SetUpCascadedShadowMap;
+
+struct SharedDevice {
+    instance: Instance,
+    logical_device: SetUpLogicalDevice,
+    allocator: ManuallyDrop<Arc<Mutex<Allocator>>>,
+    deletion_queue: DeletionQueue,
+}
+
+impl Drop for SharedDevice {
+    fn drop(&mut self) {
+        // The device is destroyed even if waiting fails, e.g. because it was lost
+        let _ = self.logical_device.wait();
+
+        // Resources that were dropped while the allocator was locked are leaked if it's still
+        // locked now
+        if let Ok(mut allocator) = self.allocator.try_lock() {
+            let _ = self
+                .deletion_queue
+                .flush(&self.logical_device, &mut allocator);
+        }
+
+        // The allocator frees its memory blocks when it's dropped, which requires the device
+        unsafe {
+            ManuallyDrop::drop(&mut self.allocator);
+        }
+        self.logical_device.destroy();
+        unsafe {
+            self.instance.destroy_instance(None);
+        }
+    }
+}
+
+/// A shared handle to the logical device and the allocator of a `VulkanContext`
+#[derive(Clone)]
+pub struct DeviceHandle {
+    shared: Rc<SharedDevice>,
+}
+
+impl DeviceHandle {
+    /// This function creates the first handle, which takes over the destruction of the
+    /// `logical_device`, the `allocator` and the `instance`. All instance-level objects (surfaces,
+    /// debug messengers) have to be destroyed before the last handle is dropped.
+    pub(crate) unsafe fn new(
+        instance: Instance,
+        logical_device: SetUpLogicalDevice,
+        allocator: Arc<Mutex<Allocator>>,
+    ) -> Self {
+        Self {
+            shared: Rc::new(SharedDevice {
+                instance,
+                logical_device,
+                allocator: ManuallyDrop::new(allocator),
+                deletion_queue: DeletionQueue::new(),
+            }),
+        }
+    }
+
+    pub fn logical_device(&self) -> &SetUpLogicalDevice {
+        &self.shared.logical_device
+    }
+
+    /// This function locks the allocator. `Owned` resources that are dropped while the lock is
+    /// held are pushed to the deletion queue of the handle, see `DeviceHandle::deletion_queue`.
+    pub fn lock_allocator(&self) -> crate::Result<MutexGuard<'_, Allocator>> {
+        self.shared
+            .allocator
+            .lock()
+            .ok()
+            .context("Unable to acquire allocator mutex lock".to_string())
+    }
+
+    /// This function returns the queue of the resources that were dropped while the allocator
+    /// was locked. It's flushed with `DeviceHandle::flush_deletion_queue`.
+    pub fn deletion_queue(&self) -> &DeletionQueue {
+        &self.shared.deletion_queue
+    }
+
+    /// This function destroys the resources that were dropped while the allocator was locked.
+    pub fn flush_deletion_queue(&self, allocator: &mut MutexGuard<Allocator>) -> crate::Result<()> {
+        if self.shared.deletion_queue.is_empty() {
+            return Ok(());
+        }
+        self.shared
+            .deletion_queue
+            .flush(self.logical_device(), allocator)
+    }
+
+    /// This function returns the number of handles to the device, including this one.
+    pub fn handle_count(&self) -> usize {
+        Rc::strong_count(&self.shared)
+    }
+}
+
+/// A resource that can be destroyed with a logical device and a locked allocator
+pub trait DeviceResource {
+    /// This function destroys the resource. The `allocator` is locked by the caller, e.g. by
+    /// an `Owned` or a `DeletionQueue`, so implementations never lock it themselves.
+    fn destroy_resource(
+        &mut self,
+        logical_device: &SetUpLogicalDevice,
+        allocator: &mut MutexGuard<Allocator>,
+    ) -> crate::Result<()>;
+}
+
+/// A resource that is destroyed when it's dropped
+///
+/// The resource must not be in use by the GPU anymore when it's dropped; resources that may
+/// still be used by frames in flight are passed to `VulkanContext::defer_destruction` instead.
+pub struct Owned<R: DeviceResource + 'static> {
+    resource: Option<R>,
+    device: DeviceHandle,
+}
+
+impl<R: DeviceResource + 'static> Owned<R> {
+    pub fn new(resource: R, device: DeviceHandle) -> Self {
+        Self {
+            resource: Some(resource),
+            device,
+        }
+    }
+
+    /// This function opts out of the automatic destruction and returns the resource, which has
+    /// to be destroyed manually afterwards.
+    pub fn into_inner(mut self) -> R {
+        self.resource
+            .take()
+            .expect("The resource of an Owned is only taken when it's consumed")
+    }
+
+    /// This function destroys the resource with an `allocator` that is already locked and
+    /// returns the errors that dropping the `Owned` would ignore.
+    pub fn destroy_with_allocator(
+        mut self,
+        allocator: &mut MutexGuard<Allocator>,
+    ) -> crate::Result<()> {
+        let mut resource = self
+            .resource
+            .take()
+            .expect("The resource of an Owned is only taken when it's consumed");
+        resource.destroy_resource(self.device.logical_device(), allocator)
+    }
+
+    pub fn device(&self) -> &DeviceHandle {
+        &self.device
+    }
+}
+
+impl<R: DeviceResource + 'static> Deref for Owned<R> {
+    type Target = R;
+
+    fn deref(&self) -> &Self::Target {
+        self.resource
+            .as_ref()
+            .expect("The resource of an Owned is only taken when it's consumed")
+    }
+}
+
+impl<R: DeviceResource + 'static> DerefMut for Owned<R> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.resource
+            .as_mut()
+            .expect("The resource of an Owned is only taken when it's consumed")
+    }
+}
+
+impl<R: DeviceResource + 'static> Drop for Owned<R> {
+    fn drop(&mut self) {
+        let Some(mut resource) = self.resource.take() else {
+            return;
+        };
+
+        // Blocking on the allocator would deadlock if this thread already holds the lock, so
+        // the resource is deferred instead. Errors can't be returned from `drop`, use
+        // `Owned::destroy_with_allocator` to handle them.
+        match self.device.shared.allocator.try_lock() {
+            Ok(mut allocator) => {
+                let logical_device = self.device.logical_device();
+                let _ = resource.destroy_resource(logical_device, &mut allocator);
+                let _ = self.device.flush_deletion_queue(&mut allocator);
+            }
+            Err(_) => self.device.deletion_queue().push(resource),
+        }
+    }
+}
+
+/// Destroying an `Owned` resource (e.g. from a `DeletionQueue`) consumes it, so it isn't
+/// destroyed again when it's dropped.
+impl<R: DeviceResource + 'static> DeviceResource for Owned<R> {
+    fn destroy_resource(
+        &mut self,
+        logical_device: &SetUpLogicalDevice,
+        allocator: &mut MutexGuard<Allocator>,
+    ) -> crate::Result<()> {
+        match self.resource.take() {
+            Some(mut resource) => resource.destroy_resource(logical_device, allocator),
+            None => Ok(()),
+        }
+    }
+}
+
+impl<R: DeviceResource> DeviceResource for Vec<R> {
+    fn destroy_resource(
+        &mut self,
+        logical_device: &SetUpLogicalDevice,
+        allocator: &mut MutexGuard<Allocator>,
+    ) -> crate::Result<()> {
+        for resource in self.iter_mut() {
+            resource.destroy_resource(logical_device, allocator)?;
+        }
+        self.clear();
+        Ok(())
+    }
+}
+
+impl DeviceResource for Pipeline {
+    fn destroy_resource(
+        &mut self,
+        logical_device: &SetUpLogicalDevice,
+        _: &mut MutexGuard<Allocator>,
+    ) -> crate::Result<()> {
+        unsafe {
+            logical_device.destroy_pipeline(*self, None);
+        }
+        Ok(())
+    }
+}
+
+macro_rules! impl_device_resource {
+    (<$generic:ident> $type:ty, with_allocator) => {
+        impl<$generic> DeviceResource for $type {
+            fn destroy_resource(
+                &mut self,
+                logical_device: &SetUpLogicalDevice,
+                allocator: &mut MutexGuard<Allocator>,
+            ) -> crate::Result<()> {
+                self.destroy(logical_device, allocator)
+            }
+        }
+    };
+    ($type:ty) => {
+        impl DeviceResource for $type {
+            fn destroy_resource(
+                &mut self,
+                logical_device: &SetUpLogicalDevice,
+                _: &mut MutexGuard<Allocator>,
+            ) -> crate::Result<()> {
+                self.destroy(logical_device);
+                Ok(())
+            }
+        }
+    };
+    ($type:ty, with_allocator) => {
+        impl DeviceResource for $type {
+            fn destroy_resource(
+                &mut self,
+                logical_device: &SetUpLogicalDevice,
+                allocator: &mut MutexGuard<Allocator>,
+            ) -> crate::Result<()> {
+                self.destroy(logical_device, allocator)
+            }
+        }
+    };
+}
+
+impl_device_resource!(CompiledShaderModule);
+impl_device_resource!(SetUpDescriptorSetLayout);
+impl_device_resource!(SetUpDescriptorSetLogic);
+impl_device_resource!(SetUpImageView);
+impl_device_resource!(SetUpPipelineLayout);
+impl_device_resource!(SetUpRenderPass);
+impl_device_resource!(SetUpSampler);
+impl_device_resource!(SetUpTextureRegistry);
+
+impl_device_resource!(AllocatedBuffer, with_allocator);
+impl_device_resource!(AllocatedImage, with_allocator);
+impl_device_resource!(PostProcessChain, with_allocator);
+impl_device_resource!(RenderGraph, with_allocator);
+impl_device_resource!(SetUpCascadedShadowMap, with_allocator);
+impl_device_resource!(SetUpDepthImage, with_allocator);
+impl_device_resource!(SetUpOffscreenTarget, with_allocator);
+impl_device_resource!(SetUpRingBuffer, with_allocator);
+impl_device_resource!(<T> AlignedBuffer<T>, with_allocator);
+impl_device_resource!(<T> DynamicGpuBuffer<T>, with_allocator);
+impl_device_resource!(<T> SetUpReadbackBuffer<T>, with_allocator);
+impl_device_resource!(<T> SetUpStagingBuffer<T>, with_allocator);
+impl_device_resource!(<T> SetUpStorageBuffer<T>, with_allocator);
+impl_device_resource!(<T> SetUpUniformBuffer<T>, with_allocator);
+impl_device_resource!(<T> Texture<T>, with_allocator);
+impl_device_resource!(<T> TypedAllocatedBuffer<T>, with_allocator);
+impl_device_resource!(<T> TypedAllocatedImage<T>, with_allocator);
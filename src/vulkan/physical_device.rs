@@ -1,17 +1,20 @@
-use std::ffi::CStr;
+use std::ffi::{CStr, CString};
 
-use ash::extensions::khr::Surface;
-use ash::vk::SurfaceKHR;
+use ash::extensions::khr::Swapchain;
 use ash::vk::{MemoryPropertyFlags, MemoryRequirements, MemoryType};
+use ash::vk::SampleCountFlags;
+use ash::vk::{Format, FormatFeatureFlags, FormatProperties, ImageTiling};
 use ash::vk::{
     PhysicalDevice, PhysicalDeviceFeatures, PhysicalDeviceMemoryProperties,
-    PhysicalDeviceProperties, PhysicalDeviceType,
+    PhysicalDeviceProperties,
 };
-use ash::vk::{QueueFamilyProperties, QueueFlags};
 use ash::Instance;
 
 use crate::result::Context;
 
+use super::creation_info::DepthBufferInfo;
+use super::device_requirements::{DeviceCandidate, DeviceCheck, DeviceRejection, DeviceRequirements};
+use super::queue_families::QueueFamilyIndices;
 use super::surface::SetUpSurfaceInfo;
 
 pub struct SetUpPhysicalDevice {
@@ -19,99 +22,266 @@ pub struct SetUpPhysicalDevice {
     pub device_properties: PhysicalDeviceProperties,
     pub device_memory_properties: PhysicalDeviceMemoryProperties,
     pub device_features: PhysicalDeviceFeatures,
+    /// The extensions that should be enabled on the logical device
+    pub enabled_extensions: Vec<CString>,
 
+    /// The index of the graphics queue family
     pub queue_family_index: u32,
+    pub queue_family_indices: QueueFamilyIndices,
 }
 
 impl SetUpPhysicalDevice {
-    unsafe fn is_device_suitable_for_graphics(
-        info: &QueueFamilyProperties,
-        device: &PhysicalDevice,
-        surface_loader: &Surface,
-        index: u32,
-        surface: SurfaceKHR,
-    ) -> bool {
-        info.queue_flags.contains(QueueFlags::GRAPHICS)
-            && surface_loader
-                .get_physical_device_surface_support(*device, index, surface)
-                .unwrap()
+    pub unsafe fn find_usable_device(
+        instance: &Instance,
+        surface_info: &SetUpSurfaceInfo,
+    ) -> crate::Result<Self> {
+        Self::find_device_for_surface(
+            instance,
+            ash::vk::make_api_version(0, 1, 0, 0),
+            surface_info,
+            &DeviceRequirements::default(),
+        )
     }
 
-    pub unsafe fn find_usable_device(
+    /// This function finds a usable device for rendering without a surface, e.g. for offscreen
+    /// rendering. Only graphics support is required from the queue family.
+    pub unsafe fn find_usable_headless_device(instance: &Instance) -> crate::Result<Self> {
+        Self::find_headless_device(
+            instance,
+            ash::vk::make_api_version(0, 1, 0, 0),
+            &DeviceRequirements::default(),
+        )
+    }
+
+    /// This function finds the best device that satisfies the `requirements` and is able to
+    /// present to the given surface. The swapchain extension is always required.
+    ///
+    /// The `instance_api_version` is the API version the instance was created with.
+    pub unsafe fn find_device_for_surface(
         instance: &Instance,
+        instance_api_version: u32,
         surface_info: &SetUpSurfaceInfo,
+        requirements: &DeviceRequirements,
+    ) -> crate::Result<Self> {
+        let requirements = requirements.clone().require_extension(Swapchain::name());
+        Self::find_device_with_requirements(
+            instance,
+            instance_api_version,
+            &requirements,
+            "no queue family supports graphics or presentation to the surface",
+            true,
+            |pdevice, index| {
+                surface_info
+                    .surface_loader
+                    .get_physical_device_surface_support(pdevice, index, surface_info.surface)
+                    .map_err(Into::into)
+            },
+        )
+    }
+
+    /// This function finds the best device that satisfies the `requirements` without requiring
+    /// presentation support.
+    ///
+    /// The `instance_api_version` is the API version the instance was created with.
+    pub unsafe fn find_headless_device(
+        instance: &Instance,
+        instance_api_version: u32,
+        requirements: &DeviceRequirements,
     ) -> crate::Result<Self> {
+        Self::find_device_with_requirements(
+            instance,
+            instance_api_version,
+            requirements,
+            "no queue family supports graphics",
+            false,
+            |_, _| Ok(false),
+        )
+    }
+
+    unsafe fn find_device_with_requirements<F>(
+        instance: &Instance,
+        instance_api_version: u32,
+        requirements: &DeviceRequirements,
+        queue_family_rejection_reason: &str,
+        require_present: bool,
+        supports_present: F,
+    ) -> crate::Result<Self>
+    where
+        F: Fn(PhysicalDevice, u32) -> crate::Result<bool>,
+    {
         let pdevices = instance.enumerate_physical_devices()?;
-        let mut supported_physical_devices = pdevices
-            .iter()
-            .filter_map(|pdevice| {
-                instance
-                    .get_physical_device_queue_family_properties(*pdevice)
-                    .iter()
-                    .enumerate()
-                    .find_map(|(index, info)| {
-                        let device_suitable = Self::is_device_suitable_for_graphics(
-                            &info,
-                            &pdevice,
-                            &surface_info.surface_loader,
-                            index as u32,
-                            surface_info.surface,
-                        );
-                        if device_suitable {
-                            Some((*pdevice, index))
-                        } else {
-                            None
-                        }
-                    })
-            })
-            .collect::<Vec<_>>();
-        if supported_physical_devices.is_empty() {
-            crate::bail!("No supported physical devices found!");
-        }
 
-        supported_physical_devices.sort_by_key(|(pdevice, _)| {
-            let properties = instance.get_physical_device_properties(*pdevice);
-            let device_type = properties.device_type;
-            if device_type == PhysicalDeviceType::DISCRETE_GPU {
-                2
-            } else if device_type == PhysicalDeviceType::INTEGRATED_GPU {
-                1
-            } else {
-                0
+        let mut candidates = vec![];
+        let mut rejections = vec![];
+        for (index, pdevice) in pdevices.iter().enumerate() {
+            let candidate = DeviceCandidate {
+                index,
+                physical_device: *pdevice,
+                properties: instance.get_physical_device_properties(*pdevice),
+                memory_properties: instance.get_physical_device_memory_properties(*pdevice),
+            };
+
+            // Errors of the present support query (e.g. a lost surface) are returned instead of
+            // being reported as missing present support
+            let queue_families = instance.get_physical_device_queue_family_properties(*pdevice);
+            let present_support = (0..queue_families.len() as u32)
+                .map(|index| supports_present(*pdevice, index))
+                .collect::<crate::Result<Vec<_>>>()?;
+            let queue_family_indices = QueueFamilyIndices::discover(
+                &queue_families,
+                |index| present_support[index as usize],
+                require_present,
+            );
+
+            let mut reasons = vec![];
+            if queue_family_indices.is_none() {
+                reasons.push(queue_family_rejection_reason.to_string());
             }
-        });
 
-        let (physical_device, queue_family_index) = *supported_physical_devices
-            .iter()
-            .max_by_key(|(pdevice, _)| {
-                let properties = instance.get_physical_device_memory_properties(*pdevice);
-                let mut heaps_size_sum = 0;
-                for i in 0..(properties.memory_heap_count as usize) {
-                    let heap_size = properties.memory_heaps[i].size;
-                    heaps_size_sum += heap_size;
+            match requirements.check_device(instance, instance_api_version, *pdevice)? {
+                DeviceCheck::Suitable(enabled_extensions) => {
+                    if let Some(queue_family_indices) = queue_family_indices {
+                        candidates.push((candidate, queue_family_indices, enabled_extensions));
+                    }
                 }
-                heaps_size_sum
-            })
-            .context("Unable to find usable physical device".to_string())?;
+                DeviceCheck::Rejected(requirement_reasons) => reasons.extend(requirement_reasons),
+            }
 
-        let device_memory_properties =
-            instance.get_physical_device_memory_properties(physical_device);
-        let device_properties = instance.get_physical_device_properties(physical_device);
+            if !reasons.is_empty() {
+                rejections.push(DeviceRejection {
+                    index,
+                    device_name: candidate.name(),
+                    reasons,
+                });
+            }
+        }
+
+        let selected = match requirements.effective_device_override() {
+            Some(device_override) => {
+                let selected = candidates
+                    .into_iter()
+                    .find(|(candidate, _, _)| device_override.matches(candidate.index, &candidate.name()));
+                if selected.is_none() {
+                    if let Some(rejection) = rejections
+                        .iter()
+                        .find(|rejection| device_override.matches(rejection.index, &rejection.device_name))
+                    {
+                        crate::bail!("The device selected by {:?} can't be used. {}", device_override, rejection);
+                    }
+                    crate::bail!("No device matches the device override {:?}", device_override);
+                }
+                selected
+            }
+            None => candidates
+                .into_iter()
+                .max_by_key(|(candidate, _, _)| requirements.score(candidate)),
+        };
+
+        let (candidate, queue_family_indices, enabled_extensions) = selected.context(format!(
+            "No supported physical devices found!{}",
+            rejections
+                .iter()
+                .map(|rejection| format!("\n{}", rejection))
+                .collect::<String>()
+        ))?;
+
+        let physical_device = candidate.physical_device;
         let device_features = instance.get_physical_device_features(physical_device);
 
         Ok(Self {
             physical_device,
-            device_memory_properties,
-            device_properties,
+            device_memory_properties: candidate.memory_properties,
+            device_properties: candidate.properties,
             device_features,
-            queue_family_index: queue_family_index as u32,
+            enabled_extensions,
+            queue_family_index: queue_family_indices.graphics,
+            queue_family_indices,
         })
     }
 
+    /// This function checks whether the present queue family of this device can present to the
+    /// given surface.
+    pub fn supports_surface(&self, surface_info: &SetUpSurfaceInfo) -> crate::Result<bool> {
+        unsafe {
+            Ok(surface_info
+                .surface_loader
+                .get_physical_device_surface_support(
+                    self.physical_device,
+                    self.queue_family_indices.present_or_graphics(),
+                    surface_info.surface,
+                )?)
+        }
+    }
+
     pub fn name(&self) -> &CStr {
         unsafe { CStr::from_ptr(self.device_properties.device_name.as_ptr()) }
     }
 
+    /// This function returns the sample counts that are supported for both color and depth
+    /// framebuffer attachments.
+    pub fn supported_sample_counts(&self) -> SampleCountFlags {
+        let limits = &self.device_properties.limits;
+        limits.framebuffer_color_sample_counts & limits.framebuffer_depth_sample_counts
+    }
+
+    /// This function returns the highest supported sample count that doesn't exceed the
+    /// `requested` sample count.
+    pub fn clamp_sample_count(&self, requested: SampleCountFlags) -> SampleCountFlags {
+        clamp_sample_count(requested, self.supported_sample_counts())
+    }
+
+    /// This function returns the first of the `candidates` that supports the `features` with
+    /// the given `tiling`.
+    pub fn find_supported_format(
+        &self,
+        instance: &Instance,
+        candidates: &[Format],
+        tiling: ImageTiling,
+        features: FormatFeatureFlags,
+    ) -> Option<Format> {
+        first_supported_format(candidates, tiling, features, |format| unsafe {
+            instance.get_physical_device_format_properties(self.physical_device, format)
+        })
+    }
+
+    /// This function returns whether images with the `format` (and optimal tiling) can be used
+    /// as the source and destination of blits with linear filtering, which is required for
+    /// generating mipmaps.
+    pub fn supports_linear_blit(&self, instance: &Instance, format: Format) -> bool {
+        let format_properties = unsafe {
+            instance.get_physical_device_format_properties(self.physical_device, format)
+        };
+        format_properties.optimal_tiling_features.contains(
+            FormatFeatureFlags::BLIT_SRC
+                | FormatFeatureFlags::BLIT_DST
+                | FormatFeatureFlags::SAMPLED_IMAGE_FILTER_LINEAR,
+        )
+    }
+
+    /// This function returns the highest anisotropy samplers can be created with.
+    pub fn max_sampler_anisotropy(&self) -> f32 {
+        self.device_properties.limits.max_sampler_anisotropy
+    }
+
+    /// This function returns the best depth format that can be used as a depth attachment and
+    /// satisfies the `depth_buffer_info`.
+    pub fn find_depth_format(
+        &self,
+        instance: &Instance,
+        depth_buffer_info: &DepthBufferInfo,
+    ) -> crate::Result<Format> {
+        self.find_supported_format(
+            instance,
+            depth_buffer_info.candidate_formats(),
+            ImageTiling::OPTIMAL,
+            FormatFeatureFlags::DEPTH_STENCIL_ATTACHMENT,
+        )
+        .context(format!(
+            "The device supports none of the depth formats {:?}",
+            depth_buffer_info.candidate_formats()
+        ))
+    }
+
     pub fn find_memory_type_index(
         &self,
         memory_requirements: &MemoryRequirements,
@@ -129,6 +299,46 @@ impl SetUpPhysicalDevice {
     }
 }
 
+/// This function returns the highest sample count in `supported` that doesn't exceed the
+/// `requested` sample count, or `TYPE_1` if there is none.
+pub fn clamp_sample_count(
+    requested: SampleCountFlags,
+    supported: SampleCountFlags,
+) -> SampleCountFlags {
+    [
+        SampleCountFlags::TYPE_64,
+        SampleCountFlags::TYPE_32,
+        SampleCountFlags::TYPE_16,
+        SampleCountFlags::TYPE_8,
+        SampleCountFlags::TYPE_4,
+        SampleCountFlags::TYPE_2,
+    ]
+    .into_iter()
+    .find(|&samples| samples.as_raw() <= requested.as_raw() && supported.contains(samples))
+    .unwrap_or(SampleCountFlags::TYPE_1)
+}
+
+/// This function returns the first of the `candidates` whose `FormatProperties` (queried using
+/// `format_properties`) contain the `features` for the given `tiling`.
+pub fn first_supported_format<F>(
+    candidates: &[Format],
+    tiling: ImageTiling,
+    features: FormatFeatureFlags,
+    format_properties: F,
+) -> Option<Format>
+where
+    F: Fn(Format) -> FormatProperties,
+{
+    candidates.iter().copied().find(|&format| {
+        let properties = format_properties(format);
+        let supported_features = match tiling {
+            ImageTiling::LINEAR => properties.linear_tiling_features,
+            _ => properties.optimal_tiling_features,
+        };
+        supported_features.contains(features)
+    })
+}
+
 fn memory_type_supports_flags(memory_type: MemoryType, flags: MemoryPropertyFlags) -> bool {
     memory_type.property_flags & flags == flags
 }
@@ -140,3 +350,57 @@ impl std::ops::Deref for SetUpPhysicalDevice {
         &self.physical_device
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sample_counts_are_clamped_to_supported_counts() {
+        let supported =
+            SampleCountFlags::TYPE_1 | SampleCountFlags::TYPE_2 | SampleCountFlags::TYPE_4;
+        assert_eq!(
+            SampleCountFlags::TYPE_4,
+            clamp_sample_count(SampleCountFlags::TYPE_8, supported)
+        );
+        assert_eq!(
+            SampleCountFlags::TYPE_2,
+            clamp_sample_count(SampleCountFlags::TYPE_2, supported)
+        );
+        assert_eq!(
+            SampleCountFlags::TYPE_1,
+            clamp_sample_count(SampleCountFlags::TYPE_1, supported)
+        );
+    }
+
+    #[test]
+    fn first_format_with_required_features_is_selected() {
+        let format_properties = |format| FormatProperties {
+            optimal_tiling_features: match format {
+                Format::D32_SFLOAT => FormatFeatureFlags::SAMPLED_IMAGE,
+                Format::D24_UNORM_S8_UINT | Format::D16_UNORM => {
+                    FormatFeatureFlags::DEPTH_STENCIL_ATTACHMENT
+                }
+                _ => FormatFeatureFlags::empty(),
+            },
+            ..Default::default()
+        };
+
+        let candidates = DepthBufferInfo::default().candidate_formats();
+        let selected = first_supported_format(
+            candidates,
+            ImageTiling::OPTIMAL,
+            FormatFeatureFlags::DEPTH_STENCIL_ATTACHMENT,
+            format_properties,
+        );
+        assert_eq!(Some(Format::D24_UNORM_S8_UINT), selected);
+
+        let selected_linear = first_supported_format(
+            candidates,
+            ImageTiling::LINEAR,
+            FormatFeatureFlags::DEPTH_STENCIL_ATTACHMENT,
+            format_properties,
+        );
+        assert_eq!(None, selected_linear);
+    }
+}
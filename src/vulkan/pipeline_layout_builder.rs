@@ -11,7 +11,7 @@ use super::pipeline_layout::SetUpPipelineLayout;
 #[derive(Default)]
 pub struct PipelineLayoutBuilder<'builder> {
     push_constant_ranges: Vec<PushConstantRange>,
-    set_layouts: Option<&'builder [SetUpDescriptorSetLayout]>,
+    set_layouts: Option<&'builder [DescriptorSetLayout]>,
 }
 
 impl<'builder> PipelineLayoutBuilder<'builder> {
@@ -33,7 +33,29 @@ impl<'builder> PipelineLayoutBuilder<'builder> {
         self
     }
 
+    /// This function adds a push constant range whose size isn't given by a type, e.g. a range
+    /// from a `PipelineReflection`.
+    pub fn add_raw_push_constant_range(mut self, push_constant_range: PushConstantRange) -> Self {
+        self.push_constant_ranges.push(push_constant_range);
+        self
+    }
+
     pub fn set_layouts(mut self, set_layouts: &'builder [SetUpDescriptorSetLayout]) -> Self {
+        // SAFETY: Transmuting the set_layouts slice is safe because
+        //         SetUpDescriptorSetLayout is a repr(transparent) struct that holds one
+        //         value of DescriptorSetLayout. This guarantees that a slice over
+        //         SetUpDescriptorSetLayouts has the same memory layout as a slice over
+        //         DescriptorSetLayouts.
+        let set_layouts = unsafe {
+            std::mem::transmute::<&[SetUpDescriptorSetLayout], &[DescriptorSetLayout]>(set_layouts)
+        };
+        self.set_layouts = Some(set_layouts);
+        self
+    }
+
+    /// This function sets layouts that are not owned by a `SetUpDescriptorSetLayout`, e.g.
+    /// layouts from a `DescriptorLayoutCache`.
+    pub fn set_layout_handles(mut self, set_layouts: &'builder [DescriptorSetLayout]) -> Self {
         self.set_layouts = Some(set_layouts);
         self
     }
@@ -46,16 +68,7 @@ impl<'builder> PipelineLayoutBuilder<'builder> {
                     pipeline_layout_create_info.push_constant_ranges(&self.push_constant_ranges);
             }
             if let Some(set_layouts) = self.set_layouts {
-                // SAFETY: Transmuting the set_layouts slice is safe because
-                //         SetUpDescriptorSetLayout is a repr(transparent) struct that holds one
-                //         value of DescriptorSetLayout. This guarantees that a slice over
-                //         SetUpDescriptorSetLayouts has the same memory layout as a slice over
-                //         DescriptorSetLayouts.
-                pipeline_layout_create_info =
-                    pipeline_layout_create_info.set_layouts(std::mem::transmute::<
-                        &[SetUpDescriptorSetLayout],
-                        &[DescriptorSetLayout],
-                    >(set_layouts));
+                pipeline_layout_create_info = pipeline_layout_create_info.set_layouts(set_layouts);
             }
             SetUpPipelineLayout::create_from_build_info(
                 logical_device,
@@ -0,0 +1,225 @@
+//! This is a module that contains the `SetUpBloom` structure, which extracts the bright parts of
+//! an HDR image into a half resolution image and blurs them with a separable gaussian blur.
+
+use std::sync::MutexGuard;
+
+use ash::vk::{Extent2D, ImageLayout};
+use gpu_allocator::vulkan::Allocator;
+
+use crate::vulkan::command::command_buffer::SetUpCommandBufferWithFence;
+use crate::vulkan::image::image_view::SetUpImageView;
+use crate::vulkan::image::sampler::SetUpSampler;
+use crate::vulkan::logical_device::SetUpLogicalDevice;
+
+use super::fullscreen_pass::{SetUpFullscreenPass, SetUpFullscreenTarget};
+use super::hdr_target::HDR_COLOR_FORMAT;
+use super::shaders::PostProcessShaders;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BloomSettings {
+    /// The brightness above which colors contribute to the bloom
+    pub threshold: f32,
+    /// The range below the threshold in which colors fade in smoothly
+    pub knee: f32,
+    /// The number of horizontal and vertical blur passes
+    pub blur_iterations: u32,
+}
+
+impl Default for BloomSettings {
+    fn default() -> Self {
+        Self {
+            threshold: 1.0,
+            knee: 0.5,
+            blur_iterations: 2,
+        }
+    }
+}
+
+/// The push constants of `bloom_threshold.frag` and `bloom_blur.frag`
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BloomPushConstants {
+    pub direction: [f32; 2],
+    pub threshold: f32,
+    pub knee: f32,
+}
+
+/// This function returns the extent of the bloom images for the given HDR image extent.
+pub fn bloom_extent(extent: Extent2D) -> Extent2D {
+    Extent2D {
+        width: (extent.width / 2).max(1),
+        height: (extent.height / 2).max(1),
+    }
+}
+
+pub struct SetUpBloom {
+    pub settings: BloomSettings,
+    pub threshold_pass: SetUpFullscreenPass,
+    /// The blur pass uses set 0 to blur the first image horizontally into the second image and
+    /// set 1 to blur the second image vertically back into the first image.
+    pub blur_pass: SetUpFullscreenPass,
+    pub targets: [SetUpFullscreenTarget; 2],
+}
+
+impl SetUpBloom {
+    pub unsafe fn create(
+        logical_device: &SetUpLogicalDevice,
+        allocator: &mut MutexGuard<Allocator>,
+        shaders: &PostProcessShaders,
+        settings: BloomSettings,
+        sampler: &SetUpSampler,
+        hdr_image_view: &SetUpImageView,
+        extent: Extent2D,
+    ) -> crate::Result<Self> {
+        let threshold_pass = SetUpFullscreenPass::create::<BloomPushConstants>(
+            logical_device,
+            HDR_COLOR_FORMAT,
+            ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+            1,
+            1,
+            &shaders.fullscreen_vertex,
+            &shaders.bloom_threshold_fragment,
+        )?;
+        let blur_pass = SetUpFullscreenPass::create::<BloomPushConstants>(
+            logical_device,
+            HDR_COLOR_FORMAT,
+            ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+            1,
+            2,
+            &shaders.fullscreen_vertex,
+            &shaders.bloom_blur_fragment,
+        )?;
+        let targets = Self::create_targets(logical_device, allocator, &blur_pass, extent)?;
+
+        let bloom = Self {
+            settings,
+            threshold_pass,
+            blur_pass,
+            targets,
+        };
+        bloom.update_inputs(logical_device, sampler, hdr_image_view)?;
+        Ok(bloom)
+    }
+
+    // Both passes have a single attachment with the same format, so their render passes are
+    // compatible and the targets can be used with either of them
+    unsafe fn create_targets(
+        logical_device: &SetUpLogicalDevice,
+        allocator: &mut MutexGuard<Allocator>,
+        blur_pass: &SetUpFullscreenPass,
+        extent: Extent2D,
+    ) -> crate::Result<[SetUpFullscreenTarget; 2]> {
+        let extent = bloom_extent(extent);
+        Ok([
+            SetUpFullscreenTarget::create(
+                logical_device,
+                allocator,
+                HDR_COLOR_FORMAT,
+                extent,
+                &blur_pass.render_pass,
+            )?,
+            SetUpFullscreenTarget::create(
+                logical_device,
+                allocator,
+                HDR_COLOR_FORMAT,
+                extent,
+                &blur_pass.render_pass,
+            )?,
+        ])
+    }
+
+    fn update_inputs(
+        &self,
+        logical_device: &SetUpLogicalDevice,
+        sampler: &SetUpSampler,
+        hdr_image_view: &SetUpImageView,
+    ) -> crate::Result<()> {
+        let [first, second] = &self.targets;
+        self.threshold_pass
+            .update_inputs(logical_device, 0, sampler, &[hdr_image_view])?;
+        self.blur_pass
+            .update_inputs(logical_device, 0, sampler, &[&first.color_image_view])?;
+        self.blur_pass
+            .update_inputs(logical_device, 1, sampler, &[&second.color_image_view])?;
+        Ok(())
+    }
+
+    /// This function recreates the bloom images for the new HDR image extent. The device must
+    /// be idle.
+    pub unsafe fn resize(
+        &mut self,
+        logical_device: &SetUpLogicalDevice,
+        allocator: &mut MutexGuard<Allocator>,
+        sampler: &SetUpSampler,
+        hdr_image_view: &SetUpImageView,
+        extent: Extent2D,
+    ) -> crate::Result<()> {
+        let new_targets = Self::create_targets(logical_device, allocator, &self.blur_pass, extent)?;
+        let old_targets = std::mem::replace(&mut self.targets, new_targets);
+        for mut target in old_targets {
+            target.destroy(logical_device, allocator)?;
+        }
+        self.update_inputs(logical_device, sampler, hdr_image_view)
+    }
+
+    /// This function records the bloom passes. The HDR image has to be in
+    /// `SHADER_READ_ONLY_OPTIMAL` layout.
+    pub fn record(
+        &self,
+        logical_device: &SetUpLogicalDevice,
+        command_buffer: &SetUpCommandBufferWithFence,
+    ) {
+        let [first, second] = &self.targets;
+        let push_constants = |direction| BloomPushConstants {
+            direction,
+            threshold: self.settings.threshold,
+            knee: self.settings.knee,
+        };
+
+        self.threshold_pass.record(
+            logical_device,
+            command_buffer,
+            first.framebuffer,
+            first.extent,
+            0,
+            &push_constants([0.0, 0.0]),
+        );
+        for _ in 0..self.settings.blur_iterations {
+            self.blur_pass.record(
+                logical_device,
+                command_buffer,
+                second.framebuffer,
+                second.extent,
+                0,
+                &push_constants([1.0, 0.0]),
+            );
+            self.blur_pass.record(
+                logical_device,
+                command_buffer,
+                first.framebuffer,
+                first.extent,
+                1,
+                &push_constants([0.0, 1.0]),
+            );
+        }
+    }
+
+    /// This function returns the view of the image that contains the blurred bloom after
+    /// `SetUpBloom::record`.
+    pub fn output_image_view(&self) -> &SetUpImageView {
+        &self.targets[0].color_image_view
+    }
+
+    pub fn destroy(
+        &mut self,
+        logical_device: &SetUpLogicalDevice,
+        allocator: &mut MutexGuard<Allocator>,
+    ) -> crate::Result<()> {
+        for target in self.targets.iter_mut() {
+            target.destroy(logical_device, allocator)?;
+        }
+        self.blur_pass.destroy(logical_device);
+        self.threshold_pass.destroy(logical_device);
+        Ok(())
+    }
+}
@@ -0,0 +1,254 @@
+//! This is a module that contains the `PostProcessChain` structure, which renders the scene into
+//! an HDR target and tonemaps it (with optional bloom) into the present images of a window.
+
+use std::sync::MutexGuard;
+
+use ash::vk::{ClearValue, Extent2D, Filter, Format, Framebuffer, FramebufferCreateInfo};
+use ash::vk::{ImageLayout, SamplerAddressMode};
+use gpu_allocator::vulkan::Allocator;
+
+use crate::result::Context;
+use crate::vulkan::command::command_buffer::SetUpCommandBufferWithFence;
+use crate::vulkan::image::sampler::SetUpSampler;
+use crate::vulkan::logical_device::SetUpLogicalDevice;
+use crate::vulkan::present_images::SetUpPresentImages;
+use crate::vulkan::render_pass::SetUpRenderPass;
+use crate::vulkan::surface::SetUpSurfaceInfo;
+
+use super::bloom::{BloomSettings, SetUpBloom};
+use super::fullscreen_pass::SetUpFullscreenPass;
+use super::hdr_target::SetUpHdrTarget;
+use super::shaders::PostProcessShaders;
+use super::tonemap::{GammaCorrection, TonemapPushConstants, TonemapSettings};
+
+/// A chain of post-process passes for a window
+///
+/// Each frame, the scene is rendered into the `hdr_target` using
+/// `PostProcessChain::record_scene_pass`, after which `PostProcessChain::record_post_process`
+/// records the bloom passes and the tonemapping pass into the present image. Scene pipelines
+/// have to be built for the render pass of the HDR target.
+pub struct PostProcessChain {
+    pub settings: TonemapSettings,
+    pub gamma_correction: GammaCorrection,
+
+    pub sampler: SetUpSampler,
+    pub hdr_target: SetUpHdrTarget,
+    pub bloom: Option<SetUpBloom>,
+    pub tonemap_pass: SetUpFullscreenPass,
+    pub present_framebuffers: Vec<Framebuffer>,
+}
+
+impl PostProcessChain {
+    /// This function creates the chain for the present images of a surface. Bloom is only
+    /// enabled if `bloom_settings` are given.
+    pub unsafe fn create(
+        logical_device: &SetUpLogicalDevice,
+        allocator: &mut MutexGuard<Allocator>,
+        shaders: &PostProcessShaders,
+        surface_info: &SetUpSurfaceInfo,
+        present_images: &SetUpPresentImages,
+        depth_format: Format,
+        bloom_settings: Option<BloomSettings>,
+    ) -> crate::Result<Self> {
+        let surface_format = surface_info.surface_format(0)?;
+        let extent = surface_info.surface_extent()?;
+
+        let sampler = SetUpSampler::create_with_defaults(
+            logical_device,
+            Filter::LINEAR,
+            SamplerAddressMode::CLAMP_TO_EDGE,
+        )?;
+        let hdr_target = SetUpHdrTarget::create(logical_device, allocator, extent, depth_format)?;
+        let bloom = match bloom_settings {
+            Some(bloom_settings) => Some(SetUpBloom::create(
+                logical_device,
+                allocator,
+                shaders,
+                bloom_settings,
+                &sampler,
+                &hdr_target.color_image_view,
+                extent,
+            )?),
+            None => None,
+        };
+
+        // The tonemapping pass always samples two images; without bloom, the HDR image is bound
+        // twice and the bloom intensity is zero
+        let tonemap_pass = SetUpFullscreenPass::create::<TonemapPushConstants>(
+            logical_device,
+            surface_format.format,
+            ImageLayout::PRESENT_SRC_KHR,
+            2,
+            1,
+            &shaders.fullscreen_vertex,
+            &shaders.tonemap_fragment,
+        )?;
+        let present_framebuffers = Self::create_present_framebuffers(
+            logical_device,
+            &tonemap_pass.render_pass,
+            present_images,
+            extent,
+        )?;
+
+        let chain = Self {
+            settings: TonemapSettings::default(),
+            gamma_correction: GammaCorrection::for_surface_format(surface_format),
+            sampler,
+            hdr_target,
+            bloom,
+            tonemap_pass,
+            present_framebuffers,
+        };
+        chain.update_tonemap_inputs(logical_device)?;
+        Ok(chain)
+    }
+
+    unsafe fn create_present_framebuffers(
+        logical_device: &SetUpLogicalDevice,
+        render_pass: &SetUpRenderPass,
+        present_images: &SetUpPresentImages,
+        extent: Extent2D,
+    ) -> crate::Result<Vec<Framebuffer>> {
+        let mut framebuffers = Vec::with_capacity(present_images.present_image_views.len());
+        for present_image_view in present_images.present_image_views.iter() {
+            let framebuffer_attachments = [*present_image_view];
+            let framebuffer_create_info = FramebufferCreateInfo::builder()
+                .render_pass(render_pass.render_pass)
+                .attachments(&framebuffer_attachments)
+                .width(extent.width)
+                .height(extent.height)
+                .layers(1);
+            framebuffers.push(logical_device.create_framebuffer(&framebuffer_create_info, None)?);
+        }
+        Ok(framebuffers)
+    }
+
+    fn update_tonemap_inputs(&self, logical_device: &SetUpLogicalDevice) -> crate::Result<()> {
+        let hdr_image_view = &self.hdr_target.color_image_view;
+        let bloom_image_view = self
+            .bloom
+            .as_ref()
+            .map_or(hdr_image_view, |bloom| bloom.output_image_view());
+        self.tonemap_pass.update_inputs(
+            logical_device,
+            0,
+            &self.sampler,
+            &[hdr_image_view, bloom_image_view],
+        )
+    }
+
+    /// This function recreates all size-dependent resources after the swapchain was updated.
+    /// The device must be idle.
+    pub unsafe fn resize(
+        &mut self,
+        logical_device: &SetUpLogicalDevice,
+        allocator: &mut MutexGuard<Allocator>,
+        surface_info: &SetUpSurfaceInfo,
+        present_images: &SetUpPresentImages,
+    ) -> crate::Result<()> {
+        let extent = surface_info.surface_extent()?;
+        let depth_format = self.hdr_target.depth_image.format;
+
+        self.destroy_present_framebuffers(logical_device);
+        let new_hdr_target =
+            SetUpHdrTarget::create(logical_device, allocator, extent, depth_format)?;
+        let mut old_hdr_target = std::mem::replace(&mut self.hdr_target, new_hdr_target);
+        old_hdr_target.destroy(logical_device, allocator)?;
+
+        if let Some(bloom) = self.bloom.as_mut() {
+            bloom.resize(
+                logical_device,
+                allocator,
+                &self.sampler,
+                &self.hdr_target.color_image_view,
+                extent,
+            )?;
+        }
+
+        self.present_framebuffers = Self::create_present_framebuffers(
+            logical_device,
+            &self.tonemap_pass.render_pass,
+            present_images,
+            extent,
+        )?;
+        self.gamma_correction =
+            GammaCorrection::for_surface_format(surface_info.surface_format(0)?);
+        self.update_tonemap_inputs(logical_device)
+    }
+
+    /// This function records the render pass of the HDR target, in which the scene is drawn by
+    /// the `render_pass_op`.
+    pub fn record_scene_pass<RenderPassOp>(
+        &self,
+        logical_device: &SetUpLogicalDevice,
+        command_buffer: &SetUpCommandBufferWithFence,
+        clear_values: &[ClearValue],
+        render_pass_op: RenderPassOp,
+    ) -> crate::Result<()>
+    where
+        RenderPassOp: FnOnce() -> crate::Result<()>,
+    {
+        self.hdr_target.record_render_pass(
+            logical_device,
+            command_buffer,
+            clear_values,
+            render_pass_op,
+        )
+    }
+
+    /// This function records the bloom passes (if enabled) and tonemaps the HDR image into the
+    /// present image with the given index, which is in `PRESENT_SRC_KHR` layout afterwards.
+    pub fn record_post_process(
+        &self,
+        logical_device: &SetUpLogicalDevice,
+        command_buffer: &SetUpCommandBufferWithFence,
+        present_index: u32,
+    ) -> crate::Result<()> {
+        let framebuffer = *self
+            .present_framebuffers
+            .get(present_index as usize)
+            .context(format!(
+                "No present image with index {} exists",
+                present_index
+            ))?;
+
+        if let Some(bloom) = self.bloom.as_ref() {
+            bloom.record(logical_device, command_buffer);
+        }
+
+        let push_constants =
+            TonemapPushConstants::new(&self.settings, self.gamma_correction, self.bloom.is_some());
+        self.tonemap_pass.record(
+            logical_device,
+            command_buffer,
+            framebuffer,
+            self.hdr_target.extent,
+            0,
+            &push_constants,
+        );
+        Ok(())
+    }
+
+    fn destroy_present_framebuffers(&mut self, logical_device: &SetUpLogicalDevice) {
+        unsafe {
+            for framebuffer in self.present_framebuffers.drain(..) {
+                logical_device.destroy_framebuffer(framebuffer, None);
+            }
+        }
+    }
+
+    pub fn destroy(
+        &mut self,
+        logical_device: &SetUpLogicalDevice,
+        allocator: &mut MutexGuard<Allocator>,
+    ) -> crate::Result<()> {
+        self.destroy_present_framebuffers(logical_device);
+        self.tonemap_pass.destroy(logical_device);
+        if let Some(mut bloom) = self.bloom.take() {
+            bloom.destroy(logical_device, allocator)?;
+        }
+        self.hdr_target.destroy(logical_device, allocator)?;
+        self.sampler.destroy(logical_device);
+        Ok(())
+    }
+}
@@ -0,0 +1,305 @@
+//! This is a module that contains the `SetUpFullscreenPass` structure, which draws a single
+//! fullscreen triangle with a fragment shader that samples its input images, and the
+//! `SetUpFullscreenTarget`, an intermediate color image that such a pass can render into.
+
+use std::sync::MutexGuard;
+
+use ash::vk::{DescriptorSet, DescriptorType, Extent2D, Extent3D, Format};
+use ash::vk::{DynamicState, SampleCountFlags, ShaderStageFlags, SharingMode, Viewport};
+use ash::vk::{Framebuffer, FramebufferCreateInfo, ImageLayout, Pipeline, PipelineBindPoint};
+use ash::vk::{ImageAspectFlags, ImageSubresourceRange, ImageTiling, ImageType, ImageUsageFlags};
+use ash::vk::{
+    ImageViewType, PipelineDepthStencilStateCreateInfo, PipelineVertexInputStateCreateInfo,
+};
+use ash::vk::{PolygonMode, PrimitiveTopology, Rect2D, RenderPassBeginInfo, SubpassContents};
+use gpu_allocator::vulkan::Allocator;
+
+use crate::vulkan::command::command_buffer::SetUpCommandBufferWithFence;
+use crate::vulkan::descriptors::descriptor_set_layout::SetUpDescriptorSetLayout;
+use crate::vulkan::descriptors::descriptor_set_layout_builder::DescriptorSetLayoutBuilder;
+use crate::vulkan::descriptors::descriptor_set_logic::SetUpDescriptorSetLogic;
+use crate::vulkan::descriptors::descriptor_set_logic_builder::DescriptorSetLogicBuilder;
+use crate::vulkan::descriptors::descriptor_set_update_builder::DescriptorSetUpdateBuilder;
+use crate::vulkan::graphics_pipeline_builder::GraphicsPipelineBuilder;
+use crate::vulkan::image::image::AllocatedImage;
+use crate::vulkan::image::image_view::SetUpImageView;
+use crate::vulkan::image::sampler::SetUpSampler;
+use crate::vulkan::logical_device::SetUpLogicalDevice;
+use crate::vulkan::pipeline_layout::SetUpPipelineLayout;
+use crate::vulkan::pipeline_layout_builder::PipelineLayoutBuilder;
+use crate::vulkan::render_pass::SetUpRenderPass;
+use crate::vulkan::shader::CompiledShaderModule;
+
+/// A pass that draws a fullscreen triangle into a single color attachment
+///
+/// The input images are bound as combined image samplers at the bindings `0..input_count` of
+/// set 0, and the push constants are visible to the fragment shader.
+pub struct SetUpFullscreenPass {
+    pub render_pass: SetUpRenderPass,
+    pub descriptor_set_layout: SetUpDescriptorSetLayout,
+    pub descriptor_set_logic: SetUpDescriptorSetLogic,
+    pub pipeline_layout: SetUpPipelineLayout,
+    pub pipeline: Pipeline,
+    pub input_count: u32,
+}
+
+impl SetUpFullscreenPass {
+    /// This function creates the pass and allocates `set_count` descriptor sets, e.g. one for
+    /// each combination of input images the pass is recorded with.
+    pub unsafe fn create<PushConstants>(
+        logical_device: &SetUpLogicalDevice,
+        color_format: Format,
+        final_layout: ImageLayout,
+        input_count: u32,
+        set_count: u32,
+        vertex_shader: &CompiledShaderModule,
+        fragment_shader: &CompiledShaderModule,
+    ) -> crate::Result<Self> {
+        let render_pass = SetUpRenderPass::create_for_fullscreen_pass(
+            logical_device,
+            color_format,
+            final_layout,
+        )?;
+
+        let mut descriptor_set_layout_builder = DescriptorSetLayoutBuilder::new();
+        for binding in 0..input_count {
+            descriptor_set_layout_builder = descriptor_set_layout_builder.add_binding(
+                binding,
+                1,
+                DescriptorType::COMBINED_IMAGE_SAMPLER,
+                ShaderStageFlags::FRAGMENT,
+            );
+        }
+        let descriptor_set_layout = descriptor_set_layout_builder.build(logical_device)?;
+
+        // Every set is allocated with the same layout
+        let set_layouts = (0..set_count)
+            .map(|_| SetUpDescriptorSetLayout {
+                descriptor_set_layout: descriptor_set_layout.descriptor_set_layout,
+            })
+            .collect::<Vec<_>>();
+        let descriptor_set_logic = DescriptorSetLogicBuilder::new()
+            .add_pool_size(
+                DescriptorType::COMBINED_IMAGE_SAMPLER,
+                input_count * set_count,
+            )
+            .set_layouts(&set_layouts)
+            .build(logical_device)?;
+
+        let pipeline_layout = PipelineLayoutBuilder::new()
+            .add_push_constant_range::<PushConstants>(0, ShaderStageFlags::FRAGMENT)
+            .set_layouts(std::slice::from_ref(&descriptor_set_layout))
+            .build(logical_device)?;
+
+        // The viewport and scissor are dynamic, so the pipeline doesn't depend on the extent
+        let pipeline = GraphicsPipelineBuilder::new()
+            .add_shader_stage_from_module(vertex_shader)
+            .add_shader_stage_from_module(fragment_shader)
+            .vertex_input(PipelineVertexInputStateCreateInfo::default())
+            .input_assembly_with_topology(PrimitiveTopology::TRIANGLE_LIST)
+            .rasterization_with_polygon_mode(PolygonMode::FILL)
+            .multisample_with_samples(SampleCountFlags::TYPE_1)
+            .color_blend_attachment_with_defaults()
+            .depth_stencil(PipelineDepthStencilStateCreateInfo::default())
+            .add_dynamic_state(DynamicState::VIEWPORT)
+            .add_dynamic_state(DynamicState::SCISSOR)
+            .viewport(Viewport::default())
+            .scissor(Rect2D::default())
+            .build(logical_device, &render_pass, &pipeline_layout)?;
+
+        Ok(Self {
+            render_pass,
+            descriptor_set_layout,
+            descriptor_set_logic,
+            pipeline_layout,
+            pipeline,
+            input_count,
+        })
+    }
+
+    pub fn descriptor_set(&self, index: usize) -> DescriptorSet {
+        *self.descriptor_set_logic.get_set(index)
+    }
+
+    /// This function binds the `inputs` to the descriptor set with the given index. The images
+    /// have to be in `SHADER_READ_ONLY_OPTIMAL` layout when the pass is recorded.
+    pub fn update_inputs(
+        &self,
+        logical_device: &SetUpLogicalDevice,
+        set_index: usize,
+        sampler: &SetUpSampler,
+        inputs: &[&SetUpImageView],
+    ) -> crate::Result<()> {
+        crate::verify!(
+            inputs.len() == self.input_count as usize,
+            "The fullscreen pass expects {} inputs, got {}",
+            (self.input_count),
+            (inputs.len())
+        );
+
+        let mut update_builder = DescriptorSetUpdateBuilder::new();
+        for (binding, input) in inputs.iter().enumerate() {
+            update_builder = update_builder.add_image_descriptor(
+                sampler,
+                input,
+                binding as u32,
+                DescriptorType::COMBINED_IMAGE_SAMPLER,
+            );
+        }
+        update_builder.update(logical_device, self.descriptor_set_logic.get_set(set_index));
+        Ok(())
+    }
+
+    /// This function records the pass into the `framebuffer`, which must have been created for
+    /// the render pass of this pass.
+    pub fn record<PushConstants>(
+        &self,
+        logical_device: &SetUpLogicalDevice,
+        command_buffer: &SetUpCommandBufferWithFence,
+        framebuffer: Framebuffer,
+        extent: Extent2D,
+        set_index: usize,
+        push_constants: &PushConstants,
+    ) {
+        let render_area: Rect2D = extent.into();
+        let render_pass_begin_info = RenderPassBeginInfo::builder()
+            .render_pass(self.render_pass.render_pass)
+            .framebuffer(framebuffer)
+            .render_area(render_area);
+        let viewport = Viewport {
+            x: 0.0,
+            y: 0.0,
+            width: extent.width as f32,
+            height: extent.height as f32,
+            min_depth: 0.0,
+            max_depth: 1.0,
+        };
+
+        unsafe {
+            logical_device.cmd_begin_render_pass(
+                command_buffer.command_buffer,
+                &render_pass_begin_info,
+                SubpassContents::INLINE,
+            );
+            logical_device.cmd_bind_pipeline(
+                command_buffer.command_buffer,
+                PipelineBindPoint::GRAPHICS,
+                self.pipeline,
+            );
+            logical_device.cmd_set_viewport(command_buffer.command_buffer, 0, &[viewport]);
+            logical_device.cmd_set_scissor(command_buffer.command_buffer, 0, &[render_area]);
+            logical_device.cmd_bind_descriptor_sets(
+                command_buffer.command_buffer,
+                PipelineBindPoint::GRAPHICS,
+                self.pipeline_layout.pipeline_layout,
+                0,
+                &[self.descriptor_set(set_index)],
+                &[],
+            );
+        }
+        command_buffer.push_constants(
+            logical_device,
+            self.pipeline_layout.pipeline_layout,
+            ShaderStageFlags::FRAGMENT,
+            0,
+            push_constants,
+        );
+        unsafe {
+            logical_device.cmd_draw(command_buffer.command_buffer, 3, 1, 0, 0);
+            logical_device.cmd_end_render_pass(command_buffer.command_buffer);
+        }
+    }
+
+    pub fn destroy(&mut self, logical_device: &SetUpLogicalDevice) {
+        unsafe {
+            logical_device.destroy_pipeline(self.pipeline, None);
+        }
+        self.pipeline_layout.destroy(logical_device);
+        self.descriptor_set_logic.destroy(logical_device);
+        self.descriptor_set_layout.destroy(logical_device);
+        self.render_pass.destroy(logical_device);
+    }
+}
+
+/// An intermediate color image of a post-process chain that is rendered into by one pass and
+/// sampled by the next
+pub struct SetUpFullscreenTarget {
+    pub extent: Extent2D,
+    pub color_image: AllocatedImage,
+    pub color_image_view: SetUpImageView,
+    pub framebuffer: Framebuffer,
+}
+
+impl SetUpFullscreenTarget {
+    pub unsafe fn create(
+        logical_device: &SetUpLogicalDevice,
+        allocator: &mut MutexGuard<Allocator>,
+        format: Format,
+        extent: Extent2D,
+        render_pass: &SetUpRenderPass,
+    ) -> crate::Result<Self> {
+        let color_image = AllocatedImage::allocate(
+            logical_device,
+            allocator,
+            ImageType::TYPE_2D,
+            format,
+            Extent3D {
+                width: extent.width,
+                height: extent.height,
+                depth: 1,
+            },
+            1,
+            1,
+            SampleCountFlags::TYPE_1,
+            ImageTiling::OPTIMAL,
+            ImageUsageFlags::COLOR_ATTACHMENT | ImageUsageFlags::SAMPLED,
+            SharingMode::EXCLUSIVE,
+        )?;
+        let color_image_view = SetUpImageView::create(
+            logical_device,
+            ImageViewType::TYPE_2D,
+            format,
+            color_subresource_range(),
+            color_image.image,
+        )?;
+
+        let framebuffer_attachments = [color_image_view.image_view];
+        let framebuffer_create_info = FramebufferCreateInfo::builder()
+            .render_pass(render_pass.render_pass)
+            .attachments(&framebuffer_attachments)
+            .width(extent.width)
+            .height(extent.height)
+            .layers(1);
+        let framebuffer = logical_device.create_framebuffer(&framebuffer_create_info, None)?;
+
+        Ok(Self {
+            extent,
+            color_image,
+            color_image_view,
+            framebuffer,
+        })
+    }
+
+    pub fn destroy(
+        &mut self,
+        logical_device: &SetUpLogicalDevice,
+        allocator: &mut MutexGuard<Allocator>,
+    ) -> crate::Result<()> {
+        unsafe {
+            logical_device.destroy_framebuffer(self.framebuffer, None);
+        }
+        self.color_image_view.destroy(logical_device);
+        self.color_image.destroy(logical_device, allocator)?;
+        Ok(())
+    }
+}
+
+pub(crate) fn color_subresource_range() -> ImageSubresourceRange {
+    ImageSubresourceRange::builder()
+        .aspect_mask(ImageAspectFlags::COLOR)
+        .base_mip_level(0)
+        .level_count(1)
+        .base_array_layer(0)
+        .layer_count(1)
+        .build()
+}
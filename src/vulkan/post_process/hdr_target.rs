@@ -0,0 +1,150 @@
+//! This is a module that contains the `SetUpHdrTarget` structure, a floating point color image
+//! with a depth image that the scene is rendered into before it is tonemapped.
+
+use std::sync::MutexGuard;
+
+use ash::vk::{ClearValue, Extent2D, Extent3D, Format, Framebuffer, FramebufferCreateInfo};
+use ash::vk::{ImageTiling, ImageType, ImageUsageFlags, ImageViewType};
+use ash::vk::{RenderPassBeginInfo, SampleCountFlags, SharingMode, SubpassContents};
+use gpu_allocator::vulkan::Allocator;
+
+use crate::vulkan::command::command_buffer::SetUpCommandBufferWithFence;
+use crate::vulkan::depth_image::SetUpDepthImage;
+use crate::vulkan::image::image::AllocatedImage;
+use crate::vulkan::image::image_view::SetUpImageView;
+use crate::vulkan::logical_device::SetUpLogicalDevice;
+use crate::vulkan::render_pass::SetUpRenderPass;
+
+use super::fullscreen_pass::color_subresource_range;
+
+/// The format of HDR color targets
+pub const HDR_COLOR_FORMAT: Format = Format::R16G16B16A16_SFLOAT;
+
+pub struct SetUpHdrTarget {
+    pub extent: Extent2D,
+    pub color_image: AllocatedImage,
+    pub color_image_view: SetUpImageView,
+    pub depth_image: SetUpDepthImage,
+    /// The render pass scene pipelines have to be built for
+    pub render_pass: SetUpRenderPass,
+    pub framebuffer: Framebuffer,
+}
+
+impl SetUpHdrTarget {
+    pub unsafe fn create(
+        logical_device: &SetUpLogicalDevice,
+        allocator: &mut MutexGuard<Allocator>,
+        extent: Extent2D,
+        depth_format: Format,
+    ) -> crate::Result<Self> {
+        let color_image = AllocatedImage::allocate(
+            logical_device,
+            allocator,
+            ImageType::TYPE_2D,
+            HDR_COLOR_FORMAT,
+            Extent3D {
+                width: extent.width,
+                height: extent.height,
+                depth: 1,
+            },
+            1,
+            1,
+            SampleCountFlags::TYPE_1,
+            ImageTiling::OPTIMAL,
+            ImageUsageFlags::COLOR_ATTACHMENT | ImageUsageFlags::SAMPLED,
+            SharingMode::EXCLUSIVE,
+        )?;
+        let color_image_view = SetUpImageView::create(
+            logical_device,
+            ImageViewType::TYPE_2D,
+            HDR_COLOR_FORMAT,
+            color_subresource_range(),
+            color_image.image,
+        )?;
+
+        let depth_image = SetUpDepthImage::create(
+            logical_device,
+            allocator,
+            depth_format,
+            extent,
+            SampleCountFlags::TYPE_1,
+            SetUpDepthImage::create_subresource_range_for_format(depth_format),
+        )?;
+
+        let render_pass = SetUpRenderPass::create_for_sampled_color_target(
+            logical_device,
+            HDR_COLOR_FORMAT,
+            depth_format,
+        )?;
+
+        let framebuffer_attachments = [
+            color_image_view.image_view,
+            depth_image.depth_image_view.image_view,
+        ];
+        let framebuffer_create_info = FramebufferCreateInfo::builder()
+            .render_pass(render_pass.render_pass)
+            .attachments(&framebuffer_attachments)
+            .width(extent.width)
+            .height(extent.height)
+            .layers(1);
+        let framebuffer = logical_device.create_framebuffer(&framebuffer_create_info, None)?;
+
+        Ok(Self {
+            extent,
+            color_image,
+            color_image_view,
+            depth_image,
+            render_pass,
+            framebuffer,
+        })
+    }
+
+    /// This function records the render pass of this target. The `clear_values` contain the
+    /// color clear value followed by the depth clear value.
+    ///
+    /// The color image is transitioned to `SHADER_READ_ONLY_OPTIMAL` at the end of the render
+    /// pass, so that it can be sampled by the post-process passes.
+    pub fn record_render_pass<RenderPassOp>(
+        &self,
+        logical_device: &SetUpLogicalDevice,
+        command_buffer: &SetUpCommandBufferWithFence,
+        clear_values: &[ClearValue],
+        render_pass_op: RenderPassOp,
+    ) -> crate::Result<()>
+    where
+        RenderPassOp: FnOnce() -> crate::Result<()>,
+    {
+        let render_pass_begin_info = RenderPassBeginInfo::builder()
+            .render_pass(self.render_pass.render_pass)
+            .framebuffer(self.framebuffer)
+            .render_area(self.extent.into())
+            .clear_values(clear_values);
+
+        unsafe {
+            logical_device.cmd_begin_render_pass(
+                command_buffer.command_buffer,
+                &render_pass_begin_info,
+                SubpassContents::INLINE,
+            );
+            render_pass_op()?;
+            logical_device.cmd_end_render_pass(command_buffer.command_buffer);
+        }
+
+        Ok(())
+    }
+
+    pub fn destroy(
+        &mut self,
+        logical_device: &SetUpLogicalDevice,
+        allocator: &mut MutexGuard<Allocator>,
+    ) -> crate::Result<()> {
+        unsafe {
+            logical_device.destroy_framebuffer(self.framebuffer, None);
+        }
+        self.render_pass.destroy(logical_device);
+        self.depth_image.destroy(logical_device, allocator)?;
+        self.color_image_view.destroy(logical_device);
+        self.color_image.destroy(logical_device, allocator)?;
+        Ok(())
+    }
+}
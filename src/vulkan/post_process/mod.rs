@@ -0,0 +1,17 @@
+//! This is a module that provides an HDR post-process chain: The scene is rendered into a
+//! floating point target, optionally blurred into a bloom image and tonemapped into the present
+//! images with gamma correction for the surface color space.
+//!
+//! - bloom: Provides an abstraction for extracting and blurring the bright parts of an HDR image
+//! - chain: Provides the `PostProcessChain`, which combines the HDR target, bloom and tonemapping for a window
+//! - fullscreen\_pass: Provides an abstraction for passes that draw a fullscreen triangle and the images they render into
+//! - hdr\_target: Provides an abstraction for the floating point color and depth images the scene is rendered into
+//! - shaders: Provides the GLSL sources of the post-process passes and their compiled shader modules
+//! - tonemap: Provides the tonemapper selection, exposure and gamma correction settings
+
+pub mod bloom;
+pub mod chain;
+pub mod fullscreen_pass;
+pub mod hdr_target;
+pub mod shaders;
+pub mod tonemap;
@@ -0,0 +1,96 @@
+//! This is a module that contains the GLSL sources of the post-process passes and the
+//! `PostProcessShaders` structure, which holds their compiled shader modules.
+
+use ash::vk::ShaderStageFlags;
+
+#[cfg(feature = "shaderc-crate")]
+use shaderc::ShaderKind;
+
+use crate::vulkan::logical_device::SetUpLogicalDevice;
+use crate::vulkan::shader::CompiledShaderModule;
+
+pub const FULLSCREEN_VERTEX_SHADER_SOURCE: &str = include_str!("shaders/fullscreen.vert");
+pub const TONEMAP_FRAGMENT_SHADER_SOURCE: &str = include_str!("shaders/tonemap.frag");
+pub const BLOOM_THRESHOLD_FRAGMENT_SHADER_SOURCE: &str =
+    include_str!("shaders/bloom_threshold.frag");
+pub const BLOOM_BLUR_FRAGMENT_SHADER_SOURCE: &str = include_str!("shaders/bloom_blur.frag");
+
+/// The compiled shader modules of all post-process passes
+pub struct PostProcessShaders {
+    pub fullscreen_vertex: CompiledShaderModule,
+    pub tonemap_fragment: CompiledShaderModule,
+    pub bloom_threshold_fragment: CompiledShaderModule,
+    pub bloom_blur_fragment: CompiledShaderModule,
+}
+
+impl PostProcessShaders {
+    /// This function creates the shader modules from SPIR-V that was compiled ahead of time from
+    /// the sources in this module (in the order of the parameters).
+    pub unsafe fn create_from_spirv(
+        logical_device: &SetUpLogicalDevice,
+        fullscreen_vertex: Vec<u8>,
+        tonemap_fragment: Vec<u8>,
+        bloom_threshold_fragment: Vec<u8>,
+        bloom_blur_fragment: Vec<u8>,
+    ) -> crate::Result<Self> {
+        Ok(Self {
+            fullscreen_vertex: CompiledShaderModule::create_shader_of_stage(
+                fullscreen_vertex,
+                logical_device,
+                ShaderStageFlags::VERTEX,
+            )?,
+            tonemap_fragment: CompiledShaderModule::create_shader_of_stage(
+                tonemap_fragment,
+                logical_device,
+                ShaderStageFlags::FRAGMENT,
+            )?,
+            bloom_threshold_fragment: CompiledShaderModule::create_shader_of_stage(
+                bloom_threshold_fragment,
+                logical_device,
+                ShaderStageFlags::FRAGMENT,
+            )?,
+            bloom_blur_fragment: CompiledShaderModule::create_shader_of_stage(
+                bloom_blur_fragment,
+                logical_device,
+                ShaderStageFlags::FRAGMENT,
+            )?,
+        })
+    }
+
+    /// This function compiles the sources in this module at runtime using shaderc.
+    #[cfg(feature = "shaderc-crate")]
+    pub unsafe fn compile(logical_device: &SetUpLogicalDevice) -> crate::Result<Self> {
+        use crate::vulkan::shader::compile_bytes;
+
+        Self::create_from_spirv(
+            logical_device,
+            compile_bytes(
+                ShaderKind::Vertex,
+                FULLSCREEN_VERTEX_SHADER_SOURCE,
+                "fullscreen.vert",
+            )?,
+            compile_bytes(
+                ShaderKind::Fragment,
+                TONEMAP_FRAGMENT_SHADER_SOURCE,
+                "tonemap.frag",
+            )?,
+            compile_bytes(
+                ShaderKind::Fragment,
+                BLOOM_THRESHOLD_FRAGMENT_SHADER_SOURCE,
+                "bloom_threshold.frag",
+            )?,
+            compile_bytes(
+                ShaderKind::Fragment,
+                BLOOM_BLUR_FRAGMENT_SHADER_SOURCE,
+                "bloom_blur.frag",
+            )?,
+        )
+    }
+
+    pub fn destroy(&mut self, logical_device: &SetUpLogicalDevice) {
+        self.fullscreen_vertex.destroy(logical_device);
+        self.tonemap_fragment.destroy(logical_device);
+        self.bloom_threshold_fragment.destroy(logical_device);
+        self.bloom_blur_fragment.destroy(logical_device);
+    }
+}
@@ -0,0 +1,28 @@
+#version 450
+
+layout(location = 0) in vec2 in_uv;
+layout(location = 0) out vec4 out_color;
+
+layout(set = 0, binding = 0) uniform sampler2D source_image;
+
+layout(push_constant) uniform BloomPushConstants {
+    vec2 direction;
+    float threshold;
+    float knee;
+} settings;
+
+// 9-tap gaussian blur using linear sampling between texels
+const float offsets[3] = float[](0.0, 1.3846153846, 3.2307692308);
+const float weights[3] = float[](0.2270270270, 0.3162162162, 0.0702702703);
+
+void main() {
+    vec2 texel_step = settings.direction / vec2(textureSize(source_image, 0));
+
+    vec3 color = texture(source_image, in_uv).rgb * weights[0];
+    for (int i = 1; i < 3; i++) {
+        color += texture(source_image, in_uv + texel_step * offsets[i]).rgb * weights[i];
+        color += texture(source_image, in_uv - texel_step * offsets[i]).rgb * weights[i];
+    }
+
+    out_color = vec4(color, 1.0);
+}
@@ -0,0 +1,24 @@
+#version 450
+
+layout(location = 0) in vec2 in_uv;
+layout(location = 0) out vec4 out_color;
+
+layout(set = 0, binding = 0) uniform sampler2D hdr_image;
+
+layout(push_constant) uniform BloomPushConstants {
+    vec2 direction;
+    float threshold;
+    float knee;
+} settings;
+
+void main() {
+    vec3 color = texture(hdr_image, in_uv).rgb;
+    float brightness = max(color.r, max(color.g, color.b));
+
+    // Soft threshold: brightness values within `knee` below the threshold fade in smoothly
+    float soft = clamp(brightness - settings.threshold + settings.knee, 0.0, 2.0 * settings.knee);
+    soft = soft * soft / (4.0 * settings.knee + 0.00001);
+    float contribution = max(soft, brightness - settings.threshold) / max(brightness, 0.00001);
+
+    out_color = vec4(color * contribution, 1.0);
+}
@@ -0,0 +1,88 @@
+#version 450
+
+layout(location = 0) in vec2 in_uv;
+layout(location = 0) out vec4 out_color;
+
+layout(set = 0, binding = 0) uniform sampler2D hdr_image;
+layout(set = 0, binding = 1) uniform sampler2D bloom_image;
+
+layout(push_constant) uniform TonemapPushConstants {
+    float exposure;
+    float bloom_intensity;
+    uint tonemapper;
+    uint apply_srgb_encoding;
+} settings;
+
+const uint TONEMAPPER_NONE = 0;
+const uint TONEMAPPER_REINHARD = 1;
+const uint TONEMAPPER_ACES = 2;
+const uint TONEMAPPER_AGX = 3;
+
+vec3 reinhard(vec3 color) {
+    return color / (1.0 + color);
+}
+
+// Krzysztof Narkowicz's fit of the ACES filmic curve
+vec3 aces(vec3 color) {
+    const float a = 2.51;
+    const float b = 0.03;
+    const float c = 2.43;
+    const float d = 0.59;
+    const float e = 0.14;
+    return clamp((color * (a * color + b)) / (color * (c * color + d) + e), 0.0, 1.0);
+}
+
+// Polynomial approximation of Troy Sobotka's AgX with the default look
+vec3 agx_contrast(vec3 x) {
+    vec3 x2 = x * x;
+    vec3 x4 = x2 * x2;
+    return 15.5 * x4 * x2 - 40.14 * x4 * x + 31.96 * x4 - 6.868 * x2 * x + 0.4298 * x2
+        + 0.1191 * x - 0.00232;
+}
+
+vec3 agx(vec3 color) {
+    const mat3 agx_inset = mat3(
+        0.842479062253094, 0.0423282422610123, 0.0423756549057051,
+        0.0784335999999992, 0.878468636469772, 0.0784336,
+        0.0792237451477643, 0.0791661274605434, 0.879142973793104);
+    const mat3 agx_outset = mat3(
+        1.19687900512017, -0.0528968517574562, -0.0529716355144438,
+        -0.0980208811401368, 1.15190312990417, -0.0980434501171241,
+        -0.0990297440797205, -0.0989611768448433, 1.15107367264116);
+    const float min_ev = -12.47393;
+    const float max_ev = 4.026069;
+
+    color = agx_inset * color;
+    color = clamp(log2(max(color, vec3(1e-10))), min_ev, max_ev);
+    color = (color - min_ev) / (max_ev - min_ev);
+    color = agx_outset * agx_contrast(color);
+
+    // The AgX curve produces display-encoded values, so they are linearized again here
+    return pow(max(color, vec3(0.0)), vec3(2.2));
+}
+
+vec3 srgb_encode(vec3 color) {
+    vec3 low = color * 12.92;
+    vec3 high = 1.055 * pow(color, vec3(1.0 / 2.4)) - 0.055;
+    return mix(high, low, lessThanEqual(color, vec3(0.0031308)));
+}
+
+void main() {
+    vec3 color = texture(hdr_image, in_uv).rgb;
+    color += texture(bloom_image, in_uv).rgb * settings.bloom_intensity;
+    color *= settings.exposure;
+
+    if (settings.tonemapper == TONEMAPPER_REINHARD) {
+        color = reinhard(color);
+    } else if (settings.tonemapper == TONEMAPPER_ACES) {
+        color = aces(color);
+    } else if (settings.tonemapper == TONEMAPPER_AGX) {
+        color = agx(color);
+    }
+    color = clamp(color, 0.0, 1.0);
+
+    if (settings.apply_srgb_encoding != 0) {
+        color = srgb_encode(color);
+    }
+    out_color = vec4(color, 1.0);
+}
@@ -0,0 +1,160 @@
+//! This is a module that contains the settings of the tonemapping pass: the tonemapper, the
+//! exposure and the gamma correction that is required for the surface format.
+
+use ash::vk::{ColorSpaceKHR, Format, SurfaceFormatKHR};
+
+/// The curve that maps HDR colors into the displayable range
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Tonemapper {
+    /// The colors are only clamped
+    None,
+    Reinhard,
+    /// Krzysztof Narkowicz's fit of the ACES filmic curve
+    #[default]
+    Aces,
+    /// An approximation of Troy Sobotka's AgX with the default look
+    AgX,
+}
+
+impl Tonemapper {
+    /// This function returns the index of the tonemapper in `tonemap.frag`.
+    pub fn shader_index(&self) -> u32 {
+        match self {
+            Self::None => 0,
+            Self::Reinhard => 1,
+            Self::Aces => 2,
+            Self::AgX => 3,
+        }
+    }
+}
+
+/// The encoding the tonemapping pass has to apply before writing to the present image
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GammaCorrection {
+    /// The colors are written as they are, either because the present image has an `_SRGB`
+    /// format (the hardware encodes the colors when writing) or because the color space is
+    /// linear.
+    None,
+    /// The colors are encoded with the sRGB transfer function in the shader
+    Srgb,
+}
+
+impl GammaCorrection {
+    /// This function determines the gamma correction for the format and color space of a
+    /// surface, as reported by `SetUpSurfaceInfo::surface_format`.
+    pub fn for_surface_format(surface_format: SurfaceFormatKHR) -> Self {
+        if is_srgb_format(surface_format.format) {
+            return Self::None;
+        }
+
+        match surface_format.color_space {
+            ColorSpaceKHR::SRGB_NONLINEAR
+            | ColorSpaceKHR::EXTENDED_SRGB_NONLINEAR_EXT
+            | ColorSpaceKHR::DISPLAY_P3_NONLINEAR_EXT => Self::Srgb,
+            _ => Self::None,
+        }
+    }
+}
+
+/// This function returns whether the `format` is encoded with the sRGB transfer function by
+/// the hardware.
+pub fn is_srgb_format(format: Format) -> bool {
+    matches!(
+        format,
+        Format::R8_SRGB
+            | Format::R8G8_SRGB
+            | Format::R8G8B8_SRGB
+            | Format::B8G8R8_SRGB
+            | Format::R8G8B8A8_SRGB
+            | Format::B8G8R8A8_SRGB
+            | Format::A8B8G8R8_SRGB_PACK32
+    )
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TonemapSettings {
+    pub tonemapper: Tonemapper,
+    /// The factor the HDR colors are multiplied with before tonemapping
+    pub exposure: f32,
+    /// The factor the bloom is multiplied with before it is added to the HDR colors. This is
+    /// ignored if the post-process chain was created without bloom.
+    pub bloom_intensity: f32,
+}
+
+impl Default for TonemapSettings {
+    fn default() -> Self {
+        Self {
+            tonemapper: Tonemapper::default(),
+            exposure: 1.0,
+            bloom_intensity: 0.04,
+        }
+    }
+}
+
+/// The push constants of `tonemap.frag`
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TonemapPushConstants {
+    pub exposure: f32,
+    pub bloom_intensity: f32,
+    pub tonemapper: u32,
+    pub apply_srgb_encoding: u32,
+}
+
+impl TonemapPushConstants {
+    pub fn new(
+        settings: &TonemapSettings,
+        gamma_correction: GammaCorrection,
+        has_bloom: bool,
+    ) -> Self {
+        Self {
+            exposure: settings.exposure,
+            bloom_intensity: if has_bloom {
+                settings.bloom_intensity
+            } else {
+                0.0
+            },
+            tonemapper: settings.tonemapper.shader_index(),
+            apply_srgb_encoding: (gamma_correction == GammaCorrection::Srgb) as u32,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn gamma_is_only_applied_to_non_srgb_formats() {
+        let surface_format = |format| SurfaceFormatKHR {
+            format,
+            color_space: ColorSpaceKHR::SRGB_NONLINEAR,
+        };
+        assert_eq!(
+            GammaCorrection::None,
+            GammaCorrection::for_surface_format(surface_format(Format::B8G8R8A8_SRGB))
+        );
+        assert_eq!(
+            GammaCorrection::Srgb,
+            GammaCorrection::for_surface_format(surface_format(Format::B8G8R8A8_UNORM))
+        );
+
+        let linear_surface_format = SurfaceFormatKHR {
+            format: Format::R16G16B16A16_SFLOAT,
+            color_space: ColorSpaceKHR::EXTENDED_SRGB_LINEAR_EXT,
+        };
+        assert_eq!(
+            GammaCorrection::None,
+            GammaCorrection::for_surface_format(linear_surface_format)
+        );
+    }
+
+    #[test]
+    fn bloom_intensity_is_zero_without_bloom() {
+        let push_constants =
+            TonemapPushConstants::new(&TonemapSettings::default(), GammaCorrection::Srgb, false);
+        assert_eq!(0.0, push_constants.bloom_intensity);
+        assert_eq!(Tonemapper::Aces.shader_index(), push_constants.tonemapper);
+        assert_eq!(1, push_constants.apply_srgb_encoding);
+    }
+}
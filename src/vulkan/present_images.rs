@@ -1,8 +1,15 @@
+use ash::vk::{AccessFlags, DependencyFlags, PipelineStageFlags};
+use ash::vk::{BufferImageCopy, Extent2D, Extent3D};
 use ash::vk::{ComponentMapping, ComponentSwizzle};
 use ash::vk::{
     Image, ImageAspectFlags, ImageSubresourceRange, ImageView, ImageViewCreateInfo, ImageViewType,
 };
+use ash::vk::{ImageLayout, ImageMemoryBarrier, ImageSubresourceLayers};
 
+use crate::result::Context;
+
+use super::buffers::readback_buffer::SetUpReadbackBuffer;
+use super::command::command_buffer::SetUpCommandBufferWithFence;
 use super::logical_device::SetUpLogicalDevice;
 use super::surface::SetUpSurfaceInfo;
 use super::swapchain::SetUpSwapchain;
@@ -71,6 +78,100 @@ impl SetUpPresentImages {
         )
     }
 
+    /// This function records a copy of the present image at `present_index` into the
+    /// `readback_buffer`.
+    ///
+    /// The present image must be in the `PRESENT_SRC_KHR` layout and is transitioned back to it
+    /// after the copy. The swapchain must have been created with `TRANSFER_SRC` usage.
+    pub fn record_copy_to_readback_buffer(
+        &self,
+        logical_device: &SetUpLogicalDevice,
+        command_buffer: &SetUpCommandBufferWithFence,
+        present_index: u32,
+        image_extent: Extent2D,
+        readback_buffer: &SetUpReadbackBuffer<u8>,
+    ) -> crate::Result<()> {
+        let image = *self
+            .present_images
+            .get(present_index as usize)
+            .context(format!("No present image with index {present_index}"))?;
+
+        let image_size = image_extent.width as usize * image_extent.height as usize * 4;
+        let readback_buffer_size = readback_buffer.buffer.element_amount;
+        crate::verify!(
+            image_size == readback_buffer_size,
+            "The readback buffer must have the same size as the present image! Expected: {image_size}, got: {readback_buffer_size}"
+        );
+
+        let subresource_range = ImageSubresourceRange {
+            aspect_mask: ImageAspectFlags::COLOR,
+            base_mip_level: 0,
+            level_count: 1,
+            base_array_layer: 0,
+            layer_count: 1,
+        };
+        let to_transfer_src_barrier = ImageMemoryBarrier::builder()
+            .image(image)
+            .src_access_mask(AccessFlags::COLOR_ATTACHMENT_WRITE)
+            .dst_access_mask(AccessFlags::TRANSFER_READ)
+            .old_layout(ImageLayout::PRESENT_SRC_KHR)
+            .new_layout(ImageLayout::TRANSFER_SRC_OPTIMAL)
+            .subresource_range(subresource_range);
+        let to_present_src_barrier = ImageMemoryBarrier::builder()
+            .image(image)
+            .src_access_mask(AccessFlags::TRANSFER_READ)
+            .dst_access_mask(AccessFlags::empty())
+            .old_layout(ImageLayout::TRANSFER_SRC_OPTIMAL)
+            .new_layout(ImageLayout::PRESENT_SRC_KHR)
+            .subresource_range(subresource_range);
+
+        let buffer_image_copy = BufferImageCopy::builder()
+            .buffer_offset(0)
+            .image_subresource(
+                ImageSubresourceLayers::builder()
+                    .aspect_mask(ImageAspectFlags::COLOR)
+                    .mip_level(0)
+                    .base_array_layer(0)
+                    .layer_count(1)
+                    .build(),
+            )
+            .image_extent(Extent3D {
+                width: image_extent.width,
+                height: image_extent.height,
+                depth: 1,
+            });
+
+        unsafe {
+            logical_device.cmd_pipeline_barrier(
+                command_buffer.command_buffer,
+                PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
+                PipelineStageFlags::TRANSFER,
+                DependencyFlags::empty(),
+                &[],
+                &[],
+                &[*to_transfer_src_barrier],
+            );
+            logical_device.cmd_copy_image_to_buffer(
+                command_buffer.command_buffer,
+                image,
+                ImageLayout::TRANSFER_SRC_OPTIMAL,
+                readback_buffer.raw_buffer(),
+                &[*buffer_image_copy],
+            );
+            logical_device.cmd_pipeline_barrier(
+                command_buffer.command_buffer,
+                PipelineStageFlags::TRANSFER,
+                PipelineStageFlags::BOTTOM_OF_PIPE,
+                DependencyFlags::empty(),
+                &[],
+                &[],
+                &[*to_present_src_barrier],
+            );
+        }
+
+        Ok(())
+    }
+
     pub fn destroy(&mut self, logical_device: &SetUpLogicalDevice) {
         unsafe {
             for image_view in self.present_image_views.iter() {
@@ -0,0 +1,173 @@
+//! This is a module that contains the `QueueFamilyIndices` structure, which stores the queue
+//! families that are used for graphics, presentation, transfer and compute work.
+//!
+//! Dedicated transfer and async compute families are only used if the device provides families
+//! without graphics support. Otherwise, that work is submitted to the graphics queue.
+
+use ash::vk::{QueueFamilyProperties, QueueFlags};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct QueueFamilyIndices {
+    pub graphics: u32,
+    /// The family used for presentation (`None` for headless devices)
+    pub present: Option<u32>,
+    /// A dedicated transfer family without graphics support, if available
+    pub transfer: Option<u32>,
+    /// An async compute family without graphics support, if available
+    pub compute: Option<u32>,
+}
+
+impl QueueFamilyIndices {
+    /// This function discovers the queue families of a device from its `queue_families`.
+    ///
+    /// `supports_present` is called with the index of a family and returns whether that family can
+    /// present to the surface. If `require_present` is set, `None` is returned when no family can
+    /// present. A graphics family that can also present is preferred.
+    ///
+    /// For transfers, a family that only supports transfers is preferred over one that also
+    /// supports compute. For compute, a family that doesn't support graphics is required.
+    pub fn discover<F>(
+        queue_families: &[QueueFamilyProperties],
+        supports_present: F,
+        require_present: bool,
+    ) -> Option<Self>
+    where
+        F: Fn(u32) -> bool,
+    {
+        let find_family = |predicate: &dyn Fn(u32, &QueueFamilyProperties) -> bool| {
+            queue_families
+                .iter()
+                .enumerate()
+                .filter(|(_, family)| family.queue_count > 0)
+                .find(|(index, family)| predicate(*index as u32, family))
+                .map(|(index, _)| index as u32)
+        };
+
+        let is_graphics =
+            |family: &QueueFamilyProperties| family.queue_flags.contains(QueueFlags::GRAPHICS);
+        let is_compute =
+            |family: &QueueFamilyProperties| family.queue_flags.contains(QueueFlags::COMPUTE);
+        let is_transfer =
+            |family: &QueueFamilyProperties| family.queue_flags.contains(QueueFlags::TRANSFER);
+
+        let graphics = find_family(&|index, family| {
+            is_graphics(family) && (!require_present || supports_present(index))
+        })
+        .or_else(|| find_family(&|_, family| is_graphics(family)))?;
+
+        let present = if supports_present(graphics) {
+            Some(graphics)
+        } else {
+            find_family(&|index, _| supports_present(index))
+        };
+        if require_present && present.is_none() {
+            return None;
+        }
+
+        let transfer = find_family(&|_, family| {
+            is_transfer(family) && !is_graphics(family) && !is_compute(family)
+        })
+        .or_else(|| find_family(&|_, family| is_transfer(family) && !is_graphics(family)));
+
+        let compute = find_family(&|_, family| is_compute(family) && !is_graphics(family));
+
+        Some(Self {
+            graphics,
+            present,
+            transfer,
+            compute,
+        })
+    }
+
+    /// This function returns the family that is used for transfers, which is the graphics family
+    /// if no dedicated transfer family exists.
+    pub fn transfer_or_graphics(&self) -> u32 {
+        self.transfer.unwrap_or(self.graphics)
+    }
+
+    /// This function returns the family that is used for compute work, which is the graphics
+    /// family if no async compute family exists.
+    pub fn compute_or_graphics(&self) -> u32 {
+        self.compute.unwrap_or(self.graphics)
+    }
+
+    /// This function returns the family that is used for presentation, which is the graphics
+    /// family for headless devices.
+    pub fn present_or_graphics(&self) -> u32 {
+        self.present.unwrap_or(self.graphics)
+    }
+
+    /// This function returns all distinct queue families in use, starting with the graphics family.
+    pub fn unique_families(&self) -> Vec<u32> {
+        let mut families = vec![self.graphics];
+        for family in [self.present, self.transfer, self.compute]
+            .into_iter()
+            .flatten()
+        {
+            if !families.contains(&family) {
+                families.push(family);
+            }
+        }
+        families
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn family(queue_flags: QueueFlags) -> QueueFamilyProperties {
+        QueueFamilyProperties {
+            queue_flags,
+            queue_count: 1,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn dedicated_families_are_discovered() {
+        let families = [
+            family(QueueFlags::GRAPHICS | QueueFlags::COMPUTE | QueueFlags::TRANSFER),
+            family(QueueFlags::COMPUTE | QueueFlags::TRANSFER),
+            family(QueueFlags::TRANSFER),
+        ];
+        let indices = QueueFamilyIndices::discover(&families, |index| index == 0, true).unwrap();
+
+        assert_eq!(0, indices.graphics);
+        assert_eq!(Some(0), indices.present);
+        assert_eq!(Some(2), indices.transfer);
+        assert_eq!(Some(1), indices.compute);
+        assert_eq!(vec![0, 2, 1], indices.unique_families());
+    }
+
+    #[test]
+    fn single_family_falls_back_to_graphics() {
+        let families = [family(
+            QueueFlags::GRAPHICS | QueueFlags::COMPUTE | QueueFlags::TRANSFER,
+        )];
+        let indices = QueueFamilyIndices::discover(&families, |_| false, false).unwrap();
+
+        assert_eq!(None, indices.present);
+        assert_eq!(0, indices.transfer_or_graphics());
+        assert_eq!(0, indices.compute_or_graphics());
+        assert_eq!(vec![0], indices.unique_families());
+    }
+
+    #[test]
+    fn graphics_family_with_present_support_is_preferred() {
+        let families = [
+            family(QueueFlags::GRAPHICS),
+            family(QueueFlags::GRAPHICS | QueueFlags::COMPUTE),
+        ];
+        let indices = QueueFamilyIndices::discover(&families, |index| index == 1, true).unwrap();
+
+        assert_eq!(1, indices.graphics);
+        assert_eq!(Some(1), indices.present);
+    }
+
+    #[test]
+    fn missing_present_support_is_rejected_if_required() {
+        let families = [family(QueueFlags::GRAPHICS)];
+        assert!(QueueFamilyIndices::discover(&families, |_| false, true).is_none());
+    }
+}
@@ -0,0 +1,298 @@
+//! This is a module that contains the `QueueOwnershipTransfer` structure, which records the
+//! barriers that are required to transfer the ownership of `EXCLUSIVE` buffers and images between
+//! queue families.
+//!
+//! An ownership transfer consists of a release barrier, which is recorded into a command buffer
+//! submitted to the source queue, and a matching acquire barrier, which is recorded into a command
+//! buffer submitted to the destination queue. The acquire submission has to wait for the release
+//! submission, e.g. using a semaphore.
+//!
+//! If both queue families are the same, no ownership is transferred, but the barriers are still
+//! recorded with `QUEUE_FAMILY_IGNORED`: The release barrier performs the layout transition and
+//! makes the writes of the source access available to all later commands, and the acquire barrier
+//! makes them visible to the destination access (without transitioning the layout again).
+
+use ash::vk::{AccessFlags, Buffer, BufferMemoryBarrier, DependencyFlags, DeviceSize};
+use ash::vk::{Image, ImageLayout, ImageMemoryBarrier, ImageSubresourceRange, PipelineStageFlags};
+use ash::vk::{QUEUE_FAMILY_IGNORED, WHOLE_SIZE};
+
+use super::command::command_buffer::SetUpCommandBufferWithFence;
+use super::logical_device::SetUpLogicalDevice;
+
+/// A range of a buffer whose ownership is transferred
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BufferOwnershipRange {
+    pub buffer: Buffer,
+    pub offset: DeviceSize,
+    pub size: DeviceSize,
+}
+
+impl BufferOwnershipRange {
+    pub fn new(buffer: Buffer, offset: DeviceSize, size: DeviceSize) -> Self {
+        Self {
+            buffer,
+            offset,
+            size,
+        }
+    }
+
+    /// This function returns the range that covers the whole `buffer`.
+    pub fn whole(buffer: Buffer) -> Self {
+        Self::new(buffer, 0, WHOLE_SIZE)
+    }
+}
+
+/// The subresources of an image whose ownership is transferred, with the layout transition that
+/// is performed by the transfer
+#[derive(Clone, Copy, Debug)]
+pub struct ImageOwnershipRange {
+    pub image: Image,
+    pub subresource_range: ImageSubresourceRange,
+    pub old_layout: ImageLayout,
+    pub new_layout: ImageLayout,
+}
+
+impl ImageOwnershipRange {
+    pub fn new(
+        image: Image,
+        subresource_range: ImageSubresourceRange,
+        old_layout: ImageLayout,
+        new_layout: ImageLayout,
+    ) -> Self {
+        Self {
+            image,
+            subresource_range,
+            old_layout,
+            new_layout,
+        }
+    }
+}
+
+/// An access of a queue to a resource, i.e. the access mask and the pipeline stage it happens in
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct QueueAccess {
+    pub access_mask: AccessFlags,
+    pub stage: PipelineStageFlags,
+}
+
+impl QueueAccess {
+    pub fn new(access_mask: AccessFlags, stage: PipelineStageFlags) -> Self {
+        Self { access_mask, stage }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct QueueOwnershipTransfer {
+    pub src_queue_family_index: u32,
+    pub dst_queue_family_index: u32,
+}
+
+impl QueueOwnershipTransfer {
+    pub fn new(src_queue_family_index: u32, dst_queue_family_index: u32) -> Self {
+        Self {
+            src_queue_family_index,
+            dst_queue_family_index,
+        }
+    }
+
+    /// This function returns whether the ownership has to be transferred, i.e. whether the source
+    /// and destination queue families are different.
+    pub fn is_required(&self) -> bool {
+        self.src_queue_family_index != self.dst_queue_family_index
+    }
+
+    /// This function returns the queue family indices of the barriers, which are ignored if no
+    /// ownership transfer is required.
+    fn barrier_queue_family_indices(&self) -> (u32, u32) {
+        if self.is_required() {
+            (self.src_queue_family_index, self.dst_queue_family_index)
+        } else {
+            (QUEUE_FAMILY_IGNORED, QUEUE_FAMILY_IGNORED)
+        }
+    }
+
+    /// This function returns the destination of a release barrier: Nothing if the ownership is
+    /// transferred (the acquire barrier defines it), otherwise all later commands.
+    fn release_destination(&self) -> QueueAccess {
+        if self.is_required() {
+            QueueAccess::new(AccessFlags::empty(), PipelineStageFlags::BOTTOM_OF_PIPE)
+        } else {
+            QueueAccess::new(
+                AccessFlags::MEMORY_READ | AccessFlags::MEMORY_WRITE,
+                PipelineStageFlags::ALL_COMMANDS,
+            )
+        }
+    }
+
+    /// This function returns the source of an acquire barrier: Nothing if the ownership is
+    /// transferred (the release barrier defines it), otherwise all previous commands.
+    fn acquire_source(&self) -> QueueAccess {
+        if self.is_required() {
+            QueueAccess::new(AccessFlags::empty(), PipelineStageFlags::TOP_OF_PIPE)
+        } else {
+            QueueAccess::new(AccessFlags::MEMORY_WRITE, PipelineStageFlags::ALL_COMMANDS)
+        }
+    }
+
+    fn buffer_barrier(
+        &self,
+        range: BufferOwnershipRange,
+        src_access: QueueAccess,
+        dst_access: QueueAccess,
+    ) -> BufferMemoryBarrier {
+        let (src_queue_family_index, dst_queue_family_index) = self.barrier_queue_family_indices();
+        *BufferMemoryBarrier::builder()
+            .buffer(range.buffer)
+            .offset(range.offset)
+            .size(range.size)
+            .src_access_mask(src_access.access_mask)
+            .dst_access_mask(dst_access.access_mask)
+            .src_queue_family_index(src_queue_family_index)
+            .dst_queue_family_index(dst_queue_family_index)
+    }
+
+    fn image_barrier(
+        &self,
+        range: ImageOwnershipRange,
+        src_access: QueueAccess,
+        dst_access: QueueAccess,
+    ) -> ImageMemoryBarrier {
+        let (src_queue_family_index, dst_queue_family_index) = self.barrier_queue_family_indices();
+        *ImageMemoryBarrier::builder()
+            .image(range.image)
+            .subresource_range(range.subresource_range)
+            .old_layout(range.old_layout)
+            .new_layout(range.new_layout)
+            .src_access_mask(src_access.access_mask)
+            .dst_access_mask(dst_access.access_mask)
+            .src_queue_family_index(src_queue_family_index)
+            .dst_queue_family_index(dst_queue_family_index)
+    }
+
+    unsafe fn record_barriers(
+        logical_device: &SetUpLogicalDevice,
+        command_buffer: &SetUpCommandBufferWithFence,
+        src_access: QueueAccess,
+        dst_access: QueueAccess,
+        buffer_barriers: &[BufferMemoryBarrier],
+        image_barriers: &[ImageMemoryBarrier],
+    ) {
+        logical_device.cmd_pipeline_barrier(
+            command_buffer.command_buffer,
+            src_access.stage,
+            dst_access.stage,
+            DependencyFlags::empty(),
+            &[],
+            buffer_barriers,
+            image_barriers,
+        );
+    }
+
+    /// This function records the release barrier for the given range of a buffer. The
+    /// `command_buffer` has to be submitted to a queue of the source family.
+    ///
+    /// `src_access` describes the last access of the source queue.
+    pub fn record_buffer_release(
+        &self,
+        logical_device: &SetUpLogicalDevice,
+        command_buffer: &SetUpCommandBufferWithFence,
+        range: BufferOwnershipRange,
+        src_access: QueueAccess,
+    ) {
+        let dst_access = self.release_destination();
+        let barrier = self.buffer_barrier(range, src_access, dst_access);
+        unsafe {
+            Self::record_barriers(
+                logical_device,
+                command_buffer,
+                src_access,
+                dst_access,
+                &[barrier],
+                &[],
+            );
+        }
+    }
+
+    /// This function records the acquire barrier for the given range of a buffer. The
+    /// `command_buffer` has to be submitted to a queue of the destination family.
+    ///
+    /// `dst_access` describes the first access of the destination queue.
+    pub fn record_buffer_acquire(
+        &self,
+        logical_device: &SetUpLogicalDevice,
+        command_buffer: &SetUpCommandBufferWithFence,
+        range: BufferOwnershipRange,
+        dst_access: QueueAccess,
+    ) {
+        let src_access = self.acquire_source();
+        let barrier = self.buffer_barrier(range, src_access, dst_access);
+        unsafe {
+            Self::record_barriers(
+                logical_device,
+                command_buffer,
+                src_access,
+                dst_access,
+                &[barrier],
+                &[],
+            );
+        }
+    }
+
+    /// This function records the release barrier for an image. The `command_buffer` has to be
+    /// submitted to a queue of the source family.
+    ///
+    /// The `range` (including the layout transition) has to be specified identically for the
+    /// release and the acquire barrier.
+    pub fn record_image_release(
+        &self,
+        logical_device: &SetUpLogicalDevice,
+        command_buffer: &SetUpCommandBufferWithFence,
+        range: ImageOwnershipRange,
+        src_access: QueueAccess,
+    ) {
+        let dst_access = self.release_destination();
+        let barrier = self.image_barrier(range, src_access, dst_access);
+        unsafe {
+            Self::record_barriers(
+                logical_device,
+                command_buffer,
+                src_access,
+                dst_access,
+                &[],
+                &[barrier],
+            );
+        }
+    }
+
+    /// This function records the acquire barrier for an image. The `command_buffer` has to be
+    /// submitted to a queue of the destination family.
+    pub fn record_image_acquire(
+        &self,
+        logical_device: &SetUpLogicalDevice,
+        command_buffer: &SetUpCommandBufferWithFence,
+        range: ImageOwnershipRange,
+        dst_access: QueueAccess,
+    ) {
+        // Without an ownership transfer, the release barrier already transitioned the layout
+        let src_access = self.acquire_source();
+        let range = if self.is_required() {
+            range
+        } else {
+            ImageOwnershipRange {
+                old_layout: range.new_layout,
+                ..range
+            }
+        };
+        let barrier = self.image_barrier(range, src_access, dst_access);
+        unsafe {
+            Self::record_barriers(
+                logical_device,
+                command_buffer,
+                src_access,
+                dst_access,
+                &[],
+                &[barrier],
+            );
+        }
+    }
+}
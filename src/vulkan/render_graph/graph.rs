@@ -0,0 +1,556 @@
+//! This is a module that contains the `RenderGraph` structure, which owns the declared resources
+//! and passes, the physical transient images, and the render passes and framebuffers of graphics
+//! passes.
+
+use std::collections::HashMap;
+use std::sync::MutexGuard;
+
+use ash::vk::{AttachmentDescription, AttachmentReference, AttachmentStoreOp, ClearValue};
+use ash::vk::{AttachmentLoadOp, SubpassDescription};
+use ash::vk::{DependencyFlags, ImageMemoryBarrier, ImageSubresourceRange, PipelineStageFlags};
+use ash::vk::{Extent2D, Extent3D, Image, ImageTiling, ImageType, ImageView, ImageViewType};
+use ash::vk::{Framebuffer, FramebufferCreateInfo, ImageAspectFlags, SharingMode};
+use ash::vk::{PipelineBindPoint, Rect2D, RenderPassBeginInfo, SubpassContents};
+use ash::vk::{REMAINING_ARRAY_LAYERS, REMAINING_MIP_LEVELS};
+use gpu_allocator::vulkan::Allocator;
+
+use crate::result::Context;
+use crate::vulkan::command::command_buffer::SetUpCommandBufferWithFence;
+use crate::vulkan::depth_image::format_aspect_mask;
+use crate::vulkan::image::image::AllocatedImage;
+use crate::vulkan::image::image_view::SetUpImageView;
+use crate::vulkan::logical_device::SetUpLogicalDevice;
+use crate::vulkan::render_pass::SetUpRenderPass;
+
+use super::pass::{BoundImage, PassContext, RenderGraphPass};
+use super::resource::{ImportedImageDescription, ResourceDescription};
+use super::resource::{ResourceId, TransientImageDescription};
+use super::schedule::{ImageBarrierInfo, Schedule, ScheduledPass};
+
+struct PhysicalImage {
+    image: AllocatedImage,
+    image_view: SetUpImageView,
+    extent: Extent2D,
+}
+
+/// A graph of passes that declare which images they read and write.
+///
+/// After all resources and passes were added, the graph has to be compiled using
+/// `RenderGraph::compile`. Compiling culls unused passes, assigns transient images to (possibly
+/// shared) physical images, and creates the render passes of graphics passes. When the swapchain
+/// is resized, `RenderGraph::resize` recreates all size-dependent images and framebuffers.
+///
+/// Imported images (e.g. the present image) have to be bound using
+/// `RenderGraph::bind_imported_image` before every execution.
+#[derive(Default)]
+pub struct RenderGraph {
+    resource_names: Vec<String>,
+    descriptions: Vec<ResourceDescription>,
+    passes: Vec<RenderGraphPass>,
+    imported_images: HashMap<ResourceId, BoundImage>,
+
+    schedule: Option<Schedule>,
+    swapchain_extent: Extent2D,
+    render_passes: HashMap<usize, SetUpRenderPass>,
+    physical_images: Vec<PhysicalImage>,
+    framebuffers: HashMap<(usize, Vec<ImageView>), Framebuffer>,
+}
+
+impl RenderGraph {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn add_resource(&mut self, name: &str, description: ResourceDescription) -> ResourceId {
+        self.resource_names.push(name.to_string());
+        self.descriptions.push(description);
+        ResourceId(self.descriptions.len() - 1)
+    }
+
+    /// This function declares an image that is allocated and owned by the graph.
+    pub fn create_transient_image(
+        &mut self,
+        name: &str,
+        description: TransientImageDescription,
+    ) -> ResourceId {
+        self.add_resource(name, ResourceDescription::Transient(description))
+    }
+
+    /// This function declares an image that is owned outside of the graph.
+    pub fn import_image(
+        &mut self,
+        name: &str,
+        description: ImportedImageDescription,
+    ) -> ResourceId {
+        self.add_resource(name, ResourceDescription::Imported(description))
+    }
+
+    pub fn add_pass(&mut self, pass: RenderGraphPass) {
+        self.passes.push(pass);
+    }
+
+    pub fn resource_name(&self, resource: ResourceId) -> Option<&str> {
+        self.resource_names.get(resource.0).map(String::as_str)
+    }
+
+    /// This function binds the physical image of an imported resource for the next executions.
+    pub fn bind_imported_image(
+        &mut self,
+        resource: ResourceId,
+        image: Image,
+        image_view: ImageView,
+        extent: Extent2D,
+    ) -> crate::Result<()> {
+        let description = self
+            .descriptions
+            .get(resource.0)
+            .context(format!("The resource {:?} doesn't exist", resource))?;
+        crate::verify!(
+            description.is_imported(),
+            "The resource '{}' is not an imported image",
+            (self.resource_names[resource.0])
+        );
+
+        self.imported_images.insert(
+            resource,
+            BoundImage {
+                image,
+                image_view,
+                extent,
+            },
+        );
+        Ok(())
+    }
+
+    fn schedule(&self) -> crate::Result<&Schedule> {
+        self.schedule.as_ref().context(
+            "The render graph was not compiled yet! Use RenderGraph::compile to do so".to_string(),
+        )
+    }
+
+    /// This function returns the passes in the order they are executed, without culled passes.
+    pub fn scheduled_pass_names(&self) -> crate::Result<Vec<&str>> {
+        Ok(self
+            .schedule()?
+            .passes
+            .iter()
+            .map(|scheduled_pass| self.passes[scheduled_pass.pass_index].name())
+            .collect())
+    }
+
+    /// This function returns the render pass that was created for the graphics pass with the
+    /// given name, e.g. for creating the pipelines that are used in that pass.
+    pub fn render_pass(&self, pass_name: &str) -> crate::Result<&SetUpRenderPass> {
+        let pass_index = self
+            .passes
+            .iter()
+            .position(|pass| pass.name() == pass_name)
+            .context(format!(
+                "The render graph has no pass named '{}'",
+                pass_name
+            ))?;
+        self.render_passes.get(&pass_index).context(format!(
+            "The pass '{}' is not a scheduled graphics pass",
+            pass_name
+        ))
+    }
+
+    /// This function schedules the passes, creates the render passes of all graphics passes and
+    /// allocates the transient images for the given swapchain extent.
+    pub fn compile(
+        &mut self,
+        logical_device: &SetUpLogicalDevice,
+        allocator: &mut MutexGuard<Allocator>,
+        swapchain_extent: Extent2D,
+    ) -> crate::Result<()> {
+        self.destroy(logical_device, allocator)?;
+
+        let schedule = super::schedule::schedule(&self.descriptions, &self.passes)?;
+        for scheduled_pass in schedule.passes.iter() {
+            if !scheduled_pass.attachments.is_empty() {
+                let render_pass = self.create_render_pass(logical_device, scheduled_pass)?;
+                self.render_passes
+                    .insert(scheduled_pass.pass_index, render_pass);
+            }
+        }
+        self.schedule = Some(schedule);
+
+        self.allocate_physical_images(logical_device, allocator, swapchain_extent)
+    }
+
+    /// This function recreates all transient images and framebuffers for the new swapchain
+    /// extent. The render passes stay valid, so pipelines don't have to be recreated.
+    pub fn resize(
+        &mut self,
+        logical_device: &SetUpLogicalDevice,
+        allocator: &mut MutexGuard<Allocator>,
+        swapchain_extent: Extent2D,
+    ) -> crate::Result<()> {
+        self.destroy_size_dependents(logical_device, allocator)?;
+        self.allocate_physical_images(logical_device, allocator, swapchain_extent)
+    }
+
+    fn create_render_pass(
+        &self,
+        logical_device: &SetUpLogicalDevice,
+        scheduled_pass: &ScheduledPass,
+    ) -> crate::Result<SetUpRenderPass> {
+        let mut attachment_descriptions = vec![];
+        let mut color_attachment_refs = vec![];
+        let mut depth_attachment_ref = None;
+        for (index, attachment) in scheduled_pass.attachments.iter().enumerate() {
+            let description = &self.descriptions[attachment.resource.0];
+            let layout = attachment.access.layout();
+            let has_stencil =
+                format_aspect_mask(description.format()).contains(ImageAspectFlags::STENCIL);
+
+            // The graph transitions the attachments before the render pass begins, so the layout
+            // doesn't change during the render pass
+            attachment_descriptions.push(
+                AttachmentDescription::builder()
+                    .format(description.format())
+                    .samples(description.samples())
+                    .load_op(attachment.load_op)
+                    .store_op(attachment.store_op)
+                    .stencil_load_op(if has_stencil {
+                        attachment.load_op
+                    } else {
+                        AttachmentLoadOp::DONT_CARE
+                    })
+                    .stencil_store_op(if has_stencil {
+                        attachment.store_op
+                    } else {
+                        AttachmentStoreOp::DONT_CARE
+                    })
+                    .initial_layout(layout)
+                    .final_layout(layout)
+                    .build(),
+            );
+
+            let reference = AttachmentReference {
+                attachment: index as u32,
+                layout,
+            };
+            if attachment.access.is_color_attachment() {
+                color_attachment_refs.push(reference);
+            } else {
+                depth_attachment_ref = Some(reference);
+            }
+        }
+
+        let mut subpass = SubpassDescription::builder()
+            .color_attachments(&color_attachment_refs)
+            .pipeline_bind_point(PipelineBindPoint::GRAPHICS);
+        if let Some(depth_attachment_ref) = depth_attachment_ref.as_ref() {
+            subpass = subpass.depth_stencil_attachment(depth_attachment_ref);
+        }
+
+        unsafe {
+            SetUpRenderPass::create_with_renderpass_attachments_and_subpasses(
+                logical_device,
+                &attachment_descriptions,
+                std::slice::from_ref(&subpass),
+                &[],
+            )
+        }
+    }
+
+    fn allocate_physical_images(
+        &mut self,
+        logical_device: &SetUpLogicalDevice,
+        allocator: &mut MutexGuard<Allocator>,
+        swapchain_extent: Extent2D,
+    ) -> crate::Result<()> {
+        self.swapchain_extent = swapchain_extent;
+
+        let slots = self.schedule()?.physical_slots.clone();
+        for slot in slots {
+            let description = slot.description;
+            let extent = description.size.resolve(swapchain_extent);
+
+            let mut view_aspect_mask = format_aspect_mask(description.format);
+            if view_aspect_mask.contains(ImageAspectFlags::DEPTH) {
+                // Sampled depth-stencil images can only be accessed through a single aspect
+                view_aspect_mask = ImageAspectFlags::DEPTH;
+            }
+
+            unsafe {
+                let image = AllocatedImage::allocate(
+                    logical_device,
+                    allocator,
+                    ImageType::TYPE_2D,
+                    description.format,
+                    Extent3D {
+                        width: extent.width,
+                        height: extent.height,
+                        depth: 1,
+                    },
+                    1,
+                    1,
+                    description.samples,
+                    ImageTiling::OPTIMAL,
+                    slot.usage,
+                    SharingMode::EXCLUSIVE,
+                )?;
+                let image_view = SetUpImageView::create(
+                    logical_device,
+                    ImageViewType::TYPE_2D,
+                    description.format,
+                    ImageSubresourceRange::builder()
+                        .aspect_mask(view_aspect_mask)
+                        .base_mip_level(0)
+                        .level_count(1)
+                        .base_array_layer(0)
+                        .layer_count(1)
+                        .build(),
+                    image.image,
+                )?;
+
+                self.physical_images.push(PhysicalImage {
+                    image,
+                    image_view,
+                    extent,
+                });
+            }
+        }
+
+        Ok(())
+    }
+
+    fn bound_images(&self) -> crate::Result<HashMap<ResourceId, BoundImage>> {
+        let schedule = self.schedule()?;
+        let mut bound_images = self.imported_images.clone();
+        for (index, slot) in schedule.resource_slots.iter().enumerate() {
+            if let Some(slot) = slot {
+                let physical_image = &self.physical_images[*slot];
+                bound_images.insert(
+                    ResourceId(index),
+                    BoundImage {
+                        image: physical_image.image.image,
+                        image_view: physical_image.image_view.image_view,
+                        extent: physical_image.extent,
+                    },
+                );
+            }
+        }
+        Ok(bound_images)
+    }
+
+    fn record_barriers(
+        &self,
+        logical_device: &SetUpLogicalDevice,
+        command_buffer: &SetUpCommandBufferWithFence,
+        bound_images: &HashMap<ResourceId, BoundImage>,
+        barriers: &[ImageBarrierInfo],
+    ) -> crate::Result<()> {
+        if barriers.is_empty() {
+            return Ok(());
+        }
+
+        let mut src_stage = PipelineStageFlags::empty();
+        let mut dst_stage = PipelineStageFlags::empty();
+        let mut image_barriers = Vec::with_capacity(barriers.len());
+        for barrier in barriers.iter() {
+            let bound_image = bound_images.get(&barrier.resource).context(format!(
+                "No image is bound to the resource '{}'",
+                self.resource_names[barrier.resource.0]
+            ))?;
+            let format = self.descriptions[barrier.resource.0].format();
+
+            src_stage |= barrier.src_stage;
+            dst_stage |= barrier.dst_stage;
+            image_barriers.push(
+                ImageMemoryBarrier::builder()
+                    .image(bound_image.image)
+                    .old_layout(barrier.old_layout)
+                    .new_layout(barrier.new_layout)
+                    .src_access_mask(barrier.src_access)
+                    .dst_access_mask(barrier.dst_access)
+                    .subresource_range(
+                        ImageSubresourceRange::builder()
+                            .aspect_mask(format_aspect_mask(format))
+                            .base_mip_level(0)
+                            .level_count(REMAINING_MIP_LEVELS)
+                            .base_array_layer(0)
+                            .layer_count(REMAINING_ARRAY_LAYERS)
+                            .build(),
+                    )
+                    .build(),
+            );
+        }
+
+        unsafe {
+            logical_device.cmd_pipeline_barrier(
+                command_buffer.command_buffer,
+                src_stage,
+                dst_stage,
+                DependencyFlags::empty(),
+                &[],
+                &[],
+                &image_barriers,
+            );
+        }
+        Ok(())
+    }
+
+    fn get_or_create_framebuffer(
+        &mut self,
+        logical_device: &SetUpLogicalDevice,
+        scheduled_pass: &ScheduledPass,
+        bound_images: &HashMap<ResourceId, BoundImage>,
+    ) -> crate::Result<(Framebuffer, Extent2D)> {
+        let mut attachment_views = vec![];
+        let mut extent = None;
+        for attachment in scheduled_pass.attachments.iter() {
+            let bound_image = bound_images.get(&attachment.resource).context(format!(
+                "No image is bound to the attachment '{}'",
+                self.resource_names[attachment.resource.0]
+            ))?;
+            let attachment_extent = *extent.get_or_insert(bound_image.extent);
+            crate::verify!(
+                attachment_extent == bound_image.extent,
+                "The attachments of pass '{}' have different sizes",
+                (self.passes[scheduled_pass.pass_index].name)
+            );
+            attachment_views.push(bound_image.image_view);
+        }
+        let extent = extent.context("Graphics passes need at least one attachment".to_string())?;
+
+        let key = (scheduled_pass.pass_index, attachment_views);
+        if let Some(framebuffer) = self.framebuffers.get(&key) {
+            return Ok((*framebuffer, extent));
+        }
+
+        let render_pass = &self.render_passes[&scheduled_pass.pass_index];
+        let framebuffer_create_info = FramebufferCreateInfo::builder()
+            .render_pass(render_pass.render_pass)
+            .attachments(&key.1)
+            .width(extent.width)
+            .height(extent.height)
+            .layers(1);
+        let framebuffer =
+            unsafe { logical_device.create_framebuffer(&framebuffer_create_info, None)? };
+        self.framebuffers.insert(key, framebuffer);
+        Ok((framebuffer, extent))
+    }
+
+    /// This function records all scheduled passes, including the barriers between them, into
+    /// the `command_buffer`.
+    pub fn execute(
+        &mut self,
+        logical_device: &SetUpLogicalDevice,
+        command_buffer: &SetUpCommandBufferWithFence,
+    ) -> crate::Result<()> {
+        let bound_images = self.bound_images()?;
+        let scheduled_passes = self.schedule()?.passes.clone();
+
+        for scheduled_pass in scheduled_passes.iter() {
+            self.record_barriers(
+                logical_device,
+                command_buffer,
+                &bound_images,
+                &scheduled_pass.barriers,
+            )?;
+
+            let pass_index = scheduled_pass.pass_index;
+            if scheduled_pass.attachments.is_empty() {
+                let context = PassContext {
+                    bound_images: &bound_images,
+                    extent: self.swapchain_extent,
+                    render_pass: None,
+                };
+                if let Some(record) = self.passes[pass_index].record.as_ref() {
+                    record(logical_device, command_buffer, &context)?;
+                }
+                continue;
+            }
+
+            let (framebuffer, extent) =
+                self.get_or_create_framebuffer(logical_device, scheduled_pass, &bound_images)?;
+            let pass = &self.passes[pass_index];
+            let render_pass = self.render_passes[&pass_index].render_pass;
+            let clear_values = scheduled_pass
+                .attachments
+                .iter()
+                .map(|attachment| {
+                    pass.clear_values
+                        .get(&attachment.resource)
+                        .copied()
+                        .unwrap_or_default()
+                })
+                .collect::<Vec<ClearValue>>();
+
+            let render_pass_begin_info = RenderPassBeginInfo::builder()
+                .render_pass(render_pass)
+                .framebuffer(framebuffer)
+                .render_area(Rect2D {
+                    offset: Default::default(),
+                    extent,
+                })
+                .clear_values(&clear_values);
+
+            let context = PassContext {
+                bound_images: &bound_images,
+                extent,
+                render_pass: Some(render_pass),
+            };
+            unsafe {
+                logical_device.cmd_begin_render_pass(
+                    command_buffer.command_buffer,
+                    &render_pass_begin_info,
+                    SubpassContents::INLINE,
+                );
+                let result = match pass.record.as_ref() {
+                    Some(record) => record(logical_device, command_buffer, &context),
+                    None => Ok(()),
+                };
+                logical_device.cmd_end_render_pass(command_buffer.command_buffer);
+                result?;
+            }
+        }
+
+        let final_barriers = &self.schedule()?.final_barriers;
+        self.record_barriers(
+            logical_device,
+            command_buffer,
+            &bound_images,
+            final_barriers,
+        )
+    }
+
+    fn destroy_size_dependents(
+        &mut self,
+        logical_device: &SetUpLogicalDevice,
+        allocator: &mut MutexGuard<Allocator>,
+    ) -> crate::Result<()> {
+        unsafe {
+            for (_, framebuffer) in self.framebuffers.drain() {
+                logical_device.destroy_framebuffer(framebuffer, None);
+            }
+        }
+
+        for mut physical_image in self.physical_images.drain(..) {
+            physical_image.image_view.destroy(logical_device);
+            physical_image.image.destroy(logical_device, allocator)?;
+        }
+
+        Ok(())
+    }
+
+    /// This function destroys all Vulkan objects that were created by the graph. The declared
+    /// resources and passes are kept, so the graph can be compiled again.
+    pub fn destroy(
+        &mut self,
+        logical_device: &SetUpLogicalDevice,
+        allocator: &mut MutexGuard<Allocator>,
+    ) -> crate::Result<()> {
+        self.destroy_size_dependents(logical_device, allocator)?;
+
+        for (_, mut render_pass) in self.render_passes.drain() {
+            render_pass.destroy(logical_device);
+        }
+        self.schedule = None;
+
+        Ok(())
+    }
+}
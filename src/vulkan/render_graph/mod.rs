@@ -0,0 +1,14 @@
+//! This is a module that provides a render graph, in which passes declare the images they read
+//! and write. The graph then schedules the passes, inserts the required pipeline barriers and
+//! layout transitions, allocates (and aliases) transient images and creates the render passes and
+//! framebuffers of graphics passes.
+//!
+//! - graph: Provides the `RenderGraph` structure that owns the resources and passes
+//! - pass: Provides an abstraction for declaring passes and the context passed to their recording functions
+//! - resource: Provides the resource descriptions and the ways in which passes can access resources
+//! - schedule: Provides the scheduling logic (culling, aliasing and barrier generation)
+
+pub mod graph;
+pub mod pass;
+pub mod resource;
+pub mod schedule;
@@ -0,0 +1,183 @@
+//! This is a module that contains the `RenderGraphPass` builder, which declares the resources a
+//! pass accesses and the commands it records, as well as the `PassContext` that is passed to the
+//! recording function.
+
+use std::collections::HashMap;
+
+use ash::vk::{ClearColorValue, ClearDepthStencilValue, ClearValue};
+use ash::vk::{Extent2D, Image, ImageView, RenderPass};
+
+use crate::result::Context;
+use crate::vulkan::command::command_buffer::SetUpCommandBufferWithFence;
+use crate::vulkan::logical_device::SetUpLogicalDevice;
+
+use super::resource::{ResourceAccess, ResourceId};
+
+pub type PassRecordFn = Box<
+    dyn Fn(&SetUpLogicalDevice, &SetUpCommandBufferWithFence, &PassContext) -> crate::Result<()>,
+>;
+
+/// A pass of a `RenderGraph`
+///
+/// Passes that write color or depth attachments are graphics passes: The graph creates a render
+/// pass and framebuffers for their attachments and begins the render pass before calling the
+/// recording function. All other passes (compute, transfer) record their commands directly.
+pub struct RenderGraphPass {
+    pub(crate) name: String,
+    pub(crate) accesses: Vec<(ResourceId, ResourceAccess)>,
+    pub(crate) clear_values: HashMap<ResourceId, ClearValue>,
+    pub(crate) has_side_effects: bool,
+    pub(crate) record: Option<PassRecordFn>,
+}
+
+impl RenderGraphPass {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            accesses: vec![],
+            clear_values: HashMap::new(),
+            has_side_effects: false,
+            record: None,
+        }
+    }
+
+    /// This function declares that the pass accesses the `resource` in the given way.
+    pub fn access(mut self, resource: ResourceId, access: ResourceAccess) -> Self {
+        self.accesses.push((resource, access));
+        self
+    }
+
+    /// This function declares a color attachment whose previous contents are preserved.
+    pub fn color_attachment(self, resource: ResourceId) -> Self {
+        self.access(resource, ResourceAccess::ColorAttachmentWrite)
+    }
+
+    /// This function declares a color attachment that is cleared at the start of the pass.
+    pub fn color_attachment_with_clear(
+        mut self,
+        resource: ResourceId,
+        clear: ClearColorValue,
+    ) -> Self {
+        self.clear_values
+            .insert(resource, ClearValue { color: clear });
+        self.color_attachment(resource)
+    }
+
+    /// This function declares a depth attachment whose previous contents are preserved.
+    pub fn depth_attachment(self, resource: ResourceId) -> Self {
+        self.access(resource, ResourceAccess::DepthStencilAttachmentWrite)
+    }
+
+    /// This function declares a depth attachment that is cleared at the start of the pass.
+    pub fn depth_attachment_with_clear(mut self, resource: ResourceId, depth: f32) -> Self {
+        let depth_stencil = ClearDepthStencilValue { depth, stencil: 0 };
+        self.clear_values
+            .insert(resource, ClearValue { depth_stencil });
+        self.depth_attachment(resource)
+    }
+
+    /// This function declares a depth attachment that is only used for depth testing.
+    pub fn read_only_depth_attachment(self, resource: ResourceId) -> Self {
+        self.access(resource, ResourceAccess::DepthStencilAttachmentRead)
+    }
+
+    /// This function declares that the `resource` is sampled in a fragment shader.
+    pub fn sampled_in_fragment_shader(self, resource: ResourceId) -> Self {
+        self.access(resource, ResourceAccess::FragmentShaderSampled)
+    }
+
+    /// This function declares that the `resource` is sampled in a compute shader.
+    pub fn sampled_in_compute_shader(self, resource: ResourceId) -> Self {
+        self.access(resource, ResourceAccess::ComputeShaderSampled)
+    }
+
+    pub fn storage_image_read(self, resource: ResourceId) -> Self {
+        self.access(resource, ResourceAccess::ComputeStorageRead)
+    }
+
+    pub fn storage_image_write(self, resource: ResourceId) -> Self {
+        self.access(resource, ResourceAccess::ComputeStorageWrite)
+    }
+
+    pub fn transfer_source(self, resource: ResourceId) -> Self {
+        self.access(resource, ResourceAccess::TransferSource)
+    }
+
+    pub fn transfer_destination(self, resource: ResourceId) -> Self {
+        self.access(resource, ResourceAccess::TransferDestination)
+    }
+
+    /// This function marks the pass as having side effects outside of the graph (e.g. writing a
+    /// buffer that is read on the CPU), so that it is never culled.
+    pub fn has_side_effects(mut self) -> Self {
+        self.has_side_effects = true;
+        self
+    }
+
+    pub fn record<F>(mut self, record: F) -> Self
+    where
+        F: Fn(&SetUpLogicalDevice, &SetUpCommandBufferWithFence, &PassContext) -> crate::Result<()>
+            + 'static,
+    {
+        self.record = Some(Box::new(record));
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn is_graphics_pass(&self) -> bool {
+        self.accesses
+            .iter()
+            .any(|(_, access)| access.is_attachment())
+    }
+
+    /// This function returns whether the pass overwrites the whole `resource` without reading
+    /// its previous contents.
+    pub(crate) fn overwrites(&self, resource: ResourceId) -> bool {
+        self.clear_values.contains_key(&resource)
+    }
+}
+
+/// The physical image that is bound to a resource during the execution of a pass
+#[derive(Clone, Copy, Debug)]
+pub struct BoundImage {
+    pub image: Image,
+    pub image_view: ImageView,
+    pub extent: Extent2D,
+}
+
+/// The information that is available to the recording function of a pass
+pub struct PassContext<'graph> {
+    pub(crate) bound_images: &'graph HashMap<ResourceId, BoundImage>,
+    pub(crate) extent: Extent2D,
+    pub(crate) render_pass: Option<RenderPass>,
+}
+
+impl<'graph> PassContext<'graph> {
+    fn bound_image(&self, resource: ResourceId) -> crate::Result<&BoundImage> {
+        self.bound_images
+            .get(&resource)
+            .context(format!("No image is bound to resource {:?}", resource))
+    }
+
+    pub fn image(&self, resource: ResourceId) -> crate::Result<Image> {
+        Ok(self.bound_image(resource)?.image)
+    }
+
+    pub fn image_view(&self, resource: ResourceId) -> crate::Result<ImageView> {
+        Ok(self.bound_image(resource)?.image_view)
+    }
+
+    /// This function returns the render area of a graphics pass, or the swapchain extent for
+    /// other passes.
+    pub fn extent(&self) -> Extent2D {
+        self.extent
+    }
+
+    /// This function returns the render pass that was created for a graphics pass.
+    pub fn render_pass(&self) -> Option<RenderPass> {
+        self.render_pass
+    }
+}
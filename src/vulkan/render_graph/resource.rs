@@ -0,0 +1,231 @@
+//! This is a module that contains the resource types of the render graph: ids, descriptions of
+//! transient images, imported images and the ways in which passes access resources.
+
+use ash::vk::{AccessFlags, Extent2D, Format, ImageLayout, ImageUsageFlags};
+use ash::vk::{PipelineStageFlags, SampleCountFlags};
+
+/// An identifier for a resource of a `RenderGraph`
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ResourceId(pub(crate) usize);
+
+impl ResourceId {
+    pub fn index(&self) -> usize {
+        self.0
+    }
+}
+
+/// The size of a transient image
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ResourceSize {
+    /// The size is the swapchain extent multiplied by the factor (e.g. `0.5` for half resolution)
+    SwapchainRelative(f32),
+    /// The size is fixed and doesn't change when the swapchain is resized
+    Absolute(Extent2D),
+}
+
+impl ResourceSize {
+    pub fn resolve(&self, swapchain_extent: Extent2D) -> Extent2D {
+        match self {
+            Self::SwapchainRelative(factor) => Extent2D {
+                width: ((swapchain_extent.width as f32 * factor) as u32).max(1),
+                height: ((swapchain_extent.height as f32 * factor) as u32).max(1),
+            },
+            Self::Absolute(extent) => *extent,
+        }
+    }
+
+    pub fn is_swapchain_relative(&self) -> bool {
+        matches!(self, Self::SwapchainRelative(_))
+    }
+}
+
+/// The description of a transient image, which is allocated and owned by the render graph
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TransientImageDescription {
+    pub format: Format,
+    pub size: ResourceSize,
+    pub samples: SampleCountFlags,
+}
+
+impl TransientImageDescription {
+    /// This function creates the description of an image with the size of the swapchain.
+    pub fn new(format: Format) -> Self {
+        Self {
+            format,
+            size: ResourceSize::SwapchainRelative(1.0),
+            samples: SampleCountFlags::TYPE_1,
+        }
+    }
+
+    pub fn size(mut self, size: ResourceSize) -> Self {
+        self.size = size;
+        self
+    }
+
+    pub fn samples(mut self, samples: SampleCountFlags) -> Self {
+        self.samples = samples;
+        self
+    }
+
+    /// This function returns whether an image with this description can be reused for an image
+    /// with the `other` description.
+    pub fn is_compatible_with(&self, other: &Self) -> bool {
+        self.format == other.format && self.size == other.size && self.samples == other.samples
+    }
+}
+
+/// The description of an image that is owned outside of the render graph, e.g. a present image
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ImportedImageDescription {
+    pub format: Format,
+    /// The layout the image is in before the graph is executed
+    pub initial_layout: ImageLayout,
+    /// The layout the image is transitioned to after the graph was executed
+    pub final_layout: ImageLayout,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ResourceDescription {
+    Transient(TransientImageDescription),
+    Imported(ImportedImageDescription),
+}
+
+impl ResourceDescription {
+    pub fn format(&self) -> Format {
+        match self {
+            Self::Transient(description) => description.format,
+            Self::Imported(description) => description.format,
+        }
+    }
+
+    pub fn samples(&self) -> SampleCountFlags {
+        match self {
+            Self::Transient(description) => description.samples,
+            Self::Imported(_) => SampleCountFlags::TYPE_1,
+        }
+    }
+
+    pub fn is_imported(&self) -> bool {
+        matches!(self, Self::Imported(_))
+    }
+}
+
+/// The ways in which a pass can access an image
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ResourceAccess {
+    ColorAttachmentWrite,
+    DepthStencilAttachmentWrite,
+    DepthStencilAttachmentRead,
+    /// The image is sampled in a fragment shader
+    FragmentShaderSampled,
+    /// The image is sampled in a compute shader
+    ComputeShaderSampled,
+    /// The image is read as a storage image in a compute shader
+    ComputeStorageRead,
+    /// The image is written as a storage image in a compute shader
+    ComputeStorageWrite,
+    TransferSource,
+    TransferDestination,
+}
+
+impl ResourceAccess {
+    pub fn stage(&self) -> PipelineStageFlags {
+        match self {
+            Self::ColorAttachmentWrite => PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
+            Self::DepthStencilAttachmentWrite | Self::DepthStencilAttachmentRead => {
+                PipelineStageFlags::EARLY_FRAGMENT_TESTS | PipelineStageFlags::LATE_FRAGMENT_TESTS
+            }
+            Self::FragmentShaderSampled => PipelineStageFlags::FRAGMENT_SHADER,
+            Self::ComputeShaderSampled | Self::ComputeStorageRead | Self::ComputeStorageWrite => {
+                PipelineStageFlags::COMPUTE_SHADER
+            }
+            Self::TransferSource | Self::TransferDestination => PipelineStageFlags::TRANSFER,
+        }
+    }
+
+    pub fn access_mask(&self) -> AccessFlags {
+        match self {
+            Self::ColorAttachmentWrite => {
+                AccessFlags::COLOR_ATTACHMENT_READ | AccessFlags::COLOR_ATTACHMENT_WRITE
+            }
+            Self::DepthStencilAttachmentWrite => {
+                AccessFlags::DEPTH_STENCIL_ATTACHMENT_READ
+                    | AccessFlags::DEPTH_STENCIL_ATTACHMENT_WRITE
+            }
+            Self::DepthStencilAttachmentRead => AccessFlags::DEPTH_STENCIL_ATTACHMENT_READ,
+            Self::FragmentShaderSampled | Self::ComputeShaderSampled | Self::ComputeStorageRead => {
+                AccessFlags::SHADER_READ
+            }
+            Self::ComputeStorageWrite => AccessFlags::SHADER_READ | AccessFlags::SHADER_WRITE,
+            Self::TransferSource => AccessFlags::TRANSFER_READ,
+            Self::TransferDestination => AccessFlags::TRANSFER_WRITE,
+        }
+    }
+
+    pub fn layout(&self) -> ImageLayout {
+        match self {
+            Self::ColorAttachmentWrite => ImageLayout::COLOR_ATTACHMENT_OPTIMAL,
+            Self::DepthStencilAttachmentWrite => ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL,
+            Self::DepthStencilAttachmentRead => ImageLayout::DEPTH_STENCIL_READ_ONLY_OPTIMAL,
+            Self::FragmentShaderSampled | Self::ComputeShaderSampled => {
+                ImageLayout::SHADER_READ_ONLY_OPTIMAL
+            }
+            Self::ComputeStorageRead | Self::ComputeStorageWrite => ImageLayout::GENERAL,
+            Self::TransferSource => ImageLayout::TRANSFER_SRC_OPTIMAL,
+            Self::TransferDestination => ImageLayout::TRANSFER_DST_OPTIMAL,
+        }
+    }
+
+    pub fn image_usage(&self) -> ImageUsageFlags {
+        match self {
+            Self::ColorAttachmentWrite => ImageUsageFlags::COLOR_ATTACHMENT,
+            Self::DepthStencilAttachmentWrite | Self::DepthStencilAttachmentRead => {
+                ImageUsageFlags::DEPTH_STENCIL_ATTACHMENT
+            }
+            Self::FragmentShaderSampled | Self::ComputeShaderSampled => ImageUsageFlags::SAMPLED,
+            Self::ComputeStorageRead | Self::ComputeStorageWrite => ImageUsageFlags::STORAGE,
+            Self::TransferSource => ImageUsageFlags::TRANSFER_SRC,
+            Self::TransferDestination => ImageUsageFlags::TRANSFER_DST,
+        }
+    }
+
+    pub fn is_write(&self) -> bool {
+        matches!(
+            self,
+            Self::ColorAttachmentWrite
+                | Self::DepthStencilAttachmentWrite
+                | Self::ComputeStorageWrite
+                | Self::TransferDestination
+        )
+    }
+
+    pub fn is_color_attachment(&self) -> bool {
+        *self == Self::ColorAttachmentWrite
+    }
+
+    pub fn is_depth_stencil_attachment(&self) -> bool {
+        matches!(
+            self,
+            Self::DepthStencilAttachmentWrite | Self::DepthStencilAttachmentRead
+        )
+    }
+
+    pub fn is_attachment(&self) -> bool {
+        self.is_color_attachment() || self.is_depth_stencil_attachment()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn swapchain_relative_sizes_are_scaled_and_clamped() {
+        let extent = Extent2D {
+            width: 1280,
+            height: 1,
+        };
+        let resolved = ResourceSize::SwapchainRelative(0.5).resolve(extent);
+        assert_eq!((640, 1), (resolved.width, resolved.height));
+    }
+}
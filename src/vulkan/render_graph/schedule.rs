@@ -0,0 +1,498 @@
+//! This is a module that contains the scheduling logic of the render graph.
+//!
+//! Scheduling happens in four steps:
+//! 1. Passes that don't contribute to an imported image and don't have side effects are culled.
+//! 2. The remaining passes are validated, so that no pass reads a transient image before it was
+//!    written.
+//! 3. Transient images with non-overlapping lifetimes and compatible descriptions are assigned to
+//!    the same physical image (aliasing).
+//! 4. The pipeline barriers, layout transitions and attachment load/store operations of every pass
+//!    are derived from the accesses of the previous passes.
+//!
+//! Passes are executed in declaration order, since every pass can only depend on passes that
+//! were declared before it.
+
+use std::collections::{HashMap, HashSet};
+
+use ash::vk::{AccessFlags, ImageLayout, ImageUsageFlags, PipelineStageFlags};
+use ash::vk::{AttachmentLoadOp, AttachmentStoreOp};
+
+use super::pass::RenderGraphPass;
+use super::resource::{ResourceAccess, ResourceDescription, ResourceId, TransientImageDescription};
+
+/// A pipeline barrier for a single image, including a layout transition
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ImageBarrierInfo {
+    pub resource: ResourceId,
+    pub old_layout: ImageLayout,
+    pub new_layout: ImageLayout,
+    pub src_stage: PipelineStageFlags,
+    pub src_access: AccessFlags,
+    pub dst_stage: PipelineStageFlags,
+    pub dst_access: AccessFlags,
+}
+
+/// An attachment of a graphics pass
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct AttachmentInfo {
+    pub resource: ResourceId,
+    pub access: ResourceAccess,
+    pub load_op: AttachmentLoadOp,
+    pub store_op: AttachmentStoreOp,
+}
+
+#[derive(Clone, Debug)]
+pub struct ScheduledPass {
+    pub pass_index: usize,
+    /// The barriers that are recorded before the pass
+    pub barriers: Vec<ImageBarrierInfo>,
+    /// The color attachments in declaration order, followed by the depth attachment
+    pub attachments: Vec<AttachmentInfo>,
+}
+
+/// A physical image that backs one or more transient images
+#[derive(Clone, Copy, Debug)]
+pub struct PhysicalImageSlot {
+    pub description: TransientImageDescription,
+    pub usage: ImageUsageFlags,
+}
+
+#[derive(Clone, Debug)]
+pub struct Schedule {
+    pub passes: Vec<ScheduledPass>,
+    /// The barriers that transition imported images to their final layouts
+    pub final_barriers: Vec<ImageBarrierInfo>,
+    pub physical_slots: Vec<PhysicalImageSlot>,
+    /// The physical slot of every resource (`None` for imported and unused resources)
+    pub resource_slots: Vec<Option<usize>>,
+}
+
+#[derive(Clone, Copy)]
+struct ImageState {
+    layout: ImageLayout,
+    stage: PipelineStageFlags,
+    access: AccessFlags,
+    written: bool,
+}
+
+/// This function returns the distinct accesses of a pass and verifies that every resource is
+/// only accessed in a single way.
+fn distinct_accesses(
+    pass: &RenderGraphPass,
+    resource_count: usize,
+) -> crate::Result<Vec<(ResourceId, ResourceAccess)>> {
+    let mut accesses: Vec<(ResourceId, ResourceAccess)> = vec![];
+    for (resource, access) in pass.accesses.iter() {
+        crate::verify!(
+            resource.0 < resource_count,
+            "Pass '{}' accesses the unknown resource {:?}",
+            (pass.name),
+            resource
+        );
+
+        match accesses.iter().find(|(other, _)| other == resource) {
+            Some((_, other_access)) => crate::verify!(
+                other_access == access,
+                "Pass '{}' accesses resource {:?} both as {:?} and {:?}",
+                (pass.name),
+                resource,
+                other_access,
+                access
+            ),
+            None => accesses.push((*resource, *access)),
+        }
+    }
+    Ok(accesses)
+}
+
+fn cull_passes(
+    descriptions: &[ResourceDescription],
+    passes: &[RenderGraphPass],
+    accesses: &[Vec<(ResourceId, ResourceAccess)>],
+) -> Vec<usize> {
+    let mut needed: HashSet<ResourceId> = (0..descriptions.len())
+        .filter(|index| descriptions[*index].is_imported())
+        .map(ResourceId)
+        .collect();
+
+    let mut kept = vec![];
+    for (index, pass) in passes.iter().enumerate().rev() {
+        let writes_needed_resource = accesses[index]
+            .iter()
+            .any(|(resource, access)| access.is_write() && needed.contains(resource));
+        if !pass.has_side_effects && !writes_needed_resource {
+            continue;
+        }
+
+        kept.push(index);
+        for (resource, _) in accesses[index].iter() {
+            // Overwritten resources don't depend on the passes before this one
+            if pass.overwrites(*resource) && !descriptions[resource.0].is_imported() {
+                needed.remove(resource);
+            } else {
+                needed.insert(*resource);
+            }
+        }
+    }
+
+    kept.reverse();
+    kept
+}
+
+fn assign_physical_slots(
+    descriptions: &[ResourceDescription],
+    kept: &[usize],
+    accesses: &[Vec<(ResourceId, ResourceAccess)>],
+) -> (Vec<PhysicalImageSlot>, Vec<Option<usize>>) {
+    // The first and last position in the schedule at which each resource is used
+    let mut lifetimes: HashMap<ResourceId, (usize, usize)> = HashMap::new();
+    let mut usages: HashMap<ResourceId, ImageUsageFlags> = HashMap::new();
+    for (position, pass_index) in kept.iter().enumerate() {
+        for (resource, access) in accesses[*pass_index].iter() {
+            let lifetime = lifetimes.entry(*resource).or_insert((position, position));
+            lifetime.1 = position;
+            *usages.entry(*resource).or_default() |= access.image_usage();
+        }
+    }
+
+    let mut transient_resources = lifetimes
+        .iter()
+        .filter_map(|(resource, lifetime)| match descriptions[resource.0] {
+            ResourceDescription::Transient(description) => {
+                Some((*resource, *lifetime, description))
+            }
+            ResourceDescription::Imported(_) => None,
+        })
+        .collect::<Vec<_>>();
+    transient_resources.sort_by_key(|(resource, (first, _), _)| (*first, *resource));
+
+    let mut slots: Vec<PhysicalImageSlot> = vec![];
+    let mut slot_last_uses: Vec<usize> = vec![];
+    let mut resource_slots = vec![None; descriptions.len()];
+    for (resource, (first, last), description) in transient_resources {
+        let usage = usages[&resource];
+        let reusable_slot = slots.iter().enumerate().position(|(slot_index, slot)| {
+            slot.description.is_compatible_with(&description) && slot_last_uses[slot_index] < first
+        });
+
+        let slot_index = match reusable_slot {
+            Some(slot_index) => {
+                slots[slot_index].usage |= usage;
+                slot_last_uses[slot_index] = last;
+                slot_index
+            }
+            None => {
+                slots.push(PhysicalImageSlot { description, usage });
+                slot_last_uses.push(last);
+                slots.len() - 1
+            }
+        };
+        resource_slots[resource.0] = Some(slot_index);
+    }
+
+    (slots, resource_slots)
+}
+
+/// This function schedules the `passes` that access the resources with the given `descriptions`.
+pub fn schedule(
+    descriptions: &[ResourceDescription],
+    passes: &[RenderGraphPass],
+) -> crate::Result<Schedule> {
+    let accesses = passes
+        .iter()
+        .map(|pass| distinct_accesses(pass, descriptions.len()))
+        .collect::<crate::Result<Vec<_>>>()?;
+
+    let kept = cull_passes(descriptions, passes, &accesses);
+    let (physical_slots, resource_slots) = assign_physical_slots(descriptions, &kept, &accesses);
+
+    // The state of imported images is tracked per resource, the state of transient images per
+    // physical slot, so that aliased images are synchronized with their previous users
+    let state_key = |resource: ResourceId| match resource_slots[resource.0] {
+        Some(slot_index) => descriptions.len() + slot_index,
+        None => resource.0,
+    };
+    let mut states: HashMap<usize, ImageState> = HashMap::new();
+    for (index, description) in descriptions.iter().enumerate() {
+        if let ResourceDescription::Imported(imported) = description {
+            states.insert(
+                index,
+                ImageState {
+                    layout: imported.initial_layout,
+                    stage: PipelineStageFlags::ALL_COMMANDS,
+                    access: AccessFlags::empty(),
+                    written: false,
+                },
+            );
+        }
+    }
+
+    let mut touched: HashSet<ResourceId> = HashSet::new();
+    let mut scheduled_passes = vec![];
+    for (position, pass_index) in kept.iter().enumerate() {
+        let pass = &passes[*pass_index];
+        let mut barriers = vec![];
+        let mut color_attachments = vec![];
+        let mut depth_attachments = vec![];
+
+        for (resource, access) in accesses[*pass_index].iter() {
+            let description = &descriptions[resource.0];
+            let first_use = !touched.contains(resource);
+            crate::verify!(
+                !first_use || description.is_imported() || access.is_write(),
+                "Pass '{}' reads resource {:?} before it is written",
+                (pass.name),
+                resource
+            );
+
+            let key = state_key(*resource);
+            let previous_state = states.get(&key).copied();
+            let old_layout = match previous_state {
+                Some(state) if !first_use || description.is_imported() => state.layout,
+                _ => ImageLayout::UNDEFINED,
+            };
+
+            let needs_barrier = match previous_state {
+                Some(state) => {
+                    old_layout != access.layout()
+                        || state.written
+                        || access.is_write()
+                        || (first_use && !description.is_imported())
+                }
+                None => true,
+            };
+
+            if needs_barrier {
+                let (src_stage, src_access) = match previous_state {
+                    Some(state) if !state.stage.is_empty() => (
+                        state.stage,
+                        if state.written {
+                            state.access
+                        } else {
+                            AccessFlags::empty()
+                        },
+                    ),
+                    _ => (PipelineStageFlags::TOP_OF_PIPE, AccessFlags::empty()),
+                };
+                barriers.push(ImageBarrierInfo {
+                    resource: *resource,
+                    old_layout,
+                    new_layout: access.layout(),
+                    src_stage,
+                    src_access,
+                    dst_stage: access.stage(),
+                    dst_access: access.access_mask(),
+                });
+                states.insert(
+                    key,
+                    ImageState {
+                        layout: access.layout(),
+                        stage: access.stage(),
+                        access: access.access_mask(),
+                        written: access.is_write(),
+                    },
+                );
+            } else if let Some(state) = states.get_mut(&key) {
+                // Later writes have to wait for all readers
+                state.stage |= access.stage();
+                state.access |= access.access_mask();
+            }
+
+            if access.is_attachment() {
+                let load_op = if pass.overwrites(*resource) {
+                    AttachmentLoadOp::CLEAR
+                } else if old_layout == ImageLayout::UNDEFINED {
+                    AttachmentLoadOp::DONT_CARE
+                } else {
+                    AttachmentLoadOp::LOAD
+                };
+
+                let used_later = description.is_imported()
+                    || kept[position + 1..].iter().any(|later_index| {
+                        accesses[*later_index]
+                            .iter()
+                            .any(|(later_resource, _)| later_resource == resource)
+                    });
+                let store_op = if used_later {
+                    AttachmentStoreOp::STORE
+                } else {
+                    AttachmentStoreOp::DONT_CARE
+                };
+
+                let attachment = AttachmentInfo {
+                    resource: *resource,
+                    access: *access,
+                    load_op,
+                    store_op,
+                };
+                if access.is_color_attachment() {
+                    color_attachments.push(attachment);
+                } else {
+                    depth_attachments.push(attachment);
+                }
+            }
+
+            touched.insert(*resource);
+        }
+
+        crate::verify!(
+            depth_attachments.len() <= 1,
+            "Pass '{}' uses more than one depth attachment",
+            (pass.name)
+        );
+
+        let mut attachments = color_attachments;
+        attachments.append(&mut depth_attachments);
+        scheduled_passes.push(ScheduledPass {
+            pass_index: *pass_index,
+            barriers,
+            attachments,
+        });
+    }
+
+    let mut final_barriers = vec![];
+    for (index, description) in descriptions.iter().enumerate() {
+        if let ResourceDescription::Imported(imported) = description {
+            let state = states[&index];
+            if state.layout != imported.final_layout || state.written {
+                final_barriers.push(ImageBarrierInfo {
+                    resource: ResourceId(index),
+                    old_layout: state.layout,
+                    new_layout: imported.final_layout,
+                    src_stage: state.stage,
+                    src_access: if state.written {
+                        state.access
+                    } else {
+                        AccessFlags::empty()
+                    },
+                    dst_stage: PipelineStageFlags::BOTTOM_OF_PIPE,
+                    dst_access: AccessFlags::empty(),
+                });
+            }
+        }
+    }
+
+    Ok(Schedule {
+        passes: scheduled_passes,
+        final_barriers,
+        physical_slots,
+        resource_slots,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use ash::vk::{ClearColorValue, Format};
+
+    use super::super::resource::ImportedImageDescription;
+    use super::*;
+
+    fn transient(format: Format) -> ResourceDescription {
+        ResourceDescription::Transient(TransientImageDescription::new(format))
+    }
+
+    fn present_image() -> ResourceDescription {
+        ResourceDescription::Imported(ImportedImageDescription {
+            format: Format::B8G8R8A8_UNORM,
+            initial_layout: ImageLayout::UNDEFINED,
+            final_layout: ImageLayout::PRESENT_SRC_KHR,
+        })
+    }
+
+    #[test]
+    fn passes_that_do_not_contribute_to_the_output_are_culled() {
+        let descriptions = [present_image(), transient(Format::R16G16B16A16_SFLOAT)];
+        let passes = [
+            RenderGraphPass::new("unused").color_attachment(ResourceId(1)),
+            RenderGraphPass::new("final").color_attachment(ResourceId(0)),
+        ];
+
+        let schedule = schedule(&descriptions, &passes).unwrap();
+        let scheduled = schedule
+            .passes
+            .iter()
+            .map(|pass| pass.pass_index)
+            .collect::<Vec<_>>();
+        assert_eq!(vec![1], scheduled);
+        assert!(schedule.physical_slots.is_empty());
+    }
+
+    #[test]
+    fn reading_a_written_image_transitions_its_layout() {
+        let descriptions = [present_image(), transient(Format::R16G16B16A16_SFLOAT)];
+        let passes = [
+            RenderGraphPass::new("scene")
+                .color_attachment_with_clear(ResourceId(1), ClearColorValue::default()),
+            RenderGraphPass::new("tonemap")
+                .sampled_in_fragment_shader(ResourceId(1))
+                .color_attachment(ResourceId(0)),
+        ];
+
+        let schedule = schedule(&descriptions, &passes).unwrap();
+        assert_eq!(2, schedule.passes.len());
+
+        let scene = &schedule.passes[0];
+        assert_eq!(AttachmentLoadOp::CLEAR, scene.attachments[0].load_op);
+        assert_eq!(AttachmentStoreOp::STORE, scene.attachments[0].store_op);
+
+        let transition = schedule.passes[1]
+            .barriers
+            .iter()
+            .find(|barrier| barrier.resource == ResourceId(1))
+            .unwrap();
+        assert_eq!(ImageLayout::COLOR_ATTACHMENT_OPTIMAL, transition.old_layout);
+        assert_eq!(ImageLayout::SHADER_READ_ONLY_OPTIMAL, transition.new_layout);
+        assert_eq!(
+            AccessFlags::COLOR_ATTACHMENT_READ | AccessFlags::COLOR_ATTACHMENT_WRITE,
+            transition.src_access
+        );
+
+        assert_eq!(1, schedule.final_barriers.len());
+        assert_eq!(
+            ImageLayout::PRESENT_SRC_KHR,
+            schedule.final_barriers[0].new_layout
+        );
+    }
+
+    #[test]
+    fn transient_images_with_disjoint_lifetimes_are_aliased() {
+        let format = Format::R16G16B16A16_SFLOAT;
+        let descriptions = [
+            present_image(),
+            transient(format),
+            transient(format),
+            transient(format),
+        ];
+        let passes = [
+            RenderGraphPass::new("a").color_attachment(ResourceId(1)),
+            RenderGraphPass::new("b")
+                .sampled_in_fragment_shader(ResourceId(1))
+                .color_attachment(ResourceId(2)),
+            RenderGraphPass::new("c")
+                .sampled_in_fragment_shader(ResourceId(2))
+                .color_attachment(ResourceId(3)),
+            RenderGraphPass::new("d")
+                .sampled_in_fragment_shader(ResourceId(3))
+                .color_attachment(ResourceId(0)),
+        ];
+
+        let schedule = schedule(&descriptions, &passes).unwrap();
+        assert_eq!(2, schedule.physical_slots.len());
+        assert_eq!(schedule.resource_slots[1], schedule.resource_slots[3]);
+        assert_ne!(schedule.resource_slots[1], schedule.resource_slots[2]);
+        assert_eq!(
+            ImageUsageFlags::COLOR_ATTACHMENT | ImageUsageFlags::SAMPLED,
+            schedule.physical_slots[0].usage
+        );
+    }
+
+    #[test]
+    fn reading_an_unwritten_transient_image_is_an_error() {
+        let descriptions = [present_image(), transient(Format::R8G8B8A8_UNORM)];
+        let passes = [RenderGraphPass::new("final")
+            .sampled_in_fragment_shader(ResourceId(1))
+            .color_attachment(ResourceId(0))];
+
+        assert!(schedule(&descriptions, &passes).is_err());
+    }
+}
@@ -0,0 +1,256 @@
+//! This is a module that contains the `RenderInfo` structure, which provides information about
+//! the Vulkan instance, the selected physical device and the swapchain of a `VulkanContext`.
+//!
+//! `RenderInfo` implements `Display`, so it can be logged directly. When the egui-integration
+//! feature is enabled, it can also be shown in an egui overlay using `RenderInfo::show`.
+
+use std::fmt::Display;
+
+use ash::vk::{MemoryHeapFlags, PhysicalDeviceLimits, PhysicalDeviceType};
+use ash::vk::{PresentModeKHR, SampleCountFlags, SurfaceFormatKHR};
+
+use super::physical_device::SetUpPhysicalDevice;
+
+const NVIDIA_VENDOR_ID: u32 = 0x10DE;
+const INTEL_VENDOR_ID: u32 = 0x8086;
+
+/// This function formats a Vulkan API version as `major.minor.patch`.
+pub fn format_api_version(version: u32) -> String {
+    format!(
+        "{}.{}.{}",
+        ash::vk::api_version_major(version),
+        ash::vk::api_version_minor(version),
+        ash::vk::api_version_patch(version)
+    )
+}
+
+/// This function formats a driver version according to the version encoding of the vendor.
+///
+/// NVIDIA and Intel (on Windows) use their own encodings, all other vendors are expected to use
+/// the Vulkan version encoding.
+pub fn format_driver_version(vendor_id: u32, driver_version: u32) -> String {
+    if vendor_id == NVIDIA_VENDOR_ID {
+        format!(
+            "{}.{}.{}.{}",
+            (driver_version >> 22) & 0x3ff,
+            (driver_version >> 14) & 0xff,
+            (driver_version >> 6) & 0xff,
+            driver_version & 0x3f
+        )
+    } else if vendor_id == INTEL_VENDOR_ID && cfg!(target_os = "windows") {
+        format!("{}.{}", driver_version >> 14, driver_version & 0x3fff)
+    } else {
+        format_api_version(driver_version)
+    }
+}
+
+fn format_byte_size(size: u64) -> String {
+    const MEBIBYTE: u64 = 1024 * 1024;
+    format!("{} MiB", size / MEBIBYTE)
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct MemoryHeapInfo {
+    pub size: u64,
+    pub device_local: bool,
+}
+
+/// A subset of the `PhysicalDeviceLimits` that are most relevant for rendering
+#[derive(Clone, Copy, Debug)]
+pub struct RenderLimits {
+    pub max_image_dimension_2d: u32,
+    pub max_image_array_layers: u32,
+    pub max_framebuffer_width: u32,
+    pub max_framebuffer_height: u32,
+    pub max_push_constants_size: u32,
+    pub max_bound_descriptor_sets: u32,
+    pub max_uniform_buffer_range: u32,
+    pub max_storage_buffer_range: u32,
+    pub min_uniform_buffer_offset_alignment: u64,
+    pub max_sampler_anisotropy: f32,
+    pub max_compute_work_group_invocations: u32,
+    pub framebuffer_color_sample_counts: SampleCountFlags,
+    pub framebuffer_depth_sample_counts: SampleCountFlags,
+}
+
+impl From<&PhysicalDeviceLimits> for RenderLimits {
+    fn from(limits: &PhysicalDeviceLimits) -> Self {
+        Self {
+            max_image_dimension_2d: limits.max_image_dimension2_d,
+            max_image_array_layers: limits.max_image_array_layers,
+            max_framebuffer_width: limits.max_framebuffer_width,
+            max_framebuffer_height: limits.max_framebuffer_height,
+            max_push_constants_size: limits.max_push_constants_size,
+            max_bound_descriptor_sets: limits.max_bound_descriptor_sets,
+            max_uniform_buffer_range: limits.max_uniform_buffer_range,
+            max_storage_buffer_range: limits.max_storage_buffer_range,
+            min_uniform_buffer_offset_alignment: limits.min_uniform_buffer_offset_alignment,
+            max_sampler_anisotropy: limits.max_sampler_anisotropy,
+            max_compute_work_group_invocations: limits.max_compute_work_group_invocations,
+            framebuffer_color_sample_counts: limits.framebuffer_color_sample_counts,
+            framebuffer_depth_sample_counts: limits.framebuffer_depth_sample_counts,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct RenderInfo {
+    pub api_version: u32,
+    pub device_name: String,
+    pub device_type: PhysicalDeviceType,
+    pub vendor_id: u32,
+    pub driver_version: u32,
+    pub memory_heaps: Vec<MemoryHeapInfo>,
+    pub limits: RenderLimits,
+
+    pub instance_extensions: Vec<String>,
+    pub device_extensions: Vec<String>,
+    pub layers: Vec<String>,
+
+    /// The present mode of the swapchain (`None` for headless contexts)
+    pub present_mode: Option<PresentModeKHR>,
+    /// The format of the swapchain images (`None` for headless contexts)
+    pub swapchain_format: Option<SurfaceFormatKHR>,
+}
+
+impl RenderInfo {
+    pub fn from_physical_device(
+        physical_device: &SetUpPhysicalDevice,
+        instance_extensions: Vec<String>,
+        device_extensions: Vec<String>,
+        layers: Vec<String>,
+        present_mode: Option<PresentModeKHR>,
+        swapchain_format: Option<SurfaceFormatKHR>,
+    ) -> Self {
+        let properties = &physical_device.device_properties;
+        let memory_properties = &physical_device.device_memory_properties;
+
+        let memory_heaps = memory_properties.memory_heaps
+            [..memory_properties.memory_heap_count as usize]
+            .iter()
+            .map(|heap| MemoryHeapInfo {
+                size: heap.size,
+                device_local: heap.flags.contains(MemoryHeapFlags::DEVICE_LOCAL),
+            })
+            .collect();
+
+        Self {
+            api_version: properties.api_version,
+            device_name: physical_device.name().to_string_lossy().into_owned(),
+            device_type: properties.device_type,
+            vendor_id: properties.vendor_id,
+            driver_version: properties.driver_version,
+            memory_heaps,
+            limits: RenderLimits::from(&properties.limits),
+
+            instance_extensions,
+            device_extensions,
+            layers,
+
+            present_mode,
+            swapchain_format,
+        }
+    }
+
+    pub fn api_version_string(&self) -> String {
+        format_api_version(self.api_version)
+    }
+
+    pub fn driver_version_string(&self) -> String {
+        format_driver_version(self.vendor_id, self.driver_version)
+    }
+
+    /// This function returns the sum of the sizes of all device-local memory heaps.
+    pub fn device_local_memory(&self) -> u64 {
+        self.memory_heaps
+            .iter()
+            .filter(|heap| heap.device_local)
+            .map(|heap| heap.size)
+            .sum()
+    }
+
+    fn swapchain_description(&self) -> (String, String) {
+        let present_mode = self
+            .present_mode
+            .map_or("none".to_string(), |present_mode| format!("{:?}", present_mode));
+        let swapchain_format = self.swapchain_format.map_or("none".to_string(), |format| {
+            format!("{:?} ({:?})", format.format, format.color_space)
+        });
+        (present_mode, swapchain_format)
+    }
+
+    /// This function shows this information in an egui `Ui`.
+    #[cfg(feature = "egui-integration")]
+    pub fn show(&self, ui: &mut egui::Ui) {
+        let (present_mode, swapchain_format) = self.swapchain_description();
+
+        ui.label(format!("Device: {} ({:?})", self.device_name, self.device_type));
+        ui.label(format!("Vulkan API: {}", self.api_version_string()));
+        ui.label(format!("Driver: {}", self.driver_version_string()));
+        ui.label(format!("Present mode: {}", present_mode));
+        ui.label(format!("Swapchain format: {}", swapchain_format));
+
+        ui.collapsing("Memory heaps", |ui| {
+            for (index, heap) in self.memory_heaps.iter().enumerate() {
+                let locality = if heap.device_local { "device-local" } else { "host" };
+                ui.label(format!("{}: {} ({})", index, format_byte_size(heap.size), locality));
+            }
+        });
+
+        ui.collapsing("Limits", |ui| {
+            ui.label(format!("{:#?}", self.limits));
+        });
+
+        ui.collapsing("Extensions and layers", |ui| {
+            ui.label(format!("Instance extensions: {}", self.instance_extensions.join(", ")));
+            ui.label(format!("Device extensions: {}", self.device_extensions.join(", ")));
+            ui.label(format!("Layers: {}", self.layers.join(", ")));
+        });
+    }
+}
+
+impl Display for RenderInfo {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let (present_mode, swapchain_format) = self.swapchain_description();
+
+        writeln!(f, "Device: {} ({:?})", self.device_name, self.device_type)?;
+        writeln!(f, "Vulkan API: {}", self.api_version_string())?;
+        writeln!(f, "Driver: {}", self.driver_version_string())?;
+        writeln!(f, "Present mode: {}", present_mode)?;
+        writeln!(f, "Swapchain format: {}", swapchain_format)?;
+
+        writeln!(f, "Memory heaps:")?;
+        for (index, heap) in self.memory_heaps.iter().enumerate() {
+            let locality = if heap.device_local { "device-local" } else { "host" };
+            writeln!(f, "  {}: {} ({})", index, format_byte_size(heap.size), locality)?;
+        }
+
+        writeln!(f, "Limits: {:#?}", self.limits)?;
+        writeln!(f, "Instance extensions: {}", self.instance_extensions.join(", "))?;
+        writeln!(f, "Device extensions: {}", self.device_extensions.join(", "))?;
+        write!(f, "Layers: {}", self.layers.join(", "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn api_version_is_formatted_as_major_minor_patch() {
+        let version = ash::vk::make_api_version(0, 1, 3, 251);
+        assert_eq!("1.3.251", format_api_version(version));
+    }
+
+    #[test]
+    fn nvidia_driver_version_uses_nvidia_encoding() {
+        let version = (535 << 22) | (104 << 14) | (5 << 6) | 1;
+        assert_eq!("535.104.5.1", format_driver_version(NVIDIA_VENDOR_ID, version));
+    }
+
+    #[test]
+    fn other_driver_versions_use_vulkan_encoding() {
+        let version = ash::vk::make_api_version(0, 23, 1, 4);
+        assert_eq!("23.1.4", format_driver_version(0x1002, version));
+    }
+}
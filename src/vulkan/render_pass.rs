@@ -1,6 +1,7 @@
 use ash::vk;
 use ash::vk::AccessFlags;
 use ash::vk::Format;
+use ash::vk::ImageAspectFlags;
 use ash::vk::ImageLayout;
 use ash::vk::{AttachmentDescription, AttachmentLoadOp, AttachmentReference, AttachmentStoreOp};
 use ash::vk::{PipelineBindPoint, PipelineStageFlags};
@@ -8,6 +9,7 @@ use ash::vk::{RenderPass, RenderPassCreateInfo};
 use ash::vk::{SampleCountFlags, SubpassDependency, SubpassDescription};
 use nalgebra::Vector4;
 
+use super::depth_image::format_aspect_mask;
 use super::logical_device::SetUpLogicalDevice;
 use super::surface::SetUpSurfaceInfo;
 
@@ -20,9 +22,15 @@ pub fn color_clear_value(color: Vector4<f32>) -> vk::ClearValue {
 }
 
 pub fn default_depth_clear_value() -> vk::ClearValue {
+    depth_clear_value(false)
+}
+
+/// This function returns the depth clear value for the far plane, which is 0.0 with reverse-Z
+/// and 1.0 otherwise.
+pub fn depth_clear_value(reverse_z: bool) -> vk::ClearValue {
     vk::ClearValue {
         depth_stencil: vk::ClearDepthStencilValue {
-            depth: 1.0,
+            depth: if reverse_z { 0.0 } else { 1.0 },
             stencil: 0,
         },
     }
@@ -69,7 +77,10 @@ impl SetUpRenderPass {
         )
     }
 
-    fn get_default_color_attachment(format: ash::vk::Format) -> AttachmentDescription {
+    fn get_default_color_attachment(
+        format: ash::vk::Format,
+        final_layout: ImageLayout,
+    ) -> AttachmentDescription {
         AttachmentDescription::builder()
             .format(format)
             .samples(SampleCountFlags::TYPE_1)
@@ -78,43 +89,67 @@ impl SetUpRenderPass {
             .stencil_load_op(AttachmentLoadOp::DONT_CARE)
             .stencil_store_op(AttachmentStoreOp::DONT_CARE)
             .initial_layout(ImageLayout::UNDEFINED)
-            .final_layout(ImageLayout::PRESENT_SRC_KHR)
+            .final_layout(final_layout)
             .build()
     }
 
-    fn get_default_depth_stencil_attachment() -> AttachmentDescription {
+    /// The multisampled color attachment is only needed until it was resolved, so its contents
+    /// aren't stored.
+    fn get_multisampled_color_attachment(
+        format: ash::vk::Format,
+        samples: SampleCountFlags,
+    ) -> AttachmentDescription {
         AttachmentDescription::builder()
-            .format(Format::D16_UNORM)
-            .samples(SampleCountFlags::TYPE_1)
+            .format(format)
+            .samples(samples)
             .load_op(AttachmentLoadOp::CLEAR)
             .store_op(AttachmentStoreOp::DONT_CARE)
             .stencil_load_op(AttachmentLoadOp::DONT_CARE)
             .stencil_store_op(AttachmentStoreOp::DONT_CARE)
             .initial_layout(ImageLayout::UNDEFINED)
-            .final_layout(ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL)
+            .final_layout(ImageLayout::COLOR_ATTACHMENT_OPTIMAL)
             .build()
     }
 
-    pub unsafe fn create_with_defaults(
-        logical_device: &SetUpLogicalDevice,
-        surface_info: &SetUpSurfaceInfo,
-    ) -> crate::Result<Self> {
-        let surface_format = surface_info.surface_format(0)?;
-        let renderpass_attachments = [
-            Self::get_default_color_attachment(surface_format.format),
-            Self::get_default_depth_stencil_attachment(),
-        ];
-        let color_attachment_refs = [AttachmentReference {
-            attachment: 0,
-            layout: ImageLayout::COLOR_ATTACHMENT_OPTIMAL,
-        }];
+    fn get_resolve_attachment(
+        format: ash::vk::Format,
+        final_layout: ImageLayout,
+    ) -> AttachmentDescription {
+        AttachmentDescription::builder()
+            .format(format)
+            .samples(SampleCountFlags::TYPE_1)
+            .load_op(AttachmentLoadOp::DONT_CARE)
+            .store_op(AttachmentStoreOp::STORE)
+            .stencil_load_op(AttachmentLoadOp::DONT_CARE)
+            .stencil_store_op(AttachmentStoreOp::DONT_CARE)
+            .initial_layout(ImageLayout::UNDEFINED)
+            .final_layout(final_layout)
+            .build()
+    }
 
-        let depth_attachment_ref = AttachmentReference {
-            attachment: 1,
-            layout: ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL,
+    fn get_default_depth_stencil_attachment(
+        format: Format,
+        samples: SampleCountFlags,
+    ) -> AttachmentDescription {
+        let stencil_load_op = if format_aspect_mask(format).contains(ImageAspectFlags::STENCIL) {
+            AttachmentLoadOp::CLEAR
+        } else {
+            AttachmentLoadOp::DONT_CARE
         };
+        AttachmentDescription::builder()
+            .format(format)
+            .samples(samples)
+            .load_op(AttachmentLoadOp::CLEAR)
+            .store_op(AttachmentStoreOp::DONT_CARE)
+            .stencil_load_op(stencil_load_op)
+            .stencil_store_op(AttachmentStoreOp::DONT_CARE)
+            .initial_layout(ImageLayout::UNDEFINED)
+            .final_layout(ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL)
+            .build()
+    }
 
-        let subpass_dependencies = [
+    fn get_default_subpass_dependencies() -> [SubpassDependency; 2] {
+        [
             SubpassDependency {
                 src_subpass: vk::SUBPASS_EXTERNAL,
                 src_stage_mask: PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
@@ -133,13 +168,292 @@ impl SetUpRenderPass {
                     | AccessFlags::DEPTH_STENCIL_ATTACHMENT_WRITE,
                 ..Default::default()
             },
+        ]
+    }
+
+    unsafe fn create_with_default_attachments(
+        logical_device: &SetUpLogicalDevice,
+        color_format: Format,
+        depth_format: Format,
+        color_final_layout: ImageLayout,
+        subpass_dependencies: &[SubpassDependency],
+    ) -> crate::Result<Self> {
+        let renderpass_attachments = [
+            Self::get_default_color_attachment(color_format, color_final_layout),
+            Self::get_default_depth_stencil_attachment(depth_format, SampleCountFlags::TYPE_1),
         ];
+        let color_attachment_refs = [AttachmentReference {
+            attachment: 0,
+            layout: ImageLayout::COLOR_ATTACHMENT_OPTIMAL,
+        }];
+
+        let depth_attachment_ref = AttachmentReference {
+            attachment: 1,
+            layout: ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL,
+        };
 
         Self::create_with_color_depth_subpass(
             logical_device,
             &renderpass_attachments,
             &color_attachment_refs,
             depth_attachment_ref,
+            subpass_dependencies,
+        )
+    }
+
+    /// This function creates a render pass with a multisampled color and depth attachment.
+    /// The color attachment is resolved into the third attachment, which is transitioned to the
+    /// `resolve_final_layout` at the end of the render pass.
+    pub unsafe fn create_with_multisampled_attachments(
+        logical_device: &SetUpLogicalDevice,
+        color_format: Format,
+        depth_format: Format,
+        resolve_final_layout: ImageLayout,
+        samples: SampleCountFlags,
+        subpass_dependencies: &[SubpassDependency],
+    ) -> crate::Result<Self> {
+        let renderpass_attachments = [
+            Self::get_multisampled_color_attachment(color_format, samples),
+            Self::get_default_depth_stencil_attachment(depth_format, samples),
+            Self::get_resolve_attachment(color_format, resolve_final_layout),
+        ];
+        let color_attachment_refs = [AttachmentReference {
+            attachment: 0,
+            layout: ImageLayout::COLOR_ATTACHMENT_OPTIMAL,
+        }];
+        let depth_attachment_ref = AttachmentReference {
+            attachment: 1,
+            layout: ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL,
+        };
+        let resolve_attachment_refs = [AttachmentReference {
+            attachment: 2,
+            layout: ImageLayout::COLOR_ATTACHMENT_OPTIMAL,
+        }];
+
+        let subpass = SubpassDescription::builder()
+            .color_attachments(&color_attachment_refs)
+            .depth_stencil_attachment(&depth_attachment_ref)
+            .resolve_attachments(&resolve_attachment_refs)
+            .pipeline_bind_point(PipelineBindPoint::GRAPHICS);
+
+        Self::create_with_renderpass_attachments_and_subpasses(
+            logical_device,
+            &renderpass_attachments,
+            std::slice::from_ref(&subpass),
+            subpass_dependencies,
+        )
+    }
+
+    /// This function creates the default render pass for the present images of the surface. If
+    /// `samples` is more than `TYPE_1`, the attachments are multisampled and resolved into the
+    /// present image (see `SetUpRenderPass::create_with_multisampled_attachments`).
+    pub unsafe fn create_with_defaults(
+        logical_device: &SetUpLogicalDevice,
+        surface_info: &SetUpSurfaceInfo,
+        depth_format: Format,
+        samples: SampleCountFlags,
+    ) -> crate::Result<Self> {
+        let surface_format = surface_info.surface_format(0)?;
+
+        if samples == SampleCountFlags::TYPE_1 {
+            Self::create_with_default_attachments(
+                logical_device,
+                surface_format.format,
+                depth_format,
+                ImageLayout::PRESENT_SRC_KHR,
+                &Self::get_default_subpass_dependencies(),
+            )
+        } else {
+            Self::create_with_multisampled_attachments(
+                logical_device,
+                surface_format.format,
+                depth_format,
+                ImageLayout::PRESENT_SRC_KHR,
+                samples,
+                &Self::get_default_subpass_dependencies(),
+            )
+        }
+    }
+
+    /// This function creates a render pass for rendering into an offscreen color image and
+    /// depth image of the given formats.
+    ///
+    /// The color image is transitioned to `TRANSFER_SRC_OPTIMAL` at the end of the render pass,
+    /// so that it can be copied into a buffer afterwards.
+    pub unsafe fn create_for_offscreen_target(
+        logical_device: &SetUpLogicalDevice,
+        color_format: Format,
+        depth_format: Format,
+    ) -> crate::Result<Self> {
+        let [color_dependency, depth_dependency] = Self::get_default_subpass_dependencies();
+        let subpass_dependencies = [
+            color_dependency,
+            depth_dependency,
+            SubpassDependency {
+                src_subpass: 0,
+                dst_subpass: vk::SUBPASS_EXTERNAL,
+                src_stage_mask: PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
+                dst_stage_mask: PipelineStageFlags::TRANSFER,
+                src_access_mask: AccessFlags::COLOR_ATTACHMENT_WRITE,
+                dst_access_mask: AccessFlags::TRANSFER_READ,
+                ..Default::default()
+            },
+        ];
+
+        Self::create_with_default_attachments(
+            logical_device,
+            color_format,
+            depth_format,
+            ImageLayout::TRANSFER_SRC_OPTIMAL,
+            &subpass_dependencies,
+        )
+    }
+
+    /// This function returns the dependency that makes the color attachment writes of the
+    /// render pass visible to fragment shaders that sample the image afterwards.
+    fn get_sampled_color_output_dependency() -> SubpassDependency {
+        SubpassDependency {
+            src_subpass: 0,
+            dst_subpass: vk::SUBPASS_EXTERNAL,
+            src_stage_mask: PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
+            dst_stage_mask: PipelineStageFlags::FRAGMENT_SHADER,
+            src_access_mask: AccessFlags::COLOR_ATTACHMENT_WRITE,
+            dst_access_mask: AccessFlags::SHADER_READ,
+            ..Default::default()
+        }
+    }
+
+    /// This function creates a render pass for rendering into a color image that is sampled in
+    /// a fragment shader afterwards, e.g. an HDR target that is tonemapped in a post-process
+    /// pass.
+    ///
+    /// The color image is transitioned to `SHADER_READ_ONLY_OPTIMAL` at the end of the render
+    /// pass.
+    pub unsafe fn create_for_sampled_color_target(
+        logical_device: &SetUpLogicalDevice,
+        color_format: Format,
+        depth_format: Format,
+    ) -> crate::Result<Self> {
+        let [color_dependency, depth_dependency] = Self::get_default_subpass_dependencies();
+        let subpass_dependencies = [
+            color_dependency,
+            depth_dependency,
+            Self::get_sampled_color_output_dependency(),
+        ];
+
+        Self::create_with_default_attachments(
+            logical_device,
+            color_format,
+            depth_format,
+            ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+            &subpass_dependencies,
+        )
+    }
+
+    /// This function creates a render pass with a single color attachment and no depth
+    /// attachment, which is used by fullscreen passes that overwrite every pixel.
+    ///
+    /// The previous contents of the attachment are discarded. If the `final_layout` is
+    /// `SHADER_READ_ONLY_OPTIMAL`, the attachment writes are made visible to fragment shaders.
+    pub unsafe fn create_for_fullscreen_pass(
+        logical_device: &SetUpLogicalDevice,
+        color_format: Format,
+        final_layout: ImageLayout,
+    ) -> crate::Result<Self> {
+        let color_attachment = AttachmentDescription::builder()
+            .format(color_format)
+            .samples(SampleCountFlags::TYPE_1)
+            .load_op(AttachmentLoadOp::DONT_CARE)
+            .store_op(AttachmentStoreOp::STORE)
+            .stencil_load_op(AttachmentLoadOp::DONT_CARE)
+            .stencil_store_op(AttachmentStoreOp::DONT_CARE)
+            .initial_layout(ImageLayout::UNDEFINED)
+            .final_layout(final_layout)
+            .build();
+        let color_attachment_refs = [AttachmentReference {
+            attachment: 0,
+            layout: ImageLayout::COLOR_ATTACHMENT_OPTIMAL,
+        }];
+        let subpass = SubpassDescription::builder()
+            .color_attachments(&color_attachment_refs)
+            .pipeline_bind_point(PipelineBindPoint::GRAPHICS);
+
+        // The image may still be sampled by the previous frame's fragment shaders
+        let mut subpass_dependencies = vec![SubpassDependency {
+            src_subpass: vk::SUBPASS_EXTERNAL,
+            src_stage_mask: PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT
+                | PipelineStageFlags::FRAGMENT_SHADER,
+            dst_stage_mask: PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
+            dst_access_mask: AccessFlags::COLOR_ATTACHMENT_WRITE,
+            ..Default::default()
+        }];
+        if final_layout == ImageLayout::SHADER_READ_ONLY_OPTIMAL {
+            subpass_dependencies.push(Self::get_sampled_color_output_dependency());
+        }
+
+        Self::create_with_renderpass_attachments_and_subpasses(
+            logical_device,
+            std::slice::from_ref(&color_attachment),
+            std::slice::from_ref(&subpass),
+            &subpass_dependencies,
+        )
+    }
+
+    /// This function creates a depth-only render pass for rendering into a shadow map that is
+    /// sampled in a fragment shader afterwards.
+    ///
+    /// The depth attachment is cleared and transitioned to `SHADER_READ_ONLY_OPTIMAL` at the
+    /// end of the render pass.
+    pub unsafe fn create_for_shadow_map(
+        logical_device: &SetUpLogicalDevice,
+        depth_format: Format,
+    ) -> crate::Result<Self> {
+        let depth_attachment = AttachmentDescription::builder()
+            .format(depth_format)
+            .samples(SampleCountFlags::TYPE_1)
+            .load_op(AttachmentLoadOp::CLEAR)
+            .store_op(AttachmentStoreOp::STORE)
+            .stencil_load_op(AttachmentLoadOp::DONT_CARE)
+            .stencil_store_op(AttachmentStoreOp::DONT_CARE)
+            .initial_layout(ImageLayout::UNDEFINED)
+            .final_layout(ImageLayout::SHADER_READ_ONLY_OPTIMAL)
+            .build();
+        let depth_attachment_ref = AttachmentReference {
+            attachment: 0,
+            layout: ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL,
+        };
+        let subpass = SubpassDescription::builder()
+            .depth_stencil_attachment(&depth_attachment_ref)
+            .pipeline_bind_point(PipelineBindPoint::GRAPHICS);
+
+        let depth_test_stages =
+            PipelineStageFlags::EARLY_FRAGMENT_TESTS | PipelineStageFlags::LATE_FRAGMENT_TESTS;
+        let subpass_dependencies = [
+            // The shadow map may still be sampled by the previous frame's fragment shaders
+            SubpassDependency {
+                src_subpass: vk::SUBPASS_EXTERNAL,
+                dst_subpass: 0,
+                src_stage_mask: PipelineStageFlags::FRAGMENT_SHADER,
+                dst_stage_mask: depth_test_stages,
+                src_access_mask: AccessFlags::SHADER_READ,
+                dst_access_mask: AccessFlags::DEPTH_STENCIL_ATTACHMENT_WRITE,
+                ..Default::default()
+            },
+            SubpassDependency {
+                src_subpass: 0,
+                dst_subpass: vk::SUBPASS_EXTERNAL,
+                src_stage_mask: depth_test_stages,
+                dst_stage_mask: PipelineStageFlags::FRAGMENT_SHADER,
+                src_access_mask: AccessFlags::DEPTH_STENCIL_ATTACHMENT_WRITE,
+                dst_access_mask: AccessFlags::SHADER_READ,
+                ..Default::default()
+            },
+        ];
+
+        Self::create_with_renderpass_attachments_and_subpasses(
+            logical_device,
+            std::slice::from_ref(&depth_attachment),
+            std::slice::from_ref(&subpass),
             &subpass_dependencies,
         )
     }
@@ -0,0 +1,278 @@
+//! This is a module that contains the `ShaderCompileCache` structure, which caches compiled
+//! SPIR-V by a hash of the shader source and the compile options.
+//!
+//! Every entry stores the files that were included by the shader with hashes of their
+//! contents, so an entry is only used while all included files are unchanged. Entries are kept
+//! in memory and, if a cache directory is set, written to `<key>.spv` (the SPIR-V) and
+//! `<key>.deps` (one `<hash> <path>` line per included file) in that directory, so that they
+//! can be reused by later runs. Entries that include files whose paths aren't valid UTF-8 or
+//! contain line breaks are only cached in memory, since their paths can't be stored in the
+//! `.deps` file.
+//!
+//! The hashes are 64-bit FNV-1a hashes, which are stable across compiler versions and runs
+//! (unlike the hasher of the standard library).
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use crate::result::Context;
+
+const FNV_OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+const FNV_PRIME: u64 = 0x100000001b3;
+
+/// A 64-bit FNV-1a hasher
+#[derive(Clone, Copy, Debug)]
+pub struct StableHasher {
+    hash: u64,
+}
+
+impl Default for StableHasher {
+    fn default() -> Self {
+        Self {
+            hash: FNV_OFFSET_BASIS,
+        }
+    }
+}
+
+impl StableHasher {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.hash ^= *byte as u64;
+            self.hash = self.hash.wrapping_mul(FNV_PRIME);
+        }
+    }
+
+    /// This function writes the `string` followed by a separator, so that consecutive strings
+    /// can't produce the same hash by moving characters between them.
+    pub fn write_str(&mut self, string: &str) {
+        self.write(string.as_bytes());
+        self.write(&[0xff]);
+    }
+
+    pub fn finish(&self) -> u64 {
+        self.hash
+    }
+}
+
+/// This function returns the stable hash of the `bytes`.
+pub fn stable_hash(bytes: &[u8]) -> u64 {
+    let mut hasher = StableHasher::new();
+    hasher.write(bytes);
+    hasher.finish()
+}
+
+/// A file that a cached shader depends on, with the hash of its content when it was compiled
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ShaderDependency {
+    pub path: PathBuf,
+    pub hash: u64,
+}
+
+impl ShaderDependency {
+    pub fn new(path: PathBuf, content: &str) -> Self {
+        Self {
+            path,
+            hash: stable_hash(content.as_bytes()),
+        }
+    }
+
+    /// This function checks whether the file still has the content it was hashed with.
+    pub fn is_up_to_date(&self) -> bool {
+        std::fs::read(&self.path)
+            .map(|content| stable_hash(&content) == self.hash)
+            .unwrap_or(false)
+    }
+}
+
+/// A cached compile result
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CachedShader {
+    pub spirv: Vec<u8>,
+    pub dependencies: Vec<ShaderDependency>,
+}
+
+impl CachedShader {
+    pub fn is_up_to_date(&self) -> bool {
+        self.dependencies
+            .iter()
+            .all(|dependency| dependency.is_up_to_date())
+    }
+}
+
+#[derive(Default)]
+pub struct ShaderCompileCache {
+    entries: HashMap<u64, CachedShader>,
+    cache_directory: Option<PathBuf>,
+}
+
+impl ShaderCompileCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// This function sets the directory that entries are written to and read from. It's
+    /// created when the first entry is written.
+    pub fn cache_directory<P: Into<PathBuf>>(mut self, cache_directory: P) -> Self {
+        self.cache_directory = Some(cache_directory.into());
+        self
+    }
+
+    fn entry_path(cache_directory: &Path, key: u64, extension: &str) -> PathBuf {
+        cache_directory.join(format!("{key:016x}.{extension}"))
+    }
+
+    fn read_from_disk(cache_directory: &Path, key: u64) -> Option<CachedShader> {
+        let spirv = std::fs::read(Self::entry_path(cache_directory, key, "spv")).ok()?;
+        let dependencies = std::fs::read_to_string(Self::entry_path(cache_directory, key, "deps"))
+            .ok()?
+            .lines()
+            .map(|line| {
+                let (hash, path) = line.split_once(' ')?;
+                Some(ShaderDependency {
+                    path: PathBuf::from(path),
+                    hash: u64::from_str_radix(hash, 16).ok()?,
+                })
+            })
+            .collect::<Option<Vec<_>>>()?;
+        Some(CachedShader {
+            spirv,
+            dependencies,
+        })
+    }
+
+    fn write_to_disk(
+        cache_directory: &Path,
+        key: u64,
+        cached_shader: &CachedShader,
+    ) -> crate::Result<()> {
+        let mut dependencies = String::new();
+        for dependency in cached_shader.dependencies.iter() {
+            let path = dependency.path.to_str().context(format!(
+                "The shader dependency {:?} is not valid UTF-8",
+                dependency.path
+            ))?;
+            crate::verify!(
+                !path.contains(['\n', '\r']),
+                "The shader dependency {path:?} contains a line break"
+            );
+            dependencies.push_str(&format!("{:016x} {path}\n", dependency.hash));
+        }
+
+        std::fs::create_dir_all(cache_directory)?;
+        std::fs::write(
+            Self::entry_path(cache_directory, key, "spv"),
+            &cached_shader.spirv,
+        )?;
+        std::fs::write(Self::entry_path(cache_directory, key, "deps"), dependencies)?;
+        Ok(())
+    }
+
+    /// This function returns the SPIR-V of the `key` if it's cached in memory or on disk and
+    /// all included files are unchanged.
+    pub fn get(&mut self, key: u64) -> Option<Vec<u8>> {
+        self.get_entry(key)
+            .map(|cached_shader| cached_shader.spirv.clone())
+    }
+
+    /// This function returns the entry of the `key` like `get`, which also contains the
+    /// included files.
+    pub fn get_entry(&mut self, key: u64) -> Option<&CachedShader> {
+        if !self.entries.contains_key(&key) {
+            let cached_shader = Self::read_from_disk(self.cache_directory.as_deref()?, key)?;
+            self.entries.insert(key, cached_shader);
+        }
+
+        if !self.entries.get(&key)?.is_up_to_date() {
+            self.entries.remove(&key);
+            return None;
+        }
+        self.entries.get(&key)
+    }
+
+    /// This function caches the `spirv` of the `key`. If writing it to the cache directory
+    /// fails (e.g. because an included path can't be stored), it's still cached in memory and
+    /// the error is returned.
+    pub fn insert(
+        &mut self,
+        key: u64,
+        spirv: Vec<u8>,
+        dependencies: Vec<ShaderDependency>,
+    ) -> crate::Result<()> {
+        let cached_shader = CachedShader {
+            spirv,
+            dependencies,
+        };
+        let result = match self.cache_directory.as_deref() {
+            Some(cache_directory) => Self::write_to_disk(cache_directory, key, &cached_shader),
+            None => Ok(()),
+        };
+        self.entries.insert(key, cached_shader);
+        result
+    }
+
+    /// This function returns the number of entries in memory.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// This function removes all entries from memory. The cache directory is left untouched.
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stable_hash_matches_fnv1a() {
+        assert_eq!(0xcbf29ce484222325, stable_hash(b""));
+        assert_eq!(0xaf63dc4c8601ec8c, stable_hash(b"a"));
+    }
+
+    #[test]
+    fn entries_are_invalidated_by_changed_includes() {
+        let root = std::env::temp_dir().join(format!("voxelar-cache-{}", std::process::id()));
+        std::fs::create_dir_all(&root).unwrap();
+        let include = root.join("common.glsl");
+        std::fs::write(&include, "#define A 1").unwrap();
+
+        let mut cache = ShaderCompileCache::new().cache_directory(root.join("spirv"));
+        let dependencies = vec![ShaderDependency::new(include.clone(), "#define A 1")];
+        cache.insert(7, vec![1, 2, 3, 4], dependencies).unwrap();
+        assert_eq!(Some(vec![1, 2, 3, 4]), cache.get(7));
+
+        cache.clear();
+        assert_eq!(Some(vec![1, 2, 3, 4]), cache.get(7));
+        assert_eq!(None, cache.get(8));
+
+        std::fs::write(&include, "#define A 2").unwrap();
+        assert_eq!(None, cache.get(7));
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn dependencies_with_line_breaks_are_not_written_to_disk() {
+        let root = std::env::temp_dir().join(format!("voxelar-cache-deps-{}", std::process::id()));
+        let mut cache = ShaderCompileCache::new().cache_directory(&root);
+        let dependencies = vec![ShaderDependency::new(
+            PathBuf::from("include\n0000000000000000 other.glsl"),
+            "",
+        )];
+        assert!(cache.insert(3, vec![1, 2, 3, 4], dependencies).is_err());
+        assert!(!ShaderCompileCache::entry_path(&root, 3, "deps").exists());
+        assert_eq!(1, cache.len());
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+}
@@ -0,0 +1,255 @@
+//! This is a module that contains the `ShaderCompiler` structure, which compiles GLSL shaders
+//! at runtime with configurable compile options.
+//!
+//! In addition to the plain `compile_bytes` function, the compiler supports:
+//! - `#include` directives (see `IncludeResolver`)
+//! - Preprocessor macro definitions
+//! - The optimization level and target environment of the SPIR-V
+//! - A `ShaderCompileCache`, so that unchanged shaders are only compiled once (and, with a
+//!   cache directory, only once across runs)
+//!
+//! # Examples
+//!
+//! ```ignore
+//! let mut compiler = ShaderCompiler::new()
+//!     .add_include_path("assets/shaders/include")
+//!     .define_macro("MAX_LIGHTS", Some("16"))
+//!     .optimization_level(OptimizationLevel::Performance)
+//!     .target_env(TargetEnv::Vulkan, EnvVersion::Vulkan1_2)
+//!     .cache_directory("target/shader-cache");
+//! let compiled_bytes = compiler.compile_file("assets/shaders/block.frag", ShaderKind::Fragment)?;
+//! ```
+
+use std::cell::RefCell;
+use std::path::{Path, PathBuf};
+
+use shaderc::{CompileOptions, EnvVersion, OptimizationLevel, ResolvedInclude};
+use shaderc::{IncludeType, ShaderKind, TargetEnv};
+
+use crate::result::Context;
+
+use super::compile_cache::{ShaderCompileCache, ShaderDependency, StableHasher};
+use super::include_resolver::IncludeResolver;
+use super::SHADERC_COMPILER;
+
+/// The SPIR-V of a compiled shader and the files it included
+pub struct CompiledShaderSource {
+    pub spirv: Vec<u8>,
+    pub dependencies: Vec<ShaderDependency>,
+}
+
+pub struct ShaderCompiler {
+    include_resolver: IncludeResolver,
+    macro_definitions: Vec<(String, Option<String>)>,
+    optimization_level: OptimizationLevel,
+    target_env: Option<(TargetEnv, EnvVersion)>,
+    generate_debug_info: bool,
+    cache: Option<ShaderCompileCache>,
+}
+
+impl Default for ShaderCompiler {
+    fn default() -> Self {
+        Self {
+            include_resolver: IncludeResolver::new(),
+            macro_definitions: vec![],
+            optimization_level: OptimizationLevel::Zero,
+            target_env: None,
+            generate_debug_info: false,
+            cache: None,
+        }
+    }
+}
+
+impl ShaderCompiler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// This function adds a directory that `#include`d files are searched in.
+    pub fn add_include_path<P: Into<PathBuf>>(mut self, include_path: P) -> Self {
+        self.include_resolver = self.include_resolver.add_include_path(include_path);
+        self
+    }
+
+    pub fn include_resolver(mut self, include_resolver: IncludeResolver) -> Self {
+        self.include_resolver = include_resolver;
+        self
+    }
+
+    /// This function defines the macro `name` for all shaders, like `#define name value`.
+    pub fn define_macro(mut self, name: &str, value: Option<&str>) -> Self {
+        self.macro_definitions
+            .push((name.to_string(), value.map(str::to_string)));
+        self
+    }
+
+    pub fn optimization_level(mut self, optimization_level: OptimizationLevel) -> Self {
+        self.optimization_level = optimization_level;
+        self
+    }
+
+    pub fn target_env(mut self, target_env: TargetEnv, env_version: EnvVersion) -> Self {
+        self.target_env = Some((target_env, env_version));
+        self
+    }
+
+    pub fn generate_debug_info(mut self) -> Self {
+        self.generate_debug_info = true;
+        self
+    }
+
+    /// This function enables the in-memory compile cache.
+    pub fn cache(mut self) -> Self {
+        self.cache.get_or_insert_with(ShaderCompileCache::new);
+        self
+    }
+
+    /// This function enables the compile cache, which also stores the SPIR-V in the
+    /// `cache_directory`.
+    pub fn cache_directory<P: Into<PathBuf>>(mut self, cache_directory: P) -> Self {
+        self.cache = Some(ShaderCompileCache::new().cache_directory(cache_directory));
+        self
+    }
+
+    /// This function returns the cache key of the `source`, which includes all options that
+    /// affect the SPIR-V and the SPIR-V version of shaderc.
+    pub fn cache_key(&self, shader_kind: ShaderKind, source: &str, path: &str) -> u64 {
+        let mut hasher = StableHasher::new();
+        hasher.write_str(&format!("{:?}", shaderc::get_spirv_version()));
+        hasher.write_str(source);
+        hasher.write_str(path);
+        hasher.write_str(&format!("{shader_kind:?}"));
+        for include_path in self.include_resolver.include_paths() {
+            hasher.write_str(&include_path.to_string_lossy());
+        }
+        for (name, value) in self.macro_definitions.iter() {
+            hasher.write_str(name);
+            // The presence tag keeps `None` and `Some("")` apart
+            match value {
+                Some(value) => {
+                    hasher.write(&[1]);
+                    hasher.write_str(value);
+                }
+                None => hasher.write(&[0]),
+            }
+        }
+        hasher.write_str(&format!(
+            "{:?} {:?} {}",
+            self.optimization_level, self.target_env, self.generate_debug_info
+        ));
+        hasher.finish()
+    }
+
+    fn compile_options<'a>(
+        &'a self,
+        dependencies: &'a RefCell<Vec<ShaderDependency>>,
+    ) -> crate::Result<CompileOptions<'a>> {
+        let mut options =
+            CompileOptions::new().context("Unable to create compile options".to_string())?;
+
+        options.set_include_callback(move |requested, include_type, containing, depth| {
+            let relative = include_type == IncludeType::Relative;
+            let resolved = self
+                .include_resolver
+                .resolve(requested, relative, containing, depth)?;
+            let resolved_name = resolved.path.to_string_lossy().into_owned();
+            dependencies
+                .borrow_mut()
+                .push(ShaderDependency::new(resolved.path, &resolved.content));
+            Ok(ResolvedInclude {
+                resolved_name,
+                content: resolved.content,
+            })
+        });
+        for (name, value) in self.macro_definitions.iter() {
+            options.add_macro_definition(name, value.as_deref());
+        }
+        options.set_optimization_level(self.optimization_level);
+        if let Some((target_env, env_version)) = self.target_env {
+            options.set_target_env(target_env, env_version as u32);
+        }
+        if self.generate_debug_info {
+            options.set_generate_debug_info();
+        }
+        Ok(options)
+    }
+
+    /// This function compiles the `source` without using the cache and returns the SPIR-V
+    /// with the files it included. The `path` is used to resolve relative includes.
+    pub fn compile_uncached(
+        &self,
+        shader_kind: ShaderKind,
+        source: &str,
+        path: &str,
+    ) -> crate::Result<CompiledShaderSource> {
+        let dependencies = RefCell::new(vec![]);
+        let options = self.compile_options(&dependencies)?;
+
+        let binary_result = SHADERC_COMPILER
+            .compile_into_spirv(source, shader_kind, path, "main", Some(&options))
+            .context(format!("Unable to compile shader {path}"))?;
+        let spirv = binary_result.as_binary_u8().to_vec();
+        drop(options);
+
+        let mut dependencies = dependencies.into_inner();
+        dependencies.sort_by(|a, b| a.path.cmp(&b.path));
+        dependencies.dedup_by(|a, b| a.path == b.path);
+        Ok(CompiledShaderSource {
+            spirv,
+            dependencies,
+        })
+    }
+
+    /// This function compiles the `source`, or returns the cached SPIR-V if it was compiled
+    /// with the same options before and its includes didn't change.
+    ///
+    /// If the SPIR-V can't be written to the cache directory, the error is returned. The
+    /// SPIR-V is still cached in memory in that case.
+    pub fn compile_with_dependencies(
+        &mut self,
+        shader_kind: ShaderKind,
+        source: &str,
+        path: &str,
+    ) -> crate::Result<CompiledShaderSource> {
+        let key = self.cache_key(shader_kind, source, path);
+        if let Some(cache) = self.cache.as_mut() {
+            if let Some(cached_shader) = cache.get_entry(key) {
+                return Ok(CompiledShaderSource {
+                    spirv: cached_shader.spirv.clone(),
+                    dependencies: cached_shader.dependencies.clone(),
+                });
+            }
+        }
+
+        let compiled = self.compile_uncached(shader_kind, source, path)?;
+        if let Some(cache) = self.cache.as_mut() {
+            cache.insert(key, compiled.spirv.clone(), compiled.dependencies.clone())?;
+        }
+        Ok(compiled)
+    }
+
+    pub fn compile(
+        &mut self,
+        shader_kind: ShaderKind,
+        source: &str,
+        path: &str,
+    ) -> crate::Result<Vec<u8>> {
+        Ok(self
+            .compile_with_dependencies(shader_kind, source, path)?
+            .spirv)
+    }
+
+    /// This function reads the shader source at `path` and compiles it.
+    pub fn compile_file<P: AsRef<Path>>(
+        &mut self,
+        path: P,
+        shader_kind: ShaderKind,
+    ) -> crate::Result<Vec<u8>> {
+        let path = path.as_ref();
+        let path_str = path
+            .to_str()
+            .context(format!("The shader path {path:?} is not valid UTF-8"))?;
+        let source = std::fs::read_to_string(path)?;
+        self.compile(shader_kind, &source, path_str)
+    }
+}
@@ -0,0 +1,350 @@
+//! This is a module that contains the `ShaderHotReloader` structure, which recompiles GLSL
+//! shaders and recreates the pipelines that use them when their source files change.
+//!
+//! Every pipeline is registered with the paths of its shaders and a function that builds the
+//! pipeline from the compiled modules. `ShaderHotReloader::reload_changed` should be called
+//! after the fences of the current frame were waited for (see
+//! `VulkanContext::reload_changed_shaders`). The old pipeline and modules are pushed to the
+//! deletion queue of the frame, because previous frames may still use them. If a shader fails
+//! to compile or the pipeline can't be built, the error is returned for that pipeline and the
+//! old pipeline is kept.
+//!
+//! The shaders are compiled with the `ShaderCompiler` of the reloader, so files that they
+//! `#include` are watched as well.
+//!
+//! # Examples
+//!
+//! ```ignore
+//! let pipeline_id = hot_reloader.add_pipeline(
+//!     vulkan_context.logical_device()?,
+//!     &[("shaders/block.vert", ShaderKind::Vertex), ("shaders/block.frag", ShaderKind::Fragment)],
+//!     move |logical_device, modules| {
+//!         GraphicsPipelineBuilder::new()
+//!             .add_shader_stage_from_module(&modules[0])
+//!             .add_shader_stage_from_module(&modules[1])
+//!             // ...
+//!             .build(logical_device, &render_pass, &pipeline_layout)
+//!     },
+//! )?;
+//!
+//! // Every frame
+//! vulkan_context.wait_for_current_frame_draw_buffer_fences()?;
+//! for (id, result) in vulkan_context.reload_changed_shaders(&mut hot_reloader)? {
+//!     if let Err(err) = result {
+//!         eprintln!("Failed to reload the shaders of pipeline {id}: {err}");
+//!     }
+//! }
+//! let pipeline = hot_reloader.pipeline(pipeline_id).unwrap();
+//! ```
+
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use ash::vk::{Pipeline, ShaderStageFlags};
+use shaderc::ShaderKind;
+
+use crate::result::Context;
+use crate::vulkan::deletion_queue::DeletionQueue;
+use crate::vulkan::logical_device::SetUpLogicalDevice;
+
+use super::compiler::ShaderCompiler;
+use super::watcher::{FileWatcher, WatchedFileId};
+use super::CompiledShaderModule;
+
+/// The ID of a pipeline in a `ShaderHotReloader`
+pub type HotReloadPipelineId = usize;
+
+type PipelineBuildFn =
+    Box<dyn Fn(&SetUpLogicalDevice, &[CompiledShaderModule]) -> crate::Result<Pipeline>>;
+
+/// This function returns the shader stage of the `shader_kind`.
+pub fn shader_stage_for_kind(shader_kind: ShaderKind) -> crate::Result<ShaderStageFlags> {
+    let stage = match shader_kind {
+        ShaderKind::Vertex => ShaderStageFlags::VERTEX,
+        ShaderKind::Fragment => ShaderStageFlags::FRAGMENT,
+        ShaderKind::Compute => ShaderStageFlags::COMPUTE,
+        ShaderKind::Geometry => ShaderStageFlags::GEOMETRY,
+        ShaderKind::TessControl => ShaderStageFlags::TESSELLATION_CONTROL,
+        ShaderKind::TessEvaluation => ShaderStageFlags::TESSELLATION_EVALUATION,
+        ShaderKind::Task => ShaderStageFlags::TASK_EXT,
+        ShaderKind::Mesh => ShaderStageFlags::MESH_EXT,
+        shader_kind => crate::bail!(
+            "The shader kind {:?} can't be reloaded, use an explicit shader kind",
+            shader_kind
+        ),
+    };
+    Ok(stage)
+}
+
+/// A shader module that was loaded from a file, with the paths of the files it included
+struct LoadedShaderModule {
+    module: CompiledShaderModule,
+    dependencies: Vec<PathBuf>,
+}
+
+/// This function reads the GLSL source at `path`, compiles it with the `compiler` and creates
+/// its shader module.
+fn load_shader_module(
+    logical_device: &SetUpLogicalDevice,
+    compiler: &mut ShaderCompiler,
+    path: &Path,
+    shader_kind: ShaderKind,
+) -> crate::Result<LoadedShaderModule> {
+    let stage = shader_stage_for_kind(shader_kind)?;
+    let path_str = path
+        .to_str()
+        .context(format!("The shader path {path:?} is not valid UTF-8"))?;
+    let source = std::fs::read_to_string(path)?;
+    let compiled = compiler.compile_with_dependencies(shader_kind, &source, path_str)?;
+    let module = unsafe {
+        CompiledShaderModule::create_shader_of_stage(compiled.spirv, logical_device, stage)?
+    };
+    Ok(LoadedShaderModule {
+        module,
+        dependencies: compiled
+            .dependencies
+            .into_iter()
+            .map(|dependency| dependency.path)
+            .collect(),
+    })
+}
+
+struct HotReloadShader {
+    file_id: WatchedFileId,
+    dependency_ids: Vec<WatchedFileId>,
+    shader_kind: ShaderKind,
+}
+
+impl HotReloadShader {
+    fn is_changed(&self, changed_files: &[WatchedFileId]) -> bool {
+        changed_files.contains(&self.file_id)
+            || self
+                .dependency_ids
+                .iter()
+                .any(|dependency_id| changed_files.contains(dependency_id))
+    }
+}
+
+struct HotReloadPipeline {
+    shaders: Vec<HotReloadShader>,
+    modules: Vec<CompiledShaderModule>,
+    pipeline: Pipeline,
+    build: PipelineBuildFn,
+}
+
+#[derive(Default)]
+pub struct ShaderHotReloader {
+    watcher: FileWatcher,
+    compiler: ShaderCompiler,
+    pipelines: Vec<HotReloadPipeline>,
+}
+
+impl ShaderHotReloader {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// This function sets the minimum time between two checks of the source files.
+    pub fn poll_interval(mut self, poll_interval: Duration) -> Self {
+        self.watcher = self.watcher.poll_interval(poll_interval);
+        self
+    }
+
+    /// This function sets the compiler of the shaders, e.g. to add include paths or macro
+    /// definitions. It should be set before the first pipeline is added.
+    pub fn compiler(mut self, compiler: ShaderCompiler) -> Self {
+        self.compiler = compiler;
+        self
+    }
+
+    /// This function compiles the `shaders` and builds a pipeline from them with `build`,
+    /// which receives the modules in the order of the `shaders`. The function is called again
+    /// whenever one of the shaders changed.
+    pub fn add_pipeline<F>(
+        &mut self,
+        logical_device: &SetUpLogicalDevice,
+        shaders: &[(&str, ShaderKind)],
+        build: F,
+    ) -> crate::Result<HotReloadPipelineId>
+    where
+        F: Fn(&SetUpLogicalDevice, &[CompiledShaderModule]) -> crate::Result<Pipeline> + 'static,
+    {
+        let mut modules = Vec::with_capacity(shaders.len());
+        let mut dependencies = Vec::with_capacity(shaders.len());
+        let result = shaders.iter().try_for_each(|(path, shader_kind)| {
+            let loaded = load_shader_module(
+                logical_device,
+                &mut self.compiler,
+                Path::new(path),
+                *shader_kind,
+            )?;
+            modules.push(loaded.module);
+            dependencies.push(loaded.dependencies);
+            Ok(())
+        });
+        let result = result.and_then(|_| build(logical_device, &modules));
+        let pipeline = match result {
+            Ok(pipeline) => pipeline,
+            Err(err) => {
+                for module in modules.iter_mut() {
+                    module.destroy(logical_device);
+                }
+                return Err(err);
+            }
+        };
+
+        let shaders = shaders
+            .iter()
+            .zip(dependencies)
+            .map(|((path, shader_kind), dependencies)| HotReloadShader {
+                file_id: self.watcher.watch(*path),
+                dependency_ids: self.watch_all(dependencies),
+                shader_kind: *shader_kind,
+            })
+            .collect();
+        self.pipelines.push(HotReloadPipeline {
+            shaders,
+            modules,
+            pipeline,
+            build: Box::new(build),
+        });
+        Ok(self.pipelines.len() - 1)
+    }
+
+    fn watch_all(&mut self, paths: Vec<PathBuf>) -> Vec<WatchedFileId> {
+        paths
+            .into_iter()
+            .map(|path| self.watcher.watch(path))
+            .collect()
+    }
+
+    /// This function returns the current pipeline of the `id`, which changes when it's
+    /// reloaded.
+    pub fn pipeline(&self, id: HotReloadPipelineId) -> Option<Pipeline> {
+        self.pipelines.get(id).map(|pipeline| pipeline.pipeline)
+    }
+
+    /// This function returns the current shader modules of the pipeline of the `id`.
+    pub fn modules(&self, id: HotReloadPipelineId) -> Option<&[CompiledShaderModule]> {
+        self.pipelines
+            .get(id)
+            .map(|pipeline| pipeline.modules.as_slice())
+    }
+
+    /// This function recompiles the shaders whose source files changed and rebuilds the
+    /// pipelines that use them. The replaced pipelines and modules are pushed to the
+    /// `deletion_queue`. It returns the IDs of the pipelines whose shaders changed, together
+    /// with the result of the reload; pipelines that failed to reload keep the old pipeline.
+    pub fn reload_changed(
+        &mut self,
+        logical_device: &SetUpLogicalDevice,
+        deletion_queue: &DeletionQueue,
+    ) -> Vec<(HotReloadPipelineId, crate::Result<()>)> {
+        let changed_files = self.watcher.poll();
+        if changed_files.is_empty() {
+            return vec![];
+        }
+
+        let mut results = vec![];
+        let mut new_dependencies = vec![];
+        for (id, pipeline) in self.pipelines.iter_mut().enumerate() {
+            let changed_shaders = pipeline
+                .shaders
+                .iter()
+                .enumerate()
+                .filter(|(_, shader)| shader.is_changed(&changed_files))
+                .map(|(index, _)| index)
+                .collect::<Vec<_>>();
+            if changed_shaders.is_empty() {
+                continue;
+            }
+
+            let result = Self::reload_pipeline(
+                &self.watcher,
+                &mut self.compiler,
+                logical_device,
+                pipeline,
+                &changed_shaders,
+            );
+            match result {
+                Ok((old_pipeline, old_modules, dependencies)) => {
+                    deletion_queue.push(old_pipeline);
+                    deletion_queue.push(old_modules);
+                    new_dependencies.push((id, changed_shaders, dependencies));
+                    results.push((id, Ok(())));
+                }
+                Err(err) => results.push((id, Err(err))),
+            }
+        }
+
+        // The includes of the reloaded shaders may have changed, so they are watched again
+        for (id, changed_shaders, dependencies) in new_dependencies {
+            for (index, dependencies) in changed_shaders.into_iter().zip(dependencies) {
+                let dependency_ids = self.watch_all(dependencies);
+                self.pipelines[id].shaders[index].dependency_ids = dependency_ids;
+            }
+        }
+        results
+    }
+
+    #[allow(clippy::type_complexity)]
+    fn reload_pipeline(
+        watcher: &FileWatcher,
+        compiler: &mut ShaderCompiler,
+        logical_device: &SetUpLogicalDevice,
+        pipeline: &mut HotReloadPipeline,
+        changed_shaders: &[usize],
+    ) -> crate::Result<(Pipeline, Vec<CompiledShaderModule>, Vec<Vec<PathBuf>>)> {
+        let mut new_modules = Vec::with_capacity(changed_shaders.len());
+        let mut dependencies = Vec::with_capacity(changed_shaders.len());
+        for index in changed_shaders.iter() {
+            let shader = &pipeline.shaders[*index];
+            let path = watcher
+                .path(shader.file_id)
+                .context(format!("The shader file {} is not watched", shader.file_id))?;
+            match load_shader_module(logical_device, compiler, path, shader.shader_kind) {
+                Ok(loaded) => {
+                    new_modules.push(loaded.module);
+                    dependencies.push(loaded.dependencies);
+                }
+                Err(err) => {
+                    for module in new_modules.iter_mut() {
+                        module.destroy(logical_device);
+                    }
+                    crate::bail!("Unable to compile {:?}: {}", path, err);
+                }
+            }
+        }
+
+        // Swap the new modules in, so that the build function sees the complete set
+        for (index, module) in changed_shaders.iter().zip(new_modules.iter_mut()) {
+            std::mem::swap(&mut pipeline.modules[*index], module);
+        }
+
+        match (pipeline.build)(logical_device, &pipeline.modules) {
+            Ok(new_pipeline) => {
+                let old_pipeline = std::mem::replace(&mut pipeline.pipeline, new_pipeline);
+                Ok((old_pipeline, new_modules, dependencies))
+            }
+            Err(err) => {
+                for (index, module) in changed_shaders.iter().zip(new_modules.iter_mut()) {
+                    std::mem::swap(&mut pipeline.modules[*index], module);
+                    module.destroy(logical_device);
+                }
+                Err(err)
+            }
+        }
+    }
+
+    pub fn destroy(&mut self, logical_device: &SetUpLogicalDevice) {
+        for pipeline in self.pipelines.iter_mut() {
+            unsafe {
+                logical_device.destroy_pipeline(pipeline.pipeline, None);
+            }
+            for module in pipeline.modules.iter_mut() {
+                module.destroy(logical_device);
+            }
+        }
+        self.pipelines.clear();
+    }
+}
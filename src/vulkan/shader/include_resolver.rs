@@ -0,0 +1,145 @@
+//! This is a module that contains the `IncludeResolver` structure, which resolves the
+//! `#include` directives of GLSL shaders to files.
+//!
+//! `#include "file"` is resolved relative to the directory of the including file first and
+//! then in the include paths, `#include <file>` is only resolved in the include paths.
+
+use std::path::{Path, PathBuf};
+
+/// The default maximum depth of nested includes
+pub const DEFAULT_MAX_INCLUDE_DEPTH: usize = 20;
+
+/// A file that an include directive was resolved to
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ResolvedIncludeFile {
+    pub path: PathBuf,
+    pub content: String,
+}
+
+#[derive(Clone, Debug)]
+pub struct IncludeResolver {
+    include_paths: Vec<PathBuf>,
+    max_depth: usize,
+}
+
+impl Default for IncludeResolver {
+    fn default() -> Self {
+        Self {
+            include_paths: vec![],
+            max_depth: DEFAULT_MAX_INCLUDE_DEPTH,
+        }
+    }
+}
+
+impl IncludeResolver {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// This function adds a directory that included files are searched in. The directories are
+    /// searched in the order they were added.
+    pub fn add_include_path<P: Into<PathBuf>>(mut self, include_path: P) -> Self {
+        self.include_paths.push(include_path.into());
+        self
+    }
+
+    pub fn max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    pub fn include_paths(&self) -> &[PathBuf] {
+        &self.include_paths
+    }
+
+    /// This function returns the candidate paths of the `requested` file in the order they are
+    /// tried. `relative` is true for `#include "..."` directives.
+    pub fn candidates(&self, requested: &str, relative: bool, containing: &str) -> Vec<PathBuf> {
+        let mut candidates = vec![];
+        if relative {
+            let containing_directory = Path::new(containing).parent().unwrap_or(Path::new(""));
+            candidates.push(containing_directory.join(requested));
+        }
+        candidates.extend(
+            self.include_paths
+                .iter()
+                .map(|include_path| include_path.join(requested)),
+        );
+        candidates
+    }
+
+    /// This function resolves the `requested` file, which is included by the file `containing`
+    /// at the nesting `depth`. The error is a message for the shader compiler.
+    pub fn resolve(
+        &self,
+        requested: &str,
+        relative: bool,
+        containing: &str,
+        depth: usize,
+    ) -> Result<ResolvedIncludeFile, String> {
+        if depth > self.max_depth {
+            return Err(format!(
+                "The include depth of {} was exceeded, is {requested} included recursively?",
+                self.max_depth
+            ));
+        }
+
+        let candidates = self.candidates(requested, relative, containing);
+        for candidate in candidates.iter() {
+            if !candidate.is_file() {
+                continue;
+            }
+
+            let content = std::fs::read_to_string(candidate)
+                .map_err(|err| format!("Unable to read {}: {err}", candidate.display()))?;
+            let path = std::fs::canonicalize(candidate).unwrap_or_else(|_| candidate.clone());
+            return Ok(ResolvedIncludeFile { path, content });
+        }
+
+        Err(format!(
+            "Unable to find {requested} (included by {containing}), searched {candidates:?}"
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn relative_includes_are_searched_next_to_the_including_file_first() {
+        let root = std::env::temp_dir().join(format!("voxelar-include-{}", std::process::id()));
+        let shader_dir = root.join("shaders");
+        let include_dir = root.join("include");
+        std::fs::create_dir_all(&shader_dir).unwrap();
+        std::fs::create_dir_all(&include_dir).unwrap();
+        std::fs::write(shader_dir.join("common.glsl"), "// local").unwrap();
+        std::fs::write(include_dir.join("common.glsl"), "// global").unwrap();
+
+        let resolver = IncludeResolver::new().add_include_path(&include_dir);
+        let containing = shader_dir.join("block.frag");
+        let containing = containing.to_str().unwrap();
+
+        let local = resolver
+            .resolve("common.glsl", true, containing, 1)
+            .unwrap();
+        assert_eq!("// local", local.content);
+        let global = resolver
+            .resolve("common.glsl", false, containing, 1)
+            .unwrap();
+        assert_eq!("// global", global.content);
+        assert!(resolver
+            .resolve("missing.glsl", true, containing, 1)
+            .is_err());
+        assert!(resolver
+            .resolve(
+                "common.glsl",
+                true,
+                containing,
+                DEFAULT_MAX_INCLUDE_DEPTH + 1
+            )
+            .is_err());
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
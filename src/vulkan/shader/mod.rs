@@ -1,3 +1,26 @@
+//! This is a module that provides an abstraction for shader module creation, shader compilation
+//! (with the `shaderc-crate` feature) and SPIR-V reflection.
+//!
+//! With the `shaderc-crate` feature, `compile_bytes` compiles GLSL with the default options,
+//! while a `ShaderCompiler` supports include paths, macro definitions, optimization levels,
+//! target environments and caching of the compiled SPIR-V.
+//!
+//! - compile\_cache: Provides a cache of compiled SPIR-V that is keyed by a hash of the shader source and its compile options
+//! - compiler: Provides a configurable runtime shader compiler with include resolution, macro definitions and caching (only with the `shaderc-crate` feature)
+//! - hot\_reload: Provides a reloader that recompiles changed GLSL shaders and rebuilds the pipelines that use them (only with the `shaderc-crate` feature)
+//! - include\_resolver: Provides the resolution of `#include` directives relative to the including file and in include paths
+//! - reflection: Provides a SPIR-V parser that extracts the descriptor bindings, push constants, vertex inputs and workgroup size of shader modules
+//! - watcher: Provides a file watcher that detects changed files by polling their modification times
+
+pub mod compile_cache;
+#[cfg(feature = "shaderc-crate")]
+pub mod compiler;
+#[cfg(feature = "shaderc-crate")]
+pub mod hot_reload;
+pub mod include_resolver;
+pub mod reflection;
+pub mod watcher;
+
 use std::ffi::CString;
 use std::io::Cursor;
 
@@ -7,7 +30,7 @@ use ash::util::read_spv;
 use {
     shaderc::*,
     lazy_static::lazy_static,
-    crate::result::Context,
+    self::compiler::ShaderCompiler,
 };
 
 use ash::vk::{
@@ -16,6 +39,8 @@ use ash::vk::{
 
 use super::logical_device::SetUpLogicalDevice;
 
+use self::reflection::ShaderReflection;
+
 #[cfg(feature = "shaderc-crate")]
 lazy_static! {
     static ref SHADERC_COMPILER: Compiler = Compiler::new().unwrap();
@@ -25,6 +50,8 @@ pub struct CompiledShaderModule {
     pub shader_module: ShaderModule,
     pub stage: ShaderStageFlags,
     pub entry_name: CString,
+    /// The SPIR-V code of the module, which is kept for reflection
+    pub code: Vec<u32>,
 }
 
 impl CompiledShaderModule {
@@ -42,6 +69,7 @@ impl CompiledShaderModule {
             shader_module,
             stage,
             entry_name,
+            code,
         })
     }
 
@@ -62,6 +90,20 @@ impl CompiledShaderModule {
         }
     }
 
+    /// This function reflects the entry point of the module, which must belong to the stage of
+    /// the module.
+    pub fn reflect(&self) -> crate::Result<ShaderReflection> {
+        let reflection = ShaderReflection::parse(&self.code, self.entry_name.to_str()?)?;
+        crate::verify!(
+            reflection.stage == self.stage,
+            "The entry point {} is a {:?} shader, but the module was created for the {:?} stage",
+            (reflection.entry_point),
+            (reflection.stage),
+            (self.stage)
+        );
+        Ok(reflection)
+    }
+
     pub fn destroy(&mut self, logical_device: &SetUpLogicalDevice) {
         unsafe {
             logical_device.destroy_shader_module(self.shader_module, None);
@@ -69,28 +111,20 @@ impl CompiledShaderModule {
     }
 }
 
+/// This function compiles the `source` with the default options of a `ShaderCompiler`, which
+/// resolves `#include "..."` directives relative to the `path`.
 #[cfg(feature = "shaderc-crate")]
 pub fn compile_bytes(shader_kind: ShaderKind, source: &str, path: &str) -> crate::Result<Vec<u8>> {
-    let options = CompileOptions::new().context("Unable to create compile options".to_string())?;
-
-    let binary_result = SHADERC_COMPILER
-        .compile_into_spirv(source, shader_kind, path, "main", Some(&options))
-        .context("Unable to compile shader".to_string())?;
-    let bytes = binary_result.as_binary_u8().to_vec();
-    Ok(bytes)
+    let compiled = ShaderCompiler::new().compile_uncached(shader_kind, source, path)?;
+    Ok(compiled.spirv)
 }
 
 #[cfg(feature = "shaderc-crate")]
 pub fn compile_bytes_with_debug_info(shader_kind: ShaderKind, source: &str, path: &str) -> crate::Result<Vec<u8>> {
-    let mut options = CompileOptions::new().context("Unable to create compile options".to_string())?;
-
-    options.set_generate_debug_info();
-
-    let binary_result = SHADERC_COMPILER
-        .compile_into_spirv(source, shader_kind, path, "main", Some(&options))
-        .context("Unable to compile shader".to_string())?;
-    let bytes = binary_result.as_binary_u8().to_vec();
-    Ok(bytes)
+    let compiled = ShaderCompiler::new()
+        .generate_debug_info()
+        .compile_uncached(shader_kind, source, path)?;
+    Ok(compiled.spirv)
 }
 
 #[cfg(feature = "shaderc-crate")]
@@ -0,0 +1,1283 @@
+//! This is a module that provides a SPIR-V parser for reflecting shader modules.
+//!
+//! `ShaderReflection::parse` extracts the descriptor bindings, the push constant range, the
+//! inputs and outputs and the compute workgroup size of an entry point. The reflections of all
+//! stages of a pipeline are merged into a `PipelineReflection`, which checks that the stages agree
+//! on the bindings and on the interface between the vertex and the fragment shader, and which
+//! builds the descriptor set layouts and the pipeline layout from them.
+//!
+//! SPIR-V doesn't distinguish dynamic and non-dynamic buffers, so dynamic bindings have to be
+//! marked with `PipelineReflection::set_descriptor_type`.
+//!
+//! # Examples
+//!
+//! ```ignore
+//! let reflection = PipelineReflection::from_shader_modules(&[&vertex_shader, &fragment_shader])?;
+//! let set_layouts = reflection.build_descriptor_set_layouts(logical_device, 0)?;
+//! let pipeline_layout = reflection.build_pipeline_layout(logical_device, &set_layouts)?;
+//! ```
+
+use std::collections::{BTreeMap, HashMap};
+
+use ash::vk::VertexInputAttributeDescription;
+use ash::vk::{DescriptorType, Format, PushConstantRange, ShaderStageFlags};
+
+use crate::vulkan::descriptors::descriptor_set_layout::SetUpDescriptorSetLayout;
+use crate::vulkan::descriptors::descriptor_set_layout_builder::DescriptorSetLayoutBuilder;
+use crate::vulkan::logical_device::SetUpLogicalDevice;
+use crate::vulkan::pipeline_layout::SetUpPipelineLayout;
+use crate::vulkan::pipeline_layout_builder::PipelineLayoutBuilder;
+
+use super::CompiledShaderModule;
+
+const SPIRV_MAGIC: u32 = 0x0723_0203;
+const SPIRV_HEADER_LENGTH: usize = 5;
+
+const OP_NAME: u32 = 5;
+const OP_ENTRY_POINT: u32 = 15;
+const OP_EXECUTION_MODE: u32 = 16;
+const OP_TYPE_BOOL: u32 = 20;
+const OP_TYPE_INT: u32 = 21;
+const OP_TYPE_FLOAT: u32 = 22;
+const OP_TYPE_VECTOR: u32 = 23;
+const OP_TYPE_MATRIX: u32 = 24;
+const OP_TYPE_IMAGE: u32 = 25;
+const OP_TYPE_SAMPLER: u32 = 26;
+const OP_TYPE_SAMPLED_IMAGE: u32 = 27;
+const OP_TYPE_ARRAY: u32 = 28;
+const OP_TYPE_RUNTIME_ARRAY: u32 = 29;
+const OP_TYPE_STRUCT: u32 = 30;
+const OP_TYPE_POINTER: u32 = 32;
+const OP_CONSTANT: u32 = 43;
+const OP_SPEC_CONSTANT: u32 = 50;
+const OP_VARIABLE: u32 = 59;
+const OP_DECORATE: u32 = 71;
+const OP_MEMBER_DECORATE: u32 = 72;
+const OP_TYPE_ACCELERATION_STRUCTURE: u32 = 5341;
+
+const DECORATION_BUFFER_BLOCK: u32 = 3;
+const DECORATION_ARRAY_STRIDE: u32 = 6;
+const DECORATION_MATRIX_STRIDE: u32 = 7;
+const DECORATION_BUILT_IN: u32 = 11;
+const DECORATION_LOCATION: u32 = 30;
+const DECORATION_BINDING: u32 = 33;
+const DECORATION_DESCRIPTOR_SET: u32 = 34;
+const DECORATION_OFFSET: u32 = 35;
+
+const STORAGE_CLASS_UNIFORM_CONSTANT: u32 = 0;
+const STORAGE_CLASS_INPUT: u32 = 1;
+const STORAGE_CLASS_UNIFORM: u32 = 2;
+const STORAGE_CLASS_OUTPUT: u32 = 3;
+const STORAGE_CLASS_PUSH_CONSTANT: u32 = 9;
+const STORAGE_CLASS_STORAGE_BUFFER: u32 = 12;
+
+const EXECUTION_MODE_LOCAL_SIZE: u32 = 17;
+
+const IMAGE_DIM_BUFFER: u32 = 5;
+const IMAGE_DIM_SUBPASS_DATA: u32 = 6;
+
+/// This function returns the shader stage of a SPIR-V execution model.
+fn stage_of_execution_model(execution_model: u32) -> Option<ShaderStageFlags> {
+    match execution_model {
+        0 => Some(ShaderStageFlags::VERTEX),
+        1 => Some(ShaderStageFlags::TESSELLATION_CONTROL),
+        2 => Some(ShaderStageFlags::TESSELLATION_EVALUATION),
+        3 => Some(ShaderStageFlags::GEOMETRY),
+        4 => Some(ShaderStageFlags::FRAGMENT),
+        5 => Some(ShaderStageFlags::COMPUTE),
+        5364 => Some(ShaderStageFlags::TASK_EXT),
+        5365 => Some(ShaderStageFlags::MESH_EXT),
+        _ => None,
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum SpirvType {
+    Bool,
+    Int {
+        width: u32,
+        signed: bool,
+    },
+    Float {
+        width: u32,
+    },
+    Vector {
+        component_type: u32,
+        component_count: u32,
+    },
+    Matrix {
+        column_type: u32,
+        column_count: u32,
+    },
+    Image {
+        dim: u32,
+        sampled: u32,
+    },
+    Sampler,
+    SampledImage,
+    Array {
+        element_type: u32,
+        length_id: u32,
+    },
+    RuntimeArray {
+        element_type: u32,
+    },
+    Struct {
+        member_types: Vec<u32>,
+    },
+    Pointer {
+        storage_class: u32,
+        pointee_type: u32,
+    },
+    AccelerationStructure,
+}
+
+#[derive(Clone, Debug, Default)]
+struct Decorations {
+    descriptor_set: Option<u32>,
+    binding: Option<u32>,
+    location: Option<u32>,
+    array_stride: Option<u32>,
+    built_in: bool,
+    buffer_block: bool,
+}
+
+#[derive(Clone, Debug, Default)]
+struct MemberDecorations {
+    offset: Option<u32>,
+    matrix_stride: Option<u32>,
+}
+
+struct EntryPoint {
+    stage: ShaderStageFlags,
+    id: u32,
+    name: String,
+    interface: Vec<u32>,
+}
+
+struct Variable {
+    id: u32,
+    type_id: u32,
+    storage_class: u32,
+}
+
+/// The declarations of a SPIR-V module that are relevant for reflection
+#[derive(Default)]
+struct SpirvModule {
+    names: HashMap<u32, String>,
+    entry_points: Vec<EntryPoint>,
+    local_sizes: HashMap<u32, [u32; 3]>,
+    types: HashMap<u32, SpirvType>,
+    constants: HashMap<u32, u32>,
+    variables: Vec<Variable>,
+    decorations: HashMap<u32, Decorations>,
+    member_decorations: HashMap<(u32, u32), MemberDecorations>,
+}
+
+/// This function decodes a nul-terminated literal string and returns it together with the
+/// number of words it occupies.
+fn parse_literal_string(words: &[u32]) -> (String, usize) {
+    let mut bytes = vec![];
+    for (index, word) in words.iter().enumerate() {
+        for byte in word.to_le_bytes() {
+            if byte == 0 {
+                return (String::from_utf8_lossy(&bytes).into_owned(), index + 1);
+            }
+            bytes.push(byte);
+        }
+    }
+    (String::from_utf8_lossy(&bytes).into_owned(), words.len())
+}
+
+impl SpirvModule {
+    fn parse(code: &[u32]) -> crate::Result<Self> {
+        crate::verify!(
+            code.len() >= SPIRV_HEADER_LENGTH && code[0] == SPIRV_MAGIC,
+            "The shader code is not a valid SPIR-V module"
+        );
+
+        let mut module = Self::default();
+        let mut position = SPIRV_HEADER_LENGTH;
+        while position < code.len() {
+            let word_count = (code[position] >> 16) as usize;
+            let opcode = code[position] & 0xffff;
+            crate::verify!(
+                word_count > 0 && position + word_count <= code.len(),
+                "The SPIR-V instruction at word {} has an invalid length of {}",
+                position,
+                word_count
+            );
+
+            let operands = &code[position + 1..position + word_count];
+            module.parse_instruction(opcode, operands)?;
+            position += word_count;
+        }
+        Ok(module)
+    }
+
+    fn parse_instruction(&mut self, opcode: u32, operands: &[u32]) -> crate::Result<()> {
+        let operand = |index: usize| -> crate::Result<u32> {
+            match operands.get(index) {
+                Some(operand) => Ok(*operand),
+                None => crate::bail!(
+                    "The SPIR-V instruction with opcode {} has too few operands",
+                    opcode
+                ),
+            }
+        };
+
+        match opcode {
+            OP_NAME => {
+                let (name, _) = parse_literal_string(&operands[1.min(operands.len())..]);
+                self.names.insert(operand(0)?, name);
+            }
+            OP_ENTRY_POINT => {
+                let (name, name_length) = parse_literal_string(&operands[2.min(operands.len())..]);
+                if let Some(stage) = stage_of_execution_model(operand(0)?) {
+                    self.entry_points.push(EntryPoint {
+                        stage,
+                        id: operand(1)?,
+                        name,
+                        interface: operands[(2 + name_length).min(operands.len())..].to_vec(),
+                    });
+                }
+            }
+            OP_EXECUTION_MODE if operands.get(1) == Some(&EXECUTION_MODE_LOCAL_SIZE) => {
+                self.local_sizes
+                    .insert(operand(0)?, [operand(2)?, operand(3)?, operand(4)?]);
+            }
+            OP_TYPE_BOOL => {
+                self.types.insert(operand(0)?, SpirvType::Bool);
+            }
+            OP_TYPE_INT => {
+                let int_type = SpirvType::Int {
+                    width: operand(1)?,
+                    signed: operand(2)? != 0,
+                };
+                self.types.insert(operand(0)?, int_type);
+            }
+            OP_TYPE_FLOAT => {
+                let float_type = SpirvType::Float { width: operand(1)? };
+                self.types.insert(operand(0)?, float_type);
+            }
+            OP_TYPE_VECTOR => {
+                let vector_type = SpirvType::Vector {
+                    component_type: operand(1)?,
+                    component_count: operand(2)?,
+                };
+                self.types.insert(operand(0)?, vector_type);
+            }
+            OP_TYPE_MATRIX => {
+                let matrix_type = SpirvType::Matrix {
+                    column_type: operand(1)?,
+                    column_count: operand(2)?,
+                };
+                self.types.insert(operand(0)?, matrix_type);
+            }
+            OP_TYPE_IMAGE => {
+                let image_type = SpirvType::Image {
+                    dim: operand(2)?,
+                    sampled: operand(6)?,
+                };
+                self.types.insert(operand(0)?, image_type);
+            }
+            OP_TYPE_SAMPLER => {
+                self.types.insert(operand(0)?, SpirvType::Sampler);
+            }
+            OP_TYPE_SAMPLED_IMAGE => {
+                self.types.insert(operand(0)?, SpirvType::SampledImage);
+            }
+            OP_TYPE_ARRAY => {
+                let array_type = SpirvType::Array {
+                    element_type: operand(1)?,
+                    length_id: operand(2)?,
+                };
+                self.types.insert(operand(0)?, array_type);
+            }
+            OP_TYPE_RUNTIME_ARRAY => {
+                let array_type = SpirvType::RuntimeArray {
+                    element_type: operand(1)?,
+                };
+                self.types.insert(operand(0)?, array_type);
+            }
+            OP_TYPE_STRUCT => {
+                let struct_type = SpirvType::Struct {
+                    member_types: operands[1.min(operands.len())..].to_vec(),
+                };
+                self.types.insert(operand(0)?, struct_type);
+            }
+            OP_TYPE_POINTER => {
+                let pointer_type = SpirvType::Pointer {
+                    storage_class: operand(1)?,
+                    pointee_type: operand(2)?,
+                };
+                self.types.insert(operand(0)?, pointer_type);
+            }
+            OP_TYPE_ACCELERATION_STRUCTURE => {
+                self.types
+                    .insert(operand(0)?, SpirvType::AccelerationStructure);
+            }
+            // Only the low word of constants is needed (array lengths)
+            OP_CONSTANT | OP_SPEC_CONSTANT => {
+                self.constants.insert(operand(1)?, operand(2)?);
+            }
+            OP_VARIABLE => {
+                self.variables.push(Variable {
+                    type_id: operand(0)?,
+                    id: operand(1)?,
+                    storage_class: operand(2)?,
+                });
+            }
+            OP_DECORATE => {
+                let decorations = self.decorations.entry(operand(0)?).or_default();
+                match operand(1)? {
+                    DECORATION_DESCRIPTOR_SET => decorations.descriptor_set = Some(operand(2)?),
+                    DECORATION_BINDING => decorations.binding = Some(operand(2)?),
+                    DECORATION_LOCATION => decorations.location = Some(operand(2)?),
+                    DECORATION_ARRAY_STRIDE => decorations.array_stride = Some(operand(2)?),
+                    DECORATION_BUILT_IN => decorations.built_in = true,
+                    DECORATION_BUFFER_BLOCK => decorations.buffer_block = true,
+                    _ => {}
+                }
+            }
+            OP_MEMBER_DECORATE => {
+                let decorations = self
+                    .member_decorations
+                    .entry((operand(0)?, operand(1)?))
+                    .or_default();
+                match operand(2)? {
+                    DECORATION_OFFSET => decorations.offset = Some(operand(3)?),
+                    DECORATION_MATRIX_STRIDE => decorations.matrix_stride = Some(operand(3)?),
+                    _ => {}
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn get_type(&self, type_id: u32) -> crate::Result<&SpirvType> {
+        match self.types.get(&type_id) {
+            Some(spirv_type) => Ok(spirv_type),
+            None => crate::bail!("The SPIR-V type {} is not declared", type_id),
+        }
+    }
+
+    fn decorations(&self, id: u32) -> Decorations {
+        self.decorations.get(&id).cloned().unwrap_or_default()
+    }
+
+    fn array_length(&self, length_id: u32) -> crate::Result<u32> {
+        match self.constants.get(&length_id) {
+            Some(length) => Ok(*length),
+            None => crate::bail!("The array length {} is not a constant", length_id),
+        }
+    }
+
+    fn name(&self, id: u32) -> String {
+        self.names.get(&id).cloned().unwrap_or_default()
+    }
+
+    /// This function returns the size in bytes of a type in a buffer block. The `matrix_stride`
+    /// is the stride that was declared by the containing struct member (if any).
+    fn type_size(&self, type_id: u32, matrix_stride: Option<u32>) -> crate::Result<u32> {
+        let size = match self.get_type(type_id)? {
+            SpirvType::Bool => 4,
+            SpirvType::Int { width, .. } | SpirvType::Float { width } => width / 8,
+            SpirvType::Vector {
+                component_type,
+                component_count,
+            } => self.type_size(*component_type, None)? * component_count,
+            SpirvType::Matrix {
+                column_type,
+                column_count,
+            } => {
+                let column_size = self.type_size(*column_type, None)?;
+                matrix_stride.unwrap_or(column_size) * column_count
+            }
+            SpirvType::Array {
+                element_type,
+                length_id,
+            } => {
+                let element_stride = match self.decorations(type_id).array_stride {
+                    Some(array_stride) => array_stride,
+                    None => self.type_size(*element_type, matrix_stride)?,
+                };
+                element_stride * self.array_length(*length_id)?
+            }
+            SpirvType::Struct { member_types } => {
+                let mut size = 0;
+                for (member_index, member_type) in member_types.iter().enumerate() {
+                    let member_decorations = self
+                        .member_decorations
+                        .get(&(type_id, member_index as u32))
+                        .cloned()
+                        .unwrap_or_default();
+                    let offset = member_decorations.offset.unwrap_or(size);
+                    let member_size =
+                        self.type_size(*member_type, member_decorations.matrix_stride)?;
+                    size = size.max(offset + member_size);
+                }
+                size
+            }
+            _ => 0,
+        };
+        Ok(size)
+    }
+
+    /// This function returns the push constant range of a push constant block, which starts at
+    /// the offset of its first member.
+    fn push_constant_range(
+        &self,
+        block_type: u32,
+        stage: ShaderStageFlags,
+    ) -> crate::Result<PushConstantRange> {
+        let member_count = match self.get_type(block_type)? {
+            SpirvType::Struct { member_types } => member_types.len() as u32,
+            _ => 0,
+        };
+        let offset = (0..member_count)
+            .filter_map(|member_index| {
+                self.member_decorations
+                    .get(&(block_type, member_index))
+                    .and_then(|member_decorations| member_decorations.offset)
+            })
+            .min()
+            .unwrap_or(0);
+        let size = self.type_size(block_type, None)?.saturating_sub(offset);
+
+        Ok(PushConstantRange {
+            stage_flags: stage,
+            offset,
+            size: (size + 3) & !3,
+        })
+    }
+
+    fn descriptor_type(
+        &self,
+        storage_class: u32,
+        type_id: u32,
+    ) -> crate::Result<Option<DescriptorType>> {
+        let descriptor_type = match (storage_class, self.get_type(type_id)?) {
+            (STORAGE_CLASS_UNIFORM_CONSTANT, SpirvType::Sampler) => DescriptorType::SAMPLER,
+            (STORAGE_CLASS_UNIFORM_CONSTANT, SpirvType::SampledImage) => {
+                DescriptorType::COMBINED_IMAGE_SAMPLER
+            }
+            (STORAGE_CLASS_UNIFORM_CONSTANT, SpirvType::Image { dim, sampled }) => {
+                match (*dim, *sampled) {
+                    (IMAGE_DIM_BUFFER, 2) => DescriptorType::STORAGE_TEXEL_BUFFER,
+                    (IMAGE_DIM_BUFFER, _) => DescriptorType::UNIFORM_TEXEL_BUFFER,
+                    (IMAGE_DIM_SUBPASS_DATA, _) => DescriptorType::INPUT_ATTACHMENT,
+                    (_, 2) => DescriptorType::STORAGE_IMAGE,
+                    (_, _) => DescriptorType::SAMPLED_IMAGE,
+                }
+            }
+            (STORAGE_CLASS_UNIFORM_CONSTANT, SpirvType::AccelerationStructure) => {
+                DescriptorType::ACCELERATION_STRUCTURE_KHR
+            }
+            (STORAGE_CLASS_UNIFORM, _) => {
+                if self.decorations(type_id).buffer_block {
+                    DescriptorType::STORAGE_BUFFER
+                } else {
+                    DescriptorType::UNIFORM_BUFFER
+                }
+            }
+            (STORAGE_CLASS_STORAGE_BUFFER, _) => DescriptorType::STORAGE_BUFFER,
+            _ => return Ok(None),
+        };
+        Ok(Some(descriptor_type))
+    }
+
+    fn reflect_binding(
+        &self,
+        variable: &Variable,
+        pointee_type: u32,
+        stage: ShaderStageFlags,
+    ) -> crate::Result<Option<ReflectedBinding>> {
+        let decorations = self.decorations(variable.id);
+        let binding = match decorations.binding {
+            Some(binding) => binding,
+            None => return Ok(None),
+        };
+
+        let (count, element_type) = match self.get_type(pointee_type)? {
+            SpirvType::Array {
+                element_type,
+                length_id,
+            } => (
+                DescriptorCount::Fixed(self.array_length(*length_id)?),
+                *element_type,
+            ),
+            SpirvType::RuntimeArray { element_type } => (DescriptorCount::Runtime, *element_type),
+            _ => (DescriptorCount::Fixed(1), pointee_type),
+        };
+        let descriptor_type = match self.descriptor_type(variable.storage_class, element_type)? {
+            Some(descriptor_type) => descriptor_type,
+            None => return Ok(None),
+        };
+
+        // Blocks without an instance name are named after their type
+        let mut name = self.name(variable.id);
+        if name.is_empty() {
+            name = self.name(element_type);
+        }
+
+        Ok(Some(ReflectedBinding {
+            set: decorations.descriptor_set.unwrap_or(0),
+            binding,
+            descriptor_type,
+            count,
+            stage_flags: stage,
+            name,
+        }))
+    }
+
+    /// This function returns the interface variables of the type `type_id` that start at the
+    /// `location`. Matrices and arrays occupy the locations of all their columns or elements.
+    fn interface_variables(
+        &self,
+        name: &str,
+        location: u32,
+        type_id: u32,
+    ) -> crate::Result<Vec<ReflectedInterfaceVariable>> {
+        let variables = match self.get_type(type_id)? {
+            SpirvType::Matrix {
+                column_type,
+                column_count,
+            } => {
+                let mut variables = vec![];
+                let mut column_location = location;
+                for column in 0..*column_count {
+                    let column_name = format!("{name}[{column}]");
+                    let column_variables =
+                        self.interface_variables(&column_name, column_location, *column_type)?;
+                    column_location += location_count(&column_variables);
+                    variables.extend(column_variables);
+                }
+                variables
+            }
+            SpirvType::Array {
+                element_type,
+                length_id,
+            } => {
+                let mut variables = vec![];
+                let mut element_location = location;
+                for element in 0..self.array_length(*length_id)? {
+                    let element_name = format!("{name}[{element}]");
+                    let element_variables =
+                        self.interface_variables(&element_name, element_location, *element_type)?;
+                    element_location += location_count(&element_variables);
+                    variables.extend(element_variables);
+                }
+                variables
+            }
+            _ => match self.format_of(type_id)? {
+                Some((format, size)) => vec![ReflectedInterfaceVariable {
+                    location,
+                    format,
+                    size,
+                    name: name.to_string(),
+                }],
+                None => vec![],
+            },
+        };
+        Ok(variables)
+    }
+
+    /// This function returns the vertex format and the size in bytes of a scalar or vector type.
+    fn format_of(&self, type_id: u32) -> crate::Result<Option<(Format, u32)>> {
+        let (component_type, component_count) = match self.get_type(type_id)? {
+            SpirvType::Vector {
+                component_type,
+                component_count,
+            } => (self.get_type(*component_type)?, *component_count),
+            scalar_type => (scalar_type, 1),
+        };
+
+        let formats = match component_type {
+            SpirvType::Float { width: 16 } => [
+                Format::R16_SFLOAT,
+                Format::R16G16_SFLOAT,
+                Format::R16G16B16_SFLOAT,
+                Format::R16G16B16A16_SFLOAT,
+            ],
+            SpirvType::Float { width: 32 } => [
+                Format::R32_SFLOAT,
+                Format::R32G32_SFLOAT,
+                Format::R32G32B32_SFLOAT,
+                Format::R32G32B32A32_SFLOAT,
+            ],
+            SpirvType::Float { width: 64 } => [
+                Format::R64_SFLOAT,
+                Format::R64G64_SFLOAT,
+                Format::R64G64B64_SFLOAT,
+                Format::R64G64B64A64_SFLOAT,
+            ],
+            SpirvType::Int {
+                width: 32,
+                signed: true,
+            } => [
+                Format::R32_SINT,
+                Format::R32G32_SINT,
+                Format::R32G32B32_SINT,
+                Format::R32G32B32A32_SINT,
+            ],
+            SpirvType::Int {
+                width: 32,
+                signed: false,
+            } => [
+                Format::R32_UINT,
+                Format::R32G32_UINT,
+                Format::R32G32B32_UINT,
+                Format::R32G32B32A32_UINT,
+            ],
+            SpirvType::Int {
+                width: 64,
+                signed: true,
+            } => [
+                Format::R64_SINT,
+                Format::R64G64_SINT,
+                Format::R64G64B64_SINT,
+                Format::R64G64B64A64_SINT,
+            ],
+            SpirvType::Int {
+                width: 64,
+                signed: false,
+            } => [
+                Format::R64_UINT,
+                Format::R64G64_UINT,
+                Format::R64G64B64_UINT,
+                Format::R64G64B64A64_UINT,
+            ],
+            _ => return Ok(None),
+        };
+        if !(1..=4).contains(&component_count) {
+            return Ok(None);
+        }
+
+        let component_size = self.type_size(type_id, None)? / component_count;
+        Ok(Some((
+            formats[component_count as usize - 1],
+            component_size * component_count,
+        )))
+    }
+}
+
+/// The number of descriptors of a binding
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DescriptorCount {
+    Fixed(u32),
+    /// An unsized array, which is declared as a bindless binding
+    Runtime,
+}
+
+/// A descriptor binding that is declared in a shader
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ReflectedBinding {
+    pub set: u32,
+    pub binding: u32,
+    pub descriptor_type: DescriptorType,
+    pub count: DescriptorCount,
+    pub stage_flags: ShaderStageFlags,
+    pub name: String,
+}
+
+/// A user-defined input or output variable of a shader
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ReflectedInterfaceVariable {
+    pub location: u32,
+    pub format: Format,
+    /// The size of the variable in bytes
+    pub size: u32,
+    pub name: String,
+}
+
+impl ReflectedInterfaceVariable {
+    /// This function returns the number of locations the variable occupies: One per 16 bytes,
+    /// so that 64-bit vectors with three or four components occupy two.
+    pub fn location_count(&self) -> u32 {
+        self.size.div_ceil(16).max(1)
+    }
+}
+
+/// This function returns the number of locations the `variables` occupy together.
+fn location_count(variables: &[ReflectedInterfaceVariable]) -> u32 {
+    variables
+        .iter()
+        .map(ReflectedInterfaceVariable::location_count)
+        .sum::<u32>()
+        .max(1)
+}
+
+/// The reflection of one entry point of a SPIR-V module
+#[derive(Clone, Debug)]
+pub struct ShaderReflection {
+    pub stage: ShaderStageFlags,
+    pub entry_point: String,
+    /// The descriptor bindings, sorted by set and binding
+    pub bindings: Vec<ReflectedBinding>,
+    pub push_constant_range: Option<PushConstantRange>,
+    /// The user-defined inputs, sorted by location
+    pub inputs: Vec<ReflectedInterfaceVariable>,
+    /// The user-defined outputs, sorted by location
+    pub outputs: Vec<ReflectedInterfaceVariable>,
+    /// The local workgroup size of compute shaders
+    pub workgroup_size: Option<[u32; 3]>,
+}
+
+impl ShaderReflection {
+    /// This function reflects the entry point with the name `entry_point` of the SPIR-V `code`.
+    pub fn parse(code: &[u32], entry_point: &str) -> crate::Result<Self> {
+        let module = SpirvModule::parse(code)?;
+        let entry = module
+            .entry_points
+            .iter()
+            .find(|entry| entry.name == entry_point);
+        let entry = match entry {
+            Some(entry) => entry,
+            None => crate::bail!("The SPIR-V module has no entry point {}", entry_point),
+        };
+
+        let mut bindings = vec![];
+        let mut push_constant_range = None;
+        let mut inputs = vec![];
+        let mut outputs = vec![];
+        for variable in module.variables.iter() {
+            let pointee_type = match module.get_type(variable.type_id)? {
+                SpirvType::Pointer { pointee_type, .. } => *pointee_type,
+                _ => continue,
+            };
+
+            match variable.storage_class {
+                STORAGE_CLASS_UNIFORM_CONSTANT
+                | STORAGE_CLASS_UNIFORM
+                | STORAGE_CLASS_STORAGE_BUFFER => {
+                    if let Some(binding) =
+                        module.reflect_binding(variable, pointee_type, entry.stage)?
+                    {
+                        bindings.push(binding);
+                    }
+                }
+                STORAGE_CLASS_PUSH_CONSTANT => {
+                    push_constant_range =
+                        Some(module.push_constant_range(pointee_type, entry.stage)?);
+                }
+                STORAGE_CLASS_INPUT | STORAGE_CLASS_OUTPUT => {
+                    let decorations = module.decorations(variable.id);
+                    let location = match decorations.location {
+                        Some(location) if !decorations.built_in => location,
+                        _ => continue,
+                    };
+                    if !entry.interface.contains(&variable.id) {
+                        continue;
+                    }
+
+                    let variables = module.interface_variables(
+                        &module.name(variable.id),
+                        location,
+                        pointee_type,
+                    )?;
+                    if variable.storage_class == STORAGE_CLASS_INPUT {
+                        inputs.extend(variables);
+                    } else {
+                        outputs.extend(variables);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        bindings.sort_by_key(|binding| (binding.set, binding.binding));
+        inputs.sort_by_key(|input| input.location);
+        outputs.sort_by_key(|output| output.location);
+
+        Ok(Self {
+            stage: entry.stage,
+            entry_point: entry.name.clone(),
+            bindings,
+            push_constant_range,
+            inputs,
+            outputs,
+            workgroup_size: module.local_sizes.get(&entry.id).copied(),
+        })
+    }
+
+    /// This function returns the vertex attributes of a vertex shader, together with the stride
+    /// of the vertex data.
+    ///
+    /// The vertex data is assumed to be tightly packed: The attributes are stored in location
+    /// order without any padding between them, e.g. as in a `#[repr(C, packed)]` struct. Vertex
+    /// structs with a different order or padding (e.g. a `[f32; 3]` followed by an `f64`) need
+    /// manually specified attribute descriptions.
+    pub fn vertex_input_attributes(
+        &self,
+        binding: u32,
+    ) -> (Vec<VertexInputAttributeDescription>, u32) {
+        let mut offset = 0;
+        let attributes = self
+            .inputs
+            .iter()
+            .map(|input| {
+                let attribute = VertexInputAttributeDescription {
+                    location: input.location,
+                    binding,
+                    format: input.format,
+                    offset,
+                };
+                offset += input.size;
+                attribute
+            })
+            .collect();
+        (attributes, offset)
+    }
+}
+
+/// The merged reflection of all stages of a pipeline
+#[derive(Clone, Debug, Default)]
+pub struct PipelineReflection {
+    /// The bindings of all stages, sorted by set and binding
+    pub bindings: Vec<ReflectedBinding>,
+    pub push_constant_ranges: Vec<PushConstantRange>,
+    /// The inputs of the vertex shader
+    pub vertex_inputs: Vec<ReflectedInterfaceVariable>,
+    pub workgroup_size: Option<[u32; 3]>,
+}
+
+impl PipelineReflection {
+    /// This function merges the reflections of the `stages` and checks that bindings which are
+    /// used by multiple stages are declared identically, and that the inputs of the fragment
+    /// shader match the outputs of the vertex shader.
+    pub fn merge(stages: &[ShaderReflection]) -> crate::Result<Self> {
+        let mut merged = Self::default();
+        let mut bindings = BTreeMap::<(u32, u32), ReflectedBinding>::new();
+        let mut merged_stages = ShaderStageFlags::empty();
+
+        for stage in stages.iter() {
+            crate::verify!(
+                !merged_stages.intersects(stage.stage),
+                "The pipeline contains multiple {:?} shaders",
+                (stage.stage)
+            );
+            merged_stages |= stage.stage;
+
+            for binding in stage.bindings.iter() {
+                match bindings.get_mut(&(binding.set, binding.binding)) {
+                    Some(existing) => {
+                        crate::verify!(
+                            existing.descriptor_type == binding.descriptor_type
+                                && existing.count == binding.count,
+                            "The binding {} of set {} is declared as {:?} ({:?}) in the {:?} stages, but as {:?} ({:?}) in the {:?} stage",
+                            (binding.binding),
+                            (binding.set),
+                            (existing.descriptor_type),
+                            (existing.count),
+                            (existing.stage_flags),
+                            (binding.descriptor_type),
+                            (binding.count),
+                            (stage.stage)
+                        );
+                        existing.stage_flags |= stage.stage;
+                    }
+                    None => {
+                        bindings.insert((binding.set, binding.binding), binding.clone());
+                    }
+                }
+            }
+
+            if let Some(range) = stage.push_constant_range {
+                let existing = merged.push_constant_ranges.iter_mut().find(|existing| {
+                    existing.offset == range.offset && existing.size == range.size
+                });
+                match existing {
+                    Some(existing) => existing.stage_flags |= range.stage_flags,
+                    None => merged.push_constant_ranges.push(range),
+                }
+            }
+
+            if stage.stage == ShaderStageFlags::VERTEX {
+                merged.vertex_inputs = stage.inputs.clone();
+            }
+            if stage.workgroup_size.is_some() {
+                merged.workgroup_size = stage.workgroup_size;
+            }
+        }
+
+        let vertex = stages
+            .iter()
+            .find(|stage| stage.stage == ShaderStageFlags::VERTEX);
+        let fragment = stages
+            .iter()
+            .find(|stage| stage.stage == ShaderStageFlags::FRAGMENT);
+        let pre_rasterization_stages = ShaderStageFlags::TESSELLATION_CONTROL
+            | ShaderStageFlags::TESSELLATION_EVALUATION
+            | ShaderStageFlags::GEOMETRY;
+        if let (Some(vertex), Some(fragment)) = (vertex, fragment) {
+            if !merged_stages.intersects(pre_rasterization_stages) {
+                verify_stage_interface(vertex, fragment)?;
+            }
+        }
+
+        merged.bindings = bindings.into_values().collect();
+        Ok(merged)
+    }
+
+    /// This function reflects and merges the stages of the `shader_modules`.
+    pub fn from_shader_modules(shader_modules: &[&CompiledShaderModule]) -> crate::Result<Self> {
+        let stages = shader_modules
+            .iter()
+            .map(|shader_module| shader_module.reflect())
+            .collect::<crate::Result<Vec<_>>>()?;
+        Self::merge(&stages)
+    }
+
+    /// This function changes the type of a binding, e.g. to `UNIFORM_BUFFER_DYNAMIC` for
+    /// buffers that are bound with dynamic offsets.
+    pub fn set_descriptor_type(
+        &mut self,
+        set: u32,
+        binding: u32,
+        descriptor_type: DescriptorType,
+    ) -> crate::Result<()> {
+        let reflected_binding = self
+            .bindings
+            .iter_mut()
+            .find(|reflected| reflected.set == set && reflected.binding == binding);
+        match reflected_binding {
+            Some(reflected_binding) => {
+                reflected_binding.descriptor_type = descriptor_type;
+                Ok(())
+            }
+            None => crate::bail!(
+                "The shaders don't use the binding {} of set {}",
+                binding,
+                set
+            ),
+        }
+    }
+
+    /// This function returns the number of descriptor set layouts of the pipeline layout.
+    pub fn set_count(&self) -> u32 {
+        self.bindings
+            .iter()
+            .map(|binding| binding.set + 1)
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// This function returns one layout builder for each set. Sets that no shader uses get an
+    /// empty layout, and runtime arrays become bindless bindings with up to
+    /// `runtime_array_capacity` descriptors.
+    pub fn descriptor_set_layout_builders(
+        &self,
+        runtime_array_capacity: u32,
+    ) -> Vec<DescriptorSetLayoutBuilder> {
+        let mut builders = (0..self.set_count())
+            .map(|_| DescriptorSetLayoutBuilder::new())
+            .collect::<Vec<_>>();
+
+        for binding in self.bindings.iter() {
+            let builder = std::mem::take(&mut builders[binding.set as usize]);
+            builders[binding.set as usize] = match binding.count {
+                DescriptorCount::Fixed(count) => builder.add_binding(
+                    binding.binding,
+                    count,
+                    binding.descriptor_type,
+                    binding.stage_flags,
+                ),
+                DescriptorCount::Runtime => builder.add_bindless_binding(
+                    binding.binding,
+                    runtime_array_capacity,
+                    binding.descriptor_type,
+                    binding.stage_flags,
+                ),
+            };
+        }
+        builders
+    }
+
+    /// This function builds the descriptor set layouts of all sets (see
+    /// `descriptor_set_layout_builders`).
+    pub fn build_descriptor_set_layouts(
+        &self,
+        logical_device: &SetUpLogicalDevice,
+        runtime_array_capacity: u32,
+    ) -> crate::Result<Vec<SetUpDescriptorSetLayout>> {
+        let uses_runtime_arrays = self
+            .bindings
+            .iter()
+            .any(|binding| binding.count == DescriptorCount::Runtime);
+        crate::verify!(
+            !uses_runtime_arrays || runtime_array_capacity > 0,
+            "The shaders use runtime arrays, so the runtime array capacity must be at least 1"
+        );
+
+        let mut set_layouts = vec![];
+        for builder in self.descriptor_set_layout_builders(runtime_array_capacity) {
+            match builder.build(logical_device) {
+                Ok(set_layout) => set_layouts.push(set_layout),
+                Err(err) => {
+                    for set_layout in set_layouts.iter_mut() {
+                        set_layout.destroy(logical_device);
+                    }
+                    return Err(err);
+                }
+            }
+        }
+        Ok(set_layouts)
+    }
+
+    /// This function builds a pipeline layout with the `set_layouts` (which must contain at
+    /// least `set_count` layouts) and the push constant ranges of the shaders.
+    pub fn build_pipeline_layout(
+        &self,
+        logical_device: &SetUpLogicalDevice,
+        set_layouts: &[SetUpDescriptorSetLayout],
+    ) -> crate::Result<SetUpPipelineLayout> {
+        crate::verify!(
+            set_layouts.len() >= self.set_count() as usize,
+            "The shaders use {} descriptor sets, but only {} layouts were passed",
+            (self.set_count()),
+            (set_layouts.len())
+        );
+
+        let mut builder = PipelineLayoutBuilder::new().set_layouts(set_layouts);
+        for range in self.push_constant_ranges.iter() {
+            builder = builder.add_raw_push_constant_range(*range);
+        }
+        builder.build(logical_device)
+    }
+}
+
+/// This function checks that every input of the `consumer` stage is written by an output of the
+/// `producer` stage with the same format.
+fn verify_stage_interface(
+    producer: &ShaderReflection,
+    consumer: &ShaderReflection,
+) -> crate::Result<()> {
+    for input in consumer.inputs.iter() {
+        let output = producer
+            .outputs
+            .iter()
+            .find(|output| output.location == input.location);
+        match output {
+            Some(output) => crate::verify!(
+                output.format == input.format,
+                "The {:?} shader input {} at location {} has the format {:?}, but the {:?} shader output {} has the format {:?}",
+                (consumer.stage),
+                (input.name),
+                (input.location),
+                (input.format),
+                (producer.stage),
+                (output.name),
+                (output.format)
+            ),
+            None => crate::bail!(
+                "The {:?} shader input {} at location {} is not written by the {:?} shader",
+                (consumer.stage),
+                (input.name),
+                (input.location),
+                (producer.stage)
+            ),
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A minimal SPIR-V assembler for the declarations that are reflected
+    struct Assembler {
+        words: Vec<u32>,
+    }
+
+    impl Assembler {
+        fn new() -> Self {
+            Self {
+                words: vec![SPIRV_MAGIC, 0x0001_0000, 0, 100, 0],
+            }
+        }
+
+        fn op(mut self, opcode: u32, operands: &[u32]) -> Self {
+            self.words
+                .push(((operands.len() as u32 + 1) << 16) | opcode);
+            self.words.extend_from_slice(operands);
+            self
+        }
+
+        fn entry_point(self, execution_model: u32, id: u32, interface: &[u32]) -> Self {
+            // "main" followed by a terminating nul word
+            let mut operands = vec![execution_model, id, u32::from_le_bytes(*b"main"), 0];
+            operands.extend_from_slice(interface);
+            self.op(OP_ENTRY_POINT, &operands)
+        }
+    }
+
+    const FLOAT: u32 = 1;
+    const VEC2: u32 = 2;
+    const VEC4: u32 = 3;
+    const MAT4: u32 = 4;
+
+    fn with_float_types(assembler: Assembler) -> Assembler {
+        assembler
+            .op(OP_TYPE_FLOAT, &[FLOAT, 32])
+            .op(OP_TYPE_VECTOR, &[VEC2, FLOAT, 2])
+            .op(OP_TYPE_VECTOR, &[VEC4, FLOAT, 4])
+            .op(OP_TYPE_MATRIX, &[MAT4, VEC4, 4])
+    }
+
+    #[test]
+    fn bindings_push_constants_and_inputs_are_reflected() {
+        let assembler = Assembler::new().entry_point(4, 100, &[30]);
+        let code = with_float_types(assembler)
+            // layout(set = 0, binding = 1) uniform Scene { vec4 color; }
+            .op(OP_TYPE_STRUCT, &[10, VEC4])
+            .op(OP_TYPE_POINTER, &[11, STORAGE_CLASS_UNIFORM, 10])
+            .op(OP_VARIABLE, &[11, 12, STORAGE_CLASS_UNIFORM])
+            .op(OP_DECORATE, &[12, DECORATION_DESCRIPTOR_SET, 0])
+            .op(OP_DECORATE, &[12, DECORATION_BINDING, 1])
+            // layout(set = 1, binding = 0) uniform sampler2D textures[4]
+            .op(OP_TYPE_IMAGE, &[13, FLOAT, 1, 0, 0, 0, 1, 0])
+            .op(OP_TYPE_SAMPLED_IMAGE, &[14, 13])
+            .op(OP_TYPE_INT, &[15, 32, 0])
+            .op(OP_CONSTANT, &[15, 16, 4])
+            .op(OP_TYPE_ARRAY, &[17, 14, 16])
+            .op(OP_TYPE_POINTER, &[18, STORAGE_CLASS_UNIFORM_CONSTANT, 17])
+            .op(OP_VARIABLE, &[18, 19, STORAGE_CLASS_UNIFORM_CONSTANT])
+            .op(OP_DECORATE, &[19, DECORATION_DESCRIPTOR_SET, 1])
+            .op(OP_DECORATE, &[19, DECORATION_BINDING, 0])
+            // layout(push_constant) uniform Constants { mat4 model; vec4 tint; }
+            .op(OP_TYPE_STRUCT, &[20, MAT4, VEC4])
+            .op(OP_MEMBER_DECORATE, &[20, 0, DECORATION_OFFSET, 0])
+            .op(OP_MEMBER_DECORATE, &[20, 0, DECORATION_MATRIX_STRIDE, 16])
+            .op(OP_MEMBER_DECORATE, &[20, 1, DECORATION_OFFSET, 64])
+            .op(OP_TYPE_POINTER, &[21, STORAGE_CLASS_PUSH_CONSTANT, 20])
+            .op(OP_VARIABLE, &[21, 22, STORAGE_CLASS_PUSH_CONSTANT])
+            // layout(location = 2) in vec2 uv
+            .op(OP_TYPE_POINTER, &[23, STORAGE_CLASS_INPUT, VEC2])
+            .op(OP_VARIABLE, &[23, 30, STORAGE_CLASS_INPUT])
+            .op(OP_DECORATE, &[30, DECORATION_LOCATION, 2])
+            .words;
+
+        let reflection = ShaderReflection::parse(&code, "main").unwrap();
+        assert_eq!(ShaderStageFlags::FRAGMENT, reflection.stage);
+
+        let bindings = &reflection.bindings;
+        assert_eq!(2, bindings.len());
+        assert_eq!((0, 1), (bindings[0].set, bindings[0].binding));
+        assert_eq!(DescriptorType::UNIFORM_BUFFER, bindings[0].descriptor_type);
+        assert_eq!((1, 0), (bindings[1].set, bindings[1].binding));
+        assert_eq!(
+            DescriptorType::COMBINED_IMAGE_SAMPLER,
+            bindings[1].descriptor_type
+        );
+        assert_eq!(DescriptorCount::Fixed(4), bindings[1].count);
+
+        let push_constant_range = reflection.push_constant_range.unwrap();
+        assert_eq!(
+            (0, 80),
+            (push_constant_range.offset, push_constant_range.size)
+        );
+
+        assert_eq!(1, reflection.inputs.len());
+        assert_eq!(2, reflection.inputs[0].location);
+        assert_eq!(Format::R32G32_SFLOAT, reflection.inputs[0].format);
+    }
+
+    #[test]
+    fn mismatching_stages_are_rejected() {
+        let binding = |descriptor_type, stage_flags| ReflectedBinding {
+            set: 0,
+            binding: 0,
+            descriptor_type,
+            count: DescriptorCount::Fixed(1),
+            stage_flags,
+            name: String::new(),
+        };
+        let variable = |format| ReflectedInterfaceVariable {
+            location: 0,
+            format,
+            size: 8,
+            name: String::new(),
+        };
+        let vertex = ShaderReflection {
+            stage: ShaderStageFlags::VERTEX,
+            entry_point: "main".to_string(),
+            bindings: vec![binding(
+                DescriptorType::UNIFORM_BUFFER,
+                ShaderStageFlags::VERTEX,
+            )],
+            push_constant_range: None,
+            inputs: vec![],
+            outputs: vec![variable(Format::R32G32_SFLOAT)],
+            workgroup_size: None,
+        };
+        let fragment = ShaderReflection {
+            stage: ShaderStageFlags::FRAGMENT,
+            bindings: vec![binding(
+                DescriptorType::UNIFORM_BUFFER,
+                ShaderStageFlags::FRAGMENT,
+            )],
+            outputs: vec![],
+            inputs: vec![variable(Format::R32G32_SFLOAT)],
+            ..vertex.clone()
+        };
+
+        let merged = PipelineReflection::merge(&[vertex.clone(), fragment.clone()]).unwrap();
+        assert_eq!(
+            ShaderStageFlags::VERTEX | ShaderStageFlags::FRAGMENT,
+            merged.bindings[0].stage_flags
+        );
+
+        let storage_fragment = ShaderReflection {
+            bindings: vec![binding(
+                DescriptorType::STORAGE_BUFFER,
+                ShaderStageFlags::FRAGMENT,
+            )],
+            ..fragment.clone()
+        };
+        assert!(PipelineReflection::merge(&[vertex.clone(), storage_fragment]).is_err());
+
+        let integer_fragment = ShaderReflection {
+            inputs: vec![variable(Format::R32G32_SINT)],
+            ..fragment
+        };
+        assert!(PipelineReflection::merge(&[vertex, integer_fragment]).is_err());
+    }
+
+    #[test]
+    fn double_vectors_occupy_two_locations() {
+        const DOUBLE: u32 = 5;
+        const DVEC3: u32 = 6;
+        const DMAT3: u32 = 7;
+
+        let code = Assembler::new()
+            .entry_point(0, 100, &[30, 31])
+            .op(OP_TYPE_FLOAT, &[DOUBLE, 64])
+            .op(OP_TYPE_VECTOR, &[DVEC3, DOUBLE, 3])
+            .op(OP_TYPE_MATRIX, &[DMAT3, DVEC3, 3])
+            // layout(location = 0) in dmat3 transform
+            .op(OP_TYPE_POINTER, &[20, STORAGE_CLASS_INPUT, DMAT3])
+            .op(OP_VARIABLE, &[20, 30, STORAGE_CLASS_INPUT])
+            .op(OP_DECORATE, &[30, DECORATION_LOCATION, 0])
+            // layout(location = 6) in double weight
+            .op(OP_TYPE_POINTER, &[21, STORAGE_CLASS_INPUT, DOUBLE])
+            .op(OP_VARIABLE, &[21, 31, STORAGE_CLASS_INPUT])
+            .op(OP_DECORATE, &[31, DECORATION_LOCATION, 6])
+            .words;
+
+        let reflection = ShaderReflection::parse(&code, "main").unwrap();
+        let locations = reflection
+            .inputs
+            .iter()
+            .map(|input| input.location)
+            .collect::<Vec<_>>();
+        assert_eq!(vec![0, 2, 4, 6], locations);
+        assert_eq!(Format::R64G64B64_SFLOAT, reflection.inputs[0].format);
+
+        let (attributes, stride) = reflection.vertex_input_attributes(0);
+        assert_eq!(48, attributes[2].offset);
+        assert_eq!(80, stride);
+    }
+}
@@ -0,0 +1,139 @@
+//! This is a module that contains the `FileWatcher` structure, which detects changes of files by
+//! polling their modification times.
+//!
+//! Polling doesn't require a background thread or platform-specific APIs, and the changes are
+//! reported exactly when `poll` is called, e.g. once per frame. Files that are missing while
+//! they are polled (e.g. because an editor replaces them on save) are skipped until they exist
+//! again.
+
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+
+/// The default minimum time between two polls of a `FileWatcher`
+pub const DEFAULT_POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// The ID of a file in a `FileWatcher`
+pub type WatchedFileId = usize;
+
+struct WatchedFile {
+    path: PathBuf,
+    modified: Option<SystemTime>,
+}
+
+fn modification_time(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
+
+pub struct FileWatcher {
+    files: Vec<WatchedFile>,
+    poll_interval: Duration,
+    last_poll: Option<Instant>,
+}
+
+impl Default for FileWatcher {
+    fn default() -> Self {
+        Self {
+            files: vec![],
+            poll_interval: DEFAULT_POLL_INTERVAL,
+            last_poll: None,
+        }
+    }
+}
+
+impl FileWatcher {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// This function sets the minimum time between two polls, so that `poll` can be called
+    /// every frame without querying the file system every frame.
+    pub fn poll_interval(mut self, poll_interval: Duration) -> Self {
+        self.poll_interval = poll_interval;
+        self
+    }
+
+    /// This function starts watching the file at `path`. Changes are reported relative to its
+    /// current modification time. If the file is already watched, its existing ID is returned.
+    pub fn watch<P: Into<PathBuf>>(&mut self, path: P) -> WatchedFileId {
+        let path = path.into();
+        if let Some(id) = self.files.iter().position(|file| file.path == path) {
+            return id;
+        }
+
+        let modified = modification_time(&path);
+        self.files.push(WatchedFile { path, modified });
+        self.files.len() - 1
+    }
+
+    pub fn path(&self, id: WatchedFileId) -> Option<&Path> {
+        self.files.get(id).map(|file| file.path.as_path())
+    }
+
+    pub fn len(&self) -> usize {
+        self.files.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+
+    /// This function returns the IDs of the files that changed since the last poll. If the poll
+    /// interval didn't pass yet, no files are checked.
+    pub fn poll(&mut self) -> Vec<WatchedFileId> {
+        let now = Instant::now();
+        if let Some(last_poll) = self.last_poll {
+            if now.duration_since(last_poll) < self.poll_interval {
+                return vec![];
+            }
+        }
+        self.last_poll = Some(now);
+        self.poll_now()
+    }
+
+    /// This function returns the IDs of the files that changed since the last poll, regardless
+    /// of the poll interval.
+    pub fn poll_now(&mut self) -> Vec<WatchedFileId> {
+        let mut changed = vec![];
+        for (id, file) in self.files.iter_mut().enumerate() {
+            let modified = modification_time(&file.path);
+            if modified.is_some() && modified != file.modified {
+                file.modified = modified;
+                changed.push(id);
+            }
+        }
+        changed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::fs::File;
+
+    #[test]
+    fn modified_files_are_reported_once() {
+        let path =
+            std::env::temp_dir().join(format!("voxelar-watcher-{}.glsl", std::process::id()));
+        let file = File::create(&path).unwrap();
+        file.set_modified(SystemTime::UNIX_EPOCH).unwrap();
+
+        let mut watcher = FileWatcher::new();
+        let id = watcher.watch(&path);
+        let missing = watcher.watch(path.with_extension("missing"));
+        assert!(watcher.poll_now().is_empty());
+
+        file.set_modified(SystemTime::now()).unwrap();
+        assert_eq!(vec![id], watcher.poll_now());
+        assert!(watcher.poll_now().is_empty());
+        assert_eq!(Some(path.as_path()), watcher.path(id));
+        assert_ne!(id, missing);
+        assert_eq!(id, watcher.watch(&path));
+
+        drop(file);
+        std::fs::remove_file(&path).unwrap();
+        assert!(watcher.poll_now().is_empty());
+    }
+}
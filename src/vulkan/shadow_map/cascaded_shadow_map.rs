@@ -0,0 +1,246 @@
+//! This is a module that contains the `SetUpCascadedShadowMap` structure, a layered depth image
+//! with one layer for each cascade, the depth-only render pass and framebuffers to render into
+//! the layers and a compare sampler to sample the shadow map with.
+
+use std::sync::MutexGuard;
+
+use ash::vk::{ClearValue, CompareOp, Extent2D, Filter, Format, FormatFeatureFlags, Framebuffer};
+use ash::vk::{FramebufferCreateInfo, ImageTiling, ImageUsageFlags, ImageViewType};
+use ash::vk::{PolygonMode, Rect2D, RenderPassBeginInfo, SampleCountFlags, SubpassContents};
+use ash::vk::{PrimitiveTopology, Viewport};
+use ash::Instance;
+use gpu_allocator::vulkan::Allocator;
+
+use crate::result::Context;
+use crate::vulkan::command::command_buffer::SetUpCommandBufferWithFence;
+use crate::vulkan::depth_image::{DepthImageDescription, SetUpDepthImage};
+use crate::vulkan::graphics_pipeline_builder::GraphicsPipelineBuilder;
+use crate::vulkan::image::image_view::SetUpImageView;
+use crate::vulkan::image::sampler::SetUpSampler;
+use crate::vulkan::logical_device::SetUpLogicalDevice;
+use crate::vulkan::physical_device::SetUpPhysicalDevice;
+use crate::vulkan::render_pass::{default_depth_clear_value, SetUpRenderPass};
+
+use super::cascades::MAX_CASCADES;
+
+/// The depth formats shadow maps can be created with, in the order of preference
+pub const SHADOW_MAP_FORMAT_CANDIDATES: [Format; 2] = [Format::D32_SFLOAT, Format::D16_UNORM];
+
+/// A depth image with one layer for each cascade of a directional light
+///
+/// The shadow map always uses a regular depth range (the far plane is at 1.0), independent of
+/// the depth buffer of the window. After the cascades were rendered, the image is in
+/// `SHADER_READ_ONLY_OPTIMAL` layout and can be bound with the `sampler` as a
+/// `sampler2DArrayShadow`.
+pub struct SetUpCascadedShadowMap {
+    pub resolution: u32,
+    pub cascade_count: u32,
+    /// The image view of the depth image covers all layers (`TYPE_2D_ARRAY`)
+    pub depth_image: SetUpDepthImage,
+    pub cascade_image_views: Vec<SetUpImageView>,
+    /// The render pass shadow pipelines have to be built for
+    pub render_pass: SetUpRenderPass,
+    pub framebuffers: Vec<Framebuffer>,
+    pub sampler: SetUpSampler,
+}
+
+impl SetUpCascadedShadowMap {
+    /// This function returns the first format of `SHADOW_MAP_FORMAT_CANDIDATES` that can be
+    /// used as a depth attachment and sampled with linear filtering.
+    pub fn find_format(
+        instance: &Instance,
+        physical_device: &SetUpPhysicalDevice,
+    ) -> crate::Result<Format> {
+        physical_device
+            .find_supported_format(
+                instance,
+                &SHADOW_MAP_FORMAT_CANDIDATES,
+                ImageTiling::OPTIMAL,
+                FormatFeatureFlags::DEPTH_STENCIL_ATTACHMENT
+                    | FormatFeatureFlags::SAMPLED_IMAGE_FILTER_LINEAR,
+            )
+            .context(format!(
+                "The device supports none of the shadow map formats {:?}",
+                SHADOW_MAP_FORMAT_CANDIDATES
+            ))
+    }
+
+    pub unsafe fn create(
+        logical_device: &SetUpLogicalDevice,
+        allocator: &mut MutexGuard<Allocator>,
+        format: Format,
+        resolution: u32,
+        cascade_count: u32,
+    ) -> crate::Result<Self> {
+        crate::verify!(
+            cascade_count > 0 && cascade_count as usize <= MAX_CASCADES,
+            "The cascade count must be between 1 and {}, got {}",
+            MAX_CASCADES,
+            cascade_count
+        );
+
+        let extent = Extent2D {
+            width: resolution,
+            height: resolution,
+        };
+        let mut subresource_range = SetUpDepthImage::create_subresource_range_for_format(format);
+        subresource_range.layer_count = cascade_count;
+        let depth_image = SetUpDepthImage::create_with_layers(
+            logical_device,
+            allocator,
+            format,
+            SampleCountFlags::TYPE_1,
+            DepthImageDescription::new(extent)
+                .array_layers(cascade_count)
+                .usage(ImageUsageFlags::DEPTH_STENCIL_ATTACHMENT | ImageUsageFlags::SAMPLED)
+                .view_type(ImageViewType::TYPE_2D_ARRAY),
+            subresource_range,
+        )?;
+
+        let render_pass = SetUpRenderPass::create_for_shadow_map(logical_device, format)?;
+
+        let mut cascade_image_views = Vec::with_capacity(cascade_count as usize);
+        let mut framebuffers = Vec::with_capacity(cascade_count as usize);
+        for cascade_index in 0..cascade_count {
+            let cascade_image_view = SetUpImageView::create(
+                logical_device,
+                ImageViewType::TYPE_2D,
+                format,
+                depth_image.layer_subresource_range(cascade_index),
+                depth_image.depth_image.image,
+            )?;
+
+            let framebuffer_attachments = [cascade_image_view.image_view];
+            let framebuffer_create_info = FramebufferCreateInfo::builder()
+                .render_pass(render_pass.render_pass)
+                .attachments(&framebuffer_attachments)
+                .width(resolution)
+                .height(resolution)
+                .layers(1);
+            framebuffers.push(logical_device.create_framebuffer(&framebuffer_create_info, None)?);
+            cascade_image_views.push(cascade_image_view);
+        }
+
+        let sampler = SetUpSampler::create_with_compare_op(
+            logical_device,
+            Filter::LINEAR,
+            CompareOp::LESS_OR_EQUAL,
+        )?;
+
+        Ok(Self {
+            resolution,
+            cascade_count,
+            depth_image,
+            cascade_image_views,
+            render_pass,
+            framebuffers,
+            sampler,
+        })
+    }
+
+    pub fn extent(&self) -> Extent2D {
+        Extent2D {
+            width: self.resolution,
+            height: self.resolution,
+        }
+    }
+
+    /// This function returns the image view that covers all cascades, which is bound to the
+    /// shaders that sample the shadow map.
+    pub fn image_view(&self) -> &SetUpImageView {
+        &self.depth_image.depth_image_view
+    }
+
+    /// This function returns a pipeline builder that is set up for rendering shadow casters
+    /// into the cascades: no color attachments, a depth bias against shadow acne and a viewport
+    /// that covers a whole cascade.
+    ///
+    /// The shader stages, the vertex input and the input assembly (if it's not a triangle list)
+    /// still have to be set.
+    pub fn pipeline_builder(
+        &self,
+        depth_bias_constant_factor: f32,
+        depth_bias_slope_factor: f32,
+    ) -> GraphicsPipelineBuilder {
+        let extent = self.extent();
+        GraphicsPipelineBuilder::new()
+            .input_assembly_with_topology(PrimitiveTopology::TRIANGLE_LIST)
+            .rasterization_with_depth_bias(
+                PolygonMode::FILL,
+                depth_bias_constant_factor,
+                depth_bias_slope_factor,
+            )
+            .multisample_with_samples(SampleCountFlags::TYPE_1)
+            .without_color_attachments()
+            .depth_stencil_with_default_ops()
+            .viewport(Viewport {
+                x: 0.0,
+                y: 0.0,
+                width: extent.width as f32,
+                height: extent.height as f32,
+                min_depth: 0.0,
+                max_depth: 1.0,
+            })
+            .scissor(Rect2D::from(extent))
+    }
+
+    /// This function records the depth-only render pass of the cascade with the given index.
+    /// The `render_pass_op` records the draw calls of the shadow casters, typically with the
+    /// view-projection matrix of the cascade as a push constant.
+    pub fn record_cascade<RenderPassOp>(
+        &self,
+        logical_device: &SetUpLogicalDevice,
+        command_buffer: &SetUpCommandBufferWithFence,
+        cascade_index: u32,
+        render_pass_op: RenderPassOp,
+    ) -> crate::Result<()>
+    where
+        RenderPassOp: FnOnce() -> crate::Result<()>,
+    {
+        crate::verify!(
+            cascade_index < self.cascade_count,
+            "The cascade index {} is out of range, the shadow map has {} cascades",
+            cascade_index,
+            (self.cascade_count)
+        );
+
+        let clear_values: [ClearValue; 1] = [default_depth_clear_value()];
+        let render_pass_begin_info = RenderPassBeginInfo::builder()
+            .render_pass(self.render_pass.render_pass)
+            .framebuffer(self.framebuffers[cascade_index as usize])
+            .render_area(self.extent().into())
+            .clear_values(&clear_values);
+
+        unsafe {
+            logical_device.cmd_begin_render_pass(
+                command_buffer.command_buffer,
+                &render_pass_begin_info,
+                SubpassContents::INLINE,
+            );
+            render_pass_op()?;
+            logical_device.cmd_end_render_pass(command_buffer.command_buffer);
+        }
+
+        Ok(())
+    }
+
+    pub fn destroy(
+        &mut self,
+        logical_device: &SetUpLogicalDevice,
+        allocator: &mut MutexGuard<Allocator>,
+    ) -> crate::Result<()> {
+        self.sampler.destroy(logical_device);
+        unsafe {
+            for framebuffer in self.framebuffers.drain(..) {
+                logical_device.destroy_framebuffer(framebuffer, None);
+            }
+        }
+        for cascade_image_view in self.cascade_image_views.iter_mut() {
+            cascade_image_view.destroy(logical_device);
+        }
+        self.cascade_image_views.clear();
+        self.render_pass.destroy(logical_device);
+        self.depth_image.destroy(logical_device, allocator)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,336 @@
+//! This is a module that contains the computation of the cascades of a directional shadow map:
+//! the split distances along the view direction of a `Camera` and the orthographic
+//! view-projection matrix of the light for each slice of the camera frustum.
+
+use nalgebra::{Matrix4, Point3, Vector3, Vector4};
+
+use crate::engine::camera::Camera;
+
+/// The maximum number of cascades that fits into `CascadeUniformData`
+pub const MAX_CASCADES: usize = 4;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CascadeSettings {
+    pub cascade_count: u32,
+    /// The blend factor between uniform (0.0) and logarithmic (1.0) split distances
+    pub split_lambda: f32,
+    /// The width and height of each cascade in texels
+    pub resolution: u32,
+    /// The distance the cascades cover, if it should be shorter than the far plane distance of
+    /// the camera
+    pub max_shadow_distance: Option<f32>,
+    /// The distance the light is moved back from each cascade, so that objects between the
+    /// light and the camera frustum still cast shadows
+    pub caster_distance: f32,
+}
+
+impl Default for CascadeSettings {
+    fn default() -> Self {
+        Self {
+            cascade_count: 4,
+            split_lambda: 0.75,
+            resolution: 2048,
+            max_shadow_distance: None,
+            caster_distance: 0.0,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Cascade {
+    /// The distance from the camera at which this cascade ends
+    pub split_distance: f32,
+    pub view_projection: Matrix4<f32>,
+}
+
+/// The cascade data as it is laid out in a (std140) uniform buffer
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CascadeUniformData {
+    pub view_projections: [Matrix4<f32>; MAX_CASCADES],
+    pub split_distances: Vector4<f32>,
+    pub cascade_count: u32,
+    _padding: [u32; 3],
+}
+
+impl CascadeUniformData {
+    /// This function creates the uniform data from the first `MAX_CASCADES` cascades.
+    pub fn new(cascades: &[Cascade]) -> Self {
+        let mut view_projections = [Matrix4::identity(); MAX_CASCADES];
+        let mut split_distances = Vector4::zeros();
+        for (index, cascade) in cascades.iter().take(MAX_CASCADES).enumerate() {
+            view_projections[index] = cascade.view_projection;
+            split_distances[index] = cascade.split_distance;
+        }
+
+        Self {
+            view_projections,
+            split_distances,
+            cascade_count: cascades.len().min(MAX_CASCADES) as u32,
+            _padding: [0; 3],
+        }
+    }
+}
+
+/// This function computes the distances at which the cascades end with the "practical split
+/// scheme", which blends between logarithmic and uniform splits. The last distance is always
+/// `far`.
+pub fn cascade_split_distances(
+    near: f32,
+    far: f32,
+    cascade_count: u32,
+    split_lambda: f32,
+) -> Vec<f32> {
+    (1..=cascade_count)
+        .map(|index| {
+            let fraction = index as f32 / cascade_count as f32;
+            let logarithmic_split = near * (far / near).powf(fraction);
+            let uniform_split = near + (far - near) * fraction;
+            split_lambda * logarithmic_split + (1.0 - split_lambda) * uniform_split
+        })
+        .collect()
+}
+
+/// This function returns the normalized device depths of the near and far planes of a
+/// projection that was created with `perspective_projection`.
+pub fn ndc_depth_range(reverse_z: bool) -> (f32, f32) {
+    if reverse_z {
+        (1.0, 0.0)
+    } else {
+        (-1.0, 1.0)
+    }
+}
+
+/// This function computes the world space corners of the frustum of a view-projection matrix.
+/// The first four corners lie on the near plane and the last four on the far plane, in the
+/// same order.
+pub fn frustum_corners(
+    inverse_view_projection: &Matrix4<f32>,
+    ndc_near_depth: f32,
+    ndc_far_depth: f32,
+) -> [Point3<f32>; 8] {
+    let mut corners = [Point3::origin(); 8];
+    for (index, corner) in corners.iter_mut().enumerate() {
+        let x = if index & 1 == 0 { -1.0 } else { 1.0 };
+        let y = if index & 2 == 0 { -1.0 } else { 1.0 };
+        let z = if index < 4 {
+            ndc_near_depth
+        } else {
+            ndc_far_depth
+        };
+
+        let world = inverse_view_projection * Vector4::new(x, y, z, 1.0);
+        *corner = Point3::from(world.xyz() / world.w);
+    }
+    corners
+}
+
+/// This function returns the corners of the slice of the frustum between the distances `from`
+/// and `to`, where `near` and `far` are the distances of the planes the `corners` lie on.
+///
+/// The depth along the view direction is linear on the edges between the near and the far
+/// corners, so the slice corners can be interpolated.
+pub fn frustum_slice_corners(
+    corners: &[Point3<f32>; 8],
+    near: f32,
+    far: f32,
+    from: f32,
+    to: f32,
+) -> [Point3<f32>; 8] {
+    let from_fraction = (from - near) / (far - near);
+    let to_fraction = (to - near) / (far - near);
+
+    let mut slice_corners = [Point3::origin(); 8];
+    for index in 0..4 {
+        let edge = corners[index + 4] - corners[index];
+        slice_corners[index] = corners[index] + edge * from_fraction;
+        slice_corners[index + 4] = corners[index] + edge * to_fraction;
+    }
+    slice_corners
+}
+
+/// This function creates an orthographic projection matrix that maps the view space depths
+/// `-znear` to `-zfar` to Vulkan's depth range of 0.0 to 1.0.
+pub fn orthographic_projection(
+    left: f32,
+    right: f32,
+    bottom: f32,
+    top: f32,
+    znear: f32,
+    zfar: f32,
+) -> Matrix4<f32> {
+    let mut projection = Matrix4::new_orthographic(left, right, bottom, top, znear, zfar);
+    // nalgebra maps the depths to -1.0 to 1.0 (like OpenGL)
+    projection[(2, 2)] = -1.0 / (zfar - znear);
+    projection[(2, 3)] = -znear / (zfar - znear);
+    projection
+}
+
+/// This function computes the view-projection matrix of a directional light for a slice of the
+/// camera frustum.
+///
+/// The projection encloses the bounding sphere of the slice, so its size doesn't change when
+/// the camera rotates, and it is offset so that world space positions always fall onto the
+/// same texels. Both prevent shadow edges from shimmering when the camera moves.
+pub fn cascade_view_projection(
+    slice_corners: &[Point3<f32>; 8],
+    light_direction: &Vector3<f32>,
+    resolution: u32,
+    caster_distance: f32,
+) -> Matrix4<f32> {
+    let center = Point3::from(
+        slice_corners
+            .iter()
+            .map(|corner| corner.coords)
+            .sum::<Vector3<f32>>()
+            / 8.0,
+    );
+    let radius = slice_corners
+        .iter()
+        .map(|corner| nalgebra::distance(corner, &center))
+        .fold(0.0f32, f32::max);
+    // Rounding the radius keeps it stable despite floating point errors
+    let radius = (radius * 16.0).ceil() / 16.0;
+
+    let light_direction = light_direction.normalize();
+    let up = if light_direction.y.abs() > 0.99 {
+        Vector3::z()
+    } else {
+        Vector3::y()
+    };
+    let eye = center - light_direction * (radius + caster_distance);
+    let view = Matrix4::look_at_rh(&eye, &center, &up);
+    let mut projection = orthographic_projection(
+        -radius,
+        radius,
+        -radius,
+        radius,
+        0.0,
+        2.0 * radius + caster_distance,
+    );
+
+    // Snap the projected world origin to the texel grid
+    let texels_per_unit = resolution as f32 / 2.0;
+    let origin = projection * view * Vector4::new(0.0, 0.0, 0.0, 1.0);
+    let origin_in_texels = origin.xy() * texels_per_unit;
+    let offset = (origin_in_texels.map(f32::round) - origin_in_texels) / texels_per_unit;
+    projection[(0, 3)] += offset.x;
+    projection[(1, 3)] += offset.y;
+
+    projection * view
+}
+
+/// This function computes the cascades of a directional light that shines in the
+/// `light_direction` for the frustum of the `camera`.
+pub fn compute_cascades<C: Camera + ?Sized>(
+    camera: &C,
+    light_direction: &Vector3<f32>,
+    settings: &CascadeSettings,
+) -> Vec<Cascade> {
+    let (near, far) = camera.clip_planes();
+    let shadow_distance = settings
+        .max_shadow_distance
+        .map_or(far, |distance| distance.min(far));
+
+    let view_projection = camera.projection_matrix() * camera.view_matrix();
+    let inverse_view_projection = view_projection
+        .try_inverse()
+        .unwrap_or_else(Matrix4::identity);
+    let (ndc_near_depth, ndc_far_depth) = ndc_depth_range(camera.reverse_z());
+    let corners = frustum_corners(&inverse_view_projection, ndc_near_depth, ndc_far_depth);
+
+    let split_distances = cascade_split_distances(
+        near,
+        shadow_distance,
+        settings.cascade_count,
+        settings.split_lambda,
+    );
+
+    let mut slice_start = near;
+    split_distances
+        .into_iter()
+        .map(|split_distance| {
+            let slice_corners =
+                frustum_slice_corners(&corners, near, far, slice_start, split_distance);
+            slice_start = split_distance;
+
+            Cascade {
+                split_distance,
+                view_projection: cascade_view_projection(
+                    &slice_corners,
+                    light_direction,
+                    settings.resolution,
+                    settings.caster_distance,
+                ),
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::camera::perspective_projection;
+
+    #[test]
+    fn split_distances_end_at_the_far_plane() {
+        let splits = cascade_split_distances(0.1, 100.0, 4, 0.75);
+        assert_eq!(4, splits.len());
+        assert!((splits[3] - 100.0).abs() < 1e-3);
+        assert!(splits.windows(2).all(|pair| pair[0] < pair[1]));
+
+        let uniform_splits = cascade_split_distances(1.0, 101.0, 4, 0.0);
+        assert!((uniform_splits[0] - 26.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn frustum_corners_lie_on_the_clip_planes() {
+        for reverse_z in [false, true] {
+            let projection = perspective_projection(1.0, 1.0, 0.5, 50.0, reverse_z);
+            let inverse = projection.try_inverse().unwrap();
+            let (ndc_near_depth, ndc_far_depth) = ndc_depth_range(reverse_z);
+            let corners = frustum_corners(&inverse, ndc_near_depth, ndc_far_depth);
+
+            assert!(corners[..4]
+                .iter()
+                .all(|corner| (corner.z + 0.5).abs() < 1e-3));
+            assert!(corners[4..]
+                .iter()
+                .all(|corner| (corner.z + 50.0).abs() < 1e-2));
+
+            let slice = frustum_slice_corners(&corners, 0.5, 50.0, 10.0, 20.0);
+            assert!((slice[0].z + 10.0).abs() < 1e-3);
+            assert!((slice[7].z + 20.0).abs() < 1e-3);
+        }
+    }
+
+    #[test]
+    fn cascades_are_snapped_to_texels() {
+        let resolution = 1024;
+        let light_direction = Vector3::new(-0.3, -1.0, 0.2);
+        let corners = |offset: f32| {
+            let inverse = perspective_projection(1.5, 1.0, 0.1, 30.0, false)
+                .try_inverse()
+                .unwrap();
+            frustum_corners(&inverse, -1.0, 1.0).map(|corner| corner + Vector3::x() * offset)
+        };
+
+        let view_projection =
+            cascade_view_projection(&corners(0.0), &light_direction, resolution, 0.0);
+        let moved_view_projection =
+            cascade_view_projection(&corners(0.013), &light_direction, resolution, 0.0);
+
+        // The world origin is projected onto a texel corner, and a small camera movement only
+        // changes the translation by whole texels
+        let texels_per_unit = resolution as f32 / 2.0;
+        for matrix in [view_projection, moved_view_projection] {
+            let origin = matrix * Vector4::new(0.0, 0.0, 0.0, 1.0);
+            let origin_in_texels = origin.xy() * texels_per_unit;
+            assert!((origin_in_texels.x - origin_in_texels.x.round()).abs() < 1e-2);
+            assert!((origin_in_texels.y - origin_in_texels.y.round()).abs() < 1e-2);
+        }
+        let rotation_difference = view_projection.fixed_view::<3, 3>(0, 0)
+            - moved_view_projection.fixed_view::<3, 3>(0, 0);
+        assert!(rotation_difference.amax() < 1e-5);
+    }
+}
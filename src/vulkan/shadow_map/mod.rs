@@ -0,0 +1,8 @@
+//! This is a module that provides cascaded shadow maps for directional lights.
+//!
+//! Module overview:
+//! - cascaded\_shadow\_map: Provides a layered depth image with depth-only render passes for each cascade and a compare sampler
+//! - cascades: Provides the computation of the cascade splits and the light matrices from the frustum of a `Camera`
+
+pub mod cascaded_shadow_map;
+pub mod cascades;
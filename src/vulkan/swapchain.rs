@@ -14,6 +14,8 @@ use super::surface::SetUpSurfaceInfo;
 pub struct SetUpSwapchain {
     pub swapchain_loader: Swapchain,
     pub swapchain: SwapchainKHR,
+    pub image_usage: ImageUsageFlags,
+    pub present_mode: PresentModeKHR,
 }
 
 impl SetUpSwapchain {
@@ -36,6 +38,21 @@ impl SetUpSwapchain {
         let surface_format = surface_info.surface_format(0)?;
         let surface_extent = surface_info.surface_extent()?;
 
+        // Concurrent swapchain images are shared between the graphics and present queue families
+        let queue_family_indices = logical_device.queue_family_indices;
+        let graphics_and_present = [
+            queue_family_indices.graphics,
+            queue_family_indices.present_or_graphics(),
+        ];
+        let (image_sharing_mode, image_queue_family_indices) =
+            if image_sharing_mode == SharingMode::CONCURRENT
+                && graphics_and_present[0] != graphics_and_present[1]
+            {
+                (SharingMode::CONCURRENT, &graphics_and_present[..])
+            } else {
+                (SharingMode::EXCLUSIVE, &[][..])
+            };
+
         let mut swapchain_create_info = SwapchainCreateInfoKHR::builder()
             .surface(surface_info.surface)
             .min_image_count(desired_image_count)
@@ -44,6 +61,7 @@ impl SetUpSwapchain {
             .image_extent(surface_extent)
             .image_usage(image_usage)
             .image_sharing_mode(image_sharing_mode)
+            .queue_family_indices(image_queue_family_indices)
             .pre_transform(pre_transform)
             .composite_alpha(composite_alpha)
             .present_mode(present_mode)
@@ -59,6 +77,8 @@ impl SetUpSwapchain {
         Ok(Self {
             swapchain_loader,
             swapchain,
+            image_usage,
+            present_mode,
         })
     }
 
@@ -87,15 +107,34 @@ impl SetUpSwapchain {
             surface_capabilities.current_transform
         };
 
+        // Transfer source usage is requested if possible, so that screenshots can be captured
+        let image_usage = if surface_capabilities
+            .supported_usage_flags
+            .contains(ImageUsageFlags::TRANSFER_SRC)
+        {
+            ImageUsageFlags::COLOR_ATTACHMENT | ImageUsageFlags::TRANSFER_SRC
+        } else {
+            ImageUsageFlags::COLOR_ATTACHMENT
+        };
+
         let present_modes = surface_info.surface_present_modes()?;
         let present_mode = present_mode_init_mode.find_present_mode(present_modes)?;
 
+        // If presentation happens on a different queue family than rendering, the images are
+        // shared concurrently instead of transferring their ownership every frame
+        let queue_family_indices = logical_device.queue_family_indices;
+        let image_sharing_mode = if queue_family_indices.present_or_graphics() != queue_family_indices.graphics {
+            SharingMode::CONCURRENT
+        } else {
+            SharingMode::EXCLUSIVE
+        };
+
         Self::create(
             instance,
             surface_info,
             desired_image_count,
-            ImageUsageFlags::COLOR_ATTACHMENT,
-            SharingMode::EXCLUSIVE,
+            image_usage,
+            image_sharing_mode,
             pre_transform,
             CompositeAlphaFlagsKHR::OPAQUE,
             present_mode,
@@ -106,6 +145,10 @@ impl SetUpSwapchain {
         )
     }
 
+    pub fn supports_transfer_src(&self) -> bool {
+        self.image_usage.contains(ImageUsageFlags::TRANSFER_SRC)
+    }
+
     pub fn destroy(&mut self) {
         unsafe {
             self.swapchain_loader
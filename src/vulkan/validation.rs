@@ -0,0 +1,177 @@
+//! This is a module that provides the parameter validation of the safe resource creation
+//! functions of the `VulkanContext`.
+//!
+//! The constructors of the wrapper structures (`Texture::create`, `TypedAllocatedBuffer::allocate`,
+//! `SetUpSampler::create` etc.) are `unsafe`, because they pass their parameters to Vulkan as-is.
+//! The functions of the `VulkanContext` check the parameters with the functions of this module
+//! first and return a `VoxelarError` if they are invalid, so they can be called without `unsafe`.
+
+use ash::vk::PhysicalDeviceLimits;
+use ash::vk::{BufferUsageFlags, Extent3D, Format, FormatFeatureFlags, ImageTiling};
+use ash::Instance;
+
+use crate::result::Context;
+
+use super::image::sampler::SamplerLod;
+use super::physical_device::SetUpPhysicalDevice;
+
+/// This function returns the size in bytes of a buffer with `element_amount` elements of type
+/// `T`. Buffers can't be empty, so both the element amount and the size of `T` must not be 0.
+pub fn buffer_size_in_bytes<T>(element_amount: usize) -> crate::Result<usize> {
+    crate::verify!(element_amount > 0, "A buffer needs at least one element");
+    crate::verify!(
+        std::mem::size_of::<T>() > 0,
+        "Buffers of the zero-sized type {} can't be created",
+        (std::any::type_name::<T>())
+    );
+    std::mem::size_of::<T>()
+        .checked_mul(element_amount)
+        .context(format!(
+            "A buffer with {} elements of type {} is too large",
+            element_amount,
+            std::any::type_name::<T>()
+        ))
+}
+
+pub fn verify_buffer_usage(usage: BufferUsageFlags) -> crate::Result<()> {
+    crate::verify!(!usage.is_empty(), "A buffer needs at least one usage flag");
+    Ok(())
+}
+
+/// This function checks that the data of a resource has the expected number of elements.
+pub fn verify_data_length(name: &str, length: usize, expected_length: usize) -> crate::Result<()> {
+    crate::verify!(
+        length == expected_length,
+        "The data of the {} has {} elements, expected {}",
+        name,
+        length,
+        expected_length
+    );
+    Ok(())
+}
+
+/// This function checks the dimensions of a 2D texture, where the `depth` is the number of
+/// channels (see `VulkanContext::create_texture`), against the limits of the device.
+pub fn verify_texture_extent(
+    texture_extent: Extent3D,
+    limits: &PhysicalDeviceLimits,
+) -> crate::Result<()> {
+    let Extent3D {
+        width,
+        height,
+        depth: channels,
+    } = texture_extent;
+    crate::verify!(
+        width > 0 && height > 0,
+        "A texture can't be empty, got {}x{}",
+        width,
+        height
+    );
+    crate::verify!(
+        width <= limits.max_image_dimension2_d && height <= limits.max_image_dimension2_d,
+        "A {}x{} texture exceeds the maximum size of {} texels of the device",
+        width,
+        height,
+        (limits.max_image_dimension2_d)
+    );
+    crate::verify!(
+        (1..=4).contains(&channels),
+        "A texture has between 1 and 4 channels, got {}",
+        channels
+    );
+    Ok(())
+}
+
+pub fn verify_array_layers(array_layers: u32, limits: &PhysicalDeviceLimits) -> crate::Result<()> {
+    crate::verify!(
+        array_layers > 0 && array_layers <= limits.max_image_array_layers,
+        "The layer count must be between 1 and {}, got {}",
+        (limits.max_image_array_layers),
+        array_layers
+    );
+    Ok(())
+}
+
+/// This function checks that the `format` supports the `required_features` with the given
+/// `tiling`.
+pub fn verify_format_features(
+    instance: &Instance,
+    physical_device: &SetUpPhysicalDevice,
+    format: Format,
+    tiling: ImageTiling,
+    required_features: FormatFeatureFlags,
+) -> crate::Result<()> {
+    let supported = physical_device
+        .find_supported_format(instance, &[format], tiling, required_features)
+        .is_some();
+    crate::verify!(
+        supported,
+        "The format {:?} doesn't support {:?} with {:?} tiling",
+        format,
+        required_features,
+        tiling
+    );
+    Ok(())
+}
+
+pub fn verify_sampler_lod(lod: &SamplerLod, limits: &PhysicalDeviceLimits) -> crate::Result<()> {
+    crate::verify!(
+        lod.min_lod.is_finite() && lod.max_lod.is_finite() && lod.mip_lod_bias.is_finite(),
+        "The LOD parameters of a sampler must be finite, got {:?}",
+        lod
+    );
+    crate::verify!(
+        0.0 <= lod.min_lod && lod.min_lod <= lod.max_lod,
+        "The LOD range of a sampler must satisfy 0 <= min_lod <= max_lod, got {} to {}",
+        (lod.min_lod),
+        (lod.max_lod)
+    );
+    crate::verify!(
+        lod.mip_lod_bias.abs() <= limits.max_sampler_lod_bias,
+        "The mip LOD bias {} exceeds the limit of {} of the device",
+        (lod.mip_lod_bias),
+        (limits.max_sampler_lod_bias)
+    );
+    Ok(())
+}
+
+pub fn verify_max_anisotropy(max_anisotropy: Option<f32>) -> crate::Result<()> {
+    if let Some(max_anisotropy) = max_anisotropy {
+        crate::verify!(
+            max_anisotropy >= 1.0,
+            "The maximum anisotropy must be at least 1.0, got {}",
+            max_anisotropy
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn buffer_sizes_are_checked() {
+        assert_eq!(48, buffer_size_in_bytes::<[f32; 3]>(4).unwrap());
+        assert!(buffer_size_in_bytes::<u32>(0).is_err());
+        assert!(buffer_size_in_bytes::<()>(4).is_err());
+        assert!(buffer_size_in_bytes::<u64>(usize::MAX).is_err());
+    }
+
+    #[test]
+    fn texture_extents_are_checked_against_limits() {
+        let limits = PhysicalDeviceLimits {
+            max_image_dimension2_d: 4096,
+            ..Default::default()
+        };
+        let extent = |width, height, depth| Extent3D {
+            width,
+            height,
+            depth,
+        };
+        assert!(verify_texture_extent(extent(256, 256, 4), &limits).is_ok());
+        assert!(verify_texture_extent(extent(0, 256, 4), &limits).is_err());
+        assert!(verify_texture_extent(extent(8192, 256, 4), &limits).is_err());
+        assert!(verify_texture_extent(extent(256, 256, 5), &limits).is_err());
+    }
+}
@@ -0,0 +1,397 @@
+//! This is a module that contains the `WindowTarget` structure, which bundles all data structures
+//! that belong to a single window: its surface, swapchain, present images, depth image,
+//! multisampled color image, render pass, framebuffers and per-frame data.
+//!
+//! A `VulkanContext` stores one `WindowTarget` per window and routes calls by `WindowId`.
+
+use std::cell::{Cell, RefCell};
+use std::sync::MutexGuard;
+
+use ash::vk::Format;
+use ash::vk::SampleCountFlags;
+use ash::{Entry, Instance};
+use gpu_allocator::vulkan::Allocator;
+use paste::paste;
+use winit::window::WindowId;
+
+use crate::result::Context;
+use crate::window::VoxelarWindow;
+
+use super::buffers::ring_buffer::SetUpRingBuffer;
+use super::creation_info::{DataStructureCreationInfo, DepthBufferInfo, PresentModeInitMode};
+use super::creation_info::RenderingPath;
+use super::depth_image::SetUpDepthImage;
+use super::frame_data::FrameData;
+use super::framebuffers::SetUpFramebuffers;
+use super::logical_device::SetUpLogicalDevice;
+use super::msaa_color_image::SetUpMsaaColorImage;
+use super::per_frame::PerFrame;
+use super::physical_device::SetUpPhysicalDevice;
+use super::present_images::SetUpPresentImages;
+use super::render_pass::SetUpRenderPass;
+use super::surface::SetUpSurfaceInfo;
+use super::swapchain::SetUpSwapchain;
+
+pub struct WindowTarget {
+    pub window_id: WindowId,
+    pub surface_info: SetUpSurfaceInfo,
+
+    pub swapchain: Option<SetUpSwapchain>,
+    pub present_images: Option<SetUpPresentImages>,
+    pub depth_image: Option<SetUpDepthImage>,
+    /// The multisampled color image, which only exists if MSAA is enabled
+    pub msaa_color_image: Option<SetUpMsaaColorImage>,
+    /// The render pass, which only exists for `RenderingPath::RenderPass`
+    pub render_pass: Option<SetUpRenderPass>,
+    /// The framebuffers, which only exist for `RenderingPath::RenderPass`
+    pub framebuffers: Option<SetUpFramebuffers>,
+    pub rendering_path: RenderingPath,
+
+    pub frames: PerFrame<FrameData>,
+    /// The ring buffer for per-frame data, whose region of the current frame is reset once the
+    /// draw buffers of the frame completed
+    pub ring_buffer: RefCell<Option<SetUpRingBuffer>>,
+
+    /// The number of samples of the color and depth attachments, clamped to the sample counts
+    /// that are supported by the device
+    pub msaa_samples: SampleCountFlags,
+
+    pub depth_buffer_info: DepthBufferInfo,
+    /// The depth format that was selected for the `depth_buffer_info`
+    pub depth_format: Format,
+
+    /// The index of the present image that was acquired most recently, if it is still valid
+    pub current_present_index: Cell<Option<u32>>,
+}
+
+impl WindowTarget {
+    pub unsafe fn create(
+        window: &VoxelarWindow,
+        entry: &Entry,
+        instance: &Instance,
+    ) -> crate::Result<Self> {
+        let surface_info = SetUpSurfaceInfo::create(window, entry, instance)?;
+
+        Ok(Self {
+            window_id: window.id(),
+            surface_info,
+
+            swapchain: None,
+            present_images: None,
+            depth_image: None,
+            msaa_color_image: None,
+            render_pass: None,
+            framebuffers: None,
+            rendering_path: RenderingPath::default(),
+
+            frames: PerFrame::empty(),
+            ring_buffer: RefCell::new(None),
+
+            msaa_samples: SampleCountFlags::TYPE_1,
+
+            depth_buffer_info: DepthBufferInfo::default(),
+            depth_format: Format::D16_UNORM,
+
+            current_present_index: Cell::new(None),
+        })
+    }
+
+    generate_safe_getter!(
+        swapchain,
+        SetUpSwapchain,
+        "No swapchain was set up for this window yet! Use WindowTarget::create_swapchain to do so"
+    );
+
+    generate_safe_getter!(
+        present_images,
+        SetUpPresentImages,
+        "No present images were set up for this window yet! Use WindowTarget::create_present_images to do so"
+    );
+
+    generate_safe_getter!(
+        depth_image,
+        SetUpDepthImage,
+        "No depth image was set up for this window yet! Use WindowTarget::create_depth_image to do so"
+    );
+
+    generate_safe_getter!(
+        render_pass,
+        SetUpRenderPass,
+        "No render pass was set up for this window yet! Use WindowTarget::create_render_pass to do so"
+    );
+
+    generate_safe_getter!(
+        framebuffers,
+        SetUpFramebuffers,
+        "No framebuffers were set up for this window yet! Use WindowTarget::create_framebuffers to do so"
+    );
+
+    pub fn create_swapchain(
+        &mut self,
+        instance: &Instance,
+        logical_device: &SetUpLogicalDevice,
+        present_mode_init_mode: PresentModeInitMode,
+    ) -> crate::Result<()> {
+        unsafe {
+            let new_swapchain = SetUpSwapchain::create_with_defaults(
+                instance,
+                &self.surface_info,
+                logical_device,
+                present_mode_init_mode,
+                self.swapchain.as_ref(),
+            )?;
+
+            if let Some(mut old_swapchain) = self.swapchain.replace(new_swapchain) {
+                old_swapchain.destroy();
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn create_present_images(&mut self, logical_device: &SetUpLogicalDevice) -> crate::Result<()> {
+        unsafe {
+            self.present_images = Some(SetUpPresentImages::create_with_defaults(
+                logical_device,
+                self.swapchain()?,
+                &self.surface_info,
+            )?);
+        }
+
+        Ok(())
+    }
+
+    pub fn create_depth_image(
+        &mut self,
+        logical_device: &SetUpLogicalDevice,
+        allocator: &mut MutexGuard<Allocator>,
+    ) -> crate::Result<()> {
+        unsafe {
+            self.depth_image = Some(SetUpDepthImage::create_with_defaults(
+                logical_device,
+                allocator,
+                &self.surface_info,
+                self.depth_format,
+                self.msaa_samples,
+            )?);
+        }
+
+        Ok(())
+    }
+
+    /// This function creates the multisampled color image if MSAA is enabled.
+    pub fn create_msaa_color_image(
+        &mut self,
+        logical_device: &SetUpLogicalDevice,
+        allocator: &mut MutexGuard<Allocator>,
+    ) -> crate::Result<()> {
+        if self.msaa_samples == SampleCountFlags::TYPE_1 {
+            self.msaa_color_image = None;
+            return Ok(());
+        }
+
+        unsafe {
+            self.msaa_color_image = Some(SetUpMsaaColorImage::create_with_defaults(
+                logical_device,
+                allocator,
+                &self.surface_info,
+                self.msaa_samples,
+            )?);
+        }
+
+        Ok(())
+    }
+
+    pub fn create_render_pass(&mut self, logical_device: &SetUpLogicalDevice) -> crate::Result<()> {
+        unsafe {
+            self.render_pass = Some(SetUpRenderPass::create_with_defaults(
+                logical_device,
+                &self.surface_info,
+                self.depth_format,
+                self.msaa_samples,
+            )?);
+        }
+
+        Ok(())
+    }
+
+    pub fn create_framebuffers(&mut self, logical_device: &SetUpLogicalDevice) -> crate::Result<()> {
+        unsafe {
+            self.framebuffers = Some(SetUpFramebuffers::create(
+                logical_device,
+                self.depth_image()?,
+                self.msaa_color_image.as_ref(),
+                &self.surface_info,
+                self.present_images()?,
+                self.render_pass()?,
+            )?);
+        }
+
+        Ok(())
+    }
+
+    pub fn create_per_frame_data(
+        &mut self,
+        logical_device: &SetUpLogicalDevice,
+        frame_overlap: u32,
+    ) -> crate::Result<()> {
+        unsafe {
+            self.frames = PerFrame::try_init(
+                |_| FrameData::create_with_defaults(logical_device),
+                frame_overlap as usize,
+            )?;
+        }
+
+        Ok(())
+    }
+
+    /// This function queries the surface information for the given `physical_device` and creates
+    /// all window-specific data structures.
+    pub fn create_data_structures(
+        &mut self,
+        instance: &Instance,
+        physical_device: &SetUpPhysicalDevice,
+        logical_device: &SetUpLogicalDevice,
+        allocator: &mut MutexGuard<Allocator>,
+        window_size: (u32, u32),
+        creation_info: &DataStructureCreationInfo,
+    ) -> crate::Result<()> {
+        crate::verify!(
+            physical_device.supports_surface(&self.surface_info)?,
+            "The queue family of the physical device can't present to the surface of window {:?}",
+            (self.window_id)
+        );
+
+        self.msaa_samples = physical_device.clamp_sample_count(creation_info.msaa_samples);
+
+        self.rendering_path = creation_info.rendering_path;
+        self.depth_buffer_info = creation_info.depth_buffer;
+        self.depth_format = physical_device.find_depth_format(instance, &self.depth_buffer_info)?;
+
+        self.surface_info.update(physical_device, window_size)?;
+        self.create_swapchain(instance, logical_device, creation_info.swapchain_present_mode)?;
+        self.create_present_images(logical_device)?;
+        self.create_depth_image(logical_device, allocator)?;
+        self.create_msaa_color_image(logical_device, allocator)?;
+        if self.rendering_path == RenderingPath::RenderPass {
+            self.create_render_pass(logical_device)?;
+            self.create_framebuffers(logical_device)?;
+        }
+        self.create_per_frame_data(logical_device, creation_info.frame_overlap)?;
+
+        Ok(())
+    }
+
+    /// This function recreates the swapchain and all data structures that depend on it, e.g.
+    /// after the window was resized.
+    pub fn update_swapchain(
+        &mut self,
+        instance: &Instance,
+        physical_device: &SetUpPhysicalDevice,
+        logical_device: &SetUpLogicalDevice,
+        allocator: &mut MutexGuard<Allocator>,
+        window_size: (u32, u32),
+        creation_info: &DataStructureCreationInfo,
+    ) -> crate::Result<()> {
+        self.surface_info.update(physical_device, window_size)?;
+
+        logical_device.wait()?;
+        self.destroy_swapchain_dependents(logical_device, allocator)?;
+
+        // The old swapchain is passed to the new one and destroyed afterwards
+        self.create_swapchain(instance, logical_device, creation_info.swapchain_present_mode)?;
+        self.create_present_images(logical_device)?;
+        self.create_depth_image(logical_device, allocator)?;
+        self.create_msaa_color_image(logical_device, allocator)?;
+        if self.rendering_path == RenderingPath::RenderPass {
+            self.create_render_pass(logical_device)?;
+            self.create_framebuffers(logical_device)?;
+        }
+        self.create_per_frame_data(logical_device, creation_info.frame_overlap)?;
+
+        Ok(())
+    }
+
+    fn destroy_swapchain_dependents(
+        &mut self,
+        logical_device: &SetUpLogicalDevice,
+        allocator: &mut MutexGuard<Allocator>,
+    ) -> crate::Result<()> {
+        if let Some(mut render_pass) = self.render_pass.take() {
+            render_pass.destroy(logical_device);
+        }
+
+        if let Some(mut framebuffers) = self.framebuffers.take() {
+            framebuffers.destroy(logical_device);
+        }
+
+        if let Some(mut depth_image) = self.depth_image.take() {
+            depth_image.destroy(logical_device, allocator)?;
+        }
+
+        if let Some(mut msaa_color_image) = self.msaa_color_image.take() {
+            msaa_color_image.destroy(logical_device, allocator)?;
+        }
+
+        if let Some(mut present_images) = self.present_images.take() {
+            present_images.destroy(logical_device);
+        }
+
+        // The device is idle at this point, so the pending deletions can be executed as well
+        for frame in self.frames.iter_mut() {
+            frame.deletion_queue.flush(logical_device, allocator)?;
+            frame.destroy(logical_device);
+        }
+        self.frames = PerFrame::empty();
+        self.current_present_index.set(None);
+
+        Ok(())
+    }
+
+    /// This function destroys all data structures of this window except for the surface.
+    pub fn destroy_data_structures(
+        &mut self,
+        logical_device: &SetUpLogicalDevice,
+        allocator: &mut MutexGuard<Allocator>,
+    ) -> crate::Result<()> {
+        self.destroy_swapchain_dependents(logical_device, allocator)?;
+
+        if let Some(mut swapchain) = self.swapchain.take() {
+            swapchain.destroy();
+        }
+
+        if let Some(mut ring_buffer) = self.ring_buffer.get_mut().take() {
+            ring_buffer.destroy(logical_device, allocator)?;
+        }
+
+        Ok(())
+    }
+
+    /// This function destroys the surface of this window.
+    ///
+    /// All other data structures have to be destroyed using `WindowTarget::destroy_data_structures`
+    /// before calling this function.
+    pub fn destroy_surface(&mut self) {
+        self.surface_info.destroy();
+    }
+
+    pub fn surface_info(&self) -> &SetUpSurfaceInfo {
+        &self.surface_info
+    }
+
+    pub fn window_id(&self) -> WindowId {
+        self.window_id
+    }
+
+    pub fn msaa_samples(&self) -> SampleCountFlags {
+        self.msaa_samples
+    }
+
+    pub fn reverse_z(&self) -> bool {
+        self.depth_buffer_info.reverse_z
+    }
+
+    pub fn frame_overlap(&self) -> usize {
+        self.frames.len()
+    }
+}
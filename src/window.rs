@@ -2,7 +2,7 @@ use raw_window_handle::{HasRawDisplayHandle, RawDisplayHandle};
 use raw_window_handle::{HasRawWindowHandle, RawWindowHandle};
 use winit::event::Event;
 use winit::event_loop::{ControlFlow, EventLoop, EventLoopWindowTarget};
-use winit::window::{Window, WindowBuilder};
+use winit::window::{Window, WindowBuilder, WindowId};
 
 #[derive(Debug, PartialEq, Eq)]
 pub enum VoxelarWindowMode {
@@ -17,13 +17,17 @@ pub struct VoxelarWindow {
 impl VoxelarWindow {
     pub fn from_window_builder(
         window_builder: WindowBuilder,
-        event_loop: &EventLoop<()>,
+        event_loop_target: &EventLoopWindowTarget<()>,
     ) -> crate::Result<Self> {
-        let window = window_builder.build(&event_loop)?;
+        let window = window_builder.build(event_loop_target)?;
 
         Ok(Self { window })
     }
 
+    pub fn id(&self) -> WindowId {
+        self.window.id()
+    }
+
     pub fn raw_window_handle(&self) -> RawWindowHandle {
         self.window.raw_window_handle()
     }